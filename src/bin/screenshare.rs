@@ -0,0 +1,755 @@
+// Command-line front end for scans.
+//
+// Exit codes: 0 when the scan (or saved report) is clean, 1 when it is suspicious or likely cheating,
+// 2 on errors. A watch exits with 1 if any of its scans was suspicious. Scans are recorded in the
+// scan history unless --no-history is passed, `diff` compares them with the previous screenshare.
+// `forensics` looks for programs that ran before the scan, also in artifacts copied off a machine,
+// `prefetch` shows everything a copied Prefetch file records.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use basic_screensharing_tool::core::{
+    anticheat::Anticheat,
+    config::{Config, ConfigOverrides, NotifierKind, SinkConfig},
+    forensics::{collector::ForensicsCollector, prefetch::prefetch_file::PrefetchFile},
+    handle::{handle_manager::HandleManager, SystemHandleType},
+    history::{report_diff::ReportDiff, scan_history::ScanHistory},
+    overlay::{
+        fixture_window_source::FixtureWindowSource,
+        window_info::WindowInfo,
+        window_source::{default_source, WindowSource},
+    },
+    process::{process_query::ProcessQuery, Process},
+    report::{HostInfo, ScanReport},
+    scoring::Verdict,
+    uploading::report_sink::{ReportSink, SinkFuture},
+    watch::watcher::Watcher,
+};
+
+const EXIT_CLEAN: u8 = 0;
+const EXIT_SUSPICIOUS: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+#[derive(Parser)]
+#[command(name = "screenshare", version, about = "Screenshare anticheat scanner")]
+struct Cli {
+    /// More log output (-v debug, -vv trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scan a running game for suspicious handles, overlays, modules and code
+    Scan(Box<ScanArgs>),
+    /// Keep rescanning a running game and report handles, overlays and modules as they appear
+    Watch(Box<WatchArgs>),
+    /// Dump the handles that pass the suspicious handle filters
+    ListHandles(ListHandlesArgs),
+    /// Dump every top-level window
+    ListWindows(ListWindowsArgs),
+    /// Render a saved report
+    Report(ReportArgs),
+    /// Show what is new, gone or unchanged since the previous screenshare of the same machine
+    Diff(DiffArgs),
+    /// List the reports in the scan history
+    History(HistoryArgs),
+    /// Look for programs that ran recently, on this machine or in artifacts copied off another one
+    Forensics(ForensicsArgs),
+    /// Show run count, run times and loaded files of Windows Prefetch files
+    Prefetch(PrefetchArgs),
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    /// Config file (defaults to $SCREENSHARE_CONFIG or ./screenshare.toml)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Built-in game profile id or path to a profile file
+    #[arg(short, long)]
+    profile: Option<String>,
+
+    /// PID of the process to scan
+    #[arg(long)]
+    pid: Option<u32>,
+
+    /// Executable name of the process to scan, instead of the profile's executables
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Full executable path of the process to scan
+    #[arg(long)]
+    exe_path: Option<String>,
+
+    /// Overlay rule file used on top of the built-in rules (repeatable)
+    #[arg(long = "rules")]
+    rules: Vec<PathBuf>,
+
+    /// Allowlist file used on top of the built-in allowlist (repeatable)
+    #[arg(long = "allowlist")]
+    allowlists: Vec<PathBuf>,
+
+    #[arg(long)]
+    suspicious_threshold: Option<u32>,
+
+    #[arg(long)]
+    likely_cheating_threshold: Option<u32>,
+
+    /// Upload reports to this URL (multipart) instead of the configured sink
+    #[arg(long, conflicts_with = "upload_dir")]
+    upload_url: Option<String>,
+
+    /// Keep reports in this directory instead of the configured sink
+    #[arg(long)]
+    upload_dir: Option<PathBuf>,
+
+    #[arg(long)]
+    discord_webhook: Option<String>,
+
+    #[arg(long)]
+    slack_webhook: Option<String>,
+
+    #[arg(long)]
+    teams_webhook: Option<String>,
+
+    #[arg(long)]
+    json_webhook: Option<String>,
+
+    /// Don't upload the report or send notifications
+    #[arg(long)]
+    offline: bool,
+
+    /// Also write the JSON report to this file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Keep the scan history in this directory instead of the configured one
+    #[arg(long)]
+    history_dir: Option<PathBuf>,
+
+    /// Don't compare with or record in the scan history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Don't look for programs that ran before the scan
+    #[arg(long)]
+    no_forensics: bool,
+
+    /// How to print the report
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    #[command(flatten)]
+    scan: ScanArgs,
+
+    /// Seconds between rescans
+    #[arg(long)]
+    interval: Option<u64>,
+
+    /// Stop after this many seconds, otherwise the watch runs until it is killed
+    #[arg(long)]
+    duration: Option<u64>,
+
+    /// Stop after this many scans
+    #[arg(long)]
+    scans: Option<usize>,
+}
+
+#[derive(Args)]
+struct ListHandlesArgs {
+    /// Only handles that point to this PID
+    #[arg(long)]
+    target: Option<u32>,
+
+    /// Keep handles without suspicious access rights
+    #[arg(long)]
+    all: bool,
+
+    /// Read handles from a .toml or .json fixture instead of the system
+    #[arg(long)]
+    fixture: Option<PathBuf>,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct ListWindowsArgs {
+    /// Read windows from a .toml or .json fixture instead of the system
+    #[arg(long)]
+    fixture: Option<PathBuf>,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Report saved with `scan --output` or downloaded from the upload sink
+    file: PathBuf,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct HistoryOptions {
+    /// Config file (defaults to $SCREENSHARE_CONFIG or ./screenshare.toml)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Scan history directory instead of the configured one
+    #[arg(long)]
+    history_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Report to compare with the screenshare before it, the last recorded one of --target if not set
+    file: Option<PathBuf>,
+
+    /// Executable name of the scanned game
+    #[arg(long, required_unless_present = "file")]
+    target: Option<String>,
+
+    /// Machine the game was scanned on (defaults to this one)
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Also list the findings both screenshares had
+    #[arg(long)]
+    unchanged: bool,
+
+    #[command(flatten)]
+    history: HistoryOptions,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct HistoryArgs {
+    /// Only reports of this machine
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Only reports of this executable
+    #[arg(long)]
+    target: Option<String>,
+
+    #[command(flatten)]
+    history: HistoryOptions,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct ForensicsArgs {
+    /// Config file (defaults to $SCREENSHARE_CONFIG or ./screenshare.toml)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// How many hours back to look
+    #[arg(long)]
+    lookback_hours: Option<u64>,
+
+    /// Only read the artifacts given here, not the processes and files of this machine
+    #[arg(long)]
+    artifacts_only: bool,
+
+    /// bash, zsh, fish or PowerShell history file (repeatable)
+    #[arg(long = "shell-history")]
+    shell_histories: Vec<PathBuf>,
+
+    /// Directory to search for recently written executables (repeatable)
+    #[arg(long = "dir")]
+    directories: Vec<PathBuf>,
+
+    /// Copy of a Windows Prefetch directory (repeatable)
+    #[arg(long = "prefetch-dir")]
+    prefetch_dirs: Vec<PathBuf>,
+
+    /// Copy of a Windows Amcache.hve (repeatable)
+    #[arg(long = "amcache")]
+    amcache_files: Vec<PathBuf>,
+
+    /// Leave out programs that are still running
+    #[arg(long)]
+    closed_only: bool,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args)]
+struct PrefetchArgs {
+    /// Prefetch (.pf) files, plain or MAM compressed
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Also list the files each program loaded
+    #[arg(long)]
+    files: bool,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    format: Format,
+}
+
+impl HistoryOptions {
+    fn open(&self) -> anyhow::Result<ScanHistory> {
+        let overrides = ConfigOverrides { history_dir: self.history_dir.clone(), ..Default::default() };
+        // Only the history directory is used, a broken upload or notifier setup doesn't matter here
+        Ok(Config::load_unchecked(self.config.as_deref(), overrides)?.history.reader())
+    }
+}
+
+impl ScanArgs {
+    fn overrides(&self) -> ConfigOverrides {
+        let upload = match (&self.upload_url, &self.upload_dir) {
+            (Some(url), _) => Some(SinkConfig::Http { url: url.clone(), field_name: None, url_pointer: None }),
+            (_, Some(path)) => Some(SinkConfig::Directory { path: path.clone() }),
+            _ => None,
+        };
+
+        let webhooks = [
+            (NotifierKind::Discord, &self.discord_webhook),
+            (NotifierKind::Slack, &self.slack_webhook),
+            (NotifierKind::Teams, &self.teams_webhook),
+            (NotifierKind::Json, &self.json_webhook),
+        ];
+
+        ConfigOverrides {
+            profile: self.profile.clone(),
+            rules: self.rules.clone(),
+            allowlists: self.allowlists.clone(),
+            suspicious_threshold: self.suspicious_threshold,
+            likely_cheating_threshold: self.likely_cheating_threshold,
+            upload,
+            webhooks: webhooks.into_iter()
+                .filter_map(|(kind, url)| url.clone().map(|url| (kind, url)))
+                .collect(),
+            history_dir: self.history_dir.clone(),
+            record_history: self.no_history.then_some(false),
+            collect_forensics: self.no_forensics.then_some(false),
+            ..Default::default()
+        }
+    }
+
+    /// The process given on the command line, if any
+    fn query(&self) -> Option<ProcessQuery> {
+        if self.pid.is_none() && self.name.is_none() && self.exe_path.is_none() {
+            return None;
+        }
+
+        let mut query = ProcessQuery::new();
+        if let Some(pid) = self.pid {
+            query.with_pid(pid);
+        }
+        if let Some(name) = &self.name {
+            query.with_name(name.clone());
+        }
+        if let Some(exe_path) = &self.exe_path {
+            query.with_exe_path(exe_path.clone());
+        }
+        Some(query)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let level = match cli.verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::builder()
+        .filter_level(level)
+        .format_target(false)
+        .format_timestamp_secs()
+        .parse_default_env()
+        .init();
+
+    let result = match cli.command {
+        Command::Scan(args) => scan(*args).map(Some),
+        Command::Watch(args) => watch(*args).map(Some),
+        Command::ListHandles(args) => list_handles(args).map(|_| None),
+        Command::ListWindows(args) => list_windows(args).map(|_| None),
+        Command::Report(args) => report(args).map(Some),
+        Command::Diff(args) => diff(args).map(|_| None),
+        Command::History(args) => history(args).map(|_| None),
+        Command::Forensics(args) => forensics(args).map(|_| None),
+        Command::Prefetch(args) => prefetch(args).map(|_| None),
+    };
+
+    match result {
+        Ok(Some(Verdict::Clean)) | Ok(None) => ExitCode::from(EXIT_CLEAN),
+        Ok(Some(_)) => ExitCode::from(EXIT_SUSPICIOUS),
+        // Output piped into `head` and the like, nothing left to report
+        Err(err) if is_broken_pipe(&err) => ExitCode::from(EXIT_CLEAN),
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+/// Opens the target and sets up the anticheat from all config layers
+fn prepare(args: &ScanArgs, overrides: ConfigOverrides) -> anyhow::Result<(Config, Anticheat)> {
+    let config = Config::load(args.config.as_deref(), overrides)?;
+    let profile = config.game_profile()?;
+
+    let process = match (args.query(), &profile) {
+        (Some(query), _) => Process::open(&query.find_one()?)?,
+        (None, Some(profile)) => profile.open_process()
+            .with_context(|| format!("Failed to find a running {}", profile.name))?,
+        (None, None) => anyhow::bail!("Nothing to scan, pass --profile or a target (--pid, --name, --exe-path)"),
+    };
+
+    let mut anticheat = Anticheat::from_process(process)?;
+    if let Some(profile) = profile {
+        anticheat.apply_profile(profile);
+    }
+    anticheat.apply_config(&config)?;
+    // The stdout sink would print the report a second time after --format json printed it
+    if matches!(args.format, Format::Json) && config.upload == SinkConfig::Stdout {
+        anticheat.with_report_sink(PrintedReport);
+    }
+    if args.offline {
+        anticheat.notifiers_mut().clear();
+    }
+
+    Ok((config, anticheat))
+}
+
+fn scan(args: ScanArgs) -> anyhow::Result<Verdict> {
+    let (_, mut anticheat) = prepare(&args, args.overrides())?;
+    anticheat.run()?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let report = anticheat.report();
+    finish(&args, &mut anticheat, report, &runtime)?;
+    Ok(anticheat.verdict())
+}
+
+fn watch(args: WatchArgs) -> anyhow::Result<Verdict> {
+    let overrides = ConfigOverrides {
+        watch_interval_secs: args.interval,
+        ..args.scan.overrides()
+    };
+    let (config, anticheat) = prepare(&args.scan, overrides)?;
+
+    let mut watcher = Watcher::new(anticheat);
+    watcher.apply_config(&config.watch);
+    watcher.max_scans = args.scans;
+    watcher.duration = args.duration.map(Duration::from_secs);
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let format = args.scan.format;
+    let result = runtime.block_on(async {
+        tokio::select! {
+            result = watcher.run(|events| {
+                let mut out = io::stdout().lock();
+                for event in events {
+                    // Events also go to the notifiers, a closed stdout is no reason to stop
+                    let _ = match format {
+                        Format::Json => writeln!(out, "{}", serde_json::to_string(event).unwrap_or_default()),
+                        Format::Text => writeln!(out, "{}", event),
+                    };
+                }
+            }) => result,
+            // Without --scans or --duration this is the usual way a watch ends
+            Ok(()) = tokio::signal::ctrl_c() => {
+                log::debug!("Interrupted, finishing the watch");
+                Ok(())
+            }
+        }
+    });
+
+    // Whatever the watch saw so far still gets reported, also when a rescan failed (e.g. the game was closed)
+    if watcher.scans() > 0 {
+        let report = watcher.final_report();
+        finish(&args.scan, watcher.anticheat_mut(), report, &runtime)?;
+    }
+    result?;
+    Ok(watcher.worst_verdict())
+}
+
+/// Records, prints, saves and delivers a report of the anticheat
+fn finish(args: &ScanArgs, anticheat: &mut Anticheat, mut report: ScanReport, runtime: &tokio::runtime::Runtime) -> anyhow::Result<()> {
+    // The report still counts without a comparison, a broken history doesn't stop it
+    if let Err(err) = anticheat.record_report(&mut report) {
+        log::warn!("Failed to record the scan history: {:#}", err);
+    }
+
+    // A closed stdout must not keep the report from being saved and delivered
+    if let Err(err) = print_report(&report, args.format) {
+        if !is_broken_pipe(&err) {
+            return Err(err);
+        }
+    }
+
+    if let Some(path) = &args.output {
+        fs::write(path, report.to_json()?)
+            .with_context(|| format!("Failed to write report to {}", path.display()))?;
+    }
+
+    // There is always an upload sink (tmpfiles unless configured otherwise), notifiers are optional
+    if !args.offline {
+        // The report is queued in the outbox when this fails, the verdict still stands. Without an
+        // outbox the report is lost, which the exit code has to show.
+        if let Err(err) = runtime.block_on(anticheat.send_report(&report)) {
+            if anticheat.outbox().is_none() {
+                return Err(err.context("Failed to deliver the report and it was not queued for a later run"));
+            }
+            log::error!("Failed to deliver the report: {:#}", err);
+        }
+    }
+
+    Ok(())
+}
+
+fn list_handles(args: ListHandlesArgs) -> anyhow::Result<()> {
+    let mut handle_manager = match &args.fixture {
+        Some(path) => HandleManager::from_fixture_file(path)?,
+        None => HandleManager::new()?,
+    };
+
+    handle_manager.filter_by_handle_type(SystemHandleType::Process);
+    if !args.all {
+        handle_manager
+            .filter_suspicious_handles()
+            .filter_anticheat_handles();
+    }
+    if let Some(target) = args.target {
+        handle_manager.filter_handles_to_target(target)?;
+    }
+    handle_manager.collect_handle_info()?;
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&handle_manager.handles)?)?,
+        Format::Text => {
+            writeln!(out, "{} handles", handle_manager.handles.len())?;
+            for handle in &handle_manager.handles {
+                writeln!(out, "{}", handle)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list_windows(args: ListWindowsArgs) -> anyhow::Result<()> {
+    let windows: Vec<WindowInfo> = match &args.fixture {
+        Some(path) => FixtureWindowSource::from_file(path)?.query_windows()?,
+        None => default_source()?.query_windows()?,
+    };
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&windows)?)?,
+        Format::Text => {
+            writeln!(out, "{} windows", windows.len())?;
+            for window in &windows {
+                writeln!(out, "{}", window)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn report(args: ReportArgs) -> anyhow::Result<Verdict> {
+    let contents = fs::read_to_string(&args.file)
+        .with_context(|| format!("Failed to read report {}", args.file.display()))?;
+    let report = ScanReport::from_json(&contents)
+        .with_context(|| format!("{} is not a scan report", args.file.display()))?;
+
+    print_report(&report, args.format)?;
+    Ok(report.assessment.verdict)
+}
+
+fn diff(args: DiffArgs) -> anyhow::Result<()> {
+    let history = args.history.open()?;
+
+    let (previous, current) = match (&args.file, &args.target) {
+        (Some(file), _) => {
+            let contents = fs::read_to_string(file)
+                .with_context(|| format!("Failed to read report {}", file.display()))?;
+            let current = ScanReport::from_json(&contents)
+                .with_context(|| format!("{} is not a scan report", file.display()))?;
+            (history.previous(&current)?, current)
+        }
+        (None, Some(target)) => {
+            let host = args.host.clone().unwrap_or_else(|| HostInfo::collect().hostname);
+            let mut reports = history.reports(&host, target)?;
+            let Some((_, current)) = reports.pop() else {
+                anyhow::bail!("No screenshare of {} on {} in {}", target, host, history.dir().display());
+            };
+            (reports.pop().map(|(_, previous)| previous), current)
+        }
+        (None, None) => anyhow::bail!("Pass a report file or --target"),
+    };
+
+    let Some(previous) = previous else {
+        anyhow::bail!(
+            "No earlier screenshare of {} on {} to compare with",
+            current.target.name, current.host.hostname,
+        );
+    };
+
+    let mut diff = ReportDiff::between(&previous, &current);
+    if !args.unchanged {
+        diff.unchanged.clear();
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&diff)?)?,
+        Format::Text => {
+            write!(out, "{}", diff)?;
+            for finding in &diff.unchanged {
+                writeln!(out, "  = {}", finding.description)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn history(args: HistoryArgs) -> anyhow::Result<()> {
+    let history = args.history.open()?;
+    let entries: Vec<_> = history.entries()?
+        .into_iter()
+        .filter(|entry| args.host.as_ref().is_none_or(|host| entry.host.eq_ignore_ascii_case(host)))
+        .filter(|entry| args.target.as_ref().is_none_or(|target| entry.target.eq_ignore_ascii_case(target)))
+        .collect();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&entries)?)?,
+        Format::Text => {
+            writeln!(out, "{} reports in {}", entries.len(), history.dir().display())?;
+            for entry in &entries {
+                writeln!(out, "{}  {}  {}  {} (score {})",
+                    entry.started_at.format("%Y-%m-%d %H:%M:%S UTC"), entry.host, entry.target, entry.verdict, entry.score)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn forensics(args: ForensicsArgs) -> anyhow::Result<()> {
+    let overrides = ConfigOverrides { lookback_hours: args.lookback_hours, ..Default::default() };
+    let mut config = Config::load_unchecked(args.config.as_deref(), overrides)?;
+    let forensics = &mut config.forensics;
+    forensics.enabled = true;
+    forensics.host_artifacts &= !args.artifacts_only;
+    forensics.shell_histories.extend(args.shell_histories);
+    forensics.directories.extend(args.directories);
+    forensics.prefetch_dirs.extend(args.prefetch_dirs);
+    forensics.amcache_files.extend(args.amcache_files);
+    config.validate_forensics()?;
+
+    let collector = config.forensics.collector().unwrap_or_else(ForensicsCollector::new);
+    let mut evidence = collector.collect();
+    if args.closed_only {
+        evidence.retain(|found| !found.running);
+    }
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&evidence)?)?,
+        Format::Text => {
+            writeln!(out, "{} recently executed programs", evidence.len())?;
+            for found in &evidence {
+                writeln!(out, "{}", found)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn prefetch(args: PrefetchArgs) -> anyhow::Result<()> {
+    let files = args.paths.iter()
+        .map(|path| PrefetchFile::from_file(path).with_context(|| format!("Failed to read prefetch file {}", path.display())))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&files)?)?,
+        Format::Text => {
+            for (path, file) in args.paths.iter().zip(&files) {
+                writeln!(out, "{} (version {}{})", path.display(), file.version, if file.compressed { ", compressed" } else { "" })?;
+                writeln!(out, "  Executable: {}", file.executable_path().unwrap_or(&file.executable))?;
+                writeln!(out, "  Path hash: {:08X}", file.hash)?;
+                writeln!(out, "  Run count: {}", file.run_count)?;
+                for run_time in &file.run_times {
+                    writeln!(out, "  Ran at: {}", run_time.format("%Y-%m-%d %H:%M:%S UTC"))?;
+                }
+                for volume in &file.volumes {
+                    writeln!(out, "  Volume: {}", volume)?;
+                }
+                writeln!(out, "  {} files loaded", file.files.len())?;
+                if args.files {
+                    for loaded in &file.files {
+                        writeln!(out, "    {}", loaded)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Stands in for the stdout sink when the report is already printed as JSON
+struct PrintedReport;
+
+impl ReportSink for PrintedReport {
+    fn describe(&self) -> String {
+        "stdout".to_string()
+    }
+
+    fn upload<'a>(&'a self, _file_name: &'a str, _contents: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(async { Ok("stdout".to_string()) })
+    }
+}
+
+fn print_report(report: &ScanReport, format: Format) -> anyhow::Result<()> {
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Json => writeln!(out, "{}", report.to_json()?)?,
+        Format::Text => write!(out, "{}", report)?,
+    }
+    Ok(())
+}
+
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>().is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
+}
@@ -0,0 +1,421 @@
+// Known-benign processes and overlays (Discord, Steam, OBS, NVIDIA, antivirus, ...).
+//
+// Findings that match an entry are moved out of the detections but kept, together with the reason
+// they were allowed, so the report still shows them.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::Path,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::core::{
+    crypto::hex,
+    handle::handle_context::HandleContext,
+    overlay::window_info::WindowInfo,
+    process::module::ModuleInfo,
+};
+
+const BUILTIN_ALLOWLIST: &str = include_str!("../../config/allowlist.toml");
+
+// Variables a path may start with, and their usual value. They are resolved through the shell's known
+// folders, never the environment: the player could point %ProgramFiles% at their own directory.
+const DIR_VARIABLES: &[(&str, &str)] = &[
+    ("SystemRoot", r"C:\Windows"),
+    ("ProgramFiles", r"C:\Program Files"),
+    ("ProgramFiles(x86)", r"C:\Program Files (x86)"),
+    ("ProgramData", r"C:\ProgramData"),
+];
+
+// Directories only administrators can write to. Renaming a cheat after an allowed program doesn't
+// get it in there.
+const TRUSTED_DIRS: &[&str] = &[
+    "%SystemRoot%",
+    "%ProgramFiles%",
+    "%ProgramFiles(x86)%",
+    r"%ProgramData%\Microsoft\Windows Defender",
+    "/usr",
+    "/opt",
+];
+
+/// A single allowed process or window. Every criterion that is set has to match.
+///
+/// Handles are matched on `image_path` and `sha256` (of the owning executable), modules on those of
+/// the module file, windows on `window_class`, `window_title` and `owner_image_path`. All comparisons
+/// ignore case. Paths have to be full paths below a trusted directory (which may be written as
+/// %SystemRoot%, %ProgramFiles%, ...), with `*` standing for one directory, unless `sha256` pins the
+/// executable. Then a bare file name matches anywhere.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AllowlistEntry {
+    pub name: String,
+    pub reason: String,
+    pub image_path: Option<String>,   // Full path, or just the file name if sha256 is set
+    pub sha256: Option<String>,       // Hex encoded hash of the executable
+    pub window_class: Option<String>,
+    pub window_title: Option<String>,
+    pub owner_image_path: Option<String>, // Executable of the window's process, required for window entries
+}
+
+/// A finding that was suppressed, and why
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Allowed<T> {
+    pub finding: T,
+    pub entry: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Allowlist {
+    #[serde(default, rename = "allow")]
+    pub entries: Vec<AllowlistEntry>,
+}
+
+impl AllowlistEntry {
+    fn is_window_entry(&self) -> bool {
+        self.window_class.is_some() || self.window_title.is_some()
+    }
+
+    fn is_image_entry(&self) -> bool {
+        self.image_path.is_some() || self.sha256.is_some()
+    }
+
+    fn matches_image_path(&self, path: &str) -> bool {
+        let Some(pattern) = &self.image_path else {
+            return true;
+        };
+
+        // Bare file name, compare against the last path component. Only the hash makes that safe.
+        if !pattern.contains(['\\', '/']) {
+            return self.sha256.is_some() && path.rsplit(['\\', '/'])
+                .next()
+                .is_some_and(|file_name| file_name.eq_ignore_ascii_case(pattern));
+        }
+
+        (self.sha256.is_some() || is_trusted_path(pattern)) && matches_path(pattern, path)
+    }
+
+    fn matches_window(&self, window: &WindowInfo) -> bool {
+        // Class and title are whatever the cheat sets, the owning executable is what counts
+        self.is_window_entry()
+            && self.owner_image_path.as_ref().is_some_and(|owner| {
+                is_trusted_path(owner) && matches_path(owner, &window.owner_image_path)
+            })
+            && self.window_class.as_ref().is_none_or(|class| class.eq_ignore_ascii_case(&window.class_name))
+            && self.window_title.as_ref().is_none_or(|title| title.eq_ignore_ascii_case(&window.title))
+    }
+
+    /// Why the entry can't be trusted to single out the program it names, empty if it can
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let Some(image_path) = self.image_path.as_ref().filter(|_| self.sha256.is_none()) {
+            if !is_trusted_path(image_path) {
+                problems.push(format!(
+                    "allowlist entry \"{}\": image_path {} has to be a full path below a system or program directory, or be pinned with sha256",
+                    self.name, image_path,
+                ));
+            }
+        }
+        if self.is_window_entry() {
+            match &self.owner_image_path {
+                Some(owner) if !is_trusted_path(owner) => problems.push(format!(
+                    "allowlist entry \"{}\": owner_image_path {} has to be a full path below a system or program directory",
+                    self.name, owner,
+                )),
+                Some(_) => {}
+                None => problems.push(format!("allowlist entry \"{}\": window entries need an owner_image_path", self.name)),
+            }
+        }
+        problems
+    }
+}
+
+/// Path with a leading %VARIABLE% replaced by its value on this machine
+pub(crate) fn expand(path: &str) -> String {
+    let Some((name, rest)) = path.strip_prefix('%').and_then(|path| path.split_once('%')) else {
+        return path.to_string();
+    };
+    match DIR_VARIABLES.iter().find(|(variable, _)| variable.eq_ignore_ascii_case(name)) {
+        Some((variable, default)) => format!("{}{}", known_folder(variable).unwrap_or_else(|| default.to_string()), rest),
+        None => path.to_string(),
+    }
+}
+
+/// Location of a DIR_VARIABLES folder as the shell knows it, independent of the environment
+#[cfg(windows)]
+fn known_folder(variable: &str) -> Option<String> {
+    use windows::Win32::{
+        System::Com::CoTaskMemFree,
+        UI::Shell::{
+            SHGetKnownFolderPath,
+            FOLDERID_ProgramData,
+            FOLDERID_ProgramFiles,
+            FOLDERID_ProgramFilesX86,
+            FOLDERID_Windows,
+            KF_FLAG_DEFAULT,
+        },
+    };
+
+    let folder = match variable {
+        "SystemRoot" => &FOLDERID_Windows,
+        "ProgramFiles" => &FOLDERID_ProgramFiles,
+        "ProgramFiles(x86)" => &FOLDERID_ProgramFilesX86,
+        "ProgramData" => &FOLDERID_ProgramData,
+        _ => return None,
+    };
+
+    unsafe {
+        let path = SHGetKnownFolderPath(folder, KF_FLAG_DEFAULT, None).ok()?;
+        let value = path.to_string().ok();
+        CoTaskMemFree(Some(path.as_ptr() as *const std::ffi::c_void));
+        value
+    }
+}
+
+#[cfg(not(windows))]
+fn known_folder(_variable: &str) -> Option<String> {
+    None
+}
+
+/// Whether a path pattern lies below one of the trusted directories
+fn is_trusted_path(pattern: &str) -> bool {
+    let pattern = expand(pattern);
+    !pattern.split(['\\', '/']).any(|component| component == "..")
+        && TRUSTED_DIRS.iter().any(|dir| {
+            let dir = expand(dir);
+            pattern.get(..dir.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(&dir))
+                && pattern[dir.len()..].starts_with(['\\', '/'])
+        })
+}
+
+/// Compares a full path pattern with a path, component by component. `*` matches one component.
+fn matches_path(pattern: &str, path: &str) -> bool {
+    let pattern = expand(pattern);
+    let pattern: Vec<&str> = pattern.split(['\\', '/']).collect();
+    let path: Vec<&str> = path.split(['\\', '/']).collect();
+    pattern.len() == path.len()
+        && pattern.iter().zip(&path).all(|(expected, actual)| *expected == "*" || expected.eq_ignore_ascii_case(actual))
+}
+
+/// Hex encoded SHA-256 of a file
+pub fn sha256_file(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hex(&hasher.finalize()))
+}
+
+impl Allowlist {
+    pub fn new(entries: Vec<AllowlistEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Common launchers, overlays and antivirus shipped with the tool
+    pub fn builtin() -> Self {
+        Self::from_toml(BUILTIN_ALLOWLIST).expect("Built-in allowlist is invalid")
+    }
+
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        let allowlist: Self = toml::from_str(contents)?;
+        allowlist.validate()?;
+        Ok(allowlist)
+    }
+
+    /// Fails with every entry that would allow more than the program it names
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let problems: Vec<String> = self.entries.iter().flat_map(AllowlistEntry::problems).collect();
+        if !problems.is_empty() {
+            anyhow::bail!("Invalid allowlist: {}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Loads entries from a .toml or .json file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read allowlist from {}", path.display()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let allowlist: Self = serde_json::from_str(&contents)?;
+                allowlist.validate()?;
+                Ok(allowlist)
+            }
+            Some("toml") => Self::from_toml(&contents),
+            _ => anyhow::bail!("Unsupported allowlist format: {}", path.display()),
+        }
+    }
+
+    pub fn extend(&mut self, other: Allowlist) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Finds the entry that allows a handle, based on its owner's image path and hash
+    pub fn match_handle(&self, handle: &HandleContext) -> Option<&AllowlistEntry> {
+        self.match_handle_cached(handle, &mut HashMap::new())
+    }
+
+    /// Same as match_handle, but hashes each image at most once across calls
+    pub(crate) fn match_handle_cached(
+        &self,
+        handle: &HandleContext,
+        hashes: &mut HashMap<String, Option<String>>,
+    ) -> Option<&AllowlistEntry> {
+        let (_, win32_path) = handle.paths()?;
+        if win32_path.is_empty() {
+            return None;
+        }
+
+        self.entries.iter()
+            .filter(|entry| entry.is_image_entry() && entry.matches_image_path(win32_path))
+            .find(|entry| {
+                let Some(expected) = &entry.sha256 else {
+                    return true;
+                };
+
+                let hash = hashes.entry(win32_path.clone())
+                    .or_insert_with(|| sha256_file(win32_path).ok());
+                hash.as_ref().is_some_and(|hash| hash.eq_ignore_ascii_case(expected))
+            })
+    }
+
+    /// Finds the entry that allows a window, based on its class and title
+    pub fn match_window(&self, window: &WindowInfo) -> Option<&AllowlistEntry> {
+        self.entries.iter().find(|entry| entry.matches_window(window))
+    }
+
+    pub fn match_module(&self, module: &ModuleInfo) -> Option<&AllowlistEntry> {
+        self.entries.iter()
+            .filter(|entry| entry.is_image_entry() && entry.matches_image_path(&module.path))
+            .find(|entry| entry.sha256.as_ref().is_none_or(|expected| {
+                module.sha256.as_ref().is_some_and(|hash| hash.eq_ignore_ascii_case(expected))
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::handle::{
+        SystemHandleEntry,
+        handle_info::HandleInfo,
+        PROCESS_ALL_ACCESS,
+    };
+    use crate::core::temp_dir::TempDir;
+
+    fn handle(path: &str) -> HandleContext {
+        HandleContext {
+            raw: SystemHandleEntry { process_id: 1001, granted_access: PROCESS_ALL_ACCESS, ..Default::default() },
+            info: Some(HandleInfo::from_paths(path, path, PROCESS_ALL_ACCESS)),
+        }
+    }
+
+    #[test]
+    fn builtin_allowlist_parses() {
+        let allowlist = Allowlist::builtin();
+        assert!(!allowlist.entries.is_empty());
+        assert!(allowlist.entries.iter().all(|entry| !entry.reason.is_empty()));
+    }
+
+    #[test]
+    fn matches_handles_by_full_path_below_trusted_dirs() {
+        let allowlist = Allowlist::builtin();
+
+        let steam = handle(r"C:\Program Files (x86)\Steam\steam.exe");
+        assert_eq!(allowlist.match_handle(&steam).unwrap().name, "Steam");
+
+        let defender = handle(r"c:\programdata\microsoft\windows defender\platform\4.18.24090.11-0\msmpeng.exe");
+        assert_eq!(allowlist.match_handle(&defender).unwrap().name, "Microsoft Defender");
+
+        // Same file names anywhere else
+        assert!(allowlist.match_handle(&handle(r"C:\Users\player\Downloads\steam.exe")).is_none());
+        assert!(allowlist.match_handle(&handle(r"C:\Users\player\Downloads\MsMpEng.exe")).is_none());
+        assert!(allowlist.match_handle(&handle(r"C:\ProgramData\Microsoft\Windows Defender\MsMpEng.exe")).is_none());
+        assert!(allowlist.match_handle(&handle("")).is_none());
+    }
+
+    #[test]
+    fn ignores_folder_variables_from_the_environment() {
+        let dir = TempDir::new("allowlist_env");
+        let fake = dir.path().to_string_lossy();
+        std::env::set_var("ProgramFiles(x86)", fake.as_ref());
+
+        let overlay = format!(r"{}\Steam\gameoverlayui64.exe", fake);
+        let trusted = is_trusted_path(&overlay);
+        let matched = matches_path(r"%ProgramFiles(x86)%\Steam\gameoverlayui64.exe", &overlay);
+        let handle = Allowlist::builtin().match_handle(&handle(&overlay)).is_some();
+        std::env::remove_var("ProgramFiles(x86)");
+
+        assert!(!trusted);
+        assert!(!matched);
+        assert!(!handle);
+    }
+
+    #[test]
+    fn rejects_entries_that_match_anywhere() {
+        let bare = "[[allow]]\nname = \"Discord\"\nimage_path = \"Discord.exe\"";
+        assert!(Allowlist::from_toml(bare).unwrap_err().to_string().contains("pinned with sha256"));
+
+        let user_dir = "[[allow]]\nname = \"Tool\"\nimage_path = 'C:\\Users\\player\\tool.exe'";
+        assert!(Allowlist::from_toml(user_dir).is_err());
+        let escaping = "[[allow]]\nname = \"Tool\"\nimage_path = '%ProgramFiles%\\..\\Users\\player\\tool.exe'";
+        assert!(Allowlist::from_toml(escaping).is_err());
+
+        let window = "[[allow]]\nname = \"Overlay\"\nwindow_class = \"CEF-OSC-WIDGET\"";
+        assert!(Allowlist::from_toml(window).unwrap_err().to_string().contains("need an owner_image_path"));
+
+        let pinned = "[[allow]]\nname = \"Discord\"\nimage_path = \"Discord.exe\"\nsha256 = \"00\"";
+        assert!(Allowlist::from_toml(pinned).is_ok());
+
+        // Built in code instead of loaded, still not matched by name alone
+        let allowlist = Allowlist::new(vec![AllowlistEntry {
+            name: "Discord".into(),
+            image_path: Some("Discord.exe".into()),
+            ..Default::default()
+        }]);
+        assert!(allowlist.match_handle(&handle(r"C:\Users\player\Downloads\Discord.exe")).is_none());
+    }
+
+    #[test]
+    fn matches_handles_by_hash() {
+        let dir = TempDir::new("allowlist_hash");
+        let path = dir.join("pinned.bin");
+        fs::write(&path, b"not a cheat").unwrap();
+        let path_string = path.to_string_lossy().to_string();
+
+        let hash = sha256_file(&path).unwrap();
+        let allowlist = Allowlist::new(vec![AllowlistEntry {
+            name: "Pinned".into(),
+            sha256: Some(hash.to_uppercase()),
+            ..Default::default()
+        }]);
+        assert!(allowlist.match_handle(&handle(&path_string)).is_some());
+
+        fs::write(&path, b"now it is").unwrap();
+        assert!(allowlist.match_handle(&handle(&path_string)).is_none());
+    }
+
+    #[test]
+    fn matches_windows_by_owner_class_and_title() {
+        let allowlist = Allowlist::builtin();
+        let nvidia = WindowInfo {
+            class_name: "CEF-OSC-WIDGET".into(),
+            title: "NVIDIA GeForce Overlay".into(),
+            owner_image_path: r"C:\Program Files\NVIDIA Corporation\NVIDIA app\CEF\NVIDIA Overlay.exe".into(),
+            ..Default::default()
+        };
+        assert_eq!(allowlist.match_window(&nvidia).unwrap().name, "NVIDIA App overlay window");
+
+        let unknown = WindowInfo { title: "Totally Legit".into(), ..nvidia.clone() };
+        assert!(allowlist.match_window(&unknown).is_none());
+
+        // Class and title copied by an overlay hijacking cheat
+        let hijack = WindowInfo { owner_image_path: r"C:\Users\player\Downloads\loader.exe".into(), ..nvidia };
+        assert!(allowlist.match_window(&hijack).is_none());
+    }
+}
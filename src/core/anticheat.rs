@@ -1,17 +1,10 @@
-use webhook::{
-    client::{
-        WebhookClient,
-        WebhookResult,
-    },
-    models::NonLinkButtonStyle,
-};
 use std::{
     env,
     fmt,
     fs,
-    any::Any,
-    process::Command,
 };
+#[cfg(target_os = "windows")]
+use std::process::Command;
 use crate::core::{
     handle::{
         handle_context::HandleContext,
@@ -23,103 +16,547 @@ use crate::core::{
         window_info::WindowInfo,
     },
     process::{
+        injection::{find_injected_code, InjectedRegion},
+        integrity::{self, PatchedRange},
+        module::{FileHashCache, ModuleInfo},
         Process
     },
-    uploading::upload_string_to_tmpfile,
+    profile::GameProfile,
+    report::{AllowedFindings, HostInfo, ScanReport, TargetInfo},
+    scoring::{Assessment, ScoringConfig, Verdict},
+    allowlist::{Allowed, Allowlist},
+    config::Config,
+    forensics::{collector::ForensicsCollector, execution_evidence::ExecutionEvidence},
+    history::{report_diff::ReportDiff, scan_history::ScanHistory},
+    notify::{
+        notification::{Notification, NotificationField},
+        notifier::Notifier,
+    },
+    uploading::{
+        http_sink::HttpSink,
+        outbox::{Outbox, PendingReport},
+        report_sink::ReportSink,
+        retry::RetryPolicy,
+    },
 };
-use anyhow::Result;
-use dotenvy_macro::dotenv;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+#[cfg(target_os = "linux")]
+use crate::core::handle::ptrace_detector::PtraceDetector;
 
-// Horribly awfully boof method but that's what this boof library calls for.
-const RED: &str = "15548997";
-const GREEN: &str = "5763719";
-
-pub struct Anticheat<'a> {
+pub struct Anticheat {
     process: Process,
     handle_manager: HandleManager,
+    #[cfg(target_os = "linux")]
+    ptrace_detector: PtraceDetector,
     overlay_finder: OverlayFinder,
+    scoring: ScoringConfig,
+    allowlist: Allowlist,
+    report_sink: Box<dyn ReportSink>,
+    notifiers: Vec<Box<dyn Notifier>>,
+    retry: RetryPolicy,
+    outbox: Option<Outbox>,            // Reports that failed to go out wait here for the next run
+    profile: Option<GameProfile>,
+    loaded_modules: Vec<ModuleInfo>,   // Every module of the last run
+    file_hashes: FileHashCache,        // Module hashes kept across runs, for rescans in watch mode
+    modules: Vec<ModuleInfo>,          // Loaded modules the profile doesn't expect
+    allowed_modules: Vec<Allowed<ModuleInfo>>,
+    injected: Vec<InjectedRegion>,     // Executable memory outside of any module, or writable code
+    allowed_memory: Vec<Allowed<InjectedRegion>>,
+    patched: Vec<PatchedRange>,        // Code of the game's modules that differs from disk
     handle_detections: usize,
     overlay_detections: usize,
-    past_processes: usize, // TODO! Implement this later!
-    pub webhook_url: &'a str
+    module_detections: usize,
+    injection_detections: usize,
+    patch_detections: usize,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    history: Option<ScanHistory>,      // Reports of past screenshares, to compare this one with
+    changes: Option<ReportDiff>,       // Since the previous screenshare, once the run is recorded
+    first_screenshare: bool,           // Recorded, but the history had nothing to compare with
+    forensics: Option<ForensicsCollector>,
+    executions: Vec<ExecutionEvidence>, // Programs that ran shortly before the first run
 }
 
-impl Anticheat<'_> {
-    pub fn new(process: Process) -> Self {
+impl Anticheat {
+    /// Finds the game described by the profile and applies its rules and allowlist
+    pub fn new(profile: GameProfile) -> anyhow::Result<Self> {
+        let process = profile.open_process()
+            .with_context(|| format!("Failed to find a running {}", profile.name))?;
+
+        let mut anticheat = Self::from_process(process)?;
+        anticheat.apply_profile(profile);
+        Ok(anticheat)
+    }
+
+    /// Scans an already opened process without a game profile, recording scans in the default history
+    /// and collecting this machine's execution artifacts
+    pub fn from_process(process: Process) -> anyhow::Result<Self> {
+        let mut anticheat = Self::with_handle_manager(process, HandleManager::new()?);
+        anticheat
+            .with_history(Some(ScanHistory::new(ScanHistory::default_dir())))
+            .with_forensics(Some(ForensicsCollector::for_host()));
+        Ok(anticheat)
+    }
+
+    /// Scans a prebuilt handle table (e.g. from fixtures) instead of a live system snapshot. Nothing
+    /// of the host is read or written besides the process: there is no history and no forensics.
+    pub fn with_handle_manager(process: Process, handle_manager: HandleManager) -> Self {
+        let mut overlay_finder = OverlayFinder::new();
+        overlay_finder.with_target_pid(process.pid);
+
         Self {
             process,
-            handle_manager: HandleManager::new().expect("Failed to initialize handle manager!"),
-            overlay_finder: OverlayFinder::new(),
+            handle_manager,
+            #[cfg(target_os = "linux")]
+            ptrace_detector: PtraceDetector::new(),
+            overlay_finder,
+            scoring: ScoringConfig::default(),
+            allowlist: Allowlist::builtin(),
+            report_sink: Box::new(HttpSink::tmpfiles()),
+            notifiers: Vec::new(),
+            retry: RetryPolicy::default(),
+            outbox: None,
+            profile: None,
+            loaded_modules: Vec::new(),
+            file_hashes: FileHashCache::new(),
+            modules: Vec::new(),
+            allowed_modules: Vec::new(),
+            injected: Vec::new(),
+            allowed_memory: Vec::new(),
+            patched: Vec::new(),
             handle_detections: 0,
             overlay_detections: 0,
-            past_processes: 0,
-            webhook_url: "No webhook url parsed!",
+            module_detections: 0,
+            injection_detections: 0,
+            patch_detections: 0,
+            started_at: None,
+            finished_at: None,
+            history: None,
+            changes: None,
+            first_screenshare: false,
+            forensics: None,
+            executions: Vec::new(),
         }
     }
 
-    /// Updates fields within struct after proper filtering has been completed
+    /// Adds the profile's overlay rules and allowlist entries on top of the built-in ones
+    pub fn apply_profile(&mut self, profile: GameProfile) -> &mut Self {
+        self.overlay_finder.with_rules(profile.rule_set());
+        self.allowlist.extend(profile.allowlist());
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Applies the rule and allowlist files, scoring, upload sink, notifiers and delivery settings of a
+    /// runtime configuration. The game profile is left to the caller, it decides which process to open.
+    pub fn apply_config(&mut self, config: &Config) -> anyhow::Result<&mut Self> {
+        self.overlay_finder.with_rules(config.rule_set()?);
+        self.allowlist.extend(config.allowlist()?);
+        self.scoring = config.scoring.clone();
+        self.report_sink = config.upload.build();
+        self.notifiers = config.notifiers()?;
+        self.retry = config.delivery.retry_policy();
+        self.outbox = config.delivery.outbox();
+        self.history = config.history.store();
+        self.forensics = config.forensics.collector();
+        Ok(self)
+    }
+
+    /// Updates fields within struct after proper filtering has been completed. Every run after the
+    /// first takes a new handle snapshot, so the anticheat can be run repeatedly.
     pub fn run(&mut self) -> anyhow::Result<()> {
+        match self.started_at {
+            Some(_) => {
+                self.handle_manager.refresh()?;
+            }
+            // What ran before the screenshare doesn't change between runs
+            None => self.collect_forensics(),
+        }
+        self.started_at = Some(Utc::now());
+        self.changes = None;
+        self.first_screenshare = false;
+
         // Run handle scanning
         log::debug!("Filtering possibly malicious handles for process: {} (PID: {})", self.process.name, self.process.pid);
-        // Filters handles and assigns amount of detections
-        self.handle_detections = self.handle_manager
+        // Filters handles
+        self.handle_manager
             .filter_by_handle_type(SystemHandleType::Process)
             .filter_suspicious_handles()
             .filter_anticheat_handles()
             .filter_handles_to_target(self.process.pid)?
-            .collect_handle_info()?
+            .collect_handle_info()?;
+
+        // On Linux, processes can also reach into the target through ptrace
+        #[cfg(target_os = "linux")]
+        self.merge_ptrace_handles()?;
+
+        // Set aside known-benign handles and assign amount of detections
+        self.handle_detections = self.handle_manager
+            .filter_allowlisted(&self.allowlist)
             .get_handles()
             .len();
 
         log::debug!("Done ({} handles)! Handles for process {}...", self.handle_manager.handles.len(), self.process.name);
 
         // Run overlay scanning
-        let overlays = &mut self.overlay_finder.find();
-        self.overlay_detections = overlays.len();
+        self.overlay_finder.find();
+        self.overlay_detections = self.overlay_finder
+            .filter_allowlisted(&self.allowlist)
+            .overlays
+            .len();
 
         log::debug!("Found {} suspicious overlays (not all suspicious overlays are malicious!)", self.overlay_detections);
+
+        // Compare the loaded modules against the ones the game profile expects and their files on disk
+        let modules = match self.process.modules_cached(&mut self.file_hashes) {
+            Ok(modules) => modules,
+            Err(err) => {
+                log::warn!("Failed to list modules of {} (PID: {}): {}", self.process.name, self.process.pid, err);
+                Vec::new()
+            }
+        };
+        self.scan_modules(&modules);
+        self.scan_integrity(&modules);
+
+        // Look for code that was injected without loading a module
+        self.scan_memory(&modules);
+        self.loaded_modules = modules;
+
+        self.finished_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Looks for programs that ran recently, including ones that were closed before the scan
+    fn collect_forensics(&mut self) {
+        let Some(forensics) = &self.forensics else {
+            return;
+        };
+
+        self.executions = forensics.collect();
+        log::debug!(
+            "Found {} recently executed programs, {} of them no longer running",
+            self.executions.len(),
+            self.executions.iter().filter(|evidence| !evidence.running).count(),
+        );
+    }
+
+    /// Collects modules the profile doesn't expect, setting aside allowlisted ones. Without a profile
+    /// that lists modules there is nothing to compare against.
+    fn scan_modules(&mut self, modules: &[ModuleInfo]) {
+        self.modules.clear();
+        self.allowed_modules.clear();
+        self.module_detections = 0;
+
+        let Some(profile) = self.profile.as_ref().filter(|profile| profile.checks_modules()) else {
+            return;
+        };
+
+        for module in profile.unexpected_modules(modules, self.process.main_module(modules)) {
+            match self.allowlist.match_module(&module) {
+                Some(entry) => {
+                    log::debug!("Allowing module {}: {}", module.path, entry.reason);
+                    self.allowed_modules.push(Allowed {
+                        finding: module,
+                        entry: entry.name.clone(),
+                        reason: entry.reason.clone(),
+                    });
+                }
+                None => self.modules.push(module),
+            }
+        }
+
+        self.module_detections = self.modules.len();
+        log::debug!("Found {} unexpected modules out of {}", self.module_detections, modules.len());
+    }
+
+    /// Compares the code of the game's own modules against their files on disk. Other modules are left
+    /// alone, overlays and antivirus routinely hook system libraries.
+    fn scan_integrity(&mut self, modules: &[ModuleInfo]) {
+        self.patched.clear();
+
+        let verified = modules.iter().filter(|module| match &self.profile {
+            Some(profile) if !profile.verify_modules.is_empty() => {
+                profile.verify_modules.iter().any(|name| name.eq_ignore_ascii_case(&module.name))
+            }
+            _ => module.name.eq_ignore_ascii_case(&self.process.name),
+        });
+
+        for module in verified {
+            match integrity::check_module(self.process.memory(), module) {
+                Ok(patched) => self.patched.extend(patched),
+                Err(err) => log::warn!("Failed to verify the code of {}: {}", module.name, err),
+            }
+        }
+
+        self.patch_detections = self.patched.len();
+        log::debug!("Found {} patched code ranges", self.patch_detections);
+    }
+
+    /// Collects executable memory that isn't backed by a module or is writable as well. Only games
+    /// with a profile are scanned, anything else may generate code at runtime. Regions inside an
+    /// allowlisted module (packed overlay DLLs, hook trampolines) are set aside.
+    fn scan_memory(&mut self, modules: &[ModuleInfo]) {
+        self.injected.clear();
+        self.allowed_memory.clear();
+        self.injection_detections = 0;
+
+        let Some(profile) = &self.profile else {
+            log::debug!("Skipping injected code scan, there is no game profile to tell whether the target generates code");
+            return;
+        };
+        if profile.jit_code {
+            log::debug!("Skipping injected code scan, the game profile expects JIT generated code");
+            return;
+        }
+
+        let regions = match self.process.memory_regions() {
+            Ok(regions) => find_injected_code(&regions),
+            Err(err) => {
+                log::warn!("Failed to query memory of {} (PID: {}): {}", self.process.name, self.process.pid, err);
+                Vec::new()
+            }
+        };
+
+        for region in regions {
+            let owner = modules.iter().find(|module| {
+                module.path == region.path || (module.base <= region.start && region.start < module.base.saturating_add(module.size))
+            });
+
+            match owner.and_then(|module| Some((module, self.allowlist.match_module(module)?))) {
+                Some((module, entry)) => {
+                    log::debug!("Allowing executable memory at 0x{:X} in {}: {}", region.start, module.path, entry.reason);
+                    self.allowed_memory.push(Allowed {
+                        finding: region,
+                        entry: entry.name.clone(),
+                        reason: entry.reason.clone(),
+                    });
+                }
+                None => self.injected.push(region),
+            }
+        }
+
+        self.injection_detections = self.injected.len();
+        log::debug!("Found {} suspicious executable regions", self.injection_detections);
+    }
+
+    /// Adds ptrace attachments and /proc/<pid>/mem holders that the handle chain did not already find
+    #[cfg(target_os = "linux")]
+    fn merge_ptrace_handles(&mut self) -> anyhow::Result<()> {
+        let anticheat_pid = std::process::id();
+
+        let ptrace_handles = self.ptrace_detector
+            .scan(self.process.pid)?
+            .collect_handle_info()
+            .get_handles();
+
+        for handle in ptrace_handles {
+            let already_found = self.handle_manager.handles.iter().any(|found| {
+                found.raw.process_id == handle.raw.process_id
+                    && found.raw.handle_value == handle.raw.handle_value
+                    && found.raw.granted_access == handle.raw.granted_access
+            });
+
+            if handle.raw.process_id != anticheat_pid && !already_found {
+                self.handle_manager.handles.push(handle);
+            }
+        }
+
         Ok(())
     }
 
     pub fn has_detections(&self) -> bool {
-        self.handle_detections > 0 || self.overlay_detections > 0
+        self.handle_detections > 0
+            || self.overlay_detections > 0
+            || self.module_detections > 0
+            || self.injection_detections > 0
+            || self.patch_detections > 0
+    }
+
+    /// Scores every finding of the last run and weighs them into a verdict
+    pub fn assessment(&self) -> Assessment {
+        self.scoring.assess(&self.handle_manager.handles, &self.overlay_finder.overlays, &self.modules, &self.injected, &self.patched)
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        self.assessment().verdict
+    }
+
+    /// Structured results of the last run
+    pub fn report(&self) -> ScanReport {
+        let now = Utc::now();
+
+        ScanReport {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: self.started_at.unwrap_or(now),
+            finished_at: self.finished_at.unwrap_or(now),
+            host: HostInfo::collect(),
+            target: TargetInfo {
+                name: self.process.name.clone(),
+                pid: self.process.pid,
+                profile: self.profile.as_ref().map(|profile| profile.name.clone()),
+            },
+            handles: self.handle_manager.handles.clone(),
+            overlays: self.overlay_finder.overlays.clone(),
+            modules: self.modules.clone(),
+            injected: self.injected.clone(),
+            patched: self.patched.clone(),
+            allowed: AllowedFindings {
+                handles: self.handle_manager.allowed.clone(),
+                overlays: self.overlay_finder.allowed.clone(),
+                modules: self.allowed_modules.clone(),
+                memory: self.allowed_memory.clone(),
+            },
+            assessment: self.assessment(),
+            changes: self.changes.clone(),
+            first_screenshare: self.first_screenshare,
+            executions: self.executions.clone(),
+            watch: None,
+        }
     }
 
-    /// Update
-    pub fn parse_webhook_url(&mut self) {
-        let url = dotenv!("WEBHOOK_URL");
-        self.webhook_url = url;
+    /// Compares the report of the last run with the previous screenshare of the same machine and
+    /// stores it in the history. Returns the changes, None without a history or previous report.
+    pub fn record_history(&mut self) -> anyhow::Result<Option<ReportDiff>> {
+        let mut report = self.report();
+        self.record_report(&mut report)
+    }
+
+    /// Like record_history, for a report put together elsewhere (e.g. the worst scan of a watch).
+    /// Fills in the report's changes, or marks it as the first screenshare on record.
+    pub fn record_report(&mut self, report: &mut ScanReport) -> anyhow::Result<Option<ReportDiff>> {
+        let Some(history) = &self.history else {
+            return Ok(None);
+        };
+
+        self.changes = history.diff(report)?;
+        self.first_screenshare = self.changes.is_none();
+        report.changes = self.changes.clone();
+        report.first_screenshare = self.first_screenshare;
+        history.record(report)?;
+        Ok(self.changes.clone())
     }
 
-    pub async fn send_webhook(&self) -> WebhookResult<bool> {
-        let client = WebhookClient::new(self.webhook_url);
+    /// Delivers the report of the last run to the report sink, returns where it ended up
+    pub async fn upload_report(&self) -> anyhow::Result<String> {
+        let report = self.report();
+        let file_name = report.file_name();
+        let contents = report.to_json()?;
 
-        let (description, color) = if self.has_detections() {
-            ("Found suspicious activity", "15548997")
+        log::debug!("Uploading report to {}...", self.report_sink.describe());
+        self.retry.run("Report upload", || self.report_sink.upload(&file_name, contents.as_bytes())).await
+    }
+
+    /// Uploads the report of the last run and announces it to the notifiers, retrying both with backoff.
+    /// Reports queued by earlier runs go out first. If delivery still fails, the report is queued in
+    /// the outbox (when one is set) and the error is returned.
+    pub async fn send_webhook(&self) -> anyhow::Result<()> {
+        self.send_report(&self.report()).await
+    }
+
+    /// Like send_webhook, for a report put together elsewhere (e.g. the worst scan of a watch)
+    pub async fn send_report(&self, report: &ScanReport) -> anyhow::Result<()> {
+        self.flush_outbox().await;
+
+        let mut pending = PendingReport::new(report)?;
+        match self.deliver(&mut pending).await {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                self.queue(&mut pending, &err);
+                Err(err)
+            }
         }
-        else {
-            ("Did not find suspicious activity.", "5763719")
+    }
+
+    /// Retries delivery of reports queued by earlier runs, oldest first. Stops at the first one that
+    /// fails again, the rest would most likely fail the same way. Returns how many went out.
+    pub async fn flush_outbox(&self) -> usize {
+        let Some(outbox) = &self.outbox else {
+            return 0;
         };
 
-        let all_scan_results_url = upload_string_to_tmpfile(
-            format!("{}", self),
-            &format!("temp_scan_results_{}.txt", std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs())
-        ).await.expect("Failed to upload file!");
+        let pending = match outbox.pending() {
+            Ok(pending) => pending,
+            Err(err) => {
+                log::warn!("Failed to read the outbox at {}: {}", outbox.dir().display(), err);
+                return 0;
+            }
+        };
 
-        log::debug!("Sending webhook...");
+        let mut delivered = 0;
+        for (path, mut report) in pending {
+            log::debug!("Delivering queued report {}...", report.file_name);
+            match self.deliver(&mut report).await {
+                Ok(()) => {
+                    delivered += 1;
+                    if let Err(err) = outbox.remove(&path) {
+                        log::warn!("{}", err);
+                    }
+                }
+                Err(err) => {
+                    self.queue(&mut report, &err);
+                    break;
+                }
+            }
+        }
 
-        client.send(|message| message
-            .username("Anticheat Bot")
-            .embed(|embed| embed
-                .title("Scan results")
-                .description(description)
-                .color(color)
-                .footer("Made by wakeland", None)
-                .field("All scan results", all_scan_results_url.as_str(), false)
-            )).await
+        delivered
+    }
+
+    /// Uploads the report unless an earlier attempt already did, then notifies every notifier that
+    /// didn't receive it yet
+    async fn deliver(&self, pending: &mut PendingReport) -> anyhow::Result<()> {
+        pending.attempts += 1;
+
+        let uploaded_url = match &pending.uploaded_url {
+            Some(url) => url.clone(),
+            None => {
+                log::debug!("Uploading report to {}...", self.report_sink.describe());
+                let url = self.retry.run("Report upload", || {
+                    self.report_sink.upload(&pending.file_name, pending.report.as_bytes())
+                }).await?;
+                pending.uploaded_url = Some(url.clone());
+                url
+            }
+        };
+
+        let mut notification = Notification::from_report(&pending.scan_report()?, Some(&uploaded_url));
+        // Queued by an earlier run, the findings are as old as that
+        if pending.attempts > 1 {
+            notification.fields.push(NotificationField::new("Queued at", pending.queued_at.format("%Y-%m-%d %H:%M:%S UTC")));
+        }
+        let mut failed = Vec::new();
+        for (index, notifier) in self.notifiers.iter().enumerate() {
+            // Position and description, so a retry in a later run with the same configuration skips it
+            let key = format!("{}#{}", index, notifier.describe());
+            if pending.notified.contains(&key) {
+                continue;
+            }
+
+            log::debug!("Sending {}...", notifier.describe());
+            match self.retry.run(&notifier.describe(), || notifier.notify(&notification)).await {
+                Ok(()) => pending.notified.push(key),
+                Err(err) => failed.push(format!("{:#}", err)),
+            }
+        }
+
+        if !failed.is_empty() {
+            anyhow::bail!("{} of {} notifiers failed: {}", failed.len(), self.notifiers.len(), failed.join("; "));
+        }
+
+        Ok(())
+    }
+
+    fn queue(&self, pending: &mut PendingReport, err: &anyhow::Error) {
+        pending.last_error = format!("{:#}", err);
+
+        match &self.outbox {
+            Some(outbox) => match outbox.push(pending) {
+                Ok(path) => log::warn!("Failed to deliver report, queued it in {}: {:#}", path.display(), err),
+                Err(queue_err) => log::error!("Failed to deliver report ({:#}) and to queue it: {:#}", err, queue_err),
+            },
+            None => log::error!("Failed to deliver report: {:#}", err),
+        }
     }
 
     /// Deletes the exe which the anticheat was run from
@@ -163,20 +600,85 @@ impl Anticheat<'_> {
     /// Builder for overlay finder
     pub fn overlay_finder_mut(&mut self) -> &mut OverlayFinder { &mut self.overlay_finder }
 
+    /// Weights and thresholds used for the verdict
+    pub fn scoring_mut(&mut self) -> &mut ScoringConfig { &mut self.scoring }
+
+    /// Known-benign processes and overlays (starts out with the built-in entries)
+    pub fn allowlist_mut(&mut self) -> &mut Allowlist { &mut self.allowlist }
+
+    /// Where reports are delivered (tmpfiles.org unless changed)
+    pub fn with_report_sink(&mut self, sink: impl ReportSink + 'static) -> &mut Self {
+        self.report_sink = Box::new(sink);
+        self
+    }
+
+    /// Adds a destination that is told about every delivered report
+    pub fn with_notifier(&mut self, notifier: impl Notifier + 'static) -> &mut Self {
+        self.notifiers.push(Box::new(notifier));
+        self
+    }
+
+    pub fn notifiers_mut(&mut self) -> &mut Vec<Box<dyn Notifier>> { &mut self.notifiers }
+
+    /// Timeouts and backoff for uploads and webhooks
+    pub fn retry_mut(&mut self) -> &mut RetryPolicy { &mut self.retry }
+
+    /// Where undeliverable reports are queued, None to drop them instead
+    pub fn with_outbox(&mut self, outbox: Option<Outbox>) -> &mut Self {
+        self.outbox = outbox;
+        self
+    }
+
+    /// Where evidence of recently executed programs is looked for, None to skip it
+    pub fn with_forensics(&mut self, forensics: Option<ForensicsCollector>) -> &mut Self {
+        self.forensics = forensics;
+        self
+    }
+
+    /// Where reports are kept to compare the next screenshare with, None to keep none
+    pub fn with_history(&mut self, history: Option<ScanHistory>) -> &mut Self {
+        self.history = history;
+        self
+    }
+
     // GETTERS -------------------------------------------------------------------------------------
     pub fn process(&self) -> &Process { &self.process }
     pub fn handle_manager(&self) -> &HandleManager { &self.handle_manager }
     pub fn handles(&self) -> &Vec<HandleContext> { &self.handle_manager.handles }
     pub fn handle_detections(&self) -> usize { self.handle_detections }
+    #[cfg(target_os = "linux")]
+    pub fn ptrace_detector(&self) -> &PtraceDetector { &self.ptrace_detector }
     pub fn overlay_finder(&self) -> &OverlayFinder { &self.overlay_finder }
     pub fn overlays(&self) -> &Vec<WindowInfo> { &self.overlay_finder.overlays }
     pub fn overlay_detections(&self) -> usize { self.overlay_detections }
+    pub fn loaded_modules(&self) -> &Vec<ModuleInfo> { &self.loaded_modules }
+    pub fn modules(&self) -> &Vec<ModuleInfo> { &self.modules }
+    pub fn module_detections(&self) -> usize { self.module_detections }
+    pub fn injected(&self) -> &Vec<InjectedRegion> { &self.injected }
+    pub fn injection_detections(&self) -> usize { self.injection_detections }
+    pub fn patched(&self) -> &Vec<PatchedRange> { &self.patched }
+    pub fn patch_detections(&self) -> usize { self.patch_detections }
+    pub fn scoring(&self) -> &ScoringConfig { &self.scoring }
+    pub fn allowlist(&self) -> &Allowlist { &self.allowlist }
+    pub fn report_sink(&self) -> &dyn ReportSink { self.report_sink.as_ref() }
+    pub fn notifiers(&self) -> &[Box<dyn Notifier>] { &self.notifiers }
+    pub fn retry(&self) -> &RetryPolicy { &self.retry }
+    pub fn outbox(&self) -> Option<&Outbox> { self.outbox.as_ref() }
+    pub fn history(&self) -> Option<&ScanHistory> { self.history.as_ref() }
+    pub fn forensics(&self) -> Option<&ForensicsCollector> { self.forensics.as_ref() }
+    pub fn executions(&self) -> &Vec<ExecutionEvidence> { &self.executions }
+    /// Changes since the previous screenshare, set by record_history
+    pub fn changes(&self) -> Option<&ReportDiff> { self.changes.as_ref() }
+    pub fn profile(&self) -> Option<&GameProfile> { self.profile.as_ref() }
 }
 
-impl fmt::Display for Anticheat<'_> {
+impl fmt::Display for Anticheat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let assessment = self.assessment();
+        writeln!(f, "Verdict: {} (score {})", assessment.verdict, assessment.total_score)?;
+
         if !self.has_detections() {
-            return writeln!(f, "No suspicious handles or overlays detected.");
+            return writeln!(f, "No suspicious handles, overlays, modules, memory or patched code detected.");
         }
 
         writeln!(f, "{} suspicious handles found.", self.handle_detections)?;
@@ -193,6 +695,359 @@ impl fmt::Display for Anticheat<'_> {
             }
         }
 
+        writeln!(f, "{} unexpected modules found.", self.module_detections)?;
+        for module in &self.modules {
+            writeln!(f, "  {}", module)?;
+        }
+
+        writeln!(f, "{} injected code regions found.", self.injection_detections)?;
+        for region in &self.injected {
+            writeln!(f, "  {}", region)?;
+        }
+
+        writeln!(f, "{} patched code ranges found.", self.patch_detections)?;
+        for range in &self.patched {
+            writeln!(f, "  {}", range)?;
+        }
+
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::allowlist::AllowlistEntry;
+    use crate::core::temp_dir::TempDir;
+    use crate::core::config::{ConfigOverrides, NotifierConfig, NotifierKind, SinkConfig};
+    use crate::core::notify::{
+        discord_notifier::DiscordNotifier,
+        slack_notifier::SlackNotifier,
+        teams_notifier::TeamsNotifier,
+    };
+    use crate::core::test_server::{StubResponse, TestServer};
+    use crate::core::uploading::directory_sink::DirectorySink;
+    use crate::core::handle::{
+        SystemHandleEntry,
+        PROCESS_ALL_ACCESS,
+        PROCESS_QUERY_INFORMATION,
+        PROCESS_VM_READ,
+        PROCESS_VM_WRITE,
+    };
+
+    fn process_handle(owner: u32, target: u32, access: u32) -> SystemHandleEntry {
+        SystemHandleEntry {
+            process_id: owner,
+            handle_value: 0x40,
+            object_type_index: SystemHandleType::Process as u8,
+            granted_access: access,
+            target_pid: Some(target),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn run_counts_suspicious_handles_to_target() {
+        // Target ourselves so the Linux ptrace scan has a live process to look at
+        let game_pid = std::process::id();
+        let handle_manager = HandleManager::from_entries([
+            process_handle(1001, game_pid, PROCESS_ALL_ACCESS),
+            process_handle(1002, game_pid, PROCESS_VM_READ | PROCESS_VM_WRITE),
+            process_handle(1003, game_pid, PROCESS_QUERY_INFORMATION),
+            process_handle(1004, 5555, PROCESS_ALL_ACCESS),
+        ]);
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), handle_manager);
+        anticheat.run().unwrap();
+
+        assert_eq!(anticheat.handle_detections(), 2);
+        assert!(anticheat.has_detections());
+        // Owners of fixture handles can't be resolved, which only adds to the score
+        assert_eq!(anticheat.verdict(), Verdict::LikelyCheating);
+    }
+
+    #[test]
+    fn report_round_trips_through_json() {
+        let game_pid = std::process::id();
+        let handle_manager = HandleManager::from_entries([
+            process_handle(1001, game_pid, PROCESS_ALL_ACCESS),
+        ]);
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), handle_manager);
+        anticheat.run().unwrap();
+
+        let report = anticheat.report();
+        assert_eq!(report.tool_version, env!("CARGO_PKG_VERSION"));
+        assert!(report.started_at <= report.finished_at);
+
+        let parsed = ScanReport::from_json(&report.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.target.pid, game_pid);
+        assert_eq!(parsed.handles.len(), 1);
+        assert!(parsed.handles[0].access_rights().contains(&"PROCESS_VM_WRITE".to_string()));
+        assert!(parsed.has_detections());
+    }
+
+    #[test]
+    fn collects_recently_executed_programs_once() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_forensics");
+        fs::write(dir.join("bash_history"), "cd Downloads\n./loader --inject\n").unwrap();
+
+        let mut forensics = ForensicsCollector::new();
+        forensics.with_shell_history(dir.join("bash_history"));
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries(Vec::<SystemHandleEntry>::new()));
+        anticheat.with_forensics(Some(forensics));
+
+        anticheat.run().unwrap();
+        assert_eq!(anticheat.executions().len(), 1);
+        assert_eq!(anticheat.executions()[0].name, "loader");
+
+        // Later runs keep what the first one found
+        fs::write(dir.join("bash_history"), "").unwrap();
+        anticheat.run().unwrap();
+        let report = anticheat.report();
+        assert_eq!(report.executions.len(), 1);
+        assert!(report.to_string().contains("1 recently executed programs, 1 no longer running"));
+        let notification = Notification::from_report(&report, None);
+        assert!(notification.fields.iter().any(|field| field.name == "Programs closed before the scan"));
+    }
+
+    #[test]
+    fn records_history_and_reports_changes() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_history");
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries([
+            process_handle(1001, game_pid, PROCESS_ALL_ACCESS),
+        ]));
+        anticheat.with_history(Some(ScanHistory::new(dir.path())));
+
+        anticheat.run().unwrap();
+        assert!(anticheat.record_history().unwrap().is_none(), "nothing to compare the first screenshare with");
+        let report = anticheat.report();
+        assert!(report.first_screenshare);
+        assert!(report.to_string().contains("No previous screenshare of game"));
+        assert!(Notification::from_report(&report, None).fields.iter().any(|field| field.name == "Previous screenshare"));
+
+        *anticheat.handle_manager_mut() = HandleManager::from_entries(Vec::<SystemHandleEntry>::new());
+        anticheat.run().unwrap();
+        let changes = anticheat.record_history().unwrap().unwrap();
+        assert_eq!(changes.gone.len(), 1);
+        assert!(changes.new.is_empty());
+        assert_eq!(anticheat.report().changes.unwrap().gone, changes.gone);
+        assert!(!anticheat.report().first_screenshare);
+        let notification = Notification::from_report(&anticheat.report(), None);
+        assert!(notification.fields.iter().any(|field| field.name == "Gone since last screenshare" && field.value == "1"));
+
+        let stored = anticheat.history().unwrap().reports(&anticheat.report().host.hostname, "game").unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored[1].1.changes.is_none());
+    }
+
+    #[tokio::test]
+    async fn uploads_report_to_configured_sink() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_reports");
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game.exe", game_pid), HandleManager::from_entries([
+            process_handle(1001, game_pid, PROCESS_ALL_ACCESS),
+        ]));
+        anticheat.with_report_sink(DirectorySink::new(dir.path()));
+        anticheat.run().unwrap();
+
+        let path = anticheat.upload_report().await.unwrap();
+        assert!(path.ends_with(".json"));
+        assert!(path.contains("scan_results_game.exe_"));
+
+        let parsed = ScanReport::from_json(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(parsed.handles.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queues_undeliverable_reports_until_a_later_run() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_outbox");
+        let down = TestServer::start(vec![StubResponse::new(503, "down")]);
+        let uploads = TestServer::ok("https://files.example/report.json");
+        let webhook_down = TestServer::start(vec![StubResponse::new(500, "{}")]);
+        let webhook = TestServer::start(vec![StubResponse::new(500, "{}"), StubResponse::new(204, "")]);
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries([
+            process_handle(1001, game_pid, PROCESS_ALL_ACCESS),
+        ]));
+        anticheat.with_report_sink(HttpSink::new(down.url.clone()))
+            .with_outbox(Some(Outbox::new(dir.path(), "key")))
+            .run().unwrap();
+        *anticheat.retry_mut() = RetryPolicy {
+            timeout: std::time::Duration::from_secs(5),
+            max_attempts: 2,
+            initial_backoff: std::time::Duration::from_millis(1),
+            max_backoff: std::time::Duration::from_millis(1),
+        };
+        anticheat.with_notifier(DiscordNotifier::new(webhook_down.url.clone()));
+
+        // Upload fails on every attempt, the report is kept
+        assert!(anticheat.send_webhook().await.is_err());
+        assert_eq!(down.requests().len(), 2);
+        let queued = anticheat.outbox().unwrap().pending().unwrap();
+        assert_eq!(queued.len(), 1);
+        assert!(queued[0].1.uploaded_url.is_none());
+        assert!(queued[0].1.last_error.contains("503"));
+
+        // Upload works but the webhook doesn't, the uploaded URL is remembered
+        anticheat.with_report_sink(HttpSink::new(uploads.url.clone()));
+        assert_eq!(anticheat.flush_outbox().await, 0);
+        let queued = anticheat.outbox().unwrap().pending().unwrap();
+        assert_eq!(queued[0].1.uploaded_url.as_deref(), Some("https://files.example/report.json"));
+        assert_eq!(queued[0].1.attempts, 2);
+
+        // Next run delivers it without uploading again
+        anticheat.notifiers_mut().clear();
+        anticheat.with_notifier(DiscordNotifier::new(webhook.url.clone()));
+        assert_eq!(anticheat.flush_outbox().await, 1);
+        assert!(anticheat.outbox().unwrap().pending().unwrap().is_empty());
+        assert_eq!(uploads.requests().len(), 1);
+        assert!(webhook.requests()[1].body_text().contains("https://files.example/report.json"));
+        assert!(webhook.requests()[1].body_text().contains("Queued at"));
+    }
+
+    #[tokio::test]
+    async fn flushing_stops_at_the_first_failure() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_flush");
+        let down = TestServer::start(vec![StubResponse::new(503, "down")]);
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries(Vec::<SystemHandleEntry>::new()));
+        anticheat.with_report_sink(HttpSink::new(down.url.clone()))
+            .with_outbox(Some(Outbox::new(dir.path(), "key")))
+            .run().unwrap();
+        *anticheat.retry_mut() = RetryPolicy::once(std::time::Duration::from_secs(5));
+
+        for file_name in ["first.json", "second.json", "third.json"] {
+            let mut pending = PendingReport::new(&anticheat.report()).unwrap();
+            pending.file_name = file_name.to_string();
+            anticheat.outbox().unwrap().push(&mut pending).unwrap();
+        }
+
+        assert_eq!(anticheat.flush_outbox().await, 0);
+        assert_eq!(down.requests().len(), 1);
+        assert_eq!(anticheat.outbox().unwrap().pending().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_only_notifiers_that_failed() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_notifiers");
+        let uploads = TestServer::ok("https://files.example/report.json");
+        let slack = TestServer::ok("ok");
+        let teams = TestServer::start(vec![StubResponse::new(500, "{}"), StubResponse::new(202, "")]);
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries(Vec::<SystemHandleEntry>::new()));
+        anticheat.with_report_sink(HttpSink::new(uploads.url.clone()))
+            .with_outbox(Some(Outbox::new(dir.path(), "key")))
+            .with_notifier(SlackNotifier::new(slack.url.clone()))
+            .with_notifier(TeamsNotifier::new(teams.url.clone()))
+            .run().unwrap();
+        *anticheat.retry_mut() = RetryPolicy::once(std::time::Duration::from_secs(5));
+
+        let err = anticheat.send_webhook().await.unwrap_err();
+        assert!(err.to_string().contains("1 of 2 notifiers failed"));
+        assert_eq!(anticheat.outbox().unwrap().pending().unwrap()[0].1.notified, ["0#Slack webhook"]);
+
+        assert_eq!(anticheat.flush_outbox().await, 1);
+        assert_eq!(slack.requests().len(), 1);
+        assert_eq!(teams.requests().len(), 2);
+    }
+
+    #[test]
+    fn applies_runtime_config() {
+        let game_pid = std::process::id();
+        let dir = TempDir::new("anticheat_config");
+        let mut config = Config::default();
+        config.apply(ConfigOverrides {
+            suspicious_threshold: Some(5),
+            upload: Some(SinkConfig::Directory { path: dir.join("reports") }),
+            outbox: Some(dir.join("outbox")),
+            outbox_key: Some("key".into()),
+            max_attempts: Some(7),
+            history_dir: Some(dir.join("history")),
+            ..Default::default()
+        });
+        config.notifiers.push(NotifierConfig::new(NotifierKind::Slack, "https://hooks.slack.com/services/T/B/secret"));
+        config.validate().unwrap();
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries(Vec::<SystemHandleEntry>::new()));
+        anticheat.apply_config(&config).unwrap();
+
+        assert_eq!(anticheat.scoring().thresholds.suspicious, 5);
+        assert!(anticheat.report_sink().describe().contains("reports"));
+        assert_eq!(anticheat.notifiers()[0].describe(), "Slack webhook");
+        assert_eq!(anticheat.retry().max_attempts, 7);
+        assert_eq!(anticheat.outbox().unwrap().dir(), dir.join("outbox"));
+        assert_eq!(anticheat.history().unwrap().dir(), dir.join("history"));
+        assert_eq!(anticheat.forensics().unwrap().lookback, std::time::Duration::from_secs(24 * 60 * 60));
+    }
+
+    #[test]
+    fn profile_adds_rules_and_allowlist_entries() {
+        let game_pid = std::process::id();
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries(Vec::<SystemHandleEntry>::new()));
+        let builtin_entries = anticheat.allowlist().entries.len();
+
+        anticheat.apply_profile(GameProfile::builtin("assaultcube").unwrap());
+
+        assert_eq!(anticheat.profile().unwrap().name, "AssaultCube");
+        assert_eq!(anticheat.allowlist().entries.len(), builtin_entries + 1);
+        assert!(anticheat.overlay_finder().rules().iter().any(|rule| rule.name == "AssaultCube external ESP"));
+        assert_eq!(anticheat.report().target.profile.as_deref(), Some("AssaultCube"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reports_modules_the_profile_does_not_expect() {
+        let game_pid = std::process::id();
+        let exe_name = env::current_exe().unwrap().file_name().unwrap().to_string_lossy().to_string();
+        let profile = GameProfile {
+            name: "test".into(),
+            expected_modules: vec!["none.so".into()],
+            ..Default::default()
+        };
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), HandleManager::from_entries(Vec::<SystemHandleEntry>::new()));
+        anticheat.apply_profile(profile);
+        anticheat.run().unwrap();
+
+        // The game's own executable is always expected, the libraries it loads are not
+        assert!(!anticheat.modules().iter().any(|module| module.name == exe_name));
+        let library = anticheat.modules().iter()
+            .find(|module| module.name.starts_with("libc.so") || module.name.starts_with("libc-"))
+            .expect("the test binary links libc")
+            .clone();
+        assert!(anticheat.has_detections());
+
+        anticheat.allowlist_mut().entries.push(AllowlistEntry {
+            name: "C library".into(),
+            reason: "Loaded by every program".into(),
+            image_path: Some(library.path.clone()),
+            sha256: library.sha256.clone(),
+            ..Default::default()
+        });
+        anticheat.run().unwrap();
+
+        let report = anticheat.report();
+        assert!(!report.modules.iter().any(|module| module.path == library.path));
+        assert!(report.allowed.modules.iter().any(|allowed| allowed.entry == "C library"));
+    }
+
+    #[test]
+    fn run_without_suspicious_handles_is_clean() {
+        let game_pid = std::process::id();
+        let handle_manager = HandleManager::from_entries([
+            process_handle(1003, game_pid, PROCESS_QUERY_INFORMATION),
+        ]);
+
+        let mut anticheat = Anticheat::with_handle_manager(Process::from_parts("game", game_pid), handle_manager);
+        anticheat.run().unwrap();
+
+        assert_eq!(anticheat.handle_detections(), 0);
+        assert!(!anticheat.has_detections());
+        assert_eq!(anticheat.verdict(), Verdict::Clean);
+    }
+}
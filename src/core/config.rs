@@ -0,0 +1,893 @@
+// Runtime configuration, layered from lowest to highest priority:
+//
+//   1. A config file (--config, $SCREENSHARE_CONFIG, or ./screenshare.toml when it exists)
+//   2. Environment variables (SCREENSHARE_*, see ConfigOverrides::from_vars)
+//   3. Command-line flags
+//
+// Secrets like webhook URLs are best kept out of the file and passed through the environment.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    allowlist::Allowlist,
+    forensics::collector::{ForensicsCollector, DEFAULT_LOOKBACK},
+    history::scan_history::{ScanHistory, DEFAULT_KEEP},
+    notify::{
+        discord_notifier::DiscordNotifier,
+        json_notifier::JsonNotifier,
+        notifier::Notifier,
+        slack_notifier::SlackNotifier,
+        teams_notifier::TeamsNotifier,
+        template::MessageTemplate,
+    },
+    overlay::overlay_rule::OverlayRuleSet,
+    profile::GameProfile,
+    scoring::ScoringConfig,
+    uploading::{
+        directory_sink::DirectorySink,
+        http_sink::HttpSink,
+        outbox::Outbox,
+        report_sink::ReportSink,
+        retry::RetryPolicy,
+        s3_sink::{S3Credentials, S3Sink},
+        stdout_sink::StdoutSink,
+    },
+};
+
+pub const CONFIG_FILE_ENV: &str = "SCREENSHARE_CONFIG";
+pub const DEFAULT_CONFIG_FILE: &str = "screenshare.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, String),
+    Parse(PathBuf, String),
+    InvalidEnv { var: String, value: String, expected: &'static str },
+    Invalid(Vec<String>), // Every problem validation found
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "Failed to read config file {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "Invalid config file {}: {}", path.display(), err),
+            ConfigError::InvalidEnv { var, value, expected } => {
+                write!(f, "Invalid value \"{}\" in {}, expected {}", value, var, expected)
+            }
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Where reports are uploaded
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    #[default]
+    Tmpfiles,
+    Http {
+        url: String,
+        #[serde(default)]
+        field_name: Option<String>,
+        #[serde(default)]
+        url_pointer: Option<String>,
+    },
+    Directory {
+        path: PathBuf,
+    },
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        #[serde(default)]
+        prefix: String,
+        #[serde(default)]
+        access_key: Option<String>,
+        #[serde(default)]
+        secret_key: Option<String>,
+    },
+    Stdout,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifierKind {
+    Discord,
+    Slack,
+    Teams,
+    Json,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifierConfig {
+    pub kind: NotifierKind,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>, // Only sent by json notifiers
+    #[serde(default)]
+    pub template: Option<PathBuf>,         // Message template file, the default texts if None
+}
+
+/// Timeouts, retries and the outbox of report delivery
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeliveryConfig {
+    pub timeout_secs: u64,
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_secs: u64,
+    pub queue_failed: bool,         // Keep undeliverable reports for the next run, needs outbox_key
+    pub outbox: Option<PathBuf>,    // Outbox::default_dir() if None
+    pub outbox_key: Option<String>, // Signs queued reports, pass it as SCREENSHARE_OUTBOX_KEY rather than in a file
+}
+
+/// Rescan timing of watch mode
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchConfig {
+    pub interval_secs: u64,
+    pub min_gap_ms: u64,  // Shortest time between two scans, however many OS events come in
+    pub os_events: bool,  // Also rescan when the OS reports a new window, where supported
+}
+
+/// Where past reports are kept to compare the next screenshare of the same machine with
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub dir: Option<PathBuf>,  // ScanHistory::default_dir() if None
+    pub keep: usize,           // Reports kept per host and game, 0 keeps all
+}
+
+/// Where evidence of programs that ran before the scan is looked for
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForensicsConfig {
+    pub enabled: bool,
+    pub lookback_hours: u64,
+    pub host_artifacts: bool,          // Running processes and the usual artifact locations of this machine
+    pub shell_histories: Vec<PathBuf>, // Artifacts read on top of (or, without host_artifacts, instead of) those
+    pub directories: Vec<PathBuf>,
+    pub prefetch_dirs: Vec<PathBuf>,
+    pub amcache_files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub profile: Option<String>,   // Built-in profile id, or path to a profile file
+    pub rules: Vec<PathBuf>,       // Overlay rule files used on top of the built-in rules
+    pub allowlists: Vec<PathBuf>,  // Allowlist files used on top of the built-in allowlist
+    pub scoring: ScoringConfig,
+    pub upload: SinkConfig,
+    #[serde(rename = "notify")]
+    pub notifiers: Vec<NotifierConfig>,
+    pub delivery: DeliveryConfig,
+    pub watch: WatchConfig,
+    pub history: HistoryConfig,
+    pub forensics: ForensicsConfig,
+}
+
+/// Settings the environment or command line can change. Lists are added to the file's lists, every
+/// other value that is set replaces the file's.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigOverrides {
+    pub profile: Option<String>,
+    pub rules: Vec<PathBuf>,
+    pub allowlists: Vec<PathBuf>,
+    pub suspicious_threshold: Option<u32>,
+    pub likely_cheating_threshold: Option<u32>,
+    pub upload: Option<SinkConfig>,
+    pub s3_access_key: Option<String>,
+    pub s3_secret_key: Option<String>,
+    pub webhooks: Vec<(NotifierKind, String)>, // Replaces the URL of the file's notifier of that kind, or adds one
+    pub timeout_secs: Option<u64>,
+    pub max_attempts: Option<u32>,
+    pub outbox: Option<PathBuf>,
+    pub outbox_key: Option<String>,
+    pub queue_failed: Option<bool>,
+    pub watch_interval_secs: Option<u64>,
+    pub history_dir: Option<PathBuf>,
+    pub record_history: Option<bool>,
+    pub collect_forensics: Option<bool>,
+    pub lookback_hours: Option<u64>,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        let retry = RetryPolicy::default();
+        Self {
+            timeout_secs: retry.timeout.as_secs(),
+            max_attempts: retry.max_attempts,
+            initial_backoff_ms: retry.initial_backoff.as_millis() as u64,
+            max_backoff_secs: retry.max_backoff.as_secs(),
+            queue_failed: true,
+            outbox: None,
+            outbox_key: None,
+        }
+    }
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10,
+            min_gap_ms: 1000,
+            os_events: true,
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: None,
+            keep: DEFAULT_KEEP,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// The history to record scans in, None if recording is disabled
+    pub fn store(&self) -> Option<ScanHistory> {
+        self.enabled.then(|| self.reader())
+    }
+
+    /// The history regardless of whether scans are recorded, to look at what is already there
+    pub fn reader(&self) -> ScanHistory {
+        ScanHistory::new(self.dir.clone().unwrap_or_else(ScanHistory::default_dir)).with_keep(self.keep)
+    }
+}
+
+impl Default for ForensicsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lookback_hours: DEFAULT_LOOKBACK.as_secs() / 3600,
+            host_artifacts: true,
+            shell_histories: Vec::new(),
+            directories: Vec::new(),
+            prefetch_dirs: Vec::new(),
+            amcache_files: Vec::new(),
+        }
+    }
+}
+
+impl ForensicsConfig {
+    /// The collector for these settings, None if collecting is disabled
+    pub fn collector(&self) -> Option<ForensicsCollector> {
+        if !self.enabled {
+            return None;
+        }
+
+        let mut collector = match self.host_artifacts {
+            true => ForensicsCollector::for_host(),
+            false => ForensicsCollector::new(),
+        };
+        collector.with_lookback(Duration::from_secs(self.lookback_hours.saturating_mul(60 * 60)));
+        collector.shell_histories.extend(self.shell_histories.iter().cloned());
+        collector.directories.extend(self.directories.iter().cloned());
+        collector.prefetch_dirs.extend(self.prefetch_dirs.iter().cloned());
+        collector.amcache_files.extend(self.amcache_files.iter().cloned());
+        Some(collector)
+    }
+
+    fn artifacts(&self) -> impl Iterator<Item = &PathBuf> {
+        self.shell_histories.iter()
+            .chain(&self.directories)
+            .chain(&self.prefetch_dirs)
+            .chain(&self.amcache_files)
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        if self.lookback_hours == 0 {
+            problems.push("forensics.lookback_hours must be at least 1".to_string());
+        }
+        for path in self.artifacts().filter(|path| !path.exists()) {
+            problems.push(format!("forensics artifact {} does not exist", path.display()));
+        }
+    }
+}
+
+impl WatchConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    pub fn min_gap(&self) -> Duration {
+        Duration::from_millis(self.min_gap_ms)
+    }
+}
+
+impl DeliveryConfig {
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_secs(self.timeout_secs),
+            max_attempts: self.max_attempts,
+            initial_backoff: Duration::from_millis(self.initial_backoff_ms),
+            max_backoff: Duration::from_secs(self.max_backoff_secs),
+        }
+    }
+
+    /// The outbox, None if failed reports aren't queued. Without a key a queued report could be edited
+    /// by the player before it is sent, so nothing is queued then.
+    pub fn outbox(&self) -> Option<Outbox> {
+        if !self.queue_failed {
+            return None;
+        }
+
+        let Some(key) = &self.outbox_key else {
+            log::warn!("Failed reports can't be queued without an outbox key to sign them with (SCREENSHARE_OUTBOX_KEY)");
+            return None;
+        };
+        Some(Outbox::new(self.outbox.clone().unwrap_or_else(Outbox::default_dir), key.as_bytes()))
+    }
+}
+
+impl SinkConfig {
+    pub fn build(&self) -> Box<dyn ReportSink> {
+        match self {
+            SinkConfig::Tmpfiles => Box::new(HttpSink::tmpfiles()),
+            SinkConfig::Http { url, field_name, url_pointer } => {
+                let mut sink = HttpSink::new(url.clone());
+                if let Some(field_name) = field_name {
+                    sink.field_name = field_name.clone();
+                }
+                sink.url_pointer = url_pointer.clone();
+                Box::new(sink)
+            }
+            SinkConfig::Directory { path } => Box::new(DirectorySink::new(path)),
+            SinkConfig::S3 { endpoint, bucket, region, prefix, access_key, secret_key } => {
+                let mut sink = S3Sink::new(endpoint.clone(), bucket.clone(), region.clone());
+                sink.prefix = prefix.clone();
+                if let (Some(access_key), Some(secret_key)) = (access_key, secret_key) {
+                    sink.credentials = Some(S3Credentials { access_key: access_key.clone(), secret_key: secret_key.clone() });
+                }
+                Box::new(sink)
+            }
+            SinkConfig::Stdout => Box::new(StdoutSink),
+        }
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        match self {
+            SinkConfig::Http { url, .. } => check_url("upload.url", url, problems),
+            SinkConfig::Directory { path } if path.as_os_str().is_empty() => {
+                problems.push("upload.path must not be empty".to_string());
+            }
+            SinkConfig::S3 { endpoint, bucket, region, access_key, secret_key, .. } => {
+                check_url("upload.endpoint", endpoint, problems);
+                if bucket.is_empty() {
+                    problems.push("upload.bucket must not be empty".to_string());
+                }
+                if region.is_empty() {
+                    problems.push("upload.region must not be empty".to_string());
+                }
+                if access_key.is_some() != secret_key.is_some() {
+                    problems.push("upload.access_key and upload.secret_key have to be set together".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl NotifierConfig {
+    pub fn new(kind: NotifierKind, url: impl Into<String>) -> Self {
+        Self { kind, url: url.into(), headers: BTreeMap::new(), template: None }
+    }
+
+    pub fn build(&self) -> anyhow::Result<Box<dyn Notifier>> {
+        let template = match &self.template {
+            Some(path) => MessageTemplate::from_file(path)?,
+            None => MessageTemplate::default(),
+        };
+
+        Ok(match self.kind {
+            NotifierKind::Discord => {
+                let mut notifier = DiscordNotifier::new(self.url.clone());
+                notifier.template = template;
+                Box::new(notifier)
+            }
+            NotifierKind::Slack => {
+                let mut notifier = SlackNotifier::new(self.url.clone());
+                notifier.template = template;
+                Box::new(notifier)
+            }
+            NotifierKind::Teams => {
+                let mut notifier = TeamsNotifier::new(self.url.clone());
+                notifier.template = template;
+                Box::new(notifier)
+            }
+            NotifierKind::Json => {
+                let mut notifier = JsonNotifier::new(self.url.clone());
+                notifier.headers = self.headers.clone().into_iter().collect();
+                notifier.template = template;
+                Box::new(notifier)
+            }
+        })
+    }
+}
+
+impl ConfigOverrides {
+    /// Reads the SCREENSHARE_* variables of this process
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(env::vars())
+    }
+
+    /// Overrides from environment variables:
+    ///
+    /// SCREENSHARE_PROFILE, SCREENSHARE_RULES and SCREENSHARE_ALLOWLISTS (path lists),
+    /// SCREENSHARE_SUSPICIOUS_THRESHOLD, SCREENSHARE_LIKELY_CHEATING_THRESHOLD,
+    /// SCREENSHARE_UPLOAD_URL (HTTP upload), SCREENSHARE_UPLOAD_DIR (directory),
+    /// SCREENSHARE_S3_ACCESS_KEY, SCREENSHARE_S3_SECRET_KEY,
+    /// SCREENSHARE_DISCORD_WEBHOOK (or WEBHOOK_URL), SCREENSHARE_SLACK_WEBHOOK,
+    /// SCREENSHARE_TEAMS_WEBHOOK, SCREENSHARE_JSON_WEBHOOK,
+    /// SCREENSHARE_TIMEOUT_SECS, SCREENSHARE_MAX_ATTEMPTS, SCREENSHARE_OUTBOX, SCREENSHARE_OUTBOX_KEY,
+    /// SCREENSHARE_QUEUE_FAILED,
+    /// SCREENSHARE_WATCH_INTERVAL_SECS, SCREENSHARE_HISTORY_DIR, SCREENSHARE_RECORD_HISTORY,
+    /// SCREENSHARE_FORENSICS, SCREENSHARE_LOOKBACK_HOURS
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Result<Self, ConfigError> {
+        let vars: BTreeMap<String, String> = vars.into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        let get = |name: &str| vars.get(name).cloned();
+
+        let mut overrides = Self {
+            profile: get("SCREENSHARE_PROFILE"),
+            rules: get("SCREENSHARE_RULES").map(|paths| env::split_paths(&paths).collect()).unwrap_or_default(),
+            allowlists: get("SCREENSHARE_ALLOWLISTS").map(|paths| env::split_paths(&paths).collect()).unwrap_or_default(),
+            suspicious_threshold: parse_var(&vars, "SCREENSHARE_SUSPICIOUS_THRESHOLD", "a number")?,
+            likely_cheating_threshold: parse_var(&vars, "SCREENSHARE_LIKELY_CHEATING_THRESHOLD", "a number")?,
+            s3_access_key: get("SCREENSHARE_S3_ACCESS_KEY"),
+            s3_secret_key: get("SCREENSHARE_S3_SECRET_KEY"),
+            timeout_secs: parse_var(&vars, "SCREENSHARE_TIMEOUT_SECS", "a number of seconds")?,
+            max_attempts: parse_var(&vars, "SCREENSHARE_MAX_ATTEMPTS", "a number")?,
+            outbox: get("SCREENSHARE_OUTBOX").map(PathBuf::from),
+            outbox_key: get("SCREENSHARE_OUTBOX_KEY"),
+            queue_failed: parse_var(&vars, "SCREENSHARE_QUEUE_FAILED", "true or false")?,
+            watch_interval_secs: parse_var(&vars, "SCREENSHARE_WATCH_INTERVAL_SECS", "a number of seconds")?,
+            history_dir: get("SCREENSHARE_HISTORY_DIR").map(PathBuf::from),
+            record_history: parse_var(&vars, "SCREENSHARE_RECORD_HISTORY", "true or false")?,
+            collect_forensics: parse_var(&vars, "SCREENSHARE_FORENSICS", "true or false")?,
+            lookback_hours: parse_var(&vars, "SCREENSHARE_LOOKBACK_HOURS", "a number of hours")?,
+            ..Default::default()
+        };
+
+        if let Some(url) = get("SCREENSHARE_UPLOAD_URL") {
+            overrides.upload = Some(SinkConfig::Http { url, field_name: None, url_pointer: None });
+        }
+        if let Some(path) = get("SCREENSHARE_UPLOAD_DIR") {
+            overrides.upload = Some(SinkConfig::Directory { path: path.into() });
+        }
+
+        let webhooks = [
+            ("WEBHOOK_URL", NotifierKind::Discord), // Name used by older builds
+            ("SCREENSHARE_DISCORD_WEBHOOK", NotifierKind::Discord),
+            ("SCREENSHARE_SLACK_WEBHOOK", NotifierKind::Slack),
+            ("SCREENSHARE_TEAMS_WEBHOOK", NotifierKind::Teams),
+            ("SCREENSHARE_JSON_WEBHOOK", NotifierKind::Json),
+        ];
+        for (var, kind) in webhooks {
+            if let Some(url) = get(var) {
+                overrides.webhooks.retain(|(existing, _)| *existing != kind);
+                overrides.webhooks.push((kind, url));
+            }
+        }
+
+        Ok(overrides)
+    }
+}
+
+impl Config {
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Loads a config from a .toml or .json file without applying any overrides
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|err| ConfigError::Read(path.to_path_buf(), err.to_string()))?;
+
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+            _ => toml::from_str(&contents).map_err(|err| err.to_string()),
+        };
+        parsed.map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+    }
+
+    /// Builds the configuration from all layers and validates it. `path` is the config file given on
+    /// the command line, if any.
+    pub fn load(path: Option<&Path>, cli: ConfigOverrides) -> Result<Self, ConfigError> {
+        let config = Self::load_unchecked(path, cli)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Builds the configuration from all layers without validating it, for commands that only use
+    /// parts of it and check those themselves
+    pub fn load_unchecked(path: Option<&Path>, cli: ConfigOverrides) -> Result<Self, ConfigError> {
+        let path = path.map(Path::to_path_buf)
+            .or_else(|| env::var_os(CONFIG_FILE_ENV).map(PathBuf::from))
+            .or_else(|| Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()));
+
+        let mut config = match path {
+            Some(path) => {
+                log::debug!("Loading config from {}", path.display());
+                Self::from_file(path)?
+            }
+            None => Self::default(),
+        };
+
+        config.apply(ConfigOverrides::from_env()?);
+        config.apply(cli);
+        Ok(config)
+    }
+
+    pub fn apply(&mut self, overrides: ConfigOverrides) {
+        if overrides.profile.is_some() {
+            self.profile = overrides.profile;
+        }
+        self.rules.extend(overrides.rules);
+        self.allowlists.extend(overrides.allowlists);
+
+        if let Some(threshold) = overrides.suspicious_threshold {
+            self.scoring.thresholds.suspicious = threshold;
+        }
+        if let Some(threshold) = overrides.likely_cheating_threshold {
+            self.scoring.thresholds.likely_cheating = threshold;
+        }
+
+        if let Some(upload) = overrides.upload {
+            self.upload = upload;
+        }
+        if overrides.s3_access_key.is_some() || overrides.s3_secret_key.is_some() {
+            match &mut self.upload {
+                SinkConfig::S3 { access_key, secret_key, .. } => {
+                    *access_key = overrides.s3_access_key.or(access_key.take());
+                    *secret_key = overrides.s3_secret_key.or(secret_key.take());
+                }
+                _ => log::warn!("Ignoring S3 credentials, reports aren't uploaded to S3"),
+            }
+        }
+
+        for (kind, url) in overrides.webhooks {
+            match self.notifiers.iter_mut().find(|notifier| notifier.kind == kind) {
+                Some(notifier) => notifier.url = url,
+                None => self.notifiers.push(NotifierConfig::new(kind, url)),
+            }
+        }
+
+        if let Some(timeout_secs) = overrides.timeout_secs {
+            self.delivery.timeout_secs = timeout_secs;
+        }
+        if let Some(max_attempts) = overrides.max_attempts {
+            self.delivery.max_attempts = max_attempts;
+        }
+        if overrides.outbox.is_some() {
+            self.delivery.outbox = overrides.outbox;
+        }
+        if overrides.outbox_key.is_some() {
+            self.delivery.outbox_key = overrides.outbox_key;
+        }
+        if let Some(queue_failed) = overrides.queue_failed {
+            self.delivery.queue_failed = queue_failed;
+        }
+        if let Some(interval_secs) = overrides.watch_interval_secs {
+            self.watch.interval_secs = interval_secs;
+        }
+        if overrides.history_dir.is_some() {
+            self.history.dir = overrides.history_dir;
+        }
+        if let Some(record_history) = overrides.record_history {
+            self.history.enabled = record_history;
+        }
+        if let Some(collect_forensics) = overrides.collect_forensics {
+            self.forensics.enabled = collect_forensics;
+        }
+        if let Some(lookback_hours) = overrides.lookback_hours {
+            self.forensics.lookback_hours = lookback_hours;
+        }
+    }
+
+    /// Checks everything that can be checked before a scan, reporting all problems at once
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if let Some(profile) = &self.profile {
+            if GameProfile::builtin(profile).is_none() && !Path::new(profile).is_file() {
+                problems.push(format!(
+                    "profile \"{}\" is neither a built-in profile ({}) nor an existing file",
+                    profile,
+                    GameProfile::builtin_ids().collect::<Vec<_>>().join(", "),
+                ));
+            }
+        }
+
+        for path in self.rules.iter().chain(&self.allowlists) {
+            if !path.is_file() {
+                problems.push(format!("{} does not exist", path.display()));
+            }
+        }
+
+        let thresholds = &self.scoring.thresholds;
+        if thresholds.suspicious == 0 || thresholds.suspicious >= thresholds.likely_cheating {
+            problems.push(format!(
+                "scoring thresholds must satisfy 0 < suspicious < likely_cheating (got {} and {})",
+                thresholds.suspicious, thresholds.likely_cheating,
+            ));
+        }
+
+        self.upload.validate(&mut problems);
+
+        for (index, notifier) in self.notifiers.iter().enumerate() {
+            check_url(&format!("notify[{}].url", index), &notifier.url, &mut problems);
+            if let Some(template) = notifier.template.as_ref().filter(|template| !template.is_file()) {
+                problems.push(format!("notify[{}].template {} does not exist", index, template.display()));
+            }
+        }
+
+        if self.delivery.timeout_secs == 0 {
+            problems.push("delivery.timeout_secs must be at least 1".to_string());
+        }
+        if self.delivery.max_attempts == 0 {
+            problems.push("delivery.max_attempts must be at least 1".to_string());
+        }
+        if self.watch.interval_secs == 0 {
+            problems.push("watch.interval_secs must be at least 1".to_string());
+        }
+        self.forensics.validate(&mut problems);
+
+        if problems.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(problems)) }
+    }
+
+    /// Checks only the forensics section, all a forensics run without a scan needs
+    pub fn validate_forensics(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        self.forensics.validate(&mut problems);
+        if problems.is_empty() { Ok(()) } else { Err(ConfigError::Invalid(problems)) }
+    }
+
+    /// The configured game profile, None to scan without one
+    pub fn game_profile(&self) -> anyhow::Result<Option<GameProfile>> {
+        let Some(profile) = &self.profile else {
+            return Ok(None);
+        };
+
+        match GameProfile::builtin(profile) {
+            Some(builtin) => Ok(Some(builtin)),
+            None => GameProfile::from_file(profile).map(Some),
+        }
+    }
+
+    /// Overlay rules from every configured rule file
+    pub fn rule_set(&self) -> anyhow::Result<OverlayRuleSet> {
+        let mut rules = OverlayRuleSet::new(Vec::new());
+        for path in &self.rules {
+            rules.extend(OverlayRuleSet::from_file(path)?);
+        }
+        Ok(rules)
+    }
+
+    /// Entries from every configured allowlist file
+    pub fn allowlist(&self) -> anyhow::Result<Allowlist> {
+        let mut allowlist = Allowlist::default();
+        for path in &self.allowlists {
+            allowlist.extend(Allowlist::from_file(path)?);
+        }
+        Ok(allowlist)
+    }
+
+    pub fn notifiers(&self) -> anyhow::Result<Vec<Box<dyn Notifier>>> {
+        self.notifiers.iter().map(NotifierConfig::build).collect()
+    }
+}
+
+/// Per-user directory the tool keeps its state in (%LOCALAPPDATA% on Windows, $XDG_STATE_HOME or
+/// ~/.local/state elsewhere, the temp directory if none of them is set)
+pub fn state_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    }
+    else {
+        env::var_os("XDG_STATE_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))
+    };
+
+    base.unwrap_or_else(env::temp_dir).join("basic_screensharing_tool")
+}
+
+fn parse_var<T: std::str::FromStr>(
+    vars: &BTreeMap<String, String>,
+    var: &str,
+    expected: &'static str,
+) -> Result<Option<T>, ConfigError> {
+    vars.get(var)
+        .map(|value| value.trim().parse().map_err(|_| ConfigError::InvalidEnv {
+            var: var.to_string(),
+            value: value.clone(),
+            expected,
+        }))
+        .transpose()
+}
+
+fn check_url(name: &str, url: &str, problems: &mut Vec<String>) {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+        Ok(_) => problems.push(format!("{} must be an http or https URL (got \"{}\")", name, url)),
+        Err(err) if url.is_empty() => problems.push(format!("{} is not set ({})", name, err)),
+        Err(err) => problems.push(format!("{} is not a valid URL: {} (got \"{}\")", name, err, url)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        profile = "assaultcube"
+
+        [scoring.thresholds]
+        suspicious = 40
+        likely_cheating = 90
+
+        [upload]
+        kind = "s3"
+        endpoint = "https://s3.eu-central-1.amazonaws.com"
+        bucket = "screenshares"
+        region = "eu-central-1"
+        prefix = "reports/"
+
+        [[notify]]
+        kind = "discord"
+
+        [[notify]]
+        kind = "json"
+        url = "https://alerts.example/hook"
+        headers = { Authorization = "Bearer token" }
+
+        [delivery]
+        max_attempts = 2
+    "#;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn example_config_parses() {
+        let mut config = Config::from_toml(include_str!("../../config/screenshare.example.toml")).unwrap();
+        config.validate().unwrap();
+        assert!(config.notifiers.is_empty());
+
+        config.apply(ConfigOverrides::from_vars(vars(&[
+            ("SCREENSHARE_DISCORD_WEBHOOK", "https://discord.com/api/webhooks/1/secret"),
+        ])).unwrap());
+        config.validate().unwrap();
+        assert_eq!(config.notifiers[0].kind, NotifierKind::Discord);
+        assert_eq!(config.delivery, DeliveryConfig::default());
+        assert_eq!(config.watch, WatchConfig::default());
+        assert_eq!(config.history, HistoryConfig::default());
+        assert_eq!(config.forensics, ForensicsConfig::default());
+    }
+
+    #[test]
+    fn parses_file_with_defaults() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        assert_eq!(config.scoring.thresholds.likely_cheating, 90);
+        assert_eq!(config.scoring.all_access, ScoringConfig::default().all_access);
+        assert_eq!(config.notifiers[1].headers["Authorization"], "Bearer token");
+        assert_eq!(config.delivery.max_attempts, 2);
+        assert_eq!(config.delivery.timeout_secs, 30);
+        assert_eq!(config.game_profile().unwrap().unwrap().name, "AssaultCube");
+
+        // Missing webhook URL of the discord notifier
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("notify[0].url is not set"), "{}", err);
+    }
+
+    #[test]
+    fn environment_then_flags_override_file() {
+        let mut config = Config::from_toml(CONFIG).unwrap();
+        config.apply(ConfigOverrides::from_vars(vars(&[
+            ("SCREENSHARE_DISCORD_WEBHOOK", "https://discord.com/api/webhooks/1/secret"),
+            ("SCREENSHARE_SLACK_WEBHOOK", "https://hooks.slack.com/services/T/B/secret"),
+            ("SCREENSHARE_S3_ACCESS_KEY", "AKID"),
+            ("SCREENSHARE_S3_SECRET_KEY", "secret"),
+            ("SCREENSHARE_SUSPICIOUS_THRESHOLD", "50"),
+            ("SCREENSHARE_PROFILE", "cs2"),
+            ("UNRELATED", "ignored"),
+        ])).unwrap());
+        config.apply(ConfigOverrides {
+            profile: Some("minecraft".into()),
+            upload: Some(SinkConfig::Stdout),
+            ..Default::default()
+        });
+
+        config.validate().unwrap();
+        assert_eq!(config.profile.as_deref(), Some("minecraft"));
+        assert_eq!(config.scoring.thresholds.suspicious, 50);
+        assert_eq!(config.upload, SinkConfig::Stdout);
+        assert_eq!(config.notifiers.len(), 3);
+        assert_eq!(config.notifiers[0].url, "https://discord.com/api/webhooks/1/secret");
+        assert_eq!(config.notifiers[2].kind, NotifierKind::Slack);
+        assert_eq!(config.notifiers().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn s3_credentials_come_from_environment() {
+        let mut config = Config::from_toml(CONFIG).unwrap();
+        config.apply(ConfigOverrides::from_vars(vars(&[
+            ("SCREENSHARE_S3_ACCESS_KEY", "AKID"),
+            ("SCREENSHARE_S3_SECRET_KEY", "secret"),
+        ])).unwrap());
+
+        let SinkConfig::S3 { access_key, secret_key, .. } = &config.upload else {
+            panic!("upload kind changed");
+        };
+        assert_eq!(access_key.as_deref(), Some("AKID"));
+        assert_eq!(secret_key.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mut config = Config::from_toml(r#"
+            profile = "not a game"
+            rules = ["/does/not/exist.toml"]
+
+            [scoring.thresholds]
+            suspicious = 90
+            likely_cheating = 40
+
+            [upload]
+            kind = "http"
+            url = "ftp://files.example"
+
+            [delivery]
+            timeout_secs = 0
+
+            [watch]
+            interval_secs = 0
+
+            [forensics]
+            lookback_hours = 0
+            amcache_files = ["/does/not/exist.hve"]
+        "#).unwrap();
+        config.notifiers.push(NotifierConfig::new(NotifierKind::Teams, "not a url"));
+
+        let ConfigError::Invalid(problems) = config.validate().unwrap_err() else {
+            panic!("expected validation problems");
+        };
+        assert_eq!(problems.len(), 9, "{:#?}", problems);
+        assert!(problems[0].contains("not a game"));
+        assert!(problems[2].contains("suspicious < likely_cheating"));
+        assert!(problems[3].contains("upload.url must be an http or https URL"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        assert!(Config::from_toml("webhook_urll = \"https://example\"").is_err());
+        assert!(Config::from_toml("[upload]\nkind = \"ftp\"").is_err());
+
+        let err = ConfigOverrides::from_vars(vars(&[("SCREENSHARE_MAX_ATTEMPTS", "many")])).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value \"many\" in SCREENSHARE_MAX_ATTEMPTS, expected a number");
+    }
+}
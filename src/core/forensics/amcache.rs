@@ -0,0 +1,125 @@
+// Programs recorded in Amcache.hve (C:\Windows\AppCompat\Programs\Amcache.hve). Windows adds an entry
+// for executables it runs or finds installed, with path and SHA-1, and keeps it after the file is
+// gone. The live file is locked by the system, so it is read from a copy (e.g. from a shadow copy).
+//
+// Windows 10 and later keep entries in Root\InventoryApplicationFile\<id>, Windows 8 in
+// Root\File\<volume>\<file id> with numbered values.
+
+use std::path::Path;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::core::forensics::{
+    execution_evidence::{file_name, EvidenceSource, ExecutionEvidence},
+    registry_hive::{filetime, HiveError, RegistryHive, RegistryKey},
+};
+
+/// A program entry of the hive
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmcacheEntry {
+    pub path: String,
+    pub sha1: Option<String>,
+    pub link_date: Option<DateTime<Utc>>,     // Compile time in the PE header
+    pub last_written: Option<DateTime<Utc>>,  // Of the entry's key, close to when it was recorded
+}
+
+/// Every program entry of the hive
+pub fn entries(hive: &RegistryHive) -> Result<Vec<AmcacheEntry>, HiveError> {
+    let mut entries = Vec::new();
+
+    if let Some(inventory) = hive.open(r"Root\InventoryApplicationFile")? {
+        for key in inventory.subkeys()? {
+            let Some(path) = string(&key, "LowerCaseLongPath")? else {
+                continue;
+            };
+            entries.push(AmcacheEntry {
+                path,
+                // "0000" followed by the SHA-1
+                sha1: string(&key, "FileId")?.map(|id| id.strip_prefix("0000").unwrap_or(&id).to_string()),
+                link_date: string(&key, "LinkDate")?
+                    .and_then(|date| NaiveDateTime::parse_from_str(&date, "%m/%d/%Y %H:%M:%S").ok())
+                    .map(|date| date.and_utc()),
+                last_written: key.last_written(),
+            });
+        }
+    }
+
+    if let Some(files) = hive.open(r"Root\File")? {
+        for volume in files.subkeys()? {
+            for key in volume.subkeys()? {
+                let Some(path) = string(&key, "15")? else {
+                    continue;
+                };
+                entries.push(AmcacheEntry {
+                    path,
+                    sha1: string(&key, "101")?.map(|id| id.strip_prefix("0000").unwrap_or(&id).to_string()),
+                    link_date: key.value("f")?.and_then(|value| value.as_u64())
+                        .and_then(|seconds| DateTime::from_timestamp(seconds as i64, 0)),
+                    last_written: key.last_written()
+                        .or_else(|| key.value("17").ok().flatten().and_then(|value| value.as_u64()).and_then(filetime)),
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Programs of a copied Amcache.hve recorded at or after `since`
+pub fn read(path: &Path, since: DateTime<Utc>) -> anyhow::Result<Vec<ExecutionEvidence>> {
+    let hive = RegistryHive::from_file(path)?;
+    let artifact = path.display().to_string();
+
+    Ok(entries(&hive)?
+        .into_iter()
+        .filter(|entry| entry.last_written.is_some_and(|written| written >= since))
+        .map(|entry| {
+            let mut details = Vec::new();
+            if let Some(sha1) = &entry.sha1 {
+                details.push(format!("SHA-1 {}", sha1));
+            }
+            if let Some(link_date) = entry.link_date {
+                details.push(format!("compiled {}", link_date.format("%Y-%m-%d")));
+            }
+
+            ExecutionEvidence::new(EvidenceSource::Amcache, file_name(&entry.path), artifact.clone())
+                .with_path(entry.path.clone())
+                .with_timestamp(entry.last_written)
+                .with_details(details.join(", "))
+        })
+        .collect())
+}
+
+fn string(key: &RegistryKey, name: &str) -> Result<Option<String>, HiveError> {
+    Ok(key.value(name)?.and_then(|value| value.as_str().map(str::to_string)).filter(|value| !value.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::forensics::registry_hive::test_hive::{build, TestKey};
+
+    #[test]
+    fn reads_inventory_and_legacy_entries() {
+        let mut loader = TestKey::new("0006a1b2c3")
+            .with_string("LowerCaseLongPath", r"c:\users\me\downloads\loader.exe")
+            .with_string("FileId", "0000da39a3ee5e6b4b0d3255bfef95601890afd80709")
+            .with_string("LinkDate", "12/24/2025 08:30:00");
+        loader.last_written = 134_116_992_000_000_000; // 2026-01-01 00:00 UTC
+        let mut legacy = TestKey::new("0000a")
+            .with_string("15", r"C:\Tools\trainer.exe")
+            .with_string("101", "0000356a192b7913b04c54574d18c28d46e6395428ab");
+        legacy.last_written = 134_085_456_000_000_000; // 2025-11-25 12:00 UTC
+
+        let hive = RegistryHive::from_bytes(build(&TestKey::new("{root}").with_subkey(
+            TestKey::new("Root")
+                .with_subkey(TestKey::new("InventoryApplicationFile").with_subkey(loader).with_subkey(TestKey::new("no path")))
+                .with_subkey(TestKey::new("File").with_subkey(TestKey::new("{volume}").with_subkey(legacy))),
+        ))).unwrap();
+
+        let entries = entries(&hive).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, r"c:\users\me\downloads\loader.exe");
+        assert_eq!(entries[0].sha1.as_deref(), Some("da39a3ee5e6b4b0d3255bfef95601890afd80709"));
+        assert_eq!(entries[0].link_date, "2025-12-24T08:30:00Z".parse().ok());
+        assert_eq!(entries[1].path, r"C:\Tools\trainer.exe");
+    }
+}
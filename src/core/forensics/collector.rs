@@ -0,0 +1,301 @@
+// Gathers evidence of recently executed programs from every configured source. Each source is read on
+// its own, a missing or unreadable artifact only costs that source. The file based sources work just
+// as well on artifacts copied off another machine.
+
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    env,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use chrono::{DateTime, Utc};
+use crate::core::forensics::{
+    amcache,
+    execution_evidence::{file_name, ExecutionEvidence},
+    prefetch,
+    process_starts::{self, RunningProcess},
+    recent_files,
+    shell_history,
+};
+
+/// How far back evidence is collected unless configured otherwise
+pub const DEFAULT_LOOKBACK: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Debug)]
+pub struct ForensicsCollector {
+    pub lookback: Duration,
+    pub live: bool,                    // Look at the processes of this machine, off for copied artifacts
+    pub shell_histories: Vec<PathBuf>,
+    pub directories: Vec<PathBuf>,     // Searched for recently written executables
+    pub prefetch_dirs: Vec<PathBuf>,
+    pub amcache_files: Vec<PathBuf>,
+}
+
+impl Default for ForensicsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ForensicsCollector {
+    /// Collects nothing until sources are added
+    pub fn new() -> Self {
+        Self {
+            lookback: DEFAULT_LOOKBACK,
+            live: false,
+            shell_histories: Vec::new(),
+            directories: Vec::new(),
+            prefetch_dirs: Vec::new(),
+            amcache_files: Vec::new(),
+        }
+    }
+
+    /// The running processes and every artifact location of this machine that exists
+    pub fn for_host() -> Self {
+        let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from);
+        let system_root = env::var_os("SystemRoot").map(PathBuf::from)
+            .or_else(|| cfg!(windows).then(|| PathBuf::from(r"C:\Windows")));
+
+        let mut shell_histories = Vec::new();
+        let mut directories = vec![env::temp_dir()];
+        if let Some(home) = &home {
+            shell_histories.push(home.join(".bash_history"));
+            shell_histories.push(home.join(".zsh_history"));
+            directories.push(home.join("Downloads"));
+        }
+        let data_home = env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".local").join("share")));
+        if let Some(data_home) = data_home {
+            shell_histories.push(data_home.join("fish").join("fish_history"));
+        }
+        if let Some(app_data) = env::var_os("APPDATA").map(PathBuf::from) {
+            shell_histories.push(app_data.join(r"Microsoft\Windows\PowerShell\PSReadLine\ConsoleHost_history.txt"));
+        }
+
+        let mut collector = Self::new();
+        collector.live = true;
+        if let Some(system_root) = system_root {
+            directories.push(system_root.join("Temp"));
+            // Amcache.hve is locked while Windows runs, it has to be passed in as a copy
+            collector.prefetch_dirs = existing(vec![system_root.join("Prefetch")]);
+        }
+        collector.shell_histories = existing(shell_histories);
+        directories.dedup();
+        collector.directories = existing(directories);
+        collector
+    }
+
+    // BUILDER METHODS -----------------------------------------------------------------------------
+    pub fn with_lookback(&mut self, lookback: Duration) -> &mut Self {
+        self.lookback = lookback;
+        self
+    }
+
+    pub fn with_shell_history(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.shell_histories.push(path.into());
+        self
+    }
+
+    pub fn with_directory(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.directories.push(path.into());
+        self
+    }
+
+    pub fn with_prefetch_dir(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.prefetch_dirs.push(path.into());
+        self
+    }
+
+    pub fn with_amcache(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.amcache_files.push(path.into());
+        self
+    }
+
+    /// Evidence from every source, newest first
+    pub fn collect(&self) -> Vec<ExecutionEvidence> {
+        self.collect_at(Utc::now())
+    }
+
+    /// Evidence from every source within the lookback before `now`, newest first. Undated evidence
+    /// (e.g. plain shell histories) is kept and sorted last.
+    pub fn collect_at(&self, now: DateTime<Utc>) -> Vec<ExecutionEvidence> {
+        // A lookback longer than chrono can represent means everything
+        let since = chrono::Duration::from_std(self.lookback).ok()
+            .and_then(|lookback| now.checked_sub_signed(lookback))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let mut evidence = Vec::new();
+
+        let processes = match self.live {
+            true => process_starts::snapshot(),
+            false => Vec::new(),
+        };
+        evidence.extend(process_starts::started_since(&processes, since));
+
+        for path in &self.shell_histories {
+            gather(&mut evidence, path, "shell history", shell_history::read(path));
+        }
+        for path in &self.directories {
+            gather(&mut evidence, path, "directory", recent_files::scan(path, since));
+        }
+        for path in &self.prefetch_dirs {
+            gather(&mut evidence, path, "prefetch directory", prefetch::scan(path, since));
+        }
+        for path in &self.amcache_files {
+            gather(&mut evidence, path, "Amcache", amcache::read(path, since));
+        }
+
+        evidence.retain(|found| found.timestamp.is_none_or(|timestamp| timestamp >= since));
+
+        mark_running(&mut evidence, &processes);
+
+        // Same program at the same time from the same source, e.g. a history listed twice
+        let mut seen = HashSet::new();
+        evidence.retain(|found| seen.insert((found.source, found.name.to_lowercase(), found.path.clone(), found.timestamp)));
+        evidence.sort_by_key(|found| Reverse(found.timestamp));
+        evidence
+    }
+}
+
+/// Marks evidence of programs that are among `processes`
+fn mark_running(evidence: &mut [ExecutionEvidence], processes: &[RunningProcess]) {
+    let running_names: HashSet<String> = processes.iter().map(|process| process.name.to_lowercase()).collect();
+    let running_paths: HashSet<String> = processes.iter()
+        .filter_map(|process| process.exe_path.as_deref().and_then(volume_path))
+        .collect();
+    for found in evidence {
+        // A loader renamed to explorer.exe must not pass for the real one, so full paths have to match
+        if let Some(path) = found.path.as_deref().and_then(volume_path) {
+            found.running |= running_paths.contains(&path);
+            continue;
+        }
+        // Prefetch names are cut off at 29 characters, a running process that starts the same counts
+        let name = found.path.as_deref().map(file_name).unwrap_or(&found.name).to_lowercase();
+        found.running |= running_names.contains(&name)
+            || (name.len() >= 29 && running_names.iter().any(|process| process.starts_with(&name)));
+    }
+}
+
+fn gather(evidence: &mut Vec<ExecutionEvidence>, path: &Path, what: &str, found: anyhow::Result<Vec<ExecutionEvidence>>) {
+    match found {
+        Ok(found) => {
+            log::debug!("Found {} programs in {} {}", found.len(), what, path.display());
+            evidence.extend(found);
+        }
+        Err(err) => log::warn!("Failed to read {} {}: {:#}", what, path.display(), err),
+    }
+}
+
+/// Lowercase path without drive or volume, None for relative paths. Prefetch writes `\VOLUME{...}\`
+/// or `\DEVICE\HARDDISKVOLUMEn\` where processes have `C:\`, the rest of the path is the same.
+fn volume_path(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\").to_lowercase();
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+    let rest = if let Some(rest) = path.strip_prefix(r"\device\") {
+        rest.split_once('\\')?.1
+    }
+    else if let Some(rest) = path.strip_prefix(r"\volume{") {
+        rest.split_once('\\')?.1
+    }
+    else if let Some(rest) = path.strip_prefix('\\') {
+        rest
+    }
+    else {
+        match path.as_bytes() {
+            [drive, b':', b'\\', ..] if drive.is_ascii_alphabetic() => &path[3..],
+            _ => return None,
+        }
+    };
+    Some(rest.to_string())
+}
+
+fn existing(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.into_iter().filter(|path| path.exists()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::core::{
+        forensics::{
+            execution_evidence::EvidenceSource,
+            registry_hive::test_hive::{build, TestKey},
+        },
+        temp_dir::TempDir,
+    };
+
+    #[test]
+    fn collects_copied_artifacts_within_the_lookback() {
+        let dir = TempDir::new("forensics_collector");
+        fs::create_dir_all(dir.join("downloads")).unwrap();
+        fs::create_dir_all(dir.join("prefetch")).unwrap();
+
+        let now = Utc::now();
+        let recent = now.timestamp() - 600;
+        let old = now.timestamp() - 3 * 24 * 60 * 60;
+        fs::write(dir.join("zsh_history"), format!(
+            ": {}:0;./old_loader\n: {}:0;wine ~/downloads/loader.exe\n: {}:0;git status\n", old, recent, recent,
+        )).unwrap();
+        fs::write(dir.join("downloads").join("loader.exe"), b"MZ").unwrap();
+        fs::write(dir.join("prefetch").join("LOADER.EXE-1A2B3C4D.pf"), b"").unwrap();
+
+        let mut entry = TestKey::new("0006a1b2c3").with_string("LowerCaseLongPath", r"c:\users\me\downloads\loader.exe");
+        entry.last_written = (recent as u64 + 11_644_473_600) * 10_000_000;
+        let hive = TestKey::new("{root}").with_subkey(TestKey::new("Root").with_subkey(TestKey::new("InventoryApplicationFile").with_subkey(entry)));
+        fs::write(dir.join("Amcache.hve"), build(&hive)).unwrap();
+
+        let mut collector = ForensicsCollector::new();
+        collector
+            .with_shell_history(dir.join("zsh_history"))
+            .with_shell_history(dir.join("zsh_history"))
+            .with_shell_history(dir.join("missing_history"))
+            .with_directory(dir.join("downloads"))
+            .with_prefetch_dir(dir.join("prefetch"))
+            .with_amcache(dir.join("Amcache.hve"));
+        let evidence = collector.collect_at(now);
+
+        let mut found: Vec<(EvidenceSource, &str)> = evidence.iter().map(|found| (found.source, found.name.as_str())).collect();
+        found.sort();
+        assert_eq!(found, [
+            (EvidenceSource::ShellHistory, "loader.exe"),
+            (EvidenceSource::RecentFile, "loader.exe"),
+            (EvidenceSource::Prefetch, "LOADER.EXE"),
+            (EvidenceSource::Amcache, "loader.exe"),
+        ]);
+        assert!(evidence.iter().all(|found| !found.running));
+        assert!(evidence.windows(2).all(|pair| pair[0].timestamp >= pair[1].timestamp));
+    }
+
+    #[test]
+    fn running_programs_match_by_full_path() {
+        let process = |name: &str, exe_path: Option<&str>| RunningProcess {
+            pid: 1, name: name.into(), exe_path: exe_path.map(str::to_string), started_at: None,
+        };
+        let processes = [
+            process("explorer.exe", Some(r"C:\Windows\explorer.exe")),
+            process("ASSAULTCUBE_TRAINER_PLUS_12.EXE", None),
+            process("loader", Some("/home/me/loader")),
+        ];
+        let mut evidence = [
+            ExecutionEvidence::new(EvidenceSource::Prefetch, "EXPLORER.EXE", "a.pf")
+                .with_path(r"\VOLUME{01da2b3c4d5e6f70-6c3a0f12}\WINDOWS\EXPLORER.EXE"),
+            ExecutionEvidence::new(EvidenceSource::Prefetch, "EXPLORER.EXE", "b.pf")
+                .with_path(r"\DEVICE\HARDDISKVOLUME3\USERS\ME\DOWNLOADS\EXPLORER.EXE"),
+            ExecutionEvidence::new(EvidenceSource::Prefetch, "ASSAULTCUBE_TRAINER_PLUS_12.E", "c.pf"),
+            ExecutionEvidence::new(EvidenceSource::ShellHistory, "loader", "history").with_path("/home/me/loader"),
+            ExecutionEvidence::new(EvidenceSource::ShellHistory, "loader", "history").with_path("/tmp/loader"),
+        ];
+        mark_running(&mut evidence, &processes);
+
+        let running: Vec<bool> = evidence.iter().map(|found| found.running).collect();
+        assert_eq!(running, [true, false, true, true, false]);
+    }
+
+    #[test]
+    fn lookback_longer_than_representable_keeps_everything() {
+        let mut collector = ForensicsCollector::new();
+        collector.with_lookback(Duration::MAX);
+        assert!(collector.collect_at(Utc::now()).is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+// A trace of a program that ran on the machine. Cheaters close their loader before the screenshare
+// starts, so what matters most here is evidence of programs that are no longer running.
+
+use std::fmt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceSource {
+    ProcessStart,   // A process that is still running, started within the lookback window
+    ShellHistory,   // A program run from bash, zsh, fish or PowerShell
+    RecentFile,     // An executable written to a temp or download directory
+    Prefetch,       // Windows Prefetch file of a program
+    Amcache,        // Windows Amcache.hve entry of a program
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionEvidence {
+    pub source: EvidenceSource,
+    pub name: String,                      // Executable file name
+    #[serde(default)]
+    pub path: Option<String>,              // Full path, where the source has one
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,  // When it ran (or was written, for recent files), None if unknown
+    #[serde(default)]
+    pub running: bool,                     // A process of that name was running at scan time
+    #[serde(default)]
+    pub details: String,
+    pub artifact: String,                  // File or place the evidence came from
+}
+
+impl ExecutionEvidence {
+    pub fn new(source: EvidenceSource, name: impl Into<String>, artifact: impl Into<String>) -> Self {
+        Self {
+            source,
+            name: name.into(),
+            path: None,
+            timestamp: None,
+            running: false,
+            details: String::new(),
+            artifact: artifact.into(),
+        }
+    }
+
+    // BUILDER METHODS -----------------------------------------------------------------------------
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: Option<DateTime<Utc>>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = details.into();
+        self
+    }
+}
+
+impl fmt::Display for EvidenceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ProcessStart => "process start",
+            Self::ShellHistory => "shell history",
+            Self::RecentFile => "recent file",
+            Self::Prefetch => "prefetch",
+            Self::Amcache => "amcache",
+        })
+    }
+}
+
+impl fmt::Display for ExecutionEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.source, self.path.as_deref().unwrap_or(&self.name))?;
+        match self.timestamp {
+            Some(timestamp) => write!(f, " at {}", timestamp.format("%Y-%m-%d %H:%M:%S UTC"))?,
+            None => write!(f, " (time unknown)")?,
+        }
+        if !self.running {
+            write!(f, ", not running")?;
+        }
+        if !self.details.is_empty() {
+            write!(f, ": {}", self.details)?;
+        }
+        Ok(())
+    }
+}
+
+/// Last path component of a Windows or Unix path
+pub(crate) fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
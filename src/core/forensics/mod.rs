@@ -0,0 +1,8 @@
+pub mod execution_evidence;
+pub mod collector;
+pub mod process_starts;
+pub mod shell_history;
+pub mod recent_files;
+pub mod prefetch;
+pub mod registry_hive;
+pub mod amcache;
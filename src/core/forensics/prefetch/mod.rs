@@ -0,0 +1,112 @@
+// Windows Prefetch files (C:\Windows\Prefetch\<EXECUTABLE>-<PATH HASH>.pf). Windows writes one for
+// every program it starts and updates it on every later start, so they tell what ran, how often and
+// when, even after the program was deleted. Files that can't be parsed still count, dated by their
+// modification time.
+
+pub mod prefetch_file;
+pub mod xpress_huffman;
+
+use std::{fs, path::Path};
+use chrono::{DateTime, Utc};
+use crate::core::forensics::{
+    execution_evidence::{EvidenceSource, ExecutionEvidence},
+    prefetch::prefetch_file::PrefetchFile,
+};
+
+/// Executable name and path hash of a Prefetch file name, e.g. ("LOADER.EXE", "1A2B3C4D")
+pub fn split_file_name(file_name: &str) -> Option<(&str, &str)> {
+    let stem = file_name.strip_suffix(".pf").or_else(|| file_name.strip_suffix(".PF"))?;
+    let (executable, hash) = stem.rsplit_once('-')?;
+    (!executable.is_empty() && hash.len() == 8 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .then_some((executable, hash))
+}
+
+/// A Prefetch file of a program that last ran at or after `since`, None for other files
+pub fn read(path: &Path, since: DateTime<Utc>) -> anyhow::Result<Option<ExecutionEvidence>> {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let Some((executable, hash)) = split_file_name(&file_name) else {
+        return Ok(None);
+    };
+
+    let prefetch = match PrefetchFile::from_file(path) {
+        Ok(prefetch) => prefetch,
+        Err(err) => {
+            log::warn!("Failed to parse prefetch file {}, using its modification time: {:#}", path.display(), err);
+            let last_run = DateTime::<Utc>::from(fs::metadata(path)?.modified()?);
+            return Ok((last_run >= since).then(|| {
+                ExecutionEvidence::new(EvidenceSource::Prefetch, executable, path.display().to_string())
+                    .with_timestamp(Some(last_run))
+                    .with_details(format!("path hash {}", hash))
+            }));
+        }
+    };
+
+    if prefetch.last_run().is_none_or(|last_run| last_run < since) {
+        return Ok(None);
+    }
+    Ok(Some(evidence(&prefetch, path)))
+}
+
+/// Evidence of the program a parsed Prefetch file belongs to
+pub fn evidence(prefetch: &PrefetchFile, path: &Path) -> ExecutionEvidence {
+    let mut details = vec![format!("ran {} times", prefetch.run_count)];
+    if prefetch.run_times.len() > 1 {
+        let earlier: Vec<String> = prefetch.run_times[1..].iter()
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .collect();
+        details.push(format!("before at {}", earlier.join(", ")));
+    }
+    details.push(format!("{} files loaded", prefetch.files.len()));
+    details.push(format!("path hash {:08X}", prefetch.hash));
+
+    let evidence = ExecutionEvidence::new(EvidenceSource::Prefetch, prefetch.executable.clone(), path.display().to_string())
+        .with_timestamp(prefetch.last_run())
+        .with_details(details.join(", "));
+    match prefetch.executable_path() {
+        Some(executable_path) => evidence.with_path(executable_path),
+        None => evidence,
+    }
+}
+
+/// Every program of the Prefetch directory (or a copy of it) that last ran at or after `since`
+pub fn scan(dir: &Path, since: DateTime<Utc>) -> anyhow::Result<Vec<ExecutionEvidence>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        match read(&path, since) {
+            Ok(Some(evidence)) => found.push(evidence),
+            Ok(None) => {}
+            Err(err) => log::warn!("Skipping prefetch file {}: {}", path.display(), err),
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_prefetch_file_names() {
+        assert_eq!(split_file_name("LOADER.EXE-1A2B3C4D.pf"), Some(("LOADER.EXE", "1A2B3C4D")));
+        assert_eq!(split_file_name("MY-TOOL.EXE-0000ABCD.pf"), Some(("MY-TOOL.EXE", "0000ABCD")));
+        assert_eq!(split_file_name("Layout.ini"), None);
+        assert_eq!(split_file_name("ReadyBoot.pf"), None);
+    }
+
+    #[test]
+    fn scans_the_sample_prefetch_directory() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/prefetch");
+        let since = "2026-10-01T00:00:00Z".parse().unwrap();
+        let mut found = scan(&dir, since).unwrap();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let names: Vec<&str> = found.iter().map(|evidence| evidence.name.as_str()).collect();
+        assert_eq!(names, ["ASSAULTCUBE_TRAINER_PLUS_12.E", "CHEATENGINE-X86_64.EXE", "INJECTOR.EXE"]);
+        let injector = &found[2];
+        assert_eq!(injector.path.as_deref(), Some(r"\VOLUME{01da2b3c4d5e6f70-6c3a0f12}\USERS\PLAYER\DOWNLOADS\INJECTOR.EXE"));
+        assert_eq!(injector.timestamp, Some("2026-10-17T09:12:40Z".parse().unwrap()));
+        assert!(injector.details.starts_with("ran 9 times, before at 2026-10-17 08:55:02, "));
+        assert!(injector.details.ends_with("3 files loaded, path hash 0C1D2E3F"));
+    }
+}
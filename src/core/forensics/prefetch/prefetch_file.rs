@@ -0,0 +1,424 @@
+// Contents of a Prefetch file: how often and when the program last ran, and the files and volumes it
+// touched in its first seconds. Versions 17 (XP, 2003), 23 (Vista, 7), 26 (8.1) and 30 (10, 11) are
+// read, Windows 10 and later store them MAM compressed.
+//
+// Layout: an 84 byte header (version, "SCCA", executable name, path hash) followed by the file
+// information, whose offsets point at the NUL separated file name strings and the volume entries.
+// Everything is little endian, times are FILETIMEs.
+
+use std::{cmp::Reverse, fmt, fs, path::Path};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::core::forensics::{
+    execution_evidence::file_name,
+    prefetch::xpress_huffman::{self, XpressError},
+    registry_hive::{decode_utf16, filetime, u32_at, u64_at},
+};
+
+const SIGNATURE: &[u8; 4] = b"SCCA";
+const HEADER_SIZE: usize = 84;
+const MAM_SIGNATURE: &[u8; 3] = b"MAM";
+const MAM_XPRESS_HUFFMAN: u8 = 0x04;
+const MAM_XPRESS_HUFFMAN_CRC: u8 = 0x84; // Followed by a CRC-32 of the compressed data
+const MAX_RUN_TIMES: usize = 8;
+const MAX_NAME_LENGTH: usize = 29;      // Longer executable names are cut off in the header and file name
+const MAX_SIZE: usize = 8 * 1024 * 1024; // Real files stay well below 1 MiB uncompressed
+
+#[derive(Debug, PartialEq)]
+pub enum PrefetchError {
+    NotPrefetch,
+    UnsupportedVersion(u32),
+    UnsupportedCompression(u8),
+    TooLarge(usize),         // Uncompressed size the MAM header claims
+    Decompression(XpressError),
+    Truncated(&'static str), // Part of the file that is cut off or points outside of it
+}
+
+impl fmt::Display for PrefetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotPrefetch => write!(f, "not a prefetch file (no SCCA or MAM signature)"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported prefetch version {}", version),
+            Self::UnsupportedCompression(format) => write!(f, "unsupported MAM compression {:#04x}", format),
+            Self::TooLarge(size) => write!(f, "uncompressed size of {} bytes is too large for a prefetch file", size),
+            Self::Decompression(err) => write!(f, "failed to decompress: {}", err),
+            Self::Truncated(what) => write!(f, "{} is outside the file", what),
+        }
+    }
+}
+
+impl std::error::Error for PrefetchError {}
+
+impl From<XpressError> for PrefetchError {
+    fn from(err: XpressError) -> Self {
+        Self::Decompression(err)
+    }
+}
+
+/// A volume the program accessed files on
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchVolume {
+    pub device_path: String,               // e.g. \VOLUME{01d9...-5a3c7e21} or \DEVICE\HARDDISKVOLUME3
+    pub serial: u32,
+    pub created: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PrefetchFile {
+    pub version: u32,
+    pub compressed: bool,                  // Stored MAM compressed
+    pub executable: String,                // At most 29 characters
+    pub hash: u32,                         // Of the executable's path (and command line, for hosts like svchost)
+    pub run_count: u32,
+    pub run_times: Vec<DateTime<Utc>>,     // Newest first, up to 8 on Windows 8 and later, 1 before
+    pub files: Vec<String>,                // Files loaded in the first seconds, with device paths
+    pub volumes: Vec<PrefetchVolume>,
+}
+
+impl PrefetchFile {
+    /// Parses a plain or MAM compressed Prefetch file
+    pub fn parse(data: &[u8]) -> Result<Self, PrefetchError> {
+        let Some(format) = data.strip_prefix(MAM_SIGNATURE).and_then(|rest| rest.first()) else {
+            return Self::parse_uncompressed(data);
+        };
+
+        let start = match *format {
+            MAM_XPRESS_HUFFMAN => 8,
+            MAM_XPRESS_HUFFMAN_CRC => 12,
+            format => return Err(PrefetchError::UnsupportedCompression(format)),
+        };
+        let size = u32_at(data, 4).ok_or(PrefetchError::Truncated("MAM header"))? as usize;
+        if size > MAX_SIZE {
+            return Err(PrefetchError::TooLarge(size));
+        }
+        let compressed = data.get(start..).ok_or(PrefetchError::Truncated("MAM header"))?;
+
+        let mut file = Self::parse_uncompressed(&xpress_huffman::decompress(compressed, size)?)?;
+        file.compressed = true;
+        Ok(file)
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::parse(&fs::read(path)?)?)
+    }
+
+    fn parse_uncompressed(data: &[u8]) -> Result<Self, PrefetchError> {
+        if data.get(4..8) != Some(SIGNATURE) {
+            return Err(PrefetchError::NotPrefetch);
+        }
+        let version = field(u32_at(data, 0), "header")?;
+        let volume_entry_size = match version {
+            17 => 40,
+            23 | 26 => 104,
+            30 => 96,
+            version => return Err(PrefetchError::UnsupportedVersion(version)),
+        };
+        let executable = decode_utf16(data.get(16..76).ok_or(PrefetchError::Truncated("header"))?);
+        let hash = field(u32_at(data, 76), "header")?;
+
+        let info = HEADER_SIZE;
+        let metrics_offset = field(u32_at(data, info), "file information")?;
+        let (run_times_offset, run_time_count, run_count_offset) = match version {
+            17 => (info + 36, 1, info + 60),
+            23 => (info + 44, 1, info + 68),
+            // Windows 10 builds from 2020 on moved the run count, the metrics then start 8 bytes earlier
+            _ if metrics_offset >= 0x130 => (info + 44, MAX_RUN_TIMES, info + 124),
+            _ => (info + 44, MAX_RUN_TIMES, info + 116),
+        };
+
+        let mut run_times = Vec::new();
+        for index in 0..run_time_count {
+            run_times.extend(filetime(field(u64_at(data, run_times_offset + index * 8), "run times")?));
+        }
+        run_times.sort_by_key(|time| Reverse(*time));
+        let run_count = field(u32_at(data, run_count_offset), "run count")?;
+
+        let names_offset = field(u32_at(data, info + 16), "file information")? as usize;
+        let names_size = field(u32_at(data, info + 20), "file information")? as usize;
+        let names = data.get(names_offset..names_offset.saturating_add(names_size))
+            .ok_or(PrefetchError::Truncated("file names"))?;
+
+        let volumes_offset = field(u32_at(data, info + 24), "file information")? as usize;
+        let volume_count = field(u32_at(data, info + 28), "file information")? as usize;
+        let mut volumes = Vec::new();
+        for index in 0..volume_count {
+            let entry = volumes_offset.saturating_add(index.saturating_mul(volume_entry_size));
+            if data.len() < entry.saturating_add(volume_entry_size) {
+                return Err(PrefetchError::Truncated("volume entry"));
+            }
+            let path_offset = volumes_offset.saturating_add(field(u32_at(data, entry), "volume entry")? as usize);
+            let path_length = field(u32_at(data, entry + 4), "volume entry")? as usize;
+            let path = data.get(path_offset..path_offset.saturating_add(path_length.saturating_mul(2)))
+                .ok_or(PrefetchError::Truncated("volume device path"))?;
+
+            volumes.push(PrefetchVolume {
+                device_path: decode_utf16(path),
+                serial: field(u32_at(data, entry + 16), "volume entry")?,
+                created: filetime(field(u64_at(data, entry + 8), "volume entry")?),
+            });
+        }
+
+        Ok(Self {
+            version,
+            compressed: false,
+            executable,
+            hash,
+            run_count,
+            run_times,
+            files: utf16_strings(names),
+            volumes,
+        })
+    }
+
+    /// When the program last ran
+    pub fn last_run(&self) -> Option<DateTime<Utc>> {
+        self.run_times.first().copied()
+    }
+
+    /// Full path of the executable, from the files it loaded
+    pub fn executable_path(&self) -> Option<&str> {
+        let executable = self.executable.to_uppercase();
+        self.files.iter()
+            .map(String::as_str)
+            .find(|file| {
+                let name = file_name(file).to_uppercase();
+                name == executable || (executable.len() >= MAX_NAME_LENGTH && name.starts_with(&executable))
+            })
+    }
+}
+
+impl fmt::Display for PrefetchVolume {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (serial {:04X}-{:04X}", self.device_path, self.serial >> 16, self.serial & 0xffff)?;
+        if let Some(created) = self.created {
+            write!(f, ", created {}", created.format("%Y-%m-%d %H:%M:%S UTC"))?;
+        }
+        write!(f, ")")
+    }
+}
+
+fn field<T>(value: Option<T>, what: &'static str) -> Result<T, PrefetchError> {
+    value.ok_or(PrefetchError::Truncated(what))
+}
+
+/// NUL separated UTF-16LE strings
+fn utf16_strings(bytes: &[u8]) -> Vec<String> {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    units.split(|unit| *unit == 0)
+        .filter(|string| !string.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect()
+}
+
+/// Builds Prefetch files for tests
+#[cfg(test)]
+pub(crate) mod test_prefetch {
+    use crate::core::forensics::prefetch::xpress_huffman;
+
+    pub struct TestPrefetch {
+        pub version: u32,
+        pub executable: String,
+        pub hash: u32,
+        pub run_count: u32,
+        pub run_times: Vec<u64>, // FILETIMEs, newest first
+        pub files: Vec<String>,
+        pub volumes: Vec<(String, u32, u64)>, // Device path, serial and creation FILETIME
+    }
+
+    impl TestPrefetch {
+        pub fn new(version: u32, executable: &str, hash: u32) -> Self {
+            Self {
+                version,
+                executable: executable.to_string(),
+                hash,
+                run_count: 0,
+                run_times: Vec::new(),
+                files: Vec::new(),
+                volumes: Vec::new(),
+            }
+        }
+
+        pub fn with_runs(mut self, run_count: u32, run_times: &[u64]) -> Self {
+            self.run_count = run_count;
+            self.run_times = run_times.to_vec();
+            self
+        }
+
+        pub fn with_file(mut self, path: &str) -> Self {
+            self.files.push(path.to_string());
+            self
+        }
+
+        pub fn with_volume(mut self, device_path: &str, serial: u32, created: u64) -> Self {
+            self.volumes.push((device_path.to_string(), serial, created));
+            self
+        }
+    }
+
+    /// Uncompressed file, in the layout of the version
+    pub fn build(prefetch: &TestPrefetch) -> Vec<u8> {
+        let (info_size, volume_entry_size) = match prefetch.version {
+            17 => (68, 40),
+            23 => (156, 104),
+            26 => (224, 104),
+            _ => (224, 96),
+        };
+
+        let mut data = vec![0u8; 84 + info_size];
+        put(&mut data, 0, &prefetch.version.to_le_bytes());
+        put(&mut data, 4, b"SCCA");
+        put(&mut data, 8, &(if prefetch.version == 17 { 0x0fu32 } else { 0x11 }).to_le_bytes());
+        put(&mut data, 16, &utf16(&prefetch.executable));
+        put(&mut data, 76, &prefetch.hash.to_le_bytes());
+
+        let info = 84;
+        // No metrics, they only repeat the file names with flags
+        let metrics_offset = data.len() as u32;
+        put(&mut data, info, &metrics_offset.to_le_bytes());
+        let (run_times_offset, run_count_offset) = match prefetch.version {
+            17 => (info + 36, info + 60),
+            23 => (info + 44, info + 68),
+            _ => (info + 44, info + 124),
+        };
+        for (index, time) in prefetch.run_times.iter().enumerate() {
+            put(&mut data, run_times_offset + index * 8, &time.to_le_bytes());
+        }
+        put(&mut data, run_count_offset, &prefetch.run_count.to_le_bytes());
+
+        let names_offset = data.len();
+        for file in &prefetch.files {
+            data.extend(utf16(file));
+            data.extend([0, 0]);
+        }
+        put(&mut data, info + 16, &(names_offset as u32).to_le_bytes());
+        let names_size = (data.len() - names_offset) as u32;
+        put(&mut data, info + 20, &names_size.to_le_bytes());
+        data.resize(data.len().next_multiple_of(8), 0);
+
+        let volumes_offset = data.len();
+        let mut strings = Vec::new();
+        let strings_offset = prefetch.volumes.len() * volume_entry_size;
+        for (device_path, serial, created) in &prefetch.volumes {
+            let mut entry = vec![0u8; volume_entry_size];
+            put(&mut entry, 0, &((strings_offset + strings.len()) as u32).to_le_bytes());
+            put(&mut entry, 4, &(device_path.encode_utf16().count() as u32).to_le_bytes());
+            put(&mut entry, 8, &created.to_le_bytes());
+            put(&mut entry, 16, &serial.to_le_bytes());
+            data.extend(entry);
+            strings.extend(utf16(device_path));
+            strings.extend([0, 0]);
+        }
+        data.extend(strings);
+        put(&mut data, info + 24, &(volumes_offset as u32).to_le_bytes());
+        put(&mut data, info + 28, &(prefetch.volumes.len() as u32).to_le_bytes());
+        let volumes_size = (data.len() - volumes_offset) as u32;
+        put(&mut data, info + 32, &volumes_size.to_le_bytes());
+
+        let size = data.len() as u32;
+        put(&mut data, 12, &size.to_le_bytes());
+        data
+    }
+
+    /// The file as Windows 10 stores it
+    pub fn compress(data: &[u8]) -> Vec<u8> {
+        let mut compressed = b"MAM\x04".to_vec();
+        compressed.extend((data.len() as u32).to_le_bytes());
+        compressed.extend(xpress_huffman::compress(data));
+        compressed
+    }
+
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn utf16(string: &str) -> Vec<u8> {
+        string.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_prefetch::{build, compress, TestPrefetch};
+
+    const NOTEPAD_XP: &[u8] = include_bytes!("../../../../tests/fixtures/prefetch/NOTEPAD.EXE-336351A9.pf");
+    const CHEATENGINE_WIN7: &[u8] = include_bytes!("../../../../tests/fixtures/prefetch/CHEATENGINE-X86_64.EXE-5A6B2D1C.pf");
+    const LOADER_WIN81: &[u8] = include_bytes!("../../../../tests/fixtures/prefetch/LOADER.EXE-8F3E2A10.pf");
+    const INJECTOR_WIN10: &[u8] = include_bytes!("../../../../tests/fixtures/prefetch/INJECTOR.EXE-0C1D2E3F.pf");
+    const TRAINER_WIN11: &[u8] = include_bytes!("../../../../tests/fixtures/prefetch/ASSAULTCUBE_TRAINER_PLUS_12.E-4D5E6F70.pf");
+
+    fn time(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn reads_every_version() {
+        let xp = PrefetchFile::parse(NOTEPAD_XP).unwrap();
+        assert_eq!((xp.version, xp.compressed, xp.executable.as_str(), xp.hash), (17, false, "NOTEPAD.EXE", 0x336351a9));
+        assert_eq!((xp.run_count, xp.run_times.as_slice()), (3, [time("2026-09-14T18:02:11Z")].as_slice()));
+        assert_eq!(xp.executable_path(), Some(r"\DEVICE\HARDDISKVOLUME1\WINDOWS\SYSTEM32\NOTEPAD.EXE"));
+        assert_eq!(xp.volumes[0].to_string(), r"\DEVICE\HARDDISKVOLUME1 (serial 6C3A-0F12, created 2019-03-02 10:00:00 UTC)");
+
+        let win7 = PrefetchFile::parse(CHEATENGINE_WIN7).unwrap();
+        assert_eq!((win7.version, win7.run_count, win7.last_run()), (23, 12, Some(time("2026-10-16T21:45:03Z"))));
+        assert_eq!(win7.executable_path(), Some(r"\DEVICE\HARDDISKVOLUME2\PROGRAM FILES\CHEAT ENGINE 7.5\CHEATENGINE-X86_64.EXE"));
+
+        let win81 = PrefetchFile::parse(LOADER_WIN81).unwrap();
+        assert_eq!((win81.version, win81.run_count, win81.run_times.len()), (26, 2, 2));
+        assert_eq!(win81.files.len(), 4);
+
+        let win10 = PrefetchFile::parse(INJECTOR_WIN10).unwrap();
+        assert_eq!((win10.version, win10.compressed, win10.run_count), (30, true, 9));
+        assert_eq!(win10.run_times.len(), 8);
+        assert!(win10.run_times.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(win10.last_run(), Some(time("2026-10-17T09:12:40Z")));
+        assert_eq!(win10.volumes.len(), 2);
+        assert_eq!(win10.executable_path(), Some(r"\VOLUME{01da2b3c4d5e6f70-6c3a0f12}\USERS\PLAYER\DOWNLOADS\INJECTOR.EXE"));
+    }
+
+    #[test]
+    fn finds_cut_off_executable_names() {
+        let trainer = PrefetchFile::parse(TRAINER_WIN11).unwrap();
+        assert_eq!(trainer.executable, "ASSAULTCUBE_TRAINER_PLUS_12.E");
+        assert_eq!(trainer.executable_path(), Some(r"\VOLUME{01da2b3c4d5e6f70-6c3a0f12}\USERS\PLAYER\DESKTOP\ASSAULTCUBE_TRAINER_PLUS_12.EXE"));
+    }
+
+    #[test]
+    fn reads_plain_and_compressed_files_alike() {
+        for version in [17, 23, 26, 30] {
+            let prefetch = TestPrefetch::new(version, "GAME.EXE", 0xdeadbeef)
+                .with_runs(1, &[134_116_992_000_000_000]) // 2026-01-01 00:00 UTC
+                .with_file(r"\DEVICE\HARDDISKVOLUME3\GAMES\GAME.EXE")
+                .with_volume(r"\DEVICE\HARDDISKVOLUME3", 0x12345678, 0);
+            let plain = PrefetchFile::parse(&build(&prefetch)).unwrap();
+            assert_eq!(plain.last_run(), Some(time("2026-01-01T00:00:00Z")));
+            assert_eq!(plain.volumes, [PrefetchVolume { device_path: r"\DEVICE\HARDDISKVOLUME3".into(), serial: 0x12345678, created: None }]);
+
+            let compressed = PrefetchFile::parse(&compress(&build(&prefetch))).unwrap();
+            assert_eq!(compressed, PrefetchFile { compressed: true, ..plain });
+        }
+    }
+
+    #[test]
+    fn reads_the_older_windows_10_layout() {
+        // Before 2020 the file information was 8 bytes shorter, with the run count at 116
+        let mut data = build(&TestPrefetch::new(30, "LOADER.EXE", 1).with_runs(0, &[134_116_992_000_000_000]));
+        data[84..88].copy_from_slice(&0x128u32.to_le_bytes());
+        data[84 + 116..84 + 120].copy_from_slice(&5u32.to_le_bytes());
+        assert_eq!(PrefetchFile::parse(&data).unwrap().run_count, 5);
+    }
+
+    #[test]
+    fn rejects_other_and_broken_files() {
+        assert_eq!(PrefetchFile::parse(b"MZ\x90\x00"), Err(PrefetchError::NotPrefetch));
+        assert_eq!(PrefetchFile::parse(b"MAM\x02\x10\x00\x00\x00"), Err(PrefetchError::UnsupportedCompression(2)));
+        assert!(matches!(PrefetchFile::parse(b"MAM\x04\x00\x10\x00\x00"), Err(PrefetchError::Decompression(_))));
+        assert_eq!(PrefetchFile::parse(b"MAM\x04\x00\x00\x00\x80"), Err(PrefetchError::TooLarge(0x8000_0000)));
+
+        let mut data = NOTEPAD_XP.to_vec();
+        data[0] = 31;
+        assert_eq!(PrefetchFile::parse(&data), Err(PrefetchError::UnsupportedVersion(31)));
+        assert_eq!(PrefetchFile::parse(&NOTEPAD_XP[..130]), Err(PrefetchError::Truncated("run count")));
+        assert_eq!(PrefetchFile::parse(&LOADER_WIN81[..LOADER_WIN81.len() - 20]), Err(PrefetchError::Truncated("volume device path")));
+    }
+}
@@ -0,0 +1,380 @@
+// LZXPRESS Huffman decompression ([MS-XCA] 2.2.4), used by the MAM compressed Prefetch files of
+// Windows 10 and later. Windows decompresses them with RtlDecompressBufferEx, this does the same from
+// the file alone.
+//
+// The data is a series of blocks of up to 64 KiB output each. A block starts with 256 bytes holding
+// the 4 bit code lengths of 512 symbols: 0-255 are literals, 256-511 matches, whose low nibble is the
+// length and high nibble the number of offset bits. The bits after the table are read as 16 bit little
+// endian words, most significant bit first, with extra length bytes taken from in between.
+
+use std::fmt;
+
+const BLOCK_SIZE: usize = 65536;
+const TABLE_SIZE: usize = 256;
+const SYMBOLS: usize = 512;
+const MAX_CODE_LENGTH: u32 = 15;
+
+#[derive(Debug, PartialEq)]
+pub enum XpressError {
+    TruncatedTable(usize),  // Input position of the block
+    InvalidTable(usize),    // Code lengths that don't form a prefix code
+    InvalidCode(usize),     // Bits that match no code, at this input position
+    InvalidMatch(usize),    // Match reaching before the start of the output, at this output position
+    MatchTooLong(usize),    // Match running past the end of its block, at this output position
+    TruncatedInput,
+}
+
+impl fmt::Display for XpressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TruncatedTable(position) => write!(f, "Huffman table at {:#x} is cut off", position),
+            Self::InvalidTable(position) => write!(f, "Huffman table at {:#x} is not a prefix code", position),
+            Self::InvalidCode(position) => write!(f, "no Huffman code matches the bits at {:#x}", position),
+            Self::InvalidMatch(position) => write!(f, "match at output {:#x} reaches before the start", position),
+            Self::MatchTooLong(position) => write!(f, "match at output {:#x} runs past the end of its block", position),
+            Self::TruncatedInput => write!(f, "compressed data ends early"),
+        }
+    }
+}
+
+impl std::error::Error for XpressError {}
+
+/// Symbol and code length for every 15 bit prefix of the input
+struct DecodingTable {
+    entries: Vec<(u16, u8)>, // Length 0 where no code matches
+}
+
+impl DecodingTable {
+    fn new(lengths: &[u8; SYMBOLS]) -> Option<Self> {
+        let mut entries = vec![(0u16, 0u8); 1 << MAX_CODE_LENGTH];
+        for (symbol, code, length) in canonical_codes(lengths)? {
+            let shift = MAX_CODE_LENGTH - length as u32;
+            let start = (code as usize) << shift;
+            entries[start..start + (1 << shift)].fill((symbol as u16, length));
+        }
+        Some(Self { entries })
+    }
+}
+
+/// Code of every symbol with a length, assigned in order of length then symbol. None if the lengths
+/// don't fit a prefix code.
+pub(crate) fn canonical_codes(lengths: &[u8; SYMBOLS]) -> Option<Vec<(usize, u32, u8)>> {
+    let mut symbols: Vec<(u8, usize)> = lengths.iter()
+        .enumerate()
+        .filter(|(_, length)| **length > 0)
+        .map(|(symbol, length)| (*length, symbol))
+        .collect();
+    symbols.sort();
+
+    let mut codes = Vec::with_capacity(symbols.len());
+    let mut code = 0u32;
+    let mut previous_length = 0u8;
+    for (length, symbol) in symbols {
+        code <<= length - previous_length;
+        if code >= 1 << length {
+            return None;
+        }
+        codes.push((symbol, code, length));
+        code += 1;
+        previous_length = length;
+    }
+    Some(codes)
+}
+
+struct Input<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Input<'_> {
+    // Past the end reads as zero: the last words of a stream are often only partly used
+    fn u16(&mut self) -> u32 {
+        let word = match self.data.get(self.position..self.position + 2) {
+            Some(bytes) => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            None => 0,
+        };
+        self.position += 2;
+        word
+    }
+
+    fn byte(&mut self) -> Result<u8, XpressError> {
+        let byte = *self.data.get(self.position).ok_or(XpressError::TruncatedInput)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn u16_strict(&mut self) -> Result<u32, XpressError> {
+        match self.data.get(self.position..self.position + 2) {
+            Some(_) => Ok(self.u16()),
+            None => Err(XpressError::TruncatedInput),
+        }
+    }
+
+    fn u32_strict(&mut self) -> Result<u32, XpressError> {
+        let bytes = self.data.get(self.position..self.position + 4).ok_or(XpressError::TruncatedInput)?;
+        self.position += 4;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Bits of the current block, most significant first
+struct BitReader {
+    next_bits: u32,
+    extra_bits: i32, // Bits left in next_bits beyond the 16 that are always there
+}
+
+impl BitReader {
+    fn new(input: &mut Input) -> Self {
+        let next_bits = (input.u16() << 16) | input.u16();
+        Self { next_bits, extra_bits: 16 }
+    }
+
+    fn peek(&self, count: u32) -> u32 {
+        match count {
+            0 => 0,
+            count => self.next_bits >> (32 - count),
+        }
+    }
+
+    fn skip(&mut self, count: u32, input: &mut Input) {
+        if count == 0 {
+            return;
+        }
+        self.next_bits = self.next_bits.checked_shl(count).unwrap_or(0);
+        self.extra_bits -= count as i32;
+        if self.extra_bits < 0 {
+            self.next_bits |= input.u16() << (-self.extra_bits) as u32;
+            self.extra_bits += 16;
+        }
+    }
+}
+
+/// Decompresses `data` into exactly `size` bytes
+pub fn decompress(data: &[u8], size: usize) -> Result<Vec<u8>, XpressError> {
+    let mut output = Vec::with_capacity(size.min(64 * BLOCK_SIZE));
+    let mut input = Input { data, position: 0 };
+
+    while output.len() < size {
+        let block_start = input.position;
+        let table = data.get(block_start..block_start + TABLE_SIZE).ok_or(XpressError::TruncatedTable(block_start))?;
+        let mut lengths = [0u8; SYMBOLS];
+        for (index, byte) in table.iter().enumerate() {
+            lengths[index * 2] = byte & 0x0f;
+            lengths[index * 2 + 1] = byte >> 4;
+        }
+        let decoding = DecodingTable::new(&lengths).ok_or(XpressError::InvalidTable(block_start))?;
+
+        input.position += TABLE_SIZE;
+        let mut bits = BitReader::new(&mut input);
+        let block_end = (output.len() + BLOCK_SIZE).min(size);
+
+        while output.len() < block_end {
+            let (symbol, length) = decoding.entries[bits.peek(MAX_CODE_LENGTH) as usize];
+            if length == 0 {
+                return Err(XpressError::InvalidCode(input.position));
+            }
+            bits.skip(length as u32, &mut input);
+
+            if symbol < 256 {
+                output.push(symbol as u8);
+                continue;
+            }
+
+            let symbol = symbol as usize - 256;
+            let mut match_length = symbol & 15;
+            let offset_bits = (symbol >> 4) as u32;
+            if match_length == 15 {
+                match_length = input.byte()? as usize;
+                if match_length == 255 {
+                    match_length = input.u16_strict()? as usize;
+                    if match_length == 0 {
+                        match_length = input.u32_strict()? as usize;
+                    }
+                    if match_length < 15 {
+                        return Err(XpressError::TruncatedInput);
+                    }
+                    match_length -= 15;
+                }
+                match_length += 15;
+            }
+            match_length += 3;
+
+            let offset = (bits.peek(offset_bits) + (1 << offset_bits)) as usize;
+            bits.skip(offset_bits, &mut input);
+
+            let start = output.len().checked_sub(offset).ok_or(XpressError::InvalidMatch(output.len()))?;
+            // Windows never lets a match cross into the next block, a longer one is a forged length
+            if match_length > block_end - output.len() {
+                return Err(XpressError::MatchTooLong(output.len()));
+            }
+            // Overlapping matches repeat what they just wrote, so this goes byte by byte
+            for index in 0..match_length {
+                output.push(output[start + index]);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Compresses for tests: greedy matches and a fixed set of code lengths, the same bit layout Windows
+/// uses but without its compression ratio
+#[cfg(test)]
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    use std::collections::{HashMap, VecDeque};
+
+    // Short codes for ASCII, longer ones for the rest and for matches
+    let mut lengths = [0u8; SYMBOLS];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=127 => 8,
+            128..=255 => 10,
+            _ => 11,
+        };
+    }
+    let codes: HashMap<usize, (u32, u8)> = canonical_codes(&lengths).unwrap()
+        .into_iter()
+        .map(|(symbol, code, length)| (symbol, (code, length)))
+        .collect();
+
+    struct BitWriter {
+        output: Vec<u8>,
+        slots: VecDeque<usize>, // Reserved places of the 16 bit words, in the order the reader takes them
+        word: u32,
+        bit_count: u32,         // Bits in word
+        written: u64,           // Bits written in this block
+    }
+
+    impl BitWriter {
+        fn reserve(&mut self) {
+            self.slots.push_back(self.output.len());
+            self.output.extend([0, 0]);
+        }
+
+        fn bits(&mut self, value: u32, count: u32) {
+            for bit in (0..count).rev() {
+                // The reader takes the next word as soon as it has used more than all but 16 bits
+                if self.written > 0 && self.written.is_multiple_of(16) {
+                    self.reserve();
+                }
+                self.word = (self.word << 1) | ((value >> bit) & 1);
+                self.bit_count += 1;
+                self.written += 1;
+                if self.bit_count == 16 {
+                    let slot = self.slots.pop_front().unwrap();
+                    self.output[slot..slot + 2].copy_from_slice(&(self.word as u16).to_le_bytes());
+                    self.word = 0;
+                    self.bit_count = 0;
+                }
+            }
+        }
+
+        fn finish_block(&mut self) {
+            if self.bit_count > 0 {
+                self.bits(0, 16 - self.bit_count);
+            }
+            self.slots.clear();
+        }
+    }
+
+    let mut writer = BitWriter { output: Vec::new(), slots: VecDeque::new(), word: 0, bit_count: 0, written: 0 };
+    let mut recent: HashMap<&[u8], usize> = HashMap::new();
+
+    for chunk_start in (0..data.len()).step_by(BLOCK_SIZE) {
+        let chunk_end = (chunk_start + BLOCK_SIZE).min(data.len());
+        writer.output.extend(lengths.chunks(2).map(|pair| pair[0] | (pair[1] << 4)));
+        writer.written = 0;
+        writer.reserve();
+        writer.reserve();
+
+        let mut position = chunk_start;
+        while position < chunk_end {
+            let candidate = data.get(position..position + 3)
+                .filter(|_| position + 3 <= chunk_end)
+                .and_then(|key| recent.get(key).copied())
+                .filter(|start| position - start < 65536);
+            let match_length = candidate.map_or(0, |start| {
+                (0..chunk_end - position).take_while(|index| data[start + index] == data[position + index]).count()
+            });
+
+            if let (Some(start), true) = (candidate, match_length >= 3) {
+                let offset = position - start;
+                let offset_bits = usize::BITS - 1 - offset.leading_zeros();
+                let length_nibble = (match_length - 3).min(15);
+                let (code, length) = codes[&(256 + ((offset_bits as usize) << 4) + length_nibble)];
+                writer.bits(code, length as u32);
+                if length_nibble == 15 {
+                    match match_length - 3 - 15 {
+                        short if short < 255 => writer.output.push(short as u8),
+                        _ => {
+                            writer.output.push(255);
+                            writer.output.extend(((match_length - 3) as u16).to_le_bytes());
+                        }
+                    }
+                }
+                writer.bits((offset - (1 << offset_bits)) as u32, offset_bits);
+
+                for index in position..position + match_length {
+                    if let Some(key) = data.get(index..index + 3) {
+                        recent.insert(key, index);
+                    }
+                }
+                position += match_length;
+            }
+            else {
+                let (code, length) = codes[&(data[position] as usize)];
+                writer.bits(code, length as u32);
+                if let Some(key) = data.get(position..position + 3) {
+                    recent.insert(key, position);
+                }
+                position += 1;
+            }
+        }
+        writer.finish_block();
+    }
+
+    writer.output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_literals_and_matches_across_blocks() {
+        // Repetitive enough for long matches, varied enough for literals of every length
+        let mut data = Vec::new();
+        for index in 0u32..60_000 {
+            data.extend(format!("{:x}", index.wrapping_mul(2_654_435_761) >> 24).as_bytes());
+            if index % 1000 == 0 {
+                data.extend([0xffu8; 700]);
+                data.extend(0u8..=255);
+            }
+        }
+        assert!(data.len() > 2 * BLOCK_SIZE);
+
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn rejects_broken_input() {
+        assert_eq!(decompress(&[0; 100], 10), Err(XpressError::TruncatedTable(0)));
+
+        // Every symbol with length 1 is no prefix code
+        assert_eq!(decompress(&[0x11; 300], 10), Err(XpressError::InvalidTable(0)));
+
+        // Only the match symbol 256 (offset 1) has a code, with nothing before it to repeat
+        let mut table = [0u8; 260];
+        table[128] = 0x01;
+        assert_eq!(decompress(&table, 10), Err(XpressError::InvalidMatch(0)));
+
+        // A literal, then a match with a 32 bit length of 2 GiB
+        let mut table = [0u8; 256].to_vec();
+        table[32] = 0x10;  // 'A', code 0
+        table[135] = 0x10; // Match of length 15 + extra bytes and offset 1, code 1
+        table.extend([0x00, 0x40, 0x00, 0x00, 0xff, 0x00, 0x00, 0xff, 0xff, 0xff, 0x7f]);
+        assert_eq!(decompress(&table, 1 << 20), Err(XpressError::MatchTooLong(1)));
+    }
+}
@@ -0,0 +1,74 @@
+// Processes running at scan time with their start times. A helper started shortly before the
+// screenshare shows up here, and the names tell which programs of the other sources are still running.
+
+use chrono::{DateTime, Utc};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use crate::core::forensics::execution_evidence::{EvidenceSource, ExecutionEvidence};
+
+/// A process of the system snapshot
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunningProcess {
+    pub pid: u32,
+    pub name: String,
+    pub exe_path: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Every process of the machine
+pub fn snapshot() -> Vec<RunningProcess> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::Always),
+    );
+
+    system.processes()
+        .iter()
+        .map(|(pid, process)| RunningProcess {
+            pid: pid.as_u32(),
+            name: process.name().to_string_lossy().to_string(),
+            exe_path: process.exe().map(|path| path.to_string_lossy().to_string()),
+            started_at: DateTime::from_timestamp(process.start_time() as i64, 0).filter(|_| process.start_time() > 0),
+        })
+        .collect()
+}
+
+/// Processes started at or after `since`, oldest first
+pub fn started_since(processes: &[RunningProcess], since: DateTime<Utc>) -> Vec<ExecutionEvidence> {
+    let mut started: Vec<&RunningProcess> = processes.iter()
+        .filter(|process| process.started_at.is_some_and(|started_at| started_at >= since))
+        .collect();
+    started.sort_by_key(|process| (process.started_at, process.pid));
+
+    started.into_iter()
+        .map(|process| {
+            let mut evidence = ExecutionEvidence::new(EvidenceSource::ProcessStart, process.name.clone(), "running processes")
+                .with_timestamp(process.started_at)
+                .with_details(format!("PID {}", process.pid));
+            if let Some(exe_path) = &process.exe_path {
+                evidence = evidence.with_path(exe_path.clone());
+            }
+            evidence.running = true;
+            evidence
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn finds_ourselves_among_recent_processes() {
+        let processes = snapshot();
+        let pid = std::process::id();
+        let us = processes.iter().find(|process| process.pid == pid).expect("own process missing");
+        let started_at = us.started_at.expect("own start time missing");
+
+        let recent = started_since(&processes, started_at - Duration::seconds(1));
+        assert!(recent.iter().any(|evidence| evidence.details == format!("PID {}", pid) && evidence.running));
+        assert!(started_since(&processes, Utc::now() + Duration::hours(1)).is_empty());
+    }
+}
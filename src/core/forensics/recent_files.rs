@@ -0,0 +1,120 @@
+// Executables written to temp and download directories shortly before the screenshare. Loaders are
+// usually downloaded, unpacked into a temp directory and deleted again afterwards, so whatever is
+// still there is worth a look.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+use chrono::{DateTime, Utc};
+use crate::core::forensics::{
+    execution_evidence::{EvidenceSource, ExecutionEvidence},
+    shell_history::EXECUTABLE_EXTENSIONS,
+};
+
+/// How deep below a directory to look, archives are usually unpacked one or two levels down
+pub const MAX_DEPTH: usize = 3;
+
+/// Executables below the directory modified at or after `since`
+pub fn scan(dir: &Path, since: DateTime<Utc>) -> anyhow::Result<Vec<ExecutionEvidence>> {
+    let mut found = Vec::new();
+    walk(dir, since, 0, &mut found)?;
+    Ok(found)
+}
+
+fn walk(dir: &Path, since: DateTime<Utc>, depth: usize, found: &mut Vec<ExecutionEvidence>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        // Symlinks aren't followed, they could lead anywhere (or in a circle)
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+
+        if file_type.is_dir() {
+            if depth + 1 < MAX_DEPTH {
+                // Other users' directories in a shared temp directory can't be read, that's fine
+                if let Err(err) = walk(&path, since, depth + 1, found) {
+                    log::debug!("Skipping {}: {}", path.display(), err);
+                }
+            }
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let Some(modified) = metadata.modified().ok().map(DateTime::<Utc>::from) else {
+            continue;
+        };
+        if modified < since || !is_executable(&path, &metadata) {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        found.push(ExecutionEvidence::new(EvidenceSource::RecentFile, name, dir.display().to_string())
+            .with_path(path.display().to_string())
+            .with_timestamp(Some(modified))
+            .with_details(format!("{} bytes written", metadata.len())));
+    }
+
+    Ok(())
+}
+
+/// By extension, executable bit or PE/ELF header
+fn is_executable(path: &Path, metadata: &fs::Metadata) -> bool {
+    let has_extension = path.extension()
+        .is_some_and(|extension| EXECUTABLE_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str()));
+    if has_extension {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 != 0 {
+            return true;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+
+    // Renamed executables (loader.tmp, update.dat) still start with their header
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|_| magic.starts_with(b"MZ") || magic == *b"\x7fELF")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::core::temp_dir::TempDir;
+
+    #[test]
+    fn finds_recent_executables_only() {
+        let dir = TempDir::new("recent_files");
+        fs::create_dir_all(dir.join("unpacked").join("bin").join("too_deep")).unwrap();
+        fs::write(dir.join("loader.exe"), b"anything").unwrap();
+        fs::write(dir.join("unpacked").join("update.dat"), b"MZ\x90\x00rest").unwrap();
+        fs::write(dir.join("unpacked").join("readme.txt"), b"nothing to run").unwrap();
+        fs::write(dir.join("unpacked").join("bin").join("deep.exe"), b"MZ").unwrap();
+        fs::write(dir.join("unpacked").join("bin").join("too_deep").join("ignored.exe"), b"MZ").unwrap();
+
+        let mut names: Vec<String> = scan(dir.path(), Utc::now() - Duration::hours(1)).unwrap()
+            .into_iter()
+            .map(|evidence| evidence.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["deep.exe", "loader.exe", "update.dat"]);
+
+        assert!(scan(dir.path(), Utc::now() + Duration::hours(1)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,476 @@
+// Read-only parser for registry hive files (regf), enough to walk keys and read small values of a
+// copied hive such as Amcache.hve without any Windows API.
+//
+// Layout: a 4096 byte base block, then hive bins holding cells. Cell offsets are relative to the end
+// of the base block, every cell starts with its size (negative while allocated). Keys are "nk" cells,
+// values "vk" cells, subkeys are listed by "lf", "lh", "li" or "ri" cells.
+
+use std::{fmt, fs, path::Path};
+use chrono::{DateTime, Utc};
+
+const BASE_BLOCK_SIZE: usize = 4096;
+const KEY_COMP_NAME: u16 = 0x20;    // Key name is Latin-1 instead of UTF-16
+const VALUE_COMP_NAME: u16 = 0x01;  // Value name is Latin-1 instead of UTF-16
+const DATA_INLINE: u32 = 0x8000_0000;
+const MAX_LIST_DEPTH: usize = 8;    // Nesting of "ri" lists, real hives use one level
+const MAX_LIST_ENTRIES: usize = 1 << 20; // Entries visited for one key, nested lists can repeat each other
+
+pub const REG_SZ: u32 = 1;
+pub const REG_EXPAND_SZ: u32 = 2;
+pub const REG_BINARY: u32 = 3;
+pub const REG_DWORD: u32 = 4;
+pub const REG_QWORD: u32 = 11;
+
+#[derive(Debug)]
+pub enum HiveError {
+    NotAHive,
+    Truncated(usize),                   // Offset that points outside the file
+    UnexpectedCell(usize, &'static str), // Offset and the cell type that was expected there
+    TooManySubkeys(usize),              // Offset of a subkey list with more entries than a hive can hold
+}
+
+impl fmt::Display for HiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAHive => write!(f, "not a registry hive (no regf signature)"),
+            Self::Truncated(offset) => write!(f, "cell at {:#x} is outside the hive", offset),
+            Self::UnexpectedCell(offset, expected) => write!(f, "cell at {:#x} is not a {} cell", offset, expected),
+            Self::TooManySubkeys(offset) => write!(f, "subkey list at {:#x} has more than {} entries", offset, MAX_LIST_ENTRIES),
+        }
+    }
+}
+
+impl std::error::Error for HiveError {}
+
+pub struct RegistryHive {
+    data: Vec<u8>,
+    root: usize,
+}
+
+/// A key of a hive
+#[derive(Clone, Copy)]
+pub struct RegistryKey<'a> {
+    hive: &'a RegistryHive,
+    offset: usize, // Of the nk cell's data
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryValue {
+    String(String),
+    Dword(u32),
+    Qword(u64),
+    Binary(Vec<u8>),
+}
+
+impl RegistryHive {
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, HiveError> {
+        if data.len() < BASE_BLOCK_SIZE || &data[..4] != b"regf" {
+            return Err(HiveError::NotAHive);
+        }
+
+        let root = u32_at(&data, 0x24).ok_or(HiveError::NotAHive)? as usize;
+        let hive = Self { data, root };
+        hive.key(root)?;
+        Ok(hive)
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::from_bytes(fs::read(path)?)?)
+    }
+
+    pub fn root(&self) -> RegistryKey<'_> {
+        RegistryKey { hive: self, offset: self.cell(self.root).expect("root checked on load").0 }
+    }
+
+    /// Key below the root by backslash separated path, ignoring case (e.g. "Root\\InventoryApplicationFile")
+    pub fn open(&self, path: &str) -> Result<Option<RegistryKey<'_>>, HiveError> {
+        let mut key = self.root();
+        for name in path.split('\\').filter(|name| !name.is_empty()) {
+            match key.subkey(name)? {
+                Some(subkey) => key = subkey,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(key))
+    }
+
+    fn key(&self, cell: usize) -> Result<RegistryKey<'_>, HiveError> {
+        let (offset, data) = self.cell(cell)?;
+        if data.len() < 76 || &data[..2] != b"nk" {
+            return Err(HiveError::UnexpectedCell(cell, "nk"));
+        }
+        Ok(RegistryKey { hive: self, offset })
+    }
+
+    /// Start and data of a cell, without the size field
+    fn cell(&self, cell: usize) -> Result<(usize, &[u8]), HiveError> {
+        let start = BASE_BLOCK_SIZE.checked_add(cell).ok_or(HiveError::Truncated(cell))?;
+        let size = i32_at(&self.data, start).ok_or(HiveError::Truncated(cell))?.unsigned_abs() as usize;
+        let end = start.checked_add(size).filter(|end| size >= 4 && *end <= self.data.len())
+            .ok_or(HiveError::Truncated(cell))?;
+        Ok((start + 4, &self.data[start + 4..end]))
+    }
+
+    /// Offsets of every key in a subkey list, following "ri" lists of lists. Every entry visited, nested
+    /// or not, counts against `budget`.
+    fn subkey_offsets(&self, list: usize, depth: usize, offsets: &mut Vec<usize>, budget: &mut usize) -> Result<(), HiveError> {
+        let (_, data) = self.cell(list)?;
+        if data.len() < 4 || depth > MAX_LIST_DEPTH {
+            return Err(HiveError::UnexpectedCell(list, "subkey list"));
+        }
+        let count = u16_at(data, 2).unwrap_or(0) as usize;
+
+        let (stride, nested) = match &data[..2] {
+            b"lf" | b"lh" => (8, false),
+            b"li" => (4, false),
+            b"ri" => (4, true),
+            _ => return Err(HiveError::UnexpectedCell(list, "subkey list")),
+        };
+
+        for index in 0..count {
+            *budget = budget.checked_sub(1).ok_or(HiveError::TooManySubkeys(list))?;
+            let offset = u32_at(data, 4 + index * stride).ok_or(HiveError::Truncated(list))? as usize;
+            match nested {
+                true => self.subkey_offsets(offset, depth + 1, offsets, budget)?,
+                false => offsets.push(offset),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> RegistryKey<'a> {
+    fn data(&self) -> &'a [u8] {
+        // Checked when the key was created
+        let size = i32_at(&self.hive.data, self.offset - 4).unwrap_or(0).unsigned_abs() as usize;
+        &self.hive.data[self.offset..self.offset - 4 + size]
+    }
+
+    pub fn name(&self) -> String {
+        let data = self.data();
+        let flags = u16_at(data, 2).unwrap_or(0);
+        let length = u16_at(data, 72).unwrap_or(0) as usize;
+        let name = data.get(76..76 + length).unwrap_or_default();
+        decode_name(name, flags & KEY_COMP_NAME != 0)
+    }
+
+    /// When the key or one of its values was last changed
+    pub fn last_written(&self) -> Option<DateTime<Utc>> {
+        filetime(u64_at(self.data(), 4)?)
+    }
+
+    pub fn subkeys(&self) -> Result<Vec<RegistryKey<'a>>, HiveError> {
+        let data = self.data();
+        let count = u32_at(data, 20).unwrap_or(0);
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let list = u32_at(data, 28).unwrap_or(0) as usize;
+        let mut offsets = Vec::new();
+        let mut budget = MAX_LIST_ENTRIES;
+        self.hive.subkey_offsets(list, 0, &mut offsets, &mut budget).map_err(|err| match err {
+            HiveError::TooManySubkeys(_) => HiveError::TooManySubkeys(list),
+            err => err,
+        })?;
+        offsets.into_iter().map(|offset| self.hive.key(offset)).collect()
+    }
+
+    pub fn subkey(&self, name: &str) -> Result<Option<RegistryKey<'a>>, HiveError> {
+        Ok(self.subkeys()?.into_iter().find(|key| key.name().eq_ignore_ascii_case(name)))
+    }
+
+    /// Every value of the key by name. Values too big for one cell (over 16 KB) are left out.
+    pub fn values(&self) -> Result<Vec<(String, RegistryValue)>, HiveError> {
+        let data = self.data();
+        let count = u32_at(data, 36).unwrap_or(0) as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let list_cell = u32_at(data, 40).unwrap_or(0) as usize;
+        let (_, list) = self.hive.cell(list_cell)?;
+        let mut values = Vec::new();
+        for index in 0..count {
+            let cell = u32_at(list, index * 4).ok_or(HiveError::Truncated(list_cell))? as usize;
+            if let Some(value) = self.value_at(cell)? {
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+
+    pub fn value(&self, name: &str) -> Result<Option<RegistryValue>, HiveError> {
+        Ok(self.values()?.into_iter().find(|(value_name, _)| value_name.eq_ignore_ascii_case(name)).map(|(_, value)| value))
+    }
+
+    fn value_at(&self, cell: usize) -> Result<Option<(String, RegistryValue)>, HiveError> {
+        let (_, data) = self.hive.cell(cell)?;
+        if data.len() < 20 || &data[..2] != b"vk" {
+            return Err(HiveError::UnexpectedCell(cell, "vk"));
+        }
+
+        let name_length = u16_at(data, 2).unwrap_or(0) as usize;
+        let size = u32_at(data, 4).unwrap_or(0);
+        let data_offset = u32_at(data, 8).unwrap_or(0);
+        let value_type = u32_at(data, 12).unwrap_or(0);
+        let flags = u16_at(data, 16).unwrap_or(0);
+        let name = decode_name(data.get(20..20 + name_length).unwrap_or_default(), flags & VALUE_COMP_NAME != 0);
+
+        let bytes = if size & DATA_INLINE != 0 {
+            // Up to 4 bytes are kept in the offset field itself
+            let length = (size & !DATA_INLINE).min(4) as usize;
+            data[8..8 + length].to_vec()
+        }
+        else {
+            let (_, cell_data) = self.hive.cell(data_offset as usize)?;
+            match cell_data.get(..size as usize) {
+                Some(bytes) if !cell_data.starts_with(b"db") => bytes.to_vec(),
+                _ => return Ok(None),
+            }
+        };
+
+        let value = match value_type {
+            REG_SZ | REG_EXPAND_SZ => RegistryValue::String(decode_utf16(&bytes)),
+            REG_DWORD if bytes.len() >= 4 => RegistryValue::Dword(u32_at(&bytes, 0).unwrap_or(0)),
+            REG_QWORD if bytes.len() >= 8 => RegistryValue::Qword(u64_at(&bytes, 0).unwrap_or(0)),
+            _ => RegistryValue::Binary(bytes),
+        };
+        Ok(Some((name, value)))
+    }
+}
+
+impl RegistryValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Dword(value) => Some(*value as u64),
+            Self::Qword(value) => Some(*value),
+            Self::String(value) => value.trim().parse().ok(),
+            Self::Binary(_) => None,
+        }
+    }
+}
+
+/// Windows FILETIME (100ns intervals since 1601) as a UTC time, None for 0 and out of range values
+pub fn filetime(value: u64) -> Option<DateTime<Utc>> {
+    const UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+    let since_epoch = value.checked_sub(UNIX_EPOCH).filter(|_| value != 0)?;
+    DateTime::from_timestamp((since_epoch / 10_000_000) as i64, (since_epoch % 10_000_000) as u32 * 100)
+}
+
+fn decode_name(bytes: &[u8], latin1: bool) -> String {
+    match latin1 {
+        true => bytes.iter().map(|byte| *byte as char).collect(),
+        false => decode_utf16(bytes),
+    }
+}
+
+/// UTF-16LE up to the first NUL
+pub(crate) fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+pub(crate) fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+pub(crate) fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn i32_at(data: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+pub(crate) fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+/// Builds hive files for tests
+#[cfg(test)]
+pub(crate) mod test_hive {
+    use super::*;
+
+    pub struct TestKey {
+        pub name: String,
+        pub last_written: u64, // FILETIME
+        pub values: Vec<(String, u32, Vec<u8>)>,
+        pub subkeys: Vec<TestKey>,
+    }
+
+    impl TestKey {
+        pub fn new(name: &str) -> Self {
+            Self { name: name.into(), last_written: 0, values: Vec::new(), subkeys: Vec::new() }
+        }
+
+        pub fn with_string(mut self, name: &str, value: &str) -> Self {
+            let bytes: Vec<u8> = value.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+            self.values.push((name.into(), REG_SZ, bytes));
+            self
+        }
+
+        pub fn with_qword(mut self, name: &str, value: u64) -> Self {
+            self.values.push((name.into(), REG_QWORD, value.to_le_bytes().to_vec()));
+            self
+        }
+
+        pub fn with_dword(mut self, name: &str, value: u32) -> Self {
+            self.values.push((name.into(), REG_DWORD, value.to_le_bytes().to_vec()));
+            self
+        }
+
+        pub fn with_subkey(mut self, key: TestKey) -> Self {
+            self.subkeys.push(key);
+            self
+        }
+    }
+
+    /// A hive file with `root` as its root key
+    pub fn build(root: &TestKey) -> Vec<u8> {
+        let mut bins = vec![0u8; 0x20];
+        bins[..4].copy_from_slice(b"hbin");
+        let root_cell = write_key(&mut bins, root);
+        let size = bins.len() as u32;
+        bins[8..12].copy_from_slice(&size.to_le_bytes());
+
+        let mut hive = vec![0u8; BASE_BLOCK_SIZE];
+        hive[..4].copy_from_slice(b"regf");
+        hive[0x24..0x28].copy_from_slice(&(root_cell as u32).to_le_bytes());
+        hive[0x28..0x2c].copy_from_slice(&size.to_le_bytes());
+        hive.extend(bins);
+        hive
+    }
+
+    fn alloc(bins: &mut Vec<u8>, data: &[u8]) -> usize {
+        let offset = bins.len();
+        let size = (data.len() + 4).div_ceil(8) * 8;
+        bins.extend((-(size as i32)).to_le_bytes());
+        bins.extend(data);
+        bins.resize(offset + size, 0);
+        offset
+    }
+
+    fn write_key(bins: &mut Vec<u8>, key: &TestKey) -> usize {
+        let subkeys: Vec<usize> = key.subkeys.iter().map(|subkey| write_key(bins, subkey)).collect();
+        let subkey_list = match subkeys.is_empty() {
+            true => u32::MAX as usize,
+            false => {
+                let mut list = b"lf".to_vec();
+                list.extend((subkeys.len() as u16).to_le_bytes());
+                for offset in &subkeys {
+                    list.extend((*offset as u32).to_le_bytes());
+                    list.extend([0; 4]);
+                }
+                alloc(bins, &list)
+            }
+        };
+
+        let values: Vec<usize> = key.values.iter().map(|(name, value_type, bytes)| {
+            let (size, data_offset) = match bytes.len() <= 4 {
+                true => {
+                    let mut inline = [0u8; 4];
+                    inline[..bytes.len()].copy_from_slice(bytes);
+                    (bytes.len() as u32 | DATA_INLINE, u32::from_le_bytes(inline))
+                }
+                false => (bytes.len() as u32, alloc(bins, bytes) as u32),
+            };
+
+            let mut vk = b"vk".to_vec();
+            vk.extend((name.len() as u16).to_le_bytes());
+            vk.extend(size.to_le_bytes());
+            vk.extend(data_offset.to_le_bytes());
+            vk.extend(value_type.to_le_bytes());
+            vk.extend(VALUE_COMP_NAME.to_le_bytes());
+            vk.extend([0; 2]);
+            vk.extend(name.as_bytes());
+            alloc(bins, &vk)
+        }).collect();
+        let value_list = match values.is_empty() {
+            true => u32::MAX as usize,
+            false => alloc(bins, &values.iter().flat_map(|offset| (*offset as u32).to_le_bytes()).collect::<Vec<u8>>()),
+        };
+
+        let mut nk = vec![0u8; 76];
+        nk[..2].copy_from_slice(b"nk");
+        nk[2..4].copy_from_slice(&KEY_COMP_NAME.to_le_bytes());
+        nk[4..12].copy_from_slice(&key.last_written.to_le_bytes());
+        nk[20..24].copy_from_slice(&(subkeys.len() as u32).to_le_bytes());
+        nk[28..32].copy_from_slice(&(subkey_list as u32).to_le_bytes());
+        nk[36..40].copy_from_slice(&(values.len() as u32).to_le_bytes());
+        nk[40..44].copy_from_slice(&(value_list as u32).to_le_bytes());
+        nk[72..74].copy_from_slice(&(key.name.len() as u16).to_le_bytes());
+        nk.extend(key.name.as_bytes());
+        alloc(bins, &nk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_hive::{build, TestKey};
+
+    #[test]
+    fn walks_keys_and_reads_values() {
+        let mut app = TestKey::new("0000abcd")
+            .with_string("LowerCaseLongPath", r"c:\users\me\downloads\loader.exe")
+            .with_qword("Size", 123_456)
+            .with_dword("Flags", 7);
+        app.last_written = 134_116_992_000_000_000; // 2026-01-01 00:00 UTC
+        let hive = RegistryHive::from_bytes(build(
+            &TestKey::new("ROOT").with_subkey(TestKey::new("InventoryApplicationFile").with_subkey(app)),
+        )).unwrap();
+
+        assert_eq!(hive.root().name(), "ROOT");
+        let key = hive.open(r"inventoryapplicationfile\0000ABCD").unwrap().unwrap();
+        assert_eq!(key.name(), "0000abcd");
+        assert_eq!(key.last_written(), "2026-01-01T00:00:00Z".parse().ok());
+        assert_eq!(key.value("lowercaselongpath").unwrap().unwrap().as_str(), Some(r"c:\users\me\downloads\loader.exe"));
+        assert_eq!(key.value("Size").unwrap(), Some(RegistryValue::Qword(123_456)));
+        assert_eq!(key.value("Flags").unwrap(), Some(RegistryValue::Dword(7)));
+        assert!(hive.open(r"InventoryApplicationFile\missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(RegistryHive::from_bytes(vec![0; 8192]), Err(HiveError::NotAHive)));
+
+        let mut hive = build(&TestKey::new("ROOT"));
+        hive[0x24..0x28].copy_from_slice(&0x10_0000u32.to_le_bytes());
+        assert!(matches!(RegistryHive::from_bytes(hive), Err(HiveError::Truncated(0x10_0000))));
+    }
+
+    #[test]
+    fn stops_at_lists_that_repeat_each_other() {
+        let mut hive = build(&TestKey::new("ROOT").with_subkey(TestKey::new("child")));
+        let root = u32_at(&hive, 0x24).unwrap() as usize;
+
+        // Eight levels of "ri" lists whose 100 entries all point at the next level, 100^8 entries in total
+        let first = hive.len() - BASE_BLOCK_SIZE;
+        let cell_size = 8 + 100 * 4;
+        for level in 0..8 {
+            let next = first + (level + 1) * cell_size;
+            hive.extend((-(cell_size as i32)).to_le_bytes());
+            hive.extend(b"ri");
+            hive.extend(100u16.to_le_bytes());
+            hive.extend((0..100).flat_map(|_| (next as u32).to_le_bytes()));
+        }
+        hive.extend((-8i32).to_le_bytes());
+        hive.extend(b"lf\0\0");
+        let list_field = BASE_BLOCK_SIZE + root + 4 + 28;
+        hive[list_field..list_field + 4].copy_from_slice(&(first as u32).to_le_bytes());
+
+        let hive = RegistryHive::from_bytes(hive).unwrap();
+        assert!(matches!(hive.root().subkeys(), Err(HiveError::TooManySubkeys(offset)) if offset == first));
+    }
+}
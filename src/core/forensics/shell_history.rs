@@ -0,0 +1,236 @@
+// Programs run from a shell, read from bash, zsh, fish and PowerShell (PSReadLine) history files.
+//
+// Only commands that start a program by path or by an executable file name count, e.g. `./loader`,
+// `wine cheat.exe` or `& "C:\Users\me\Downloads\inject.exe"`. Commands found through PATH (ls, git)
+// say nothing about what the user downloaded. Only the program is kept, the rest of a command line
+// can hold passwords and tokens and reports may be uploaded publicly.
+
+use std::{fs, path::Path};
+use chrono::{DateTime, Utc};
+use crate::core::forensics::execution_evidence::{file_name, EvidenceSource, ExecutionEvidence};
+
+/// Commands without a timestamp (plain bash and PowerShell histories) are only taken from this many
+/// last lines, older ones are unlikely to matter for the current session
+pub const MAX_UNDATED_COMMANDS: usize = 200;
+
+/// Extensions of files that are run directly
+pub const EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "com", "bat", "cmd", "ps1", "vbs", "msi", "scr", "jar", "sh", "appimage", "run", "bin"];
+
+// Words that start another program rather than being the program
+const LAUNCHERS: &[&str] = &["sudo", "doas", "env", "nohup", "exec", "time", "nice", "wine", "wine64", "proton", "start", "start-process", "saps", "&", ".", "call", "cmd", "/c"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Bash,        // Plain lines, `#<epoch>` lines before a command when HISTTIMEFORMAT is set
+    Zsh,         // `: <epoch>:<duration>;<command>` with EXTENDED_HISTORY, plain lines otherwise
+    Fish,        // `- cmd: <command>` followed by `  when: <epoch>`
+    PowerShell,  // Plain lines
+}
+
+/// A command of a history file
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryCommand {
+    pub command: String,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl HistoryFormat {
+    /// Guesses the format from the file name of a history file
+    pub fn from_path(path: &Path) -> Self {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if name.contains("fish") {
+            Self::Fish
+        }
+        else if name.contains("zsh") || name.ends_with("zhistory") {
+            Self::Zsh
+        }
+        else if name.contains("consolehost") || name.ends_with(".txt") {
+            Self::PowerShell
+        }
+        else {
+            Self::Bash
+        }
+    }
+}
+
+/// Every command of a history file, oldest first
+pub fn parse(contents: &str, format: HistoryFormat) -> Vec<HistoryCommand> {
+    let mut commands = Vec::new();
+    let mut pending_time = None;
+
+    for line in contents.lines() {
+        match format {
+            HistoryFormat::Fish => {
+                if let Some(command) = line.strip_prefix("- cmd: ") {
+                    commands.push(HistoryCommand { command: unescape_fish(command), timestamp: None });
+                }
+                else if let Some(when) = line.trim_start().strip_prefix("when: ") {
+                    if let Some(last) = commands.last_mut() {
+                        last.timestamp = parse_epoch(when);
+                    }
+                }
+            }
+            HistoryFormat::Bash | HistoryFormat::Zsh | HistoryFormat::PowerShell => {
+                if format == HistoryFormat::Bash {
+                    if let Some(time) = line.strip_prefix('#').and_then(parse_epoch) {
+                        pending_time = Some(time);
+                        continue;
+                    }
+                }
+
+                let (timestamp, command) = match line.strip_prefix(": ").and_then(|rest| rest.split_once(';')) {
+                    Some((meta, command)) if format == HistoryFormat::Zsh => {
+                        (meta.split(':').next().and_then(parse_epoch), command)
+                    }
+                    _ => (pending_time.take(), line),
+                };
+
+                if !command.trim().is_empty() {
+                    commands.push(HistoryCommand { command: command.trim().to_string(), timestamp });
+                }
+            }
+        }
+    }
+
+    commands
+}
+
+/// Evidence of every program the history file started, undated commands only from its end
+pub fn read(path: &Path) -> anyhow::Result<Vec<ExecutionEvidence>> {
+    // zsh metafies non-ASCII bytes, so the file isn't always valid UTF-8
+    let contents = String::from_utf8_lossy(&fs::read(path)?).to_string();
+    let commands = parse(&contents, HistoryFormat::from_path(path));
+    let artifact = path.display().to_string();
+
+    let undated_from = commands.len().saturating_sub(MAX_UNDATED_COMMANDS);
+    Ok(commands.iter()
+        .enumerate()
+        .filter(|(index, command)| command.timestamp.is_some() || *index >= undated_from)
+        .filter_map(|(_, command)| {
+            let program = program(&command.command)?;
+            let mut evidence = ExecutionEvidence::new(EvidenceSource::ShellHistory, file_name(&program), artifact.clone())
+                .with_timestamp(command.timestamp);
+            if program.contains(['/', '\\']) {
+                evidence = evidence.with_path(program);
+            }
+            Some(evidence)
+        })
+        .collect())
+}
+
+/// The program a command line starts, if it is given by path or executable file name
+pub fn program(command: &str) -> Option<String> {
+    let mut words = split_words(command).into_iter().peekable();
+
+    while let Some(word) = words.next() {
+        let lower = word.to_lowercase();
+        // Variable assignments (FOO=bar ./run.sh) and launcher options (sudo -u root, start "" /b)
+        if LAUNCHERS.contains(&lower.as_str()) || lower.starts_with('-') || word.is_empty()
+            || (word.contains('=') && !word.contains(['/', '\\']))
+        {
+            continue;
+        }
+        if lower == "java" {
+            // java -jar loader.jar
+            return words.find(|word| word.to_lowercase().ends_with(".jar"));
+        }
+
+        let name = file_name(&word);
+        let has_extension = name.rsplit_once('.')
+            .is_some_and(|(_, extension)| EXECUTABLE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+        return (word.contains(['/', '\\']) || has_extension).then_some(word);
+    }
+
+    None
+}
+
+/// Splits on whitespace, keeping quoted parts together and dropping the quotes
+fn split_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut quoted = false;
+
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                quoted = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if !word.is_empty() || quoted {
+                    words.push(std::mem::take(&mut word));
+                }
+                quoted = false;
+            }
+            // Only the first command of a pipeline or list
+            (None, ';' | '|') => break,
+            (None, c) => word.push(c),
+        }
+    }
+    if !word.is_empty() || quoted {
+        words.push(word);
+    }
+
+    words
+}
+
+fn parse_epoch(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(value.trim().parse().ok()?, 0)
+}
+
+fn unescape_fish(command: &str) -> String {
+    command.replace("\\n", "\n").replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::temp_dir::TempDir;
+
+    #[test]
+    fn parses_every_format() {
+        let bash = parse("ls\n#1767268800\n./loader --inject\n", HistoryFormat::Bash);
+        assert_eq!(bash[0], HistoryCommand { command: "ls".into(), timestamp: None });
+        assert_eq!(bash[1].timestamp, DateTime::from_timestamp(1767268800, 0));
+
+        let zsh = parse(": 1767268800:0;wine ~/Downloads/esp.exe\nplain\n", HistoryFormat::Zsh);
+        assert_eq!(zsh[0].command, "wine ~/Downloads/esp.exe");
+        assert_eq!(zsh[0].timestamp, DateTime::from_timestamp(1767268800, 0));
+        assert_eq!(zsh[1].timestamp, None);
+
+        let fish = parse("- cmd: ./run.sh\n  when: 1767268800\n  paths:\n    - run.sh\n- cmd: cd /tmp\n", HistoryFormat::Fish);
+        assert_eq!(fish.len(), 2);
+        assert_eq!(fish[0].timestamp, DateTime::from_timestamp(1767268800, 0));
+
+        assert_eq!(HistoryFormat::from_path(Path::new("ConsoleHost_history.txt")), HistoryFormat::PowerShell);
+        assert_eq!(HistoryFormat::from_path(Path::new(".zsh_history")), HistoryFormat::Zsh);
+    }
+
+    #[test]
+    fn finds_the_started_program() {
+        assert_eq!(program("./loader --inject").as_deref(), Some("./loader"));
+        assert_eq!(program("sudo -E FOO=1 /opt/cheat/run").as_deref(), Some("/opt/cheat/run"));
+        assert_eq!(program(r#"& "C:\Users\me\Downloads\my inject.exe" -p 4242"#).as_deref(), Some(r"C:\Users\me\Downloads\my inject.exe"));
+        assert_eq!(program("Start-Process trainer.exe").as_deref(), Some("trainer.exe"));
+        assert_eq!(program("java -Xmx1G -jar client.jar").as_deref(), Some("client.jar"));
+        assert_eq!(program("git status | less"), None);
+        assert_eq!(program("cd /tmp"), None);
+    }
+
+    #[test]
+    fn keeps_only_the_program_of_a_command() {
+        let dir = TempDir::new("shell_history");
+        let path = dir.join(".bash_history");
+        fs::write(&path, "API_TOKEN=s3cr3t ./loader --password hunter2\n").unwrap();
+
+        let evidence = read(&path).unwrap();
+
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].path.as_deref(), Some("./loader"));
+        let shown = format!("{} {:?}", evidence[0], evidence[0]);
+        assert!(!shown.contains("s3cr3t") && !shown.contains("hunter2"), "{}", shown);
+    }
+}
@@ -0,0 +1,79 @@
+// In-memory handle table, used to run the filter chain against known data instead of a live system
+
+use std::{
+    fs,
+    path::Path,
+};
+use serde::Deserialize;
+use anyhow::Context;
+use crate::core::handle::{
+    HandleError,
+    SystemHandleEntry,
+    handle_info::HandleInfo,
+    handle_source::HandleSource,
+};
+
+/// A handle record plus the owner paths a live source would have looked up
+#[derive(Clone, Default, Deserialize)]
+pub struct HandleFixture {
+    #[serde(flatten)]
+    pub entry: SystemHandleEntry,
+    #[serde(default)]
+    pub nt_path: String,
+    #[serde(default)]
+    pub win32_path: String,
+}
+
+impl From<SystemHandleEntry> for HandleFixture {
+    fn from(entry: SystemHandleEntry) -> Self {
+        Self { entry, ..Default::default() }
+    }
+}
+
+// Layout of a fixture file: a list of [[handles]] tables (TOML) or a "handles" array (JSON)
+#[derive(Deserialize)]
+struct FixtureFile {
+    handles: Vec<HandleFixture>,
+}
+
+#[derive(Clone, Default)]
+pub struct FixtureHandleSource {
+    handles: Vec<HandleFixture>,
+}
+
+impl FixtureHandleSource {
+    pub fn new(handles: Vec<HandleFixture>) -> Self {
+        Self { handles }
+    }
+
+    /// Loads fixtures from a .toml or .json file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read handle fixtures from {}", path.display()))?;
+
+        let file: FixtureFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)?,
+            Some("toml") => toml::from_str(&contents)?,
+            _ => anyhow::bail!("Unsupported handle fixture format: {}", path.display()),
+        };
+
+        Ok(Self::new(file.handles))
+    }
+}
+
+impl HandleSource for FixtureHandleSource {
+    fn query_handles(&self) -> Result<Vec<SystemHandleEntry>, HandleError> {
+        Ok(self.handles.iter().map(|fixture| fixture.entry.clone()).collect())
+    }
+
+    fn handle_info(&self, entry: &SystemHandleEntry) -> Result<HandleInfo, HandleError> {
+        let fixture = self.handles.iter()
+            .find(|fixture| {
+                fixture.entry.process_id == entry.process_id && fixture.entry.handle_value == entry.handle_value
+            })
+            .ok_or(HandleError::FailedToOpenProcess)?;
+
+        Ok(HandleInfo::from_paths(&fixture.nt_path, &fixture.win32_path, entry.granted_access))
+    }
+}
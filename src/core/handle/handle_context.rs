@@ -1,10 +1,11 @@
 use std::fmt;
+use serde::{Deserialize, Serialize};
 use crate::core::handle::{
     SystemHandleEntry,
     handle_info::HandleInfo,
 };
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct HandleContext {
     pub(crate) raw: SystemHandleEntry,
     pub(crate) info: Option<HandleInfo>,
@@ -23,6 +24,13 @@ impl HandleContext {
             (&info.nt_path, &info.win32_path)
         })
     }
+
+    pub fn signed(&self) -> Option<bool> {
+        self.info.as_ref().and_then(|info| info.signed)
+    }
+
+    pub fn process_id(&self) -> u32 { self.raw.process_id }
+    pub fn granted_access(&self) -> u32 { self.raw.granted_access }
 }
 
 impl fmt::Display for HandleContext {
@@ -44,6 +52,10 @@ impl fmt::Display for HandleContext {
             writeln!(f, "  Win32 Path: {}", win32_path)?;
         }
 
+        if let Some(signed) = self.signed() {
+            writeln!(f, "  Signed: {}", if signed { "yes" } else { "no" })?;
+        }
+
         Ok(())
     }
 }
\ No newline at end of file
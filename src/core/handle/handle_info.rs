@@ -1,11 +1,25 @@
 // Converts SystemHandles to a more easily usable and readable format
 
+#[cfg(windows)]
 use windows::{
     Win32::{
         Foundation::{
             HANDLE,
+            HWND,
             CloseHandle,
         },
+        Security::WinTrust::{
+            WinVerifyTrust,
+            WINTRUST_ACTION_GENERIC_VERIFY_V2,
+            WINTRUST_DATA,
+            WINTRUST_DATA_0,
+            WINTRUST_FILE_INFO,
+            WTD_CHOICE_FILE,
+            WTD_REVOKE_NONE,
+            WTD_STATEACTION_CLOSE,
+            WTD_STATEACTION_VERIFY,
+            WTD_UI_NONE,
+        },
         System::{
             Threading::{
                 OpenProcess,
@@ -20,22 +34,29 @@ use windows::{
         }
     },
     core::{
+        PCWSTR,
         PSTR,
     }
 };
 
 use std::fmt;
+use serde::{Deserialize, Serialize};
 use crate::core::handle::{HandleError, SystemHandleEntry};
 
 #[derive(Clone)]
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct HandleInfo {
+    #[cfg(windows)]
+    #[serde(skip)]
     handle: HANDLE,
     pub nt_path: String,
     pub win32_path: String,
     pub access_rights: Vec<String>,
+    #[serde(default)]
+    pub signed: Option<bool>, // Whether the owning executable carries a valid signature, None if unknown
 }
 
+#[cfg(windows)]
 impl HandleInfo {
     pub fn from_handle_entry(entry: SystemHandleEntry) -> Result<Self, HandleError> {
         let mut info = HandleInfo {
@@ -43,6 +64,7 @@ impl HandleInfo {
             nt_path: String::new(),
             win32_path: String::new(),
             access_rights: Vec::new(),
+            signed: None,
 
             ..Default::default()
         };
@@ -54,7 +76,7 @@ impl HandleInfo {
             let process_handle: HANDLE = OpenProcess(
                 PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
                 false,
-                entry.process_id as u32,
+                entry.process_id,
             ).map_err(|_| HandleError::FailedToOpenProcess)?;
 
             if let Err(e) = info.get_process_nt_path(process_handle) {
@@ -68,6 +90,8 @@ impl HandleInfo {
             CloseHandle(process_handle).expect("Failed to close handle!");
         }
 
+        info.get_signature_status();
+
 
         // Decode the access rights
         info.access_rights = decode_access_mask(entry.granted_access);
@@ -94,6 +118,57 @@ impl HandleInfo {
         Ok(())
     }
 
+    /// Verifies the Authenticode signature embedded in the owning executable
+    fn get_signature_status(&mut self) {
+        if self.win32_path.is_empty() {
+            return;
+        }
+
+        let wide_path: Vec<u16> = self.win32_path.encode_utf16().chain(std::iter::once(0)).collect();
+
+        unsafe {
+            let mut file_info = WINTRUST_FILE_INFO {
+                cbStruct: size_of::<WINTRUST_FILE_INFO>() as u32,
+                pcwszFilePath: PCWSTR(wide_path.as_ptr()),
+                ..Default::default()
+            };
+
+            let mut trust_data = WINTRUST_DATA {
+                cbStruct: size_of::<WINTRUST_DATA>() as u32,
+                dwUIChoice: WTD_UI_NONE,
+                fdwRevocationChecks: WTD_REVOKE_NONE,
+                dwUnionChoice: WTD_CHOICE_FILE,
+                Anonymous: WINTRUST_DATA_0 { pFile: &mut file_info },
+                dwStateAction: WTD_STATEACTION_VERIFY,
+                ..Default::default()
+            };
+
+            let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+            let status = WinVerifyTrust(
+                HWND::default(),
+                &mut action,
+                &mut trust_data as *mut _ as *mut std::ffi::c_void
+            );
+
+            // Release the state data allocated by the verify call
+            trust_data.dwStateAction = WTD_STATEACTION_CLOSE;
+            WinVerifyTrust(
+                HWND::default(),
+                &mut action,
+                &mut trust_data as *mut _ as *mut std::ffi::c_void
+            );
+
+            // Windows' own binaries are mostly signed through a catalog instead of an embedded
+            // signature, and report TRUST_E_NOSIGNATURE here. Below %SystemRoot% (only writable by
+            // administrators) that says nothing, so it stays unknown there.
+            self.signed = match status {
+                0 => Some(true),
+                TRUST_E_NOSIGNATURE if is_below_system_root(&self.win32_path) => None,
+                _ => Some(false),
+            };
+        }
+    }
+
     /// Gets a process' Win32 path from a pid
     fn get_process_win32_path(&mut self, handle: HANDLE) -> Result<(), HandleError> {
         unsafe {
@@ -116,6 +191,46 @@ impl HandleInfo {
     }
 }
 
+/// WinVerifyTrust result for a file without an embedded signature
+#[cfg(windows)]
+const TRUST_E_NOSIGNATURE: i32 = 0x800B0100u32 as i32;
+
+/// Whether a path lies below the Windows directory
+#[cfg(windows)]
+fn is_below_system_root(path: &str) -> bool {
+    let system_root = crate::core::allowlist::expand("%SystemRoot%");
+    let system_root = system_root.trim_end_matches('\\');
+    path.get(..system_root.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(system_root))
+        && path[system_root.len()..].starts_with('\\')
+}
+
+impl HandleInfo {
+    /// Builds info from already known owner paths
+    #[allow(clippy::needless_update)] // The handle field only exists on Windows
+    pub(crate) fn from_paths(nt_path: impl Into<String>, win32_path: impl Into<String>, access_mask: u32) -> Self {
+        HandleInfo {
+            nt_path: nt_path.into(),
+            win32_path: win32_path.into(),
+            access_rights: decode_access_mask(access_mask),
+            signed: None,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(not(windows))]
+impl HandleInfo {
+    pub fn from_handle_entry(entry: SystemHandleEntry) -> Result<Self, HandleError> {
+        // There is no NT/Win32 path distinction here, both hold the owner's executable
+        let exe_path = std::fs::read_link(format!("/proc/{}/exe", entry.process_id))
+            .map_err(|_| HandleError::FailedToOpenProcess)?
+            .to_string_lossy()
+            .to_string();
+
+        Ok(HandleInfo::from_paths(exe_path.clone(), exe_path, entry.granted_access))
+    }
+}
+
 /// Gets readable permissions for a handle
 fn decode_access_mask(access_mask: u32) -> Vec<String> {
     let mut rights = Vec::new();
@@ -144,11 +259,14 @@ fn decode_access_mask(access_mask: u32) -> Vec<String> {
 
 impl fmt::Debug for HandleInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("HandleInfo")
-            .field("handle", &self.handle)
+        let mut debug = f.debug_struct("HandleInfo");
+        #[cfg(windows)]
+        debug.field("handle", &self.handle);
+        debug
             .field("nt_path", &self.nt_path)
             .field("win32_path", &self.win32_path)
             .field("access_rights", &self.access_rights)
+            .field("signed", &self.signed)
             .finish()
     }
 }
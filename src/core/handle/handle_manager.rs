@@ -1,224 +1,87 @@
 
+use std::collections::HashMap;
 use sysinfo::get_current_pid;
-use windows::{
-    Win32::{
-        System::{
-            Memory::{
-                VirtualAlloc,
-                VirtualFree,
-                PAGE_EXECUTE_READWRITE,
-                MEM_COMMIT,
-                MEM_RELEASE,
-            },
-            Threading::{
-                PROCESS_ALL_ACCESS,
-                PROCESS_VM_READ,
-                PROCESS_VM_WRITE
-            }
-        },
-        Foundation::{
-            HANDLE,
-            STATUS_SUCCESS,
-
-        }
+#[cfg(windows)]
+use windows::Win32::{
+    Foundation::{
+        HANDLE,
+        CloseHandle,
+        DuplicateHandle,
+        DUPLICATE_SAME_ACCESS,
+    },
+    System::Threading::{
+        GetCurrentProcess,
+        OpenProcess,
+        PROCESS_DUP_HANDLE,
+        PROCESS_QUERY_INFORMATION,
     },
-    Wdk::{
-        System::{
-            SystemInformation::{
-                NtQuerySystemInformation,
-                SYSTEM_INFORMATION_CLASS,
-            }
-        },
-        Foundation::{
-            NtQueryObject,
-            ObjectTypeInformation,
-        }
-    }
 };
-use windows::Win32::Foundation::{CloseHandle, DuplicateHandle, DUPLICATE_SAME_ACCESS};
-use windows::Win32::System::Threading::{GetCurrentProcess, GetProcessId, OpenProcess, PROCESS_DUP_HANDLE, PROCESS_QUERY_INFORMATION};
+use crate::core::allowlist::{Allowed, Allowlist};
 use crate::core::handle::{
-    SystemHandleInformation,
-    SystemHandleEntry,
     SystemHandleType,
     handle_context::HandleContext,
+    handle_source::{self, HandleSource},
+    fixture_handle_source::{FixtureHandleSource, HandleFixture},
     HandleError,
-    ObjectTypeInformation
+    PROCESS_ALL_ACCESS,
+    PROCESS_VM_READ,
+    PROCESS_VM_WRITE,
 };
-use crate::core::handle::handle_info::HandleInfo;
-
-const SYSTEM_HANDLE_INFORMATION: i32 = 0x10; // 16
 
 pub struct HandleManager {
     pub handles: Vec<HandleContext>,
+    pub allowed: Vec<Allowed<HandleContext>>,
+    source: Box<dyn HandleSource>,
 }
 
 impl HandleManager {
+    /// Snapshots handles through the default source for this platform
     pub fn new() -> Result<Self, HandleError> {
-        let raw_handles = Self::query_system_handles()?;
-        let handles = raw_handles.into_iter()
-            .map(|raw| HandleContext {
-                raw,
-                info: None,
-            })
-            .collect();
-
-        Ok(Self { handles })
+        Self::from_boxed_source(handle_source::default_source()?)
     }
 
-    fn query_system_handles() -> Result<Vec<SystemHandleEntry>, HandleError> {
-        let mut entries = Vec::new();
-
-        unsafe {
-            // Start with a reasonable initial buffer size (4KB)
-            let mut info_length: u32 = 0x1000;
-
-            // Allocate initial memory for handle information
-            let mut buffer = VirtualAlloc(
-                Some(std::ptr::null_mut()),
-                info_length as usize,
-                MEM_COMMIT,
-                PAGE_EXECUTE_READWRITE
-            );
-
-            // First attempt to query system handles
-            // This will likely fail with STATUS_INFO_LENGTH_MISMATCH
-            // but will update info_length with the required size
-            let mut status = NtQuerySystemInformation(
-                SYSTEM_INFORMATION_CLASS(SYSTEM_HANDLE_INFORMATION),
-                buffer,
-                info_length,
-                &mut info_length,
-            );
-
-            log::debug!("Status: {:x?} | Info length: {:?}| Getting buffer", status, info_length);
-
-            // Keep trying until we get a successful query
-            while status != STATUS_SUCCESS {
-                // Free the old buffer FIRST
-                VirtualFree(buffer, 0, MEM_RELEASE)
-                    .map_err(|_| HandleError::FailedToFreeMemory)?;
-
-                // Allocate new buffer with updated size
-                buffer = VirtualAlloc(
-                    Some(std::ptr::null_mut()),
-                    info_length as usize,
-                    MEM_COMMIT,
-                    PAGE_EXECUTE_READWRITE
-                );
-
-                if buffer.is_null() {
-                    return Err(HandleError::FailedToAllocateMemory);
-                }
-
-                // Try to query with current buffer
-                status = NtQuerySystemInformation(
-                    SYSTEM_INFORMATION_CLASS(SYSTEM_HANDLE_INFORMATION),
-                    buffer,
-                    info_length,
-                    &mut info_length,
-                );
-
-                log::debug!("Status: {:x?} | Info length: {:?}| Getting buffer", status, info_length);
-            }
-
-            // We have now successfully retrieved all handles
-            log::debug!("Buffer: {:?}", buffer);
-
-            let handle_info = &*(buffer as *const SystemHandleInformation);
-            log::debug!("Number of handles: {}", handle_info.number_of_handles);
-
-            // Get handles slice and clone each entry into our vector
-            let handles = std::slice::from_raw_parts(
-                &handle_info.handles as *const SystemHandleEntry,
-                handle_info.number_of_handles as usize
-            );
-
-            entries = handles.to_vec();  // Simply clone all entries into our vector
-
-            // Clean up allocated memory
-            VirtualFree(buffer, 0, MEM_RELEASE)
-                .map_err(|_| HandleError::FailedToFreeMemory)?;
-        }
-
-        Ok(entries)
+    /// Snapshots handles through a custom source
+    pub fn with_source(source: impl HandleSource + 'static) -> Result<Self, HandleError> {
+        Self::from_boxed_source(Box::new(source))
     }
 
-    fn query_handle_type_info(&self, handle: HANDLE) -> Result<ObjectTypeInformation, HandleError> {
-        unsafe {
-            let mut info_length = 0u32;
-
-            // First call to get required buffer size
-            let mut status = NtQueryObject(
-                handle,
-                ObjectTypeInformation,
-                None,
-                0,
-                Some(&mut info_length)
-            );
-
-            if info_length == 0 {
-                return Err(HandleError::FailedToQueryObject);
-            }
-
-            // Now allocate buffer with known size
-            let mut buffer = VirtualAlloc(
-                Some(std::ptr::null_mut()),
-                info_length as usize,
-                MEM_COMMIT,
-                PAGE_EXECUTE_READWRITE
-            );
-
-            if buffer.is_null() {
-                return Err(HandleError::FailedToAllocateMemory);
-            }
-
-            while status != STATUS_SUCCESS {
-                // Free the old buffer FIRST
-                VirtualFree(buffer, 0, MEM_RELEASE)
-                    .map_err(|_| HandleError::FailedToFreeMemory)?;
-
-                // Allocate new buffer with updated size
-                buffer = VirtualAlloc(
-                    Some(std::ptr::null_mut()),
-                    info_length as usize,
-                    MEM_COMMIT,
-                    PAGE_EXECUTE_READWRITE
-                );
-
-                if buffer.is_null() {
-                    log::debug!("Failed to allocate memory!");
-                    return Err(HandleError::FailedToAllocateMemory);
-                }
+    /// Builds a manager over a fixed list of handles, without querying the system
+    pub fn from_entries(entries: impl IntoIterator<Item = impl Into<HandleFixture>>) -> Self {
+        let fixtures = entries.into_iter().map(Into::into).collect();
+        Self::with_source(FixtureHandleSource::new(fixtures))
+            .expect("Fixture handle source cannot fail")
+    }
 
-                // Try to query with new buffer
-                status = NtQueryObject(
-                    handle,
-                    ObjectTypeInformation,
-                    Some(buffer),
-                    info_length,
-                    Some(&mut info_length)
-                );
+    /// Builds a manager over handles loaded from a .toml or .json fixture file
+    pub fn from_fixture_file(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Ok(Self::with_source(FixtureHandleSource::from_file(path)?)?)
+    }
 
-            }
+    fn from_boxed_source(source: Box<dyn HandleSource>) -> Result<Self, HandleError> {
+        let mut manager = Self { handles: Vec::new(), allowed: Vec::new(), source };
+        manager.refresh()?;
+        Ok(manager)
+    }
 
-            // Extract relevant information
-            let type_info = ObjectTypeInformation {
-                type_index: (*(buffer as *const ObjectTypeInformation)).type_index,
-                total_handles: (*(buffer as *const ObjectTypeInformation)).total_handles,
-                valid_access: (*(buffer as *const ObjectTypeInformation)).valid_access,
-            };
+    /// Takes a new snapshot from the source, dropping the results of earlier filtering
+    pub fn refresh(&mut self) -> Result<&mut Self, HandleError> {
+        self.handles = self.source.query_handles()?
+            .into_iter()
+            .map(|raw| HandleContext {
+                raw,
+                info: None,
+            })
+            .collect();
+        self.allowed.clear();
 
-            VirtualFree(buffer, 0, MEM_RELEASE)
-                .map_err(|_| HandleError::FailedToFreeMemory)?;
-            Ok(type_info)
-        }
+        Ok(self)
     }
 
     /// Filter handles that are attached to our target process (but not owned by it)
     /// Keep handles that:
     /// 1. Point to our target process
     /// 2. Are NOT owned by our target process
+    #[cfg(windows)]
     pub fn test_filter_handles_to_target(&mut self, target_pid: u32) -> Result<&mut Self, HandleError> {
         let target_handle = unsafe {
             OpenProcess(
@@ -238,7 +101,7 @@ impl HandleManager {
         // Filter handles
         self.handles.retain(|handle| {
             // Skip if handle belongs to target process
-            if handle.raw.process_id as u32 == target_pid {
+            if handle.raw.process_id == target_pid {
                 return false;
             }
 
@@ -247,7 +110,7 @@ impl HandleManager {
                 let process_handle = OpenProcess(
                     PROCESS_DUP_HANDLE,
                     false,
-                    handle.raw.process_id as u32
+                    handle.raw.process_id
                 );
 
                 if let Ok(process_handle) = process_handle {
@@ -270,7 +133,7 @@ impl HandleManager {
 
                     if dup_result.is_ok() {
                         // Compare raw pointer values
-                        let is_target = handle.raw.object == target_ptr;
+                        let is_target = handle.raw.object == target_ptr as usize;
                         if is_target {
                             filtered_count += 1;
                             log::debug!("Found matching handle from PID {} with access {:x}",
@@ -295,75 +158,29 @@ impl HandleManager {
     }
 
     pub fn filter_handles_to_target(&mut self, target_pid: u32) -> Result<&mut Self, HandleError> {
-        let target_handle = unsafe {
-            OpenProcess(
-                PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,  // Add VM_READ to get more info
-                false,
-                target_pid
-            ).map_err(|_| HandleError::FailedToOpenProcess)?
-        };
-
-        log::debug!("Target process handle: {:?}", target_handle);
-
         let initial_count = self.handles.len();
         log::debug!("Initial handle count: {}", initial_count);
 
+        let source = &self.source;
         let mut filtered_count = 0;
         self.handles.retain(|handle| {
             // Skip if handle belongs to target process
-            if handle.raw.process_id as u32 == target_pid {
+            if handle.raw.process_id == target_pid {
                 return false;
             }
 
-            unsafe {
-                let process_handle = OpenProcess(
-                    PROCESS_DUP_HANDLE,
-                    false,
-                    handle.raw.process_id as u32
-                );
-
-                if let Ok(process_handle) = process_handle {
-                    let mut duplicated_handle = HANDLE::default();
-
-                    let dup_result = DuplicateHandle(
-                        process_handle,
-                        HANDLE(handle.raw.handle_value as _),
-                        GetCurrentProcess(),
-                        &mut duplicated_handle,
-                        0,
-                        false,
-                        DUPLICATE_SAME_ACCESS,
-                    );
-
-                    CloseHandle(process_handle)
-                        .map_err(|_| HandleError::FailedToCloseHandle)
-                        .unwrap();
-
-                    if dup_result.is_ok() {
-                        // Get Process ID of the duplicated handle
-                        let duplicated_pid = GetProcessId(duplicated_handle);
-
-                        let is_target = duplicated_pid == target_pid;
-                        if is_target {
-                            filtered_count += 1;
-                            log::debug!("Found matching handle from PID {} with access {:x}",
-                            handle.raw.process_id, handle.raw.granted_access);
-                        }
-                        CloseHandle(duplicated_handle)
-                            .map_err(|_| HandleError::FailedToCloseHandle)
-                            .unwrap();
-                        return is_target;
-                    }
-                }
+            // Ask the source which process this handle points to
+            let is_target = source.target_pid(&handle.raw) == Some(target_pid);
+            if is_target {
+                filtered_count += 1;
+                log::debug!("Found matching handle from PID {} with access {:x}",
+                    handle.raw.process_id, handle.raw.granted_access);
             }
-            false
+            is_target
         });
 
         log::debug!("After target process filter: found {} valid handles", filtered_count);
 
-        unsafe { CloseHandle(target_handle) }
-            .map_err(|_| HandleError::FailedToCloseHandle)?;
-
         Ok(self)
     }
 
@@ -376,14 +193,14 @@ impl HandleManager {
 
     /// Filter handles by parent's process ID
     pub fn filter_by_parent_pid(&mut self, pid: u32) -> &mut Self {
-        self.handles.retain(|handle| handle.raw.process_id as u32 == pid);
+        self.handles.retain(|handle| handle.raw.process_id == pid);
         self
     }
 
     /// Enrich filtered handles with additional information
     pub fn collect_handle_info(&mut self) -> Result<&mut Self, HandleError> {
         for handle in &mut self.handles {
-            handle.info = Some(HandleInfo::from_handle_entry(handle.raw.clone())?);
+            handle.info = Some(self.source.handle_info(&handle.raw)?);
         }
         Ok(self)
     }
@@ -398,12 +215,12 @@ impl HandleManager {
 
     /// Filters suspicious handles based on predefined attributes
     pub fn filter_suspicious_handles(&mut self) -> &mut Self {
-        let read_write_mask = PROCESS_VM_READ.0 | PROCESS_VM_WRITE.0;
+        let read_write_mask = PROCESS_VM_READ | PROCESS_VM_WRITE;
 
         self.handles = self.handles
             .iter()
             .filter(|entry| {
-                entry.raw.granted_access == PROCESS_ALL_ACCESS.0 ||
+                entry.raw.granted_access == PROCESS_ALL_ACCESS ||
                     (entry.raw.granted_access & read_write_mask) == read_write_mask
             })
             .cloned()
@@ -416,8 +233,7 @@ impl HandleManager {
     pub fn filter_anticheat_handles(&mut self) -> &mut Self {
         let anticheat_pid = get_current_pid()
             .unwrap()
-            .as_u32()
-            as u16;
+            .as_u32();
 
         self.handles = self.handles
             .iter()
@@ -430,8 +246,153 @@ impl HandleManager {
         self
     }
 
+    /// Moves handles owned by allowlisted processes into `allowed`.
+    /// Needs collect_handle_info to have run, as entries match on the owner's image.
+    pub fn filter_allowlisted(&mut self, allowlist: &Allowlist) -> &mut Self {
+        let mut hashes = HashMap::new();
+        let mut kept = Vec::with_capacity(self.handles.len());
+
+        for handle in self.handles.drain(..) {
+            match allowlist.match_handle_cached(&handle, &mut hashes) {
+                Some(entry) => {
+                    log::debug!("Allowing handle from PID {}: {}", handle.raw.process_id, entry.reason);
+                    self.allowed.push(Allowed {
+                        finding: handle,
+                        entry: entry.name.clone(),
+                        reason: entry.reason.clone(),
+                    });
+                }
+                None => kept.push(handle),
+            }
+        }
+
+        self.handles = kept;
+        log::debug!("After allowlist filter: {} handles ({} allowed)", self.handles.len(), self.allowed.len());
+        self
+    }
+
     /// Get the final filtered and enriched handles
     pub fn get_handles(&self) -> Vec<HandleContext> {
         self.handles.to_vec()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::handle::SystemHandleEntry;
+
+    const GAME_PID: u32 = 4242;
+
+    fn fixture_manager() -> HandleManager {
+        HandleManager::from_fixture_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/handles.toml"))
+            .unwrap()
+    }
+
+    fn owner_pids(manager: &HandleManager) -> Vec<u32> {
+        manager.handles.iter().map(|handle| handle.raw.process_id).collect()
+    }
+
+    #[test]
+    fn toml_and_json_fixtures_match() {
+        let json = HandleManager::from_fixture_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/handles.json"))
+            .unwrap();
+        let toml = fixture_manager();
+
+        assert_eq!(toml.handles.len(), 6);
+        assert_eq!(owner_pids(&json), owner_pids(&toml));
+    }
+
+    #[test]
+    fn filters_by_handle_type() {
+        let mut manager = fixture_manager();
+        manager.filter_by_handle_type(SystemHandleType::Process);
+        assert_eq!(owner_pids(&manager), vec![1001, 1002, 1003, 1004, GAME_PID]);
+
+        manager.filter_by_handle_type(SystemHandleType::File);
+        assert!(manager.handles.is_empty());
+    }
+
+    #[test]
+    fn filters_suspicious_handles() {
+        let mut manager = fixture_manager();
+        manager.filter_suspicious_handles();
+        assert_eq!(owner_pids(&manager), vec![1001, 1002, 1004, GAME_PID]);
+    }
+
+    #[test]
+    fn filters_handles_to_target() {
+        let mut manager = fixture_manager();
+        manager.filter_handles_to_target(GAME_PID).unwrap();
+        assert_eq!(owner_pids(&manager), vec![1001, 1002, 1003]);
+    }
+
+    #[test]
+    fn filters_by_access() {
+        let mut manager = fixture_manager();
+        manager.filter_by_access(PROCESS_VM_WRITE);
+        assert_eq!(owner_pids(&manager), vec![1001, 1002, 1004, GAME_PID]);
+
+        manager.filter_by_access(PROCESS_ALL_ACCESS);
+        assert_eq!(owner_pids(&manager), vec![1001, 1004, GAME_PID]);
+    }
+
+    #[test]
+    fn refresh_takes_a_new_snapshot() {
+        let mut manager = fixture_manager();
+        manager.filter_handles_to_target(GAME_PID).unwrap();
+        assert_eq!(manager.handles.len(), 3);
+
+        manager.refresh().unwrap();
+        assert_eq!(manager.handles.len(), 6);
+        assert!(manager.allowed.is_empty());
+    }
+
+    #[test]
+    fn filters_by_parent_pid() {
+        let mut manager = fixture_manager();
+        manager.filter_by_parent_pid(1002);
+        assert_eq!(owner_pids(&manager), vec![1002]);
+    }
+
+    #[test]
+    fn filters_anticheat_handles() {
+        let own_pid = std::process::id();
+        let mut manager = HandleManager::from_entries([
+            SystemHandleEntry { process_id: own_pid, handle_value: 0x10, ..Default::default() },
+            SystemHandleEntry { process_id: 1001, handle_value: 0x10, ..Default::default() },
+        ]);
+
+        manager.filter_anticheat_handles();
+        assert_eq!(owner_pids(&manager), vec![1001]);
+    }
+
+    #[test]
+    fn filters_allowlisted_handles() {
+        let mut manager = fixture_manager();
+        manager
+            .filter_handles_to_target(GAME_PID)
+            .unwrap()
+            .collect_handle_info()
+            .unwrap()
+            .filter_allowlisted(&Allowlist::builtin());
+
+        assert_eq!(owner_pids(&manager), vec![1001, 1002]);
+        assert_eq!(manager.allowed.len(), 1);
+        assert_eq!(manager.allowed[0].finding.raw.process_id, 1003);
+        assert_eq!(manager.allowed[0].entry, "Steam");
+    }
+
+    #[test]
+    fn collects_handle_info_from_fixtures() {
+        let mut manager = fixture_manager();
+        let handles = manager
+            .filter_by_parent_pid(1002)
+            .collect_handle_info()
+            .unwrap()
+            .get_handles();
+
+        let (_, win32_path) = handles[0].paths().unwrap();
+        assert_eq!(win32_path, r"C:\Tools\trainer.exe");
+        assert_eq!(handles[0].access_rights(), ["PROCESS_VM_OPERATION", "PROCESS_VM_READ", "PROCESS_VM_WRITE"]);
+    }
+}
@@ -0,0 +1,41 @@
+// Abstraction over where handle records come from, so the filter chain in HandleManager does not
+// depend on any one operating system.
+
+use crate::core::handle::{
+    HandleError,
+    SystemHandleEntry,
+    handle_info::HandleInfo,
+};
+
+pub trait HandleSource: Send + Sync {
+    /// Takes a snapshot of every handle open on the system
+    fn query_handles(&self) -> Result<Vec<SystemHandleEntry>, HandleError>;
+
+    /// Resolves the PID of the process a handle points to (if it points to one)
+    fn target_pid(&self, entry: &SystemHandleEntry) -> Option<u32> {
+        entry.target_pid
+    }
+
+    /// Collects paths and decoded access rights for a handle's owning process
+    fn handle_info(&self, entry: &SystemHandleEntry) -> Result<HandleInfo, HandleError> {
+        HandleInfo::from_handle_entry(entry.clone())
+    }
+}
+
+/// Returns the handle source for the platform we were compiled for
+pub fn default_source() -> Result<Box<dyn HandleSource>, HandleError> {
+    #[cfg(windows)]
+    {
+        Ok(Box::new(crate::core::handle::nt_handle_source::NtHandleSource))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(crate::core::handle::proc_handle_source::ProcHandleSource))
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Err(HandleError::UnsupportedPlatform)
+    }
+}
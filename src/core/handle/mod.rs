@@ -1,9 +1,32 @@
-use windows::Win32::Foundation::HANDLE;
+use serde::{Deserialize, Serialize};
 
 pub mod handle_manager;
-pub(crate) mod handle_info;
+pub mod handle_info;
 
 pub mod handle_context;
+pub mod handle_source;
+pub mod fixture_handle_source;
+
+#[cfg(windows)]
+pub mod nt_handle_source;
+#[cfg(target_os = "linux")]
+pub mod proc_handle_source;
+#[cfg(target_os = "linux")]
+pub mod ptrace_detector;
+
+// Windows process access rights. Every HandleSource reports granted access with these semantics
+// (other platforms translate their permissions into them) so the filter chain works everywhere.
+pub const PROCESS_TERMINATE: u32 = 0x0001;
+pub const PROCESS_CREATE_THREAD: u32 = 0x0002;
+pub const PROCESS_VM_OPERATION: u32 = 0x0008;
+pub const PROCESS_VM_READ: u32 = 0x0010;
+pub const PROCESS_VM_WRITE: u32 = 0x0020;
+pub const PROCESS_QUERY_INFORMATION: u32 = 0x0400;
+pub const PROCESS_ALL_ACCESS: u32 = 0x001F_FFFF;
+
+// Generic rights, used for file handles
+pub const GENERIC_READ: u32 = 0x8000_0000;
+pub const GENERIC_WRITE: u32 = 0x4000_0000;
 
 #[derive(Debug)]
 pub enum HandleError {
@@ -14,6 +37,8 @@ pub enum HandleError {
     FailedToFreeMemory,
     FailedToAllocateMemory,
     FailedToCloseHandle,
+    FailedToReadProcfs,
+    UnsupportedPlatform,
 }
 
 // Minimal Display implementation - just show the variant name
@@ -30,30 +55,19 @@ pub enum SystemHandleError {
     MemoryAllocationFailed,
 }
 
-#[repr(C)]
-pub struct SystemHandleInformation {
-    number_of_handles: u32,
-    #[cfg(target_arch = "x86")]
-    _padding: u32,  // Padding for alignment on x86
-    handles: [SystemHandleEntry; 1], // Variable length array
-}
-
-#[repr(C)]
-#[derive(Clone)]
+/// Platform-neutral handle record produced by a [`handle_source::HandleSource`].
+///
+/// Mirrors the fields of the NT `SYSTEM_HANDLE_TABLE_ENTRY_INFO` that the filters care about.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SystemHandleEntry {
-    pub(crate) process_id: u16,                   // UniqueProcessId
-    pub(crate) creator_back_trace_index: u16,     // CreatorBackTraceIndex
-    pub(crate) object_type_index: u8,             // ObjectTypeIndex
-    pub(crate) handle_attributes: u8,             // HandleAttributes
-    pub(crate) handle_value: u16,                 // HandleValue
-    pub(crate) object: *mut std::ffi::c_void,     // Object
-    pub(crate) granted_access: u32,               // GrantedAccess
-}
-
-impl SystemHandleEntry {
-    fn to_handle(&self) -> HANDLE {
-        HANDLE(self.handle_value as _)
-    }
+    pub(crate) process_id: u32,         // Process that owns the handle
+    pub(crate) object_type_index: u8,   // ObjectTypeIndex (see SystemHandleType)
+    pub(crate) handle_attributes: u8,   // HandleAttributes
+    pub(crate) handle_value: u32,       // HandleValue (file descriptor on Linux)
+    pub(crate) object: usize,           // Kernel object address (inode on Linux)
+    pub(crate) granted_access: u32,     // GrantedAccess, using Windows access mask semantics
+    pub(crate) target_pid: Option<u32>, // Process the handle points to, if the source knows it up front
 }
 
 impl std::fmt::Debug for SystemHandleEntry {
@@ -62,7 +76,10 @@ impl std::fmt::Debug for SystemHandleEntry {
             .field("process_id", &self.process_id)
             .field("handle_value", &format!("0x{:x}", self.handle_value))
             .field("object_type_index", &self.object_type_index)
+            .field("handle_attributes", &self.handle_attributes)
+            .field("object", &format!("0x{:x}", self.object))
             .field("granted_access", &format!("0x{:x}", self.granted_access))
+            .field("target_pid", &self.target_pid)
             .finish()
     }
 }
@@ -76,11 +93,3 @@ pub enum SystemHandleType {
     Sempahore = 12,
     File = 25,
 }
-
-#[derive(Debug)]
-#[repr(C)]
-struct ObjectTypeInformation {
-    type_index: u8,
-    total_handles: u32,
-    valid_access: u32,
-}
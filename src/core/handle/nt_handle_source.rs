@@ -0,0 +1,287 @@
+// Handle source backed by NtQuerySystemInformation(SystemHandleInformation)
+
+use windows::{
+    Win32::{
+        System::{
+            Memory::{
+                VirtualAlloc,
+                VirtualFree,
+                PAGE_EXECUTE_READWRITE,
+                MEM_COMMIT,
+                MEM_RELEASE,
+            },
+            Threading::{
+                GetCurrentProcess,
+                GetProcessId,
+                OpenProcess,
+                PROCESS_DUP_HANDLE,
+            },
+        },
+        Foundation::{
+            HANDLE,
+            STATUS_SUCCESS,
+            CloseHandle,
+            DuplicateHandle,
+            DUPLICATE_SAME_ACCESS,
+        }
+    },
+    Wdk::{
+        System::{
+            SystemInformation::{
+                NtQuerySystemInformation,
+                SYSTEM_INFORMATION_CLASS,
+            }
+        },
+        Foundation::{
+            NtQueryObject,
+            ObjectTypeInformation,
+        }
+    }
+};
+use crate::core::handle::{
+    SystemHandleEntry,
+    HandleError,
+    handle_source::HandleSource,
+};
+
+const SYSTEM_HANDLE_INFORMATION: i32 = 0x10; // 16
+
+#[repr(C)]
+struct SystemHandleInformation {
+    number_of_handles: u32,
+    #[cfg(target_arch = "x86")]
+    _padding: u32,  // Padding for alignment on x86
+    handles: [RawSystemHandleEntry; 1], // Variable length array
+}
+
+// Layout of SYSTEM_HANDLE_TABLE_ENTRY_INFO as returned by the kernel
+#[repr(C)]
+#[derive(Clone)]
+struct RawSystemHandleEntry {
+    process_id: u16,                   // UniqueProcessId
+    creator_back_trace_index: u16,     // CreatorBackTraceIndex
+    object_type_index: u8,             // ObjectTypeIndex
+    handle_attributes: u8,             // HandleAttributes
+    handle_value: u16,                 // HandleValue
+    object: *mut std::ffi::c_void,     // Object
+    granted_access: u32,               // GrantedAccess
+}
+
+impl From<&RawSystemHandleEntry> for SystemHandleEntry {
+    fn from(raw: &RawSystemHandleEntry) -> Self {
+        Self {
+            process_id: raw.process_id as u32,
+            object_type_index: raw.object_type_index,
+            handle_attributes: raw.handle_attributes,
+            handle_value: raw.handle_value as u32,
+            object: raw.object as usize,
+            granted_access: raw.granted_access,
+            // Resolved lazily through DuplicateHandle, see NtHandleSource::target_pid
+            target_pid: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub(crate) struct ObjectTypeInfo {
+    type_index: u8,
+    total_handles: u32,
+    valid_access: u32,
+}
+
+/// Reads the system wide handle table through the native API
+pub struct NtHandleSource;
+
+impl NtHandleSource {
+    #[allow(dead_code)]
+    fn query_handle_type_info(&self, handle: HANDLE) -> Result<ObjectTypeInfo, HandleError> {
+        unsafe {
+            let mut info_length = 0u32;
+
+            // First call to get required buffer size
+            let mut status = NtQueryObject(
+                handle,
+                ObjectTypeInformation,
+                None,
+                0,
+                Some(&mut info_length)
+            );
+
+            if info_length == 0 {
+                return Err(HandleError::FailedToQueryObject);
+            }
+
+            // Now allocate buffer with known size
+            let mut buffer = VirtualAlloc(
+                Some(std::ptr::null_mut()),
+                info_length as usize,
+                MEM_COMMIT,
+                PAGE_EXECUTE_READWRITE
+            );
+
+            if buffer.is_null() {
+                return Err(HandleError::FailedToAllocateMemory);
+            }
+
+            while status != STATUS_SUCCESS {
+                // Free the old buffer FIRST
+                VirtualFree(buffer, 0, MEM_RELEASE)
+                    .map_err(|_| HandleError::FailedToFreeMemory)?;
+
+                // Allocate new buffer with updated size
+                buffer = VirtualAlloc(
+                    Some(std::ptr::null_mut()),
+                    info_length as usize,
+                    MEM_COMMIT,
+                    PAGE_EXECUTE_READWRITE
+                );
+
+                if buffer.is_null() {
+                    log::debug!("Failed to allocate memory!");
+                    return Err(HandleError::FailedToAllocateMemory);
+                }
+
+                // Try to query with new buffer
+                status = NtQueryObject(
+                    handle,
+                    ObjectTypeInformation,
+                    Some(buffer),
+                    info_length,
+                    Some(&mut info_length)
+                );
+
+            }
+
+            // Extract relevant information
+            let type_info = ObjectTypeInfo {
+                type_index: (*(buffer as *const ObjectTypeInfo)).type_index,
+                total_handles: (*(buffer as *const ObjectTypeInfo)).total_handles,
+                valid_access: (*(buffer as *const ObjectTypeInfo)).valid_access,
+            };
+
+            VirtualFree(buffer, 0, MEM_RELEASE)
+                .map_err(|_| HandleError::FailedToFreeMemory)?;
+            Ok(type_info)
+        }
+    }
+}
+
+impl HandleSource for NtHandleSource {
+    fn query_handles(&self) -> Result<Vec<SystemHandleEntry>, HandleError> {
+        let entries;
+
+        unsafe {
+            // Start with a reasonable initial buffer size (4KB)
+            let mut info_length: u32 = 0x1000;
+
+            // Allocate initial memory for handle information
+            let mut buffer = VirtualAlloc(
+                Some(std::ptr::null_mut()),
+                info_length as usize,
+                MEM_COMMIT,
+                PAGE_EXECUTE_READWRITE
+            );
+
+            // First attempt to query system handles
+            // This will likely fail with STATUS_INFO_LENGTH_MISMATCH
+            // but will update info_length with the required size
+            let mut status = NtQuerySystemInformation(
+                SYSTEM_INFORMATION_CLASS(SYSTEM_HANDLE_INFORMATION),
+                buffer,
+                info_length,
+                &mut info_length,
+            );
+
+            log::debug!("Status: {:x?} | Info length: {:?}| Getting buffer", status, info_length);
+
+            // Keep trying until we get a successful query
+            while status != STATUS_SUCCESS {
+                // Free the old buffer FIRST
+                VirtualFree(buffer, 0, MEM_RELEASE)
+                    .map_err(|_| HandleError::FailedToFreeMemory)?;
+
+                // Allocate new buffer with updated size
+                buffer = VirtualAlloc(
+                    Some(std::ptr::null_mut()),
+                    info_length as usize,
+                    MEM_COMMIT,
+                    PAGE_EXECUTE_READWRITE
+                );
+
+                if buffer.is_null() {
+                    return Err(HandleError::FailedToAllocateMemory);
+                }
+
+                // Try to query with current buffer
+                status = NtQuerySystemInformation(
+                    SYSTEM_INFORMATION_CLASS(SYSTEM_HANDLE_INFORMATION),
+                    buffer,
+                    info_length,
+                    &mut info_length,
+                );
+
+                log::debug!("Status: {:x?} | Info length: {:?}| Getting buffer", status, info_length);
+            }
+
+            // We have now successfully retrieved all handles
+            log::debug!("Buffer: {:?}", buffer);
+
+            let handle_info = &*(buffer as *const SystemHandleInformation);
+            log::debug!("Number of handles: {}", handle_info.number_of_handles);
+
+            // Get handles slice and convert each entry into our platform-neutral record
+            let handles = std::slice::from_raw_parts(
+                &handle_info.handles as *const RawSystemHandleEntry,
+                handle_info.number_of_handles as usize
+            );
+
+            entries = handles.iter().map(SystemHandleEntry::from).collect();
+
+            // Clean up allocated memory
+            VirtualFree(buffer, 0, MEM_RELEASE)
+                .map_err(|_| HandleError::FailedToFreeMemory)?;
+        }
+
+        Ok(entries)
+    }
+
+    /// Duplicates the handle into our process and asks which process it refers to
+    fn target_pid(&self, entry: &SystemHandleEntry) -> Option<u32> {
+        unsafe {
+            let process_handle = OpenProcess(
+                PROCESS_DUP_HANDLE,
+                false,
+                entry.process_id
+            ).ok()?;
+
+            let mut duplicated_handle = HANDLE::default();
+
+            let dup_result = DuplicateHandle(
+                process_handle,
+                HANDLE(entry.handle_value as _),
+                GetCurrentProcess(),
+                &mut duplicated_handle,
+                0,
+                false,
+                DUPLICATE_SAME_ACCESS,
+            );
+
+            if CloseHandle(process_handle).is_err() {
+                log::debug!("{}", HandleError::FailedToCloseHandle);
+            }
+
+            dup_result.ok()?;
+
+            // Get Process ID of the duplicated handle
+            let duplicated_pid = GetProcessId(duplicated_handle);
+
+            if CloseHandle(duplicated_handle).is_err() {
+                log::debug!("{}", HandleError::FailedToCloseHandle);
+            }
+
+            // GetProcessId returns 0 when the handle is not a process handle
+            (duplicated_pid != 0).then_some(duplicated_pid)
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// Handle source backed by /proc/*/fd and /proc/*/fdinfo
+//
+// Linux has no system wide handle table, so every open file descriptor is translated into a
+// SystemHandleEntry. Descriptors that reference another process (pidfds and /proc/<pid>/mem) are
+// reported as process handles, with their open flags mapped onto Windows access rights.
+
+use std::fs;
+use crate::core::handle::{
+    HandleError,
+    SystemHandleEntry,
+    SystemHandleType,
+    handle_source::HandleSource,
+    GENERIC_READ,
+    GENERIC_WRITE,
+    PROCESS_QUERY_INFORMATION,
+    PROCESS_TERMINATE,
+    PROCESS_VM_OPERATION,
+    PROCESS_VM_READ,
+    PROCESS_VM_WRITE,
+};
+
+const O_ACCMODE: u32 = 0o3;
+const O_RDONLY: u32 = 0o0;
+const O_WRONLY: u32 = 0o1;
+
+/// Reads open file descriptors of every process we are allowed to inspect
+pub struct ProcHandleSource;
+
+// Fields of /proc/<pid>/fdinfo/<fd> that we care about
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FdInfo {
+    pub flags: u32,
+    pub ino: u64,
+    pub pid: Option<u32>, // Only present for pidfds
+}
+
+/// Parses the contents of a /proc/<pid>/fdinfo/<fd> file
+pub(crate) fn parse_fdinfo(contents: &str) -> FdInfo {
+    let mut info = FdInfo::default();
+
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match key {
+            "flags" => info.flags = u32::from_str_radix(value, 8).unwrap_or(0),
+            "ino" => info.ino = value.parse().unwrap_or(0),
+            // Pidfds of exited processes report -1
+            "Pid" => info.pid = value.parse::<i64>().ok().and_then(|pid| u32::try_from(pid).ok()),
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Turns a descriptor's link target and fdinfo into a handle record, or None for descriptors that
+/// are neither processes nor files (sockets, pipes, other anonymous inodes)
+pub(crate) fn classify_fd(owner_pid: u32, fd: u32, link: &str, info: &FdInfo) -> Option<SystemHandleEntry> {
+    let access_mode = info.flags & O_ACCMODE;
+    let mut entry = SystemHandleEntry {
+        process_id: owner_pid,
+        handle_value: fd,
+        object: info.ino as usize,
+        ..Default::default()
+    };
+
+    if link == "anon_inode:[pidfd]" {
+        // A pidfd can signal its process and, given ptrace rights, pull descriptors out of it
+        entry.object_type_index = SystemHandleType::Process as u8;
+        entry.granted_access = PROCESS_QUERY_INFORMATION | PROCESS_TERMINATE;
+        entry.target_pid = info.pid;
+        return Some(entry);
+    }
+
+    if let Some(target) = link.strip_prefix("/proc/").and_then(|rest| rest.strip_suffix("/mem")) {
+        // Either /proc/<pid>/mem or the per-thread /proc/<pid>/task/<tid>/mem
+        let target_pid = target.split('/').next()?.parse::<u32>().ok()?;
+
+        entry.object_type_index = SystemHandleType::Process as u8;
+        entry.granted_access = match access_mode {
+            O_RDONLY => PROCESS_VM_READ,
+            O_WRONLY => PROCESS_VM_WRITE | PROCESS_VM_OPERATION,
+            _ => PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_VM_OPERATION,
+        };
+        entry.target_pid = Some(target_pid);
+        return Some(entry);
+    }
+
+    if link.starts_with('/') {
+        entry.object_type_index = SystemHandleType::File as u8;
+        entry.granted_access = match access_mode {
+            O_RDONLY => GENERIC_READ,
+            O_WRONLY => GENERIC_WRITE,
+            _ => GENERIC_READ | GENERIC_WRITE,
+        };
+        return Some(entry);
+    }
+
+    None
+}
+
+impl ProcHandleSource {
+    /// Collects the descriptors of a single process. Fails when the process is not ours to inspect.
+    fn query_process(&self, pid: u32) -> std::io::Result<Vec<SystemHandleEntry>> {
+        let mut entries = Vec::new();
+
+        for fd_entry in fs::read_dir(format!("/proc/{}/fd", pid))? {
+            let fd_entry = fd_entry?;
+            let Some(fd) = fd_entry.file_name().to_str().and_then(|fd| fd.parse::<u32>().ok()) else {
+                continue;
+            };
+
+            // Descriptors can close between listing and reading, so skip them quietly
+            let Ok(link) = fs::read_link(fd_entry.path()) else {
+                continue;
+            };
+            let info = fs::read_to_string(format!("/proc/{}/fdinfo/{}", pid, fd))
+                .map(|contents| parse_fdinfo(&contents))
+                .unwrap_or_default();
+
+            if let Some(entry) = classify_fd(pid, fd, &link.to_string_lossy(), &info) {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+impl HandleSource for ProcHandleSource {
+    fn query_handles(&self) -> Result<Vec<SystemHandleEntry>, HandleError> {
+        let mut entries = Vec::new();
+
+        let proc_dir = fs::read_dir("/proc")
+            .map_err(|_| HandleError::FailedToReadProcfs)?;
+
+        for process_entry in proc_dir.flatten() {
+            let Some(pid) = process_entry.file_name().to_str().and_then(|pid| pid.parse::<u32>().ok()) else {
+                continue;
+            };
+
+            match self.query_process(pid) {
+                Ok(process_entries) => entries.extend(process_entries),
+                Err(e) => log::trace!("Skipping descriptors of PID {}: {}", pid, e),
+            }
+        }
+
+        log::debug!("Number of handles: {}", entries.len());
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pidfd_fdinfo() {
+        let info = parse_fdinfo("pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\nPid:\t4242\nNSpid:\t4242\n");
+        assert_eq!(info, FdInfo { flags: 0o2000002, ino: 1057, pid: Some(4242) });
+
+        let exited = parse_fdinfo("flags:\t02000002\nPid:\t-1\n");
+        assert_eq!(exited.pid, None);
+    }
+
+    #[test]
+    fn classifies_process_memory_as_process_handle() {
+        let read_only = FdInfo { flags: 0o100000, ..Default::default() };
+        let entry = classify_fd(10, 3, "/proc/1234/mem", &read_only).unwrap();
+        assert_eq!(entry.object_type_index, SystemHandleType::Process as u8);
+        assert_eq!(entry.target_pid, Some(1234));
+        assert_eq!(entry.granted_access, PROCESS_VM_READ);
+
+        let read_write = FdInfo { flags: 0o100002, ..Default::default() };
+        let entry = classify_fd(10, 3, "/proc/1234/mem", &read_write).unwrap();
+        assert_eq!(entry.granted_access, PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_VM_OPERATION);
+
+        let thread = classify_fd(10, 3, "/proc/1234/task/1240/mem", &read_only).unwrap();
+        assert_eq!(thread.target_pid, Some(1234));
+
+        assert!(classify_fd(10, 4, "socket:[5555]", &read_write).is_none());
+        assert_eq!(
+            classify_fd(10, 5, "/tmp/log.txt", &read_write).unwrap().object_type_index,
+            SystemHandleType::File as u8
+        );
+    }
+
+    #[test]
+    fn finds_own_process_memory_handle() {
+        let own_pid = std::process::id();
+        let _mem = fs::File::open("/proc/self/mem").unwrap();
+
+        let entries = ProcHandleSource.query_handles().unwrap();
+        assert!(entries.iter().any(|entry| {
+            entry.process_id == own_pid
+                && entry.target_pid == Some(own_pid)
+                && entry.granted_access & PROCESS_VM_READ != 0
+        }));
+    }
+}
@@ -0,0 +1,245 @@
+// Linux counterpart to scanning for PROCESS_VM_READ | PROCESS_VM_WRITE handles.
+//
+// A process can get at another process' memory by being ptrace-attached to it (TracerPid in
+// /proc/<pid>/status, checked for every thread since a tracer can attach to a single one) or by
+// holding /proc/<pid>/mem open. Both are reported as HandleContexts so they flow through the same
+// reporting as Windows handles.
+//
+// process_vm_readv/process_vm_writev leave no trace at all, the best that can be done is listing who
+// is able to call them. Non-root processes with CAP_SYS_PTRACE (setcap on the cheat binary, the usual
+// way around needing root) are reported as readers. Root processes have the capability anyway and are
+// left out, and with kernel.yama.ptrace_scope = 0 every process of the same user could read the game.
+
+use std::fs;
+use crate::core::handle::{
+    HandleError,
+    SystemHandleEntry,
+    SystemHandleType,
+    handle_context::HandleContext,
+    handle_info::HandleInfo,
+    handle_source::HandleSource,
+    proc_handle_source::ProcHandleSource,
+    PROCESS_ALL_ACCESS,
+    PROCESS_VM_OPERATION,
+    PROCESS_VM_READ,
+    PROCESS_VM_WRITE,
+};
+
+// Bit of CAP_SYS_PTRACE in the capability masks of /proc/<pid>/status
+const CAP_SYS_PTRACE: u64 = 1 << 19;
+
+#[derive(Default)]
+pub struct PtraceDetector {
+    pub handles: Vec<HandleContext>,
+}
+
+/// Reads a numeric field such as TracerPid or Tgid out of a /proc/<pid>/status file
+fn parse_status_field(status: &str, field: &str) -> Option<u32> {
+    status.lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .and_then(|value| value.trim().parse::<u32>().ok())
+}
+
+/// Reads the TracerPid field out of a /proc/<pid>/status file (0 means not traced)
+pub(crate) fn parse_tracer_pid(status: &str) -> Option<u32> {
+    parse_status_field(status, "TracerPid").filter(|pid| *pid != 0)
+}
+
+/// Whether a /proc/<pid>/status file belongs to a non-root process with CAP_SYS_PTRACE in effect
+fn has_ptrace_capability(status: &str) -> bool {
+    let real_uid = status.lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|uids| uids.split_whitespace().next()?.parse::<u32>().ok());
+    let effective = status.lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok());
+
+    real_uid.is_some_and(|uid| uid != 0) && effective.is_some_and(|mask| mask & CAP_SYS_PTRACE != 0)
+}
+
+impl PtraceDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Finds every process that is tracing or holds the memory of the target process
+    pub fn scan(&mut self, target_pid: u32) -> Result<&mut Self, HandleError> {
+        self.handles.clear();
+
+        for tracer_pid in Self::tracers_of(target_pid)? {
+            log::debug!("PID {} is ptrace-attached to PID {}", tracer_pid, target_pid);
+
+            // A tracer can read, write and redirect execution of its tracee
+            self.handles.push(HandleContext {
+                raw: SystemHandleEntry {
+                    process_id: tracer_pid,
+                    object_type_index: SystemHandleType::Process as u8,
+                    granted_access: PROCESS_ALL_ACCESS,
+                    target_pid: Some(target_pid),
+                    ..Default::default()
+                },
+                info: None,
+            });
+        }
+
+        let memory_handles = ProcHandleSource.query_handles()?
+            .into_iter()
+            .filter(|entry| {
+                entry.object_type_index == SystemHandleType::Process as u8
+                    && entry.target_pid == Some(target_pid)
+                    && entry.process_id != target_pid
+            })
+            .map(|raw| HandleContext { raw, info: None });
+        self.handles.extend(memory_handles);
+
+        for reader_pid in Self::capable_readers(target_pid) {
+            if self.handles.iter().any(|handle| handle.raw.process_id == reader_pid) {
+                continue;
+            }
+
+            log::debug!("PID {} has CAP_SYS_PTRACE and can read PID {} with process_vm_readv", reader_pid, target_pid);
+            self.handles.push(HandleContext {
+                raw: SystemHandleEntry {
+                    process_id: reader_pid,
+                    object_type_index: SystemHandleType::Process as u8,
+                    granted_access: PROCESS_VM_OPERATION | PROCESS_VM_READ | PROCESS_VM_WRITE,
+                    target_pid: Some(target_pid),
+                    ..Default::default()
+                },
+                info: None,
+            });
+        }
+
+        log::debug!("After ptrace scan: {} handles", self.handles.len());
+        Ok(self)
+    }
+
+    /// Collects the tracers of every thread of the target process
+    fn tracers_of(target_pid: u32) -> Result<Vec<u32>, HandleError> {
+        let mut tracers = Vec::new();
+
+        let tasks = fs::read_dir(format!("/proc/{}/task", target_pid))
+            .map_err(|_| HandleError::FailedToOpenProcess)?;
+
+        for task in tasks.flatten() {
+            let Ok(status) = fs::read_to_string(task.path().join("status")) else {
+                continue;
+            };
+
+            if let Some(tracer_tid) = parse_tracer_pid(&status) {
+                // TracerPid names the tracing thread, report the process that owns it
+                let tracer_pid = fs::read_to_string(format!("/proc/{}/status", tracer_tid))
+                    .ok()
+                    .and_then(|status| parse_status_field(&status, "Tgid"))
+                    .unwrap_or(tracer_tid);
+
+                if !tracers.contains(&tracer_pid) {
+                    tracers.push(tracer_pid);
+                }
+            }
+        }
+
+        Ok(tracers)
+    }
+
+    /// Non-root processes other than the target that hold CAP_SYS_PTRACE
+    fn capable_readers(target_pid: u32) -> Vec<u32> {
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+
+        entries.flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|pid| *pid != target_pid)
+            .filter(|pid| fs::read_to_string(format!("/proc/{}/status", pid)).is_ok_and(|status| has_ptrace_capability(&status)))
+            .collect()
+    }
+
+    /// Enrich found handles with additional information. A process whose executable can't be read
+    /// (another user's, or gone already) keeps its handle without info.
+    pub fn collect_handle_info(&mut self) -> &mut Self {
+        for handle in &mut self.handles {
+            match HandleInfo::from_handle_entry(handle.raw.clone()) {
+                Ok(info) => handle.info = Some(info),
+                Err(err) => log::warn!("Failed to resolve PID {}, keeping its access without details: {}", handle.raw.process_id, err),
+            }
+        }
+        self
+    }
+
+    /// Get the final enriched handles
+    pub fn get_handles(&self) -> Vec<HandleContext> {
+        self.handles.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn parses_tracer_pid() {
+        assert_eq!(parse_tracer_pid("Name:\tsleep\nState:\tS (sleeping)\nTracerPid:\t0\n"), None);
+        assert_eq!(parse_tracer_pid("Name:\tsleep\nTracerPid:\t4242\nUid:\t1000\n"), Some(4242));
+    }
+
+    #[test]
+    fn finds_ptrace_capability_of_non_root_processes() {
+        let status = |uid: u32, cap_eff: &str| format!("Name:\treader\nUid:\t{0}\t{0}\t{0}\t{0}\nCapEff:\t{1}\n", uid, cap_eff);
+
+        assert!(has_ptrace_capability(&status(1000, "0000000000080000")));
+        assert!(!has_ptrace_capability(&status(1000, "0000000000000000")));
+        assert!(!has_ptrace_capability(&status(0, "000001ffffffffff")));
+        assert!(!has_ptrace_capability("Name:\tkthreadd\n"));
+    }
+
+    #[test]
+    fn keeps_handles_of_processes_that_cannot_be_resolved() {
+        let mut detector = PtraceDetector::new();
+        detector.handles.push(HandleContext {
+            // PIDs never go this high, there is no /proc entry to read
+            raw: SystemHandleEntry { process_id: u32::MAX, granted_access: PROCESS_ALL_ACCESS, ..Default::default() },
+            info: None,
+        });
+
+        let handles = detector.collect_handle_info().get_handles();
+        assert_eq!(handles.len(), 1);
+        assert!(handles[0].info.is_none());
+    }
+
+    #[test]
+    fn detects_tracer_of_child() {
+        let mut command = Command::new("sleep");
+        command.arg("30").stdout(Stdio::null());
+        // SAFETY: ptrace(PTRACE_TRACEME) is async-signal-safe and only touches the child
+        unsafe {
+            command.pre_exec(|| {
+                if libc::ptrace(libc::PTRACE_TRACEME, 0, 0, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        let mut child = command.spawn().expect("Failed to start a traced child, is ptrace blocked?");
+
+        // Wait for the SIGTRAP stop at exec so the attachment is visible
+        let mut status = 0;
+        unsafe { libc::waitpid(child.id() as i32, &mut status, 0) };
+
+        let own_pid = std::process::id();
+        let found = PtraceDetector::new()
+            .scan(child.id())
+            .unwrap()
+            .get_handles()
+            .iter()
+            .any(|handle| handle.raw.process_id == own_pid && handle.raw.granted_access == PROCESS_ALL_ACCESS);
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert!(found);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod report_diff;
+pub mod scan_history;
@@ -0,0 +1,208 @@
+// Findings of two screenshares of the same machine side by side. PIDs, window handles and addresses
+// change from one session to the next, so findings are matched by what they are instead: the
+// executable behind a handle, the owner, class and title of an overlay, the path of a module.
+
+use std::{collections::BTreeMap, fmt};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    report::ScanReport,
+    scoring::Verdict,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    Handle,
+    Overlay,
+    Module,
+    InjectedCode,
+    PatchedCode,
+}
+
+/// A finding reduced to what identifies it across sessions
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FindingSummary {
+    pub kind: FindingKind,
+    pub key: String,         // Equal for the same finding in two sessions
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReportDiff {
+    pub previous_started_at: DateTime<Utc>,
+    pub previous_verdict: Verdict,
+    pub verdict: Verdict,
+    pub new: Vec<FindingSummary>,       // Only in the newer report
+    pub gone: Vec<FindingSummary>,      // Only in the older report
+    pub unchanged: Vec<FindingSummary>, // In both
+}
+
+impl FindingSummary {
+    fn new(kind: FindingKind, key: String, description: String) -> Self {
+        Self { kind, key: key.to_lowercase(), description }
+    }
+}
+
+impl ReportDiff {
+    /// What changed from `previous` to `current`
+    pub fn between(previous: &ScanReport, current: &ScanReport) -> Self {
+        let before = findings(previous);
+        let after = findings(current);
+
+        let mut diff = Self {
+            previous_started_at: previous.started_at,
+            previous_verdict: previous.assessment.verdict,
+            verdict: current.assessment.verdict,
+            new: Vec::new(),
+            gone: Vec::new(),
+            unchanged: Vec::new(),
+        };
+
+        for (key, finding) in &after {
+            match before.contains_key(key) {
+                true => diff.unchanged.push(finding.clone()),
+                false => diff.new.push(finding.clone()),
+            }
+        }
+        diff.gone = before.into_iter()
+            .filter(|(key, _)| !after.contains_key(key))
+            .map(|(_, finding)| finding)
+            .collect();
+
+        diff
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.new.is_empty() || !self.gone.is_empty()
+    }
+}
+
+/// Every finding of the report, once per identity, ordered by kind and key
+pub fn findings(report: &ScanReport) -> BTreeMap<(FindingKind, String), FindingSummary> {
+    let handles = report.handles.iter().map(|handle| {
+        let owner = handle.paths()
+            .map(|(_, win32_path)| win32_path.clone())
+            .filter(|path| !path.is_empty());
+        let rights = match handle.access_rights() {
+            [] => format!("{:#x}", handle.granted_access()),
+            rights => rights.join(", "),
+        };
+
+        match owner {
+            Some(owner) => FindingSummary::new(
+                FindingKind::Handle,
+                format!("{}|{:#x}", owner, handle.granted_access()),
+                format!("Handle from {} ({})", owner, rights),
+            ),
+            // Without an executable there is nothing better than the PID, which won't match next time
+            None => FindingSummary::new(
+                FindingKind::Handle,
+                format!("pid {}|{:#x}", handle.process_id(), handle.granted_access()),
+                format!("Handle from PID {} ({})", handle.process_id(), rights),
+            ),
+        }
+    });
+
+    let overlays = report.overlays.iter().map(|overlay| FindingSummary::new(
+        FindingKind::Overlay,
+        format!("{}|{}|{}", overlay.owner_image_path, overlay.class_name, overlay.title),
+        format!("Overlay \"{}\" ({}) of {}", overlay.title, overlay.class_name, overlay.owner_image_path),
+    ));
+
+    let modules = report.modules.iter().map(|module| FindingSummary::new(
+        FindingKind::Module,
+        module.path.clone(),
+        format!("Module {}", module.path),
+    ));
+
+    let injected = report.injected.iter().map(|region| {
+        let location = match region.path.is_empty() {
+            true => "anonymous memory".to_string(),
+            false => region.path.clone(),
+        };
+        FindingSummary::new(
+            FindingKind::InjectedCode,
+            format!("{}|{}|{}", location, region.permissions, region.size()),
+            format!("{} bytes of {} code in {}", region.size(), region.permissions, location),
+        )
+    });
+
+    let patched = report.patched.iter().map(|range| {
+        let found: String = range.found.iter().map(|byte| format!("{:02x}", byte)).collect();
+        FindingSummary::new(
+            FindingKind::PatchedCode,
+            format!("{}|{}|{}|{}", range.module, range.section, range.len, found),
+            format!("{} bytes patched in {} {}", range.len, range.module, range.section),
+        )
+    });
+
+    handles.chain(overlays)
+        .chain(modules)
+        .chain(injected)
+        .chain(patched)
+        .map(|finding| ((finding.kind, finding.key.clone()), finding))
+        .collect()
+}
+
+impl fmt::Display for ReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Compared to the screenshare of {} ({} -> {}):",
+            self.previous_started_at.format("%Y-%m-%d %H:%M UTC"), self.previous_verdict, self.verdict)?;
+
+        if !self.has_changes() {
+            writeln!(f, "  No new or gone findings ({} unchanged)", self.unchanged.len())?;
+            return Ok(());
+        }
+
+        for finding in &self.new {
+            writeln!(f, "  + {}", finding.description)?;
+        }
+        for finding in &self.gone {
+            writeln!(f, "  - {}", finding.description)?;
+        }
+        writeln!(f, "  {} new, {} gone, {} unchanged", self.new.len(), self.gone.len(), self.unchanged.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        overlay::window_info::WindowInfo,
+        process::module::ModuleInfo,
+    };
+
+    fn report(started_at: &str, modules: &[&str], overlays: &[(u32, &str)]) -> ScanReport {
+        let mut report = ScanReport::sample(started_at.parse().unwrap());
+        report.modules = modules.iter().map(|path| ModuleInfo::new(*path, 0x10000, 0x1000)).collect();
+        report.overlays = overlays.iter()
+            .map(|(pid, title)| WindowInfo {
+                pid: *pid,
+                title: title.to_string(),
+                class_name: "Overlay".into(),
+                owner_image_path: r"C:\Tools\overlay.exe".into(),
+                ..Default::default()
+            })
+            .collect();
+        report
+    }
+
+    #[test]
+    fn matches_findings_across_sessions() {
+        let previous = report("2026-01-01T12:00:00Z", &[r"C:\Tools\old.dll", r"C:\Tools\kept.dll"], &[(100, "ESP")]);
+        // Same overlay in a new process, module path in different case
+        let current = report("2026-01-02T12:00:00Z", &[r"C:\TOOLS\kept.dll", r"C:\Tools\new.dll"], &[(200, "ESP")]);
+
+        let diff = ReportDiff::between(&previous, &current);
+        let descriptions = |findings: &[FindingSummary]| findings.iter().map(|finding| finding.description.clone()).collect::<Vec<_>>();
+        assert_eq!(descriptions(&diff.new), [r"Module C:\Tools\new.dll"]);
+        assert_eq!(descriptions(&diff.gone), [r"Module C:\Tools\old.dll"]);
+        assert_eq!(diff.unchanged.len(), 2);
+        assert!(diff.has_changes());
+
+        let unchanged = ReportDiff::between(&current, &current);
+        assert!(!unchanged.has_changes());
+        assert!(unchanged.to_string().contains("No new or gone findings (3 unchanged)"));
+    }
+}
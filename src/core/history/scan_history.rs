@@ -0,0 +1,305 @@
+// Reports of past screenshares, kept on disk per host and target so a new scan can be compared with
+// the last one of the same machine:
+//
+//   <dir>/<host>-<hash>/<target>-<hash>/<start time>.json
+//
+// The history is plain JSON on the player's machine. Anyone can delete it, so reports say when there
+// was nothing to compare with instead of staying silent.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::core::{
+    config::state_dir,
+    crypto::hex,
+    history::report_diff::ReportDiff,
+    report::ScanReport,
+    scoring::Verdict,
+};
+
+/// Default number of reports kept per host and target
+pub const DEFAULT_KEEP: usize = 50;
+
+/// A stored report, without its findings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    pub host: String,
+    pub target: String,
+    pub started_at: DateTime<Utc>,
+    pub verdict: Verdict,
+    pub score: u32,
+}
+
+pub struct ScanHistory {
+    dir: PathBuf,
+    keep: usize, // Older reports of the same host and target are deleted, 0 keeps all
+}
+
+impl ScanHistory {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), keep: DEFAULT_KEEP }
+    }
+
+    /// The history below the per-user state directory, see config::state_dir
+    pub fn default_dir() -> PathBuf {
+        state_dir().join("history")
+    }
+
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stores the report, dropping the oldest reports of its host and target beyond `keep`
+    pub fn record(&self, report: &ScanReport) -> anyhow::Result<PathBuf> {
+        let dir = self.target_dir(&report.host.hostname, &report.target.name);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create scan history {}", dir.display()))?;
+
+        // Comparisons are made between stored reports, a stored comparison would only go stale
+        let mut report = report.clone();
+        report.changes = None;
+
+        // Down to the millisecond, scans started within the same second are separate screenshares
+        let path = dir.join(format!("{}.json", report.started_at.format("%Y%m%dT%H%M%S%.3fZ")));
+        // Written next to its place and renamed into it, a crash or full disk can't leave half a report
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, report.to_json()?)
+            .and_then(|_| fs::rename(&partial, &path))
+            .with_context(|| format!("Failed to store report in {}", path.display()))
+            .inspect_err(|_| { let _ = fs::remove_file(&partial); })?;
+
+        if self.keep > 0 {
+            let reports = self.reports(&report.host.hostname, &report.target.name)?;
+            for (old, _) in reports.iter().take(reports.len().saturating_sub(self.keep)) {
+                fs::remove_file(old).with_context(|| format!("Failed to remove {} from the scan history", old.display()))?;
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Stored reports of a host and target, oldest first. Files that can't be read, or that belong to
+    /// another host or target, are skipped.
+    pub fn reports(&self, host: &str, target: &str) -> anyhow::Result<Vec<(PathBuf, ScanReport)>> {
+        let dir = self.target_dir(host, target);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut reports = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let parsed = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(ScanReport::from_json(&contents)?));
+            match parsed {
+                Ok(report) if same_name(&report.host.hostname, host) && same_name(&report.target.name, target) => {
+                    reports.push((path, report));
+                }
+                Ok(report) => log::warn!(
+                    "Skipping report {} of {} on {}, it doesn't belong in this directory",
+                    path.display(), report.target.name, report.host.hostname,
+                ),
+                Err(err) => log::warn!("Skipping unreadable report {}: {}", path.display(), err),
+            }
+        }
+
+        reports.sort_by_key(|(_, report)| report.started_at);
+        Ok(reports)
+    }
+
+    /// The last stored report of the same host and target that started before this one
+    pub fn previous(&self, report: &ScanReport) -> anyhow::Result<Option<ScanReport>> {
+        Ok(self.reports(&report.host.hostname, &report.target.name)?
+            .into_iter()
+            .map(|(_, stored)| stored)
+            .rfind(|stored| stored.started_at < report.started_at))
+    }
+
+    /// Compares the report with the previous screenshare of the same machine, None if there is none
+    pub fn diff(&self, report: &ScanReport) -> anyhow::Result<Option<ReportDiff>> {
+        Ok(self.previous(report)?.map(|previous| ReportDiff::between(&previous, report)))
+    }
+
+    /// Every stored report, by host, target and start time
+    pub fn entries(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        let mut entries = Vec::new();
+        if !self.dir.exists() {
+            return Ok(entries);
+        }
+
+        for host in subdirectories(&self.dir)? {
+            for target in subdirectories(&host)? {
+                // Directory names are sanitized, the names to look them up by are in the reports
+                let Some(report) = fs::read_dir(&target)?
+                    .flatten()
+                    .filter_map(|entry| ScanReport::from_json(&fs::read_to_string(entry.path()).ok()?).ok())
+                    .find(|report| self.target_dir(&report.host.hostname, &report.target.name) == target)
+                else {
+                    continue;
+                };
+
+                entries.extend(self.reports(&report.host.hostname, &report.target.name)?
+                    .into_iter()
+                    .map(|(path, report)| HistoryEntry {
+                        path,
+                        host: report.host.hostname,
+                        target: report.target.name,
+                        started_at: report.started_at,
+                        verdict: report.assessment.verdict,
+                        score: report.assessment.total_score,
+                    }));
+            }
+        }
+
+        entries.sort_by(|a, b| (&a.host, &a.target, a.started_at).cmp(&(&b.host, &b.target, b.started_at)));
+        Ok(entries)
+    }
+
+    fn target_dir(&self, host: &str, target: &str) -> PathBuf {
+        self.dir.join(dir_name(host)).join(dir_name(target))
+    }
+}
+
+fn subdirectories(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect())
+}
+
+/// Host and executable names compare case-insensitively on Windows, so the history does too
+fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Readable part of the name plus a hash of all of it. Names that only differ in characters that
+/// get replaced (PLAYER-PC and PLAYER_PC, non-ASCII names) still end up apart.
+fn dir_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    let readable: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    let hash = hex(&Sha256::digest(name.as_bytes())[..4]);
+
+    match readable.trim_matches('.') {
+        "" => format!("_-{}", hash),
+        readable => format!("{}-{}", readable, hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::core::{process::module::ModuleInfo, temp_dir::TempDir};
+
+    #[test]
+    fn diffs_against_the_last_screenshare_of_the_same_machine() {
+        let dir = TempDir::new("scan_history_diff");
+        let history = ScanHistory::new(dir.path());
+        let start: DateTime<Utc> = "2026-01-01T12:00:00Z".parse().unwrap();
+
+        let mut first = ScanReport::sample(start);
+        first.modules.push(ModuleInfo::new(r"C:\Tools\old.dll", 0x10000, 0x1000));
+        let mut second = ScanReport::sample(start + Duration::days(1));
+        second.modules.push(ModuleInfo::new(r"C:\Tools\new.dll", 0x10000, 0x1000));
+        // Another machine scanned in between doesn't count
+        let mut elsewhere = ScanReport::sample(start + Duration::hours(12));
+        elsewhere.host.hostname = "OTHER-PC".into();
+
+        assert!(history.diff(&first).unwrap().is_none());
+        for report in [&first, &elsewhere, &second] {
+            history.record(report).unwrap();
+        }
+
+        let mut third = ScanReport::sample(start + Duration::days(2));
+        third.target.name = "AC_CLIENT.EXE".into();
+        let diff = history.diff(&third).unwrap().unwrap();
+        assert_eq!(diff.previous_started_at, second.started_at);
+        assert_eq!(diff.gone[0].description, r"Module C:\Tools\new.dll");
+        assert!(diff.new.is_empty());
+
+        let entries = history.entries().unwrap();
+        let hosts: Vec<&str> = entries.iter().map(|entry| entry.host.as_str()).collect();
+        assert_eq!(hosts, ["OTHER-PC", "PLAYER-PC", "PLAYER-PC"]);
+    }
+
+    #[test]
+    fn names_that_sanitize_alike_stay_apart() {
+        let dir = TempDir::new("scan_history_names");
+        let history = ScanHistory::new(dir.path());
+        let start: DateTime<Utc> = "2026-01-01T12:00:00Z".parse().unwrap();
+
+        let mut dash = ScanReport::sample(start);
+        dash.host.hostname = "PLAYER-PC".into();
+        let mut underscore = ScanReport::sample(start + Duration::hours(1));
+        underscore.host.hostname = "PLAYER_PC".into();
+        let mut accented = ScanReport::sample(start + Duration::hours(2));
+        accented.host.hostname = "JOSÉ-PC".into();
+        let mut umlaut = ScanReport::sample(start + Duration::hours(3));
+        umlaut.host.hostname = "JOSÜ-PC".into();
+
+        for report in [&dash, &underscore, &accented, &umlaut] {
+            history.record(report).unwrap();
+            assert!(history.diff(report).unwrap().is_none(), "{} has no earlier screenshare", report.host.hostname);
+        }
+        assert_eq!(history.reports("player-pc", "ac_client.exe").unwrap().len(), 1);
+
+        // A report copied into another machine's directory doesn't count for it
+        let copy = history.target_dir("PLAYER-PC", "ac_client.exe").join("copied.json");
+        fs::write(&copy, underscore.to_json().unwrap()).unwrap();
+        let reports = history.reports("PLAYER-PC", "ac_client.exe").unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].1.host.hostname, "PLAYER-PC");
+    }
+
+    #[test]
+    fn unfinished_writes_are_not_read_back() {
+        let dir = TempDir::new("scan_history_partial");
+        let history = ScanHistory::new(dir.path());
+        let start: DateTime<Utc> = "2026-01-01T12:00:00Z".parse().unwrap();
+        let path = history.record(&ScanReport::sample(start)).unwrap();
+
+        let target_dir = path.parent().unwrap();
+        let files: Vec<String> = fs::read_dir(target_dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, ["20260101T120000.000Z.json"]);
+
+        // Left behind by a crash while storing a later report
+        fs::write(target_dir.join("20260102T120000.000Z.json.partial"), "{\"tool_version\": ").unwrap();
+        let later = ScanReport::sample(start + Duration::days(2));
+        assert_eq!(history.previous(&later).unwrap().unwrap().started_at, start);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_reports() {
+        let dir = TempDir::new("scan_history_keep");
+        let history = ScanHistory::new(dir.path()).with_keep(2);
+        let start: DateTime<Utc> = "2026-01-01T12:00:00Z".parse().unwrap();
+        for day in 0..4 {
+            history.record(&ScanReport::sample(start + Duration::days(day))).unwrap();
+        }
+
+        let reports = history.reports("PLAYER-PC", "ac_client.exe").unwrap();
+        let starts: Vec<DateTime<Utc>> = reports.iter().map(|(_, report)| report.started_at).collect();
+        assert_eq!(starts, [start + Duration::days(2), start + Duration::days(3)]);
+    }
+}
@@ -2,4 +2,19 @@ pub mod handle;
 pub mod overlay;
 pub mod process;
 pub mod anticheat;
-pub mod uploading;
\ No newline at end of file
+pub mod uploading;
+pub mod notify;
+pub mod report;
+pub mod scoring;
+pub mod allowlist;
+pub mod profile;
+pub mod config;
+pub mod watch;
+pub mod history;
+pub mod forensics;
+pub mod crypto;
+
+#[cfg(test)]
+pub(crate) mod test_server;
+#[cfg(test)]
+pub(crate) mod temp_dir;
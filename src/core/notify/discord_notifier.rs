@@ -0,0 +1,99 @@
+// Discord webhook with a single embed, https://discord.com/developers/docs/resources/webhook#execute-webhook
+
+use serde_json::json;
+use crate::core::notify::{
+    notification::Notification,
+    notifier::{post_json, Notifier, NotifyFuture},
+    template::{MessageTemplate, RenderedMessage},
+};
+
+pub struct DiscordNotifier {
+    pub url: String,
+    pub template: MessageTemplate,
+    client: reqwest::Client,
+}
+
+impl DiscordNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            template: MessageTemplate::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn payload(message: &RenderedMessage) -> serde_json::Value {
+        let mut fields: Vec<_> = message.fields.iter()
+            .map(|field| json!({ "name": field.name, "value": field.value, "inline": true }))
+            .collect();
+        if let Some((label, url)) = &message.report_link {
+            fields.push(json!({ "name": label, "value": url, "inline": false }));
+        }
+
+        let mut embed = json!({
+            "title": message.title,
+            "description": message.description,
+            "color": message.color,
+            "fields": fields,
+            "timestamp": message.timestamp.to_rfc3339(),
+        });
+        if let Some(footer) = &message.footer {
+            embed["footer"] = json!({ "text": footer });
+        }
+
+        json!({
+            "username": message.username,
+            "embeds": [embed],
+        })
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn describe(&self) -> String {
+        "Discord webhook".to_string()
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let payload = Self::payload(&self.template.render(notification));
+            post_json(&self.client, &self.url, &[], &payload).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        scoring::Verdict,
+        test_server::{StubResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn posts_embed() {
+        let server = TestServer::start(vec![StubResponse::new(204, "")]);
+        let notifier = DiscordNotifier::new(format!("{}/api/webhooks/1/token", server.url));
+        notifier.notify(&Notification::sample(Verdict::LikelyCheating)).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/api/webhooks/1/token");
+        let payload: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(payload["username"], "Anticheat Bot");
+
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["color"], 0xED4245);
+        assert_eq!(embed["footer"]["text"], "Made by wakeland");
+        assert_eq!(embed["fields"][0]["name"], "Suspicious handles");
+        assert_eq!(embed["fields"][1]["value"], "https://files.example/report.json");
+    }
+
+    #[tokio::test]
+    async fn fails_on_rejected_webhook() {
+        let server = TestServer::start(vec![StubResponse::new(404, r#"{"message": "Unknown Webhook"}"#)]);
+        let err = DiscordNotifier::new(server.url.clone())
+            .notify(&Notification::sample(Verdict::Clean))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown Webhook"));
+    }
+}
@@ -0,0 +1,12 @@
+// Announcing scan results in chat and alerting systems.
+//
+// A Notification describes what happened independent of where it goes, a MessageTemplate turns it
+// into text, and every destination implements Notifier with its own payload format.
+
+pub mod notification;
+pub mod template;
+pub mod notifier;
+pub mod discord_notifier;
+pub mod slack_notifier;
+pub mod teams_notifier;
+pub mod json_notifier;
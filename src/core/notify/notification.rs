@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    report::ScanReport,
+    scoring::Verdict,
+    watch::watch_event::WatchEvent,
+};
+
+/// Change notifications list at most this many events, chat services limit the number of fields
+const MAX_EVENT_FIELDS: usize = 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    #[default]
+    Scan,    // Results of a complete scan
+    Changes, // Findings that appeared since the previous scan of a watch
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationField {
+    pub name: String,
+    pub value: String,
+}
+
+/// What notifiers are told about a scan
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Notification {
+    #[serde(default)]
+    pub kind: NotificationKind,
+    pub verdict: Verdict,
+    pub score: u32,
+    pub target: String,
+    pub pid: u32,
+    pub profile: Option<String>,
+    pub host: String,
+    pub report_url: Option<String>,
+    pub fields: Vec<NotificationField>, // Finding counts, only the ones that aren't zero
+    pub timestamp: DateTime<Utc>,
+}
+
+impl NotificationField {
+    pub fn new(name: impl Into<String>, value: impl ToString) -> Self {
+        Self { name: name.into(), value: value.to_string() }
+    }
+}
+
+impl Notification {
+    pub fn from_report(report: &ScanReport, report_url: Option<&str>) -> Self {
+        let counts = [
+            ("Suspicious handles", report.handles.len()),
+            ("Suspicious overlays", report.overlays.len()),
+            ("Unexpected modules", report.modules.len()),
+            ("Injected code regions", report.injected.len()),
+            ("Patched code ranges", report.patched.len()),
+            ("Allowed findings", report.allowed.len()),
+            ("New since last screenshare", report.changes.as_ref().map_or(0, |changes| changes.new.len())),
+            ("Gone since last screenshare", report.changes.as_ref().map_or(0, |changes| changes.gone.len())),
+            ("Programs closed before the scan", report.executions.iter().filter(|evidence| !evidence.running).count()),
+        ];
+
+        let mut fields: Vec<NotificationField> = counts.into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(name, count)| NotificationField::new(name, count))
+            .collect();
+        if report.first_screenshare {
+            fields.push(NotificationField::new("Previous screenshare", "None in the scan history"));
+        }
+
+        Self {
+            kind: NotificationKind::Scan,
+            verdict: report.assessment.verdict,
+            score: report.assessment.total_score,
+            target: report.target.name.clone(),
+            pid: report.target.pid,
+            profile: report.target.profile.clone(),
+            host: report.host.hostname.clone(),
+            report_url: report_url.map(str::to_string),
+            fields,
+            timestamp: report.finished_at,
+        }
+    }
+
+    /// Lists the events a watch found, with the verdict of the scan that found them
+    pub fn from_events(report: &ScanReport, events: &[WatchEvent]) -> Self {
+        let mut fields: Vec<NotificationField> = events.iter()
+            .take(MAX_EVENT_FIELDS)
+            .map(|event| {
+                NotificationField::new(event.headline(), format!("{} at {}", event.details(), event.timestamp.format("%H:%M:%S UTC")))
+            })
+            .collect();
+        if events.len() > MAX_EVENT_FIELDS {
+            fields.push(NotificationField::new("More changes", format!("{} not listed", events.len() - MAX_EVENT_FIELDS)));
+        }
+
+        Self {
+            kind: NotificationKind::Changes,
+            report_url: None,
+            fields,
+            timestamp: events.iter().map(|event| event.timestamp).max().unwrap_or(report.finished_at),
+            ..Self::from_report(report, None)
+        }
+    }
+
+    /// Notification with every field set, for tests
+    #[cfg(test)]
+    pub(crate) fn sample(verdict: Verdict) -> Self {
+        Self {
+            kind: NotificationKind::Scan,
+            verdict,
+            score: 95,
+            target: "ac_client.exe".into(),
+            pid: 4242,
+            profile: Some("AssaultCube".into()),
+            host: "PLAYER-PC".into(),
+            report_url: Some("https://files.example/report.json".into()),
+            fields: vec![NotificationField::new("Suspicious handles", 2)],
+            timestamp: Utc::now(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Abstraction over where notifications are sent, so the anticheat doesn't depend on any one chat
+// service.
+
+use std::{future::Future, pin::Pin};
+use anyhow::Context;
+use crate::core::notify::notification::Notification;
+
+pub type NotifyFuture<'a> = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send + 'a>>;
+
+pub trait Notifier: Send + Sync {
+    /// Short description used in logs (e.g. "Slack webhook")
+    fn describe(&self) -> String;
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a>;
+}
+
+/// Scheme and host of a URL, for logs. Webhook paths and queries tend to carry tokens.
+pub(crate) fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.has_host() => format!("{}/…", parsed.origin().ascii_serialization()),
+        _ => "<invalid URL>".to_string(),
+    }
+}
+
+/// POSTs a JSON payload and fails on anything but a 2xx answer
+pub(crate) async fn post_json(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &[(String, String)],
+    payload: &serde_json::Value,
+) -> anyhow::Result<()> {
+    let mut request = client.post(url).json(payload);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    // reqwest puts the full URL into its errors, which end up in logs and the outbox
+    let response = request
+        .send()
+        .await
+        .map_err(reqwest::Error::without_url)
+        .with_context(|| format!("Webhook request to {} failed", redact_url(url)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("Webhook answered with status {}: {}", status, body.trim());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// Slack incoming webhook with a legacy attachment, which Mattermost and Rocket.Chat accept as well
+
+use serde_json::json;
+use crate::core::notify::{
+    notification::Notification,
+    notifier::{post_json, Notifier, NotifyFuture},
+    template::{MessageTemplate, RenderedMessage},
+};
+
+pub struct SlackNotifier {
+    pub url: String,
+    pub template: MessageTemplate,
+    client: reqwest::Client,
+}
+
+impl SlackNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            template: MessageTemplate::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn payload(message: &RenderedMessage) -> serde_json::Value {
+        let mut fields: Vec<_> = message.fields.iter()
+            .map(|field| json!({ "title": field.name, "value": field.value, "short": true }))
+            .collect();
+        if let Some((label, url)) = &message.report_link {
+            fields.push(json!({ "title": label, "value": format!("<{}>", url), "short": false }));
+        }
+
+        let mut attachment = json!({
+            "fallback": format!("{}: {}", message.title, message.description),
+            "color": message.hex_color(),
+            "title": message.title,
+            "text": message.description,
+            "fields": fields,
+            "ts": message.timestamp.timestamp(),
+        });
+        if let Some(footer) = &message.footer {
+            attachment["footer"] = json!(footer);
+        }
+
+        json!({
+            "username": message.username,
+            "text": message.title,
+            "attachments": [attachment],
+        })
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn describe(&self) -> String {
+        "Slack webhook".to_string()
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let payload = Self::payload(&self.template.render(notification));
+            post_json(&self.client, &self.url, &[], &payload).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        scoring::Verdict,
+        test_server::TestServer,
+    };
+
+    #[tokio::test]
+    async fn posts_attachment() {
+        let server = TestServer::ok("ok");
+        SlackNotifier::new(server.url.clone())
+            .notify(&Notification::sample(Verdict::Suspicious))
+            .await
+            .unwrap();
+
+        let payload: serde_json::Value = serde_json::from_slice(&server.requests()[0].body).unwrap();
+        let attachment = &payload["attachments"][0];
+        assert_eq!(attachment["color"], "#fee75c");
+        assert_eq!(attachment["text"], "Found suspicious activity");
+        assert_eq!(attachment["fields"][1]["value"], "<https://files.example/report.json>");
+        assert_eq!(attachment["footer"], "Made by wakeland");
+    }
+}
@@ -0,0 +1,105 @@
+// Microsoft Teams workflow webhook with an Adaptive Card,
+// https://learn.microsoft.com/en-us/microsoftteams/platform/task-modules-and-cards/cards/cards-reference#adaptive-card
+
+use serde_json::json;
+use crate::core::{
+    notify::{
+        notification::Notification,
+        notifier::{post_json, Notifier, NotifyFuture},
+        template::{MessageTemplate, RenderedMessage},
+    },
+    scoring::Verdict,
+};
+
+pub struct TeamsNotifier {
+    pub url: String,
+    pub template: MessageTemplate,
+    client: reqwest::Client,
+}
+
+impl TeamsNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            template: MessageTemplate::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Adaptive Cards only know a few named colors, so the verdict picks one instead of the template
+    pub fn payload(message: &RenderedMessage, verdict: Verdict) -> serde_json::Value {
+        let color = match verdict {
+            Verdict::Clean => "good",
+            Verdict::Suspicious => "warning",
+            Verdict::LikelyCheating => "attention",
+        };
+
+        let facts: Vec<_> = message.fields.iter()
+            .map(|field| json!({ "title": field.name, "value": field.value }))
+            .collect();
+
+        let mut body = vec![
+            json!({ "type": "TextBlock", "text": message.title, "weight": "bolder", "size": "medium", "color": color }),
+            json!({ "type": "TextBlock", "text": message.description, "wrap": true }),
+        ];
+        if !facts.is_empty() {
+            body.push(json!({ "type": "FactSet", "facts": facts }));
+        }
+        if let Some(footer) = &message.footer {
+            body.push(json!({ "type": "TextBlock", "text": footer, "isSubtle": true, "size": "small" }));
+        }
+
+        let actions: Vec<_> = message.report_link.iter()
+            .map(|(label, url)| json!({ "type": "Action.OpenUrl", "title": label, "url": url }))
+            .collect();
+
+        json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": body,
+                    "actions": actions,
+                },
+            }],
+        })
+    }
+}
+
+impl Notifier for TeamsNotifier {
+    fn describe(&self) -> String {
+        "Microsoft Teams webhook".to_string()
+    }
+
+    fn notify<'a>(&'a self, notification: &'a Notification) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let payload = Self::payload(&self.template.render(notification), notification.verdict);
+            post_json(&self.client, &self.url, &[], &payload).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_server::{StubResponse, TestServer};
+
+    #[tokio::test]
+    async fn posts_adaptive_card() {
+        let server = TestServer::start(vec![StubResponse::new(202, "")]);
+        TeamsNotifier::new(server.url.clone())
+            .notify(&Notification::sample(Verdict::LikelyCheating))
+            .await
+            .unwrap();
+
+        let payload: serde_json::Value = serde_json::from_slice(&server.requests()[0].body).unwrap();
+        let card = &payload["attachments"][0]["content"];
+        assert_eq!(card["type"], "AdaptiveCard");
+        assert_eq!(card["body"][0]["color"], "attention");
+        assert_eq!(card["body"][2]["facts"][0]["value"], "2");
+        assert_eq!(card["actions"][0]["url"], "https://files.example/report.json");
+    }
+}
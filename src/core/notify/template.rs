@@ -0,0 +1,259 @@
+// Configurable text and colors of notifications.
+//
+// Titles, descriptions and the footer may contain placeholders that are filled in from the
+// notification: {target}, {pid}, {profile}, {host}, {verdict}, {score}, {report_url} and {time}.
+
+use std::{fs, path::Path};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::core::{
+    notify::notification::{Notification, NotificationField, NotificationKind},
+    scoring::Verdict,
+};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerdictStyle {
+    pub description: String,
+    pub color: u32, // 0xRRGGBB
+}
+
+/// A verdict section as written in a template file, keys it leaves out keep the default style's
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PartialVerdictStyle {
+    description: Option<String>,
+    color: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessageTemplate {
+    pub username: String,      // Sender name, where the service lets us pick one
+    pub title: String,
+    pub changes_title: String, // Title of the change notifications of watch mode
+    pub footer: String,        // Left out when empty
+    pub report_link: String,   // Label of the link to the full report
+    #[serde(deserialize_with = "clean_style")]
+    pub clean: VerdictStyle,
+    #[serde(deserialize_with = "suspicious_style")]
+    pub suspicious: VerdictStyle,
+    #[serde(deserialize_with = "likely_cheating_style")]
+    pub likely_cheating: VerdictStyle,
+}
+
+/// A notification run through a template, ready to be put into a service's payload
+#[derive(Clone, Debug)]
+pub struct RenderedMessage {
+    pub username: String,
+    pub title: String,
+    pub description: String,
+    pub color: u32,
+    pub footer: Option<String>,
+    pub fields: Vec<NotificationField>,
+    pub report_link: Option<(String, String)>, // (label, URL)
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Default for MessageTemplate {
+    fn default() -> Self {
+        Self {
+            username: "Anticheat Bot".to_string(),
+            title: "Scan results".to_string(),
+            changes_title: "Changes during screenshare".to_string(),
+            footer: "Made by wakeland".to_string(),
+            report_link: "All scan results".to_string(),
+            clean: VerdictStyle {
+                description: "Did not find suspicious activity.".to_string(),
+                color: 0x57F287,
+            },
+            suspicious: VerdictStyle {
+                description: "Found suspicious activity".to_string(),
+                color: 0xFEE75C,
+            },
+            likely_cheating: VerdictStyle {
+                description: "Likely cheating, found highly suspicious activity".to_string(),
+                color: 0xED4245,
+            },
+        }
+    }
+}
+
+impl PartialVerdictStyle {
+    fn over(self, default: VerdictStyle) -> VerdictStyle {
+        VerdictStyle {
+            description: self.description.unwrap_or(default.description),
+            color: self.color.unwrap_or(default.color),
+        }
+    }
+}
+
+fn clean_style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<VerdictStyle, D::Error> {
+    Ok(PartialVerdictStyle::deserialize(deserializer)?.over(MessageTemplate::default().clean))
+}
+
+fn suspicious_style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<VerdictStyle, D::Error> {
+    Ok(PartialVerdictStyle::deserialize(deserializer)?.over(MessageTemplate::default().suspicious))
+}
+
+fn likely_cheating_style<'de, D: Deserializer<'de>>(deserializer: D) -> Result<VerdictStyle, D::Error> {
+    Ok(PartialVerdictStyle::deserialize(deserializer)?.over(MessageTemplate::default().likely_cheating))
+}
+
+impl RenderedMessage {
+    /// Color as #rrggbb
+    pub fn hex_color(&self) -> String {
+        format!("#{:06x}", self.color)
+    }
+}
+
+impl MessageTemplate {
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Loads a template from a .toml or .json file, unset keys keep their defaults
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read message template from {}", path.display()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(serde_json::from_str(&contents)?),
+            Some("toml") => Self::from_toml(&contents),
+            _ => anyhow::bail!("Unsupported message template format: {}", path.display()),
+        }
+    }
+
+    pub fn style(&self, verdict: Verdict) -> &VerdictStyle {
+        match verdict {
+            Verdict::Clean => &self.clean,
+            Verdict::Suspicious => &self.suspicious,
+            Verdict::LikelyCheating => &self.likely_cheating,
+        }
+    }
+
+    pub fn render(&self, notification: &Notification) -> RenderedMessage {
+        let style = self.style(notification.verdict);
+        let footer = self.fill(&self.footer, notification);
+        let title = match notification.kind {
+            NotificationKind::Scan => &self.title,
+            NotificationKind::Changes => &self.changes_title,
+        };
+
+        RenderedMessage {
+            username: self.fill(&self.username, notification),
+            title: self.fill(title, notification),
+            description: self.fill(&style.description, notification),
+            color: style.color,
+            footer: (!footer.is_empty()).then_some(footer),
+            fields: notification.fields.clone(),
+            report_link: notification.report_url.as_ref()
+                .map(|url| (self.fill(&self.report_link, notification), url.clone())),
+            timestamp: notification.timestamp,
+        }
+    }
+
+    fn fill(&self, text: &str, notification: &Notification) -> String {
+        let values = [
+            ("{target}", notification.target.clone()),
+            ("{pid}", notification.pid.to_string()),
+            ("{profile}", notification.profile.clone().unwrap_or_default()),
+            ("{host}", notification.host.clone()),
+            ("{verdict}", notification.verdict.to_string()),
+            ("{score}", notification.score.to_string()),
+            ("{report_url}", notification.report_url.clone().unwrap_or_default()),
+            ("{time}", notification.timestamp.to_rfc3339()),
+        ];
+
+        // One pass, so values that contain a placeholder (a host named "{score}") stay as they are
+        let mut filled = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            filled.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let value = rest.find('}')
+                .and_then(|end| values.iter().find(|(placeholder, _)| *placeholder == &rest[..=end]));
+            match value {
+                Some((placeholder, value)) => {
+                    filled.push_str(value);
+                    rest = &rest[placeholder.len()..];
+                }
+                None => {
+                    filled.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        filled.push_str(rest);
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_template_keeps_verdict_texts() {
+        let message = MessageTemplate::default().render(&Notification::sample(Verdict::LikelyCheating));
+        assert_eq!(message.title, "Scan results");
+        assert_eq!(message.description, "Likely cheating, found highly suspicious activity");
+        assert_eq!(message.hex_color(), "#ed4245");
+        assert_eq!(message.report_link.unwrap().0, "All scan results");
+    }
+
+    #[test]
+    fn fills_placeholders_from_toml_template() {
+        let template = MessageTemplate::from_toml(r#"
+            title = "{profile} check on {host}"
+            footer = ""
+
+            [suspicious]
+            description = "{target} (PID {pid}) scored {score}, {verdict}"
+            color = 0xFFA500
+        "#).unwrap();
+
+        let message = template.render(&Notification::sample(Verdict::Suspicious));
+        assert_eq!(message.title, "AssaultCube check on PLAYER-PC");
+        assert_eq!(message.description, "ac_client.exe (PID 4242) scored 95, suspicious");
+        assert_eq!(message.color, 0xFFA500);
+        assert!(message.footer.is_none());
+        // Keys the file leaves out keep their defaults
+        assert_eq!(message.username, "Anticheat Bot");
+        assert_eq!(template.clean.description, "Did not find suspicious activity.");
+    }
+
+    #[test]
+    fn partial_verdict_sections_keep_the_other_default() {
+        let template = MessageTemplate::from_toml(r#"
+            [suspicious]
+            description = "Needs a closer look"
+
+            [likely_cheating]
+            color = 0xFF0000
+        "#).unwrap();
+        let defaults = MessageTemplate::default();
+
+        assert_eq!(template.suspicious.description, "Needs a closer look");
+        assert_eq!(template.suspicious.color, defaults.suspicious.color);
+        assert_eq!(template.likely_cheating.description, defaults.likely_cheating.description);
+        assert_eq!(template.likely_cheating.color, 0xFF0000);
+
+        let json: MessageTemplate = serde_json::from_str(r#"{"clean": {"color": 1}}"#).unwrap();
+        assert_eq!(json.clean.description, defaults.clean.description);
+    }
+
+    #[test]
+    fn leaves_placeholders_inside_values_alone() {
+        let template = MessageTemplate::from_toml(r#"
+            title = "{host} {{target}} {unknown}"
+        "#).unwrap();
+
+        let mut notification = Notification::sample(Verdict::Clean);
+        notification.host = "{score}".into();
+        assert_eq!(template.render(&notification).title, "{score} {ac_client.exe} {unknown}");
+    }
+}
@@ -0,0 +1,327 @@
+// Boolean composition of overlay criteria.
+//
+// An expression is a tree of all/any/not nodes over single criteria, e.g. "(layered AND transparent)
+// AND (topmost OR covers >80% of the main screen) AND NOT owned by the game". Evaluating it yields a
+// trace, so the report can show which parts of the expression matched.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::core::overlay::{
+    match_criteria::{RectMatch, SizeMatch, StringMatch, StringMatchMode, StyleMatch, ValueRange},
+    window_info::{ScreenMetrics, WindowInfo},
+};
+
+/// Everything an expression may need besides the window itself
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchContext {
+    pub metrics: ScreenMetrics,
+    pub target_pid: Option<u32>, // PID of the scanned game, for OwnedByTarget
+}
+
+/// In config files every node is a single key table, e.g.
+/// `{ all = [{ style_ex = { all = 0x80020 } }, { not = "owned_by_target" }] }`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CriteriaExpr {
+    All(Vec<CriteriaExpr>),
+    Any(Vec<CriteriaExpr>),
+    Not(Box<CriteriaExpr>),
+    PidOwner(u32),
+    OwnedByTarget,
+    Class(StringMatch),
+    Title(StringMatch),
+    Position(RectMatch),
+    Size(SizeMatch),
+    PercentAllScreens(f32),
+    PercentMainScreen(f32),
+    Style(StyleMatch),
+    StyleEx(StyleMatch),
+    Alpha(ValueRange),
+    HasOwner(bool),
+    Cloaked(bool),
+    CaptureExcluded(bool),
+    OwnerImage(StringMatch),
+}
+
+/// Result of evaluating one node, with the results of its children
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExprTrace {
+    pub expr: String,
+    pub matched: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negated: bool,             // A NOT node, its child matched exactly when it didn't
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ExprTrace>,
+}
+
+impl ExprTrace {
+    /// Every node that matched, outermost first. Terms under a NOT are left out, a match there is
+    /// what kept the NOT from matching.
+    pub fn matched_terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        self.collect_matched(&mut terms);
+        terms
+    }
+
+    fn collect_matched(&self, terms: &mut Vec<String>) {
+        if self.matched {
+            terms.push(self.expr.clone());
+        }
+        if self.negated {
+            return;
+        }
+        for child in &self.children {
+            child.collect_matched(terms);
+        }
+    }
+}
+
+impl CriteriaExpr {
+    pub fn all(exprs: impl IntoIterator<Item = CriteriaExpr>) -> Self {
+        Self::All(exprs.into_iter().collect())
+    }
+
+    pub fn any(exprs: impl IntoIterator<Item = CriteriaExpr>) -> Self {
+        Self::Any(exprs.into_iter().collect())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(expr: CriteriaExpr) -> Self {
+        Self::Not(Box::new(expr))
+    }
+
+    pub fn matches(&self, info: &WindowInfo, ctx: &MatchContext) -> bool {
+        match self {
+            Self::All(exprs) => exprs.iter().all(|expr| expr.matches(info, ctx)),
+            Self::Any(exprs) => exprs.iter().any(|expr| expr.matches(info, ctx)),
+            Self::Not(expr) => !expr.matches(info, ctx),
+            Self::PidOwner(pid) => info.pid == *pid,
+            Self::OwnedByTarget => ctx.target_pid == Some(info.pid),
+            Self::Class(class_name) => class_name.matches(&info.class_name),
+            Self::Title(title) => title.matches(&info.title),
+            Self::Position(position) => position.matches(&info.position),
+            Self::Size(size) => size.matches(&info.size),
+            Self::PercentAllScreens(percent) => info.screen_percentages(&ctx.metrics).0 >= *percent,
+            Self::PercentMainScreen(percent) => info.screen_percentages(&ctx.metrics).1 >= *percent,
+            Self::Style(style) => style.matches(info.style as u32),
+            Self::StyleEx(style_ex) => style_ex.matches(info.style_ex as u32),
+            Self::Alpha(alpha) => info.alpha.is_some_and(|value| alpha.contains(value as i32)),
+            Self::HasOwner(has_owner) => (info.owner != 0) == *has_owner,
+            Self::Cloaked(cloaked) => info.cloaked == *cloaked,
+            Self::CaptureExcluded(excluded) => info.is_capture_excluded() == *excluded,
+            Self::OwnerImage(image) => image.matches(&info.owner_image_path),
+        }
+    }
+
+    /// Evaluates every node (without short-circuiting) and records which ones matched
+    pub fn explain(&self, info: &WindowInfo, ctx: &MatchContext) -> ExprTrace {
+        let children: Vec<ExprTrace> = match self {
+            Self::All(exprs) | Self::Any(exprs) => exprs.iter().map(|expr| expr.explain(info, ctx)).collect(),
+            Self::Not(expr) => vec![expr.explain(info, ctx)],
+            _ => Vec::new(),
+        };
+
+        let matched = match self {
+            Self::All(_) => children.iter().all(|child| child.matched),
+            Self::Any(_) => children.iter().any(|child| child.matched),
+            Self::Not(_) => !children[0].matched,
+            _ => self.matches(info, ctx),
+        };
+
+        ExprTrace { expr: self.to_string(), matched, negated: matches!(self, Self::Not(_)), children }
+    }
+}
+
+fn write_joined(f: &mut fmt::Formatter<'_>, exprs: &[CriteriaExpr], separator: &str) -> fmt::Result {
+    write!(f, "(")?;
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            write!(f, " {} ", separator)?;
+        }
+        write!(f, "{}", expr)?;
+    }
+    write!(f, ")")
+}
+
+fn write_range(f: &mut fmt::Formatter<'_>, name: &str, range: &ValueRange) -> fmt::Result {
+    match (range.min, range.max) {
+        (Some(min), Some(max)) if min == max => write!(f, " {}={}", name, min),
+        (Some(min), Some(max)) => write!(f, " {}={}..={}", name, min, max),
+        (Some(min), None) => write!(f, " {}>={}", name, min),
+        (None, Some(max)) => write!(f, " {}<={}", name, max),
+        (None, None) => Ok(()),
+    }
+}
+
+fn write_style(f: &mut fmt::Formatter<'_>, name: &str, style: &StyleMatch) -> fmt::Result {
+    write!(f, "{}", name)?;
+    for (mode, bits) in [("any", style.any), ("all", style.all), ("none", style.none)] {
+        if bits != 0 {
+            write!(f, " {}={:#x}", mode, bits)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, name: &str, value: &StringMatch) -> fmt::Result {
+    let operator = match value.mode {
+        StringMatchMode::Exact => "is",
+        StringMatchMode::IgnoreCase => "is (ignoring case)",
+        StringMatchMode::Regex => "matches regex",
+        StringMatchMode::Glob => "matches glob",
+    };
+    write!(f, "{} {} \"{}\"", name, operator, value.pattern)
+}
+
+impl fmt::Display for CriteriaExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All(exprs) => write_joined(f, exprs, "AND"),
+            Self::Any(exprs) => write_joined(f, exprs, "OR"),
+            Self::Not(expr) => write!(f, "NOT {}", expr),
+            Self::PidOwner(pid) => write!(f, "owned by PID {}", pid),
+            Self::OwnedByTarget => write!(f, "owned by target"),
+            Self::Class(class_name) => write_string(f, "class", class_name),
+            Self::Title(title) => write_string(f, "title", title),
+            Self::Position(position) => {
+                write!(f, "position")?;
+                write_range(f, "left", &position.left)?;
+                write_range(f, "top", &position.top)?;
+                write_range(f, "right", &position.right)?;
+                write_range(f, "bottom", &position.bottom)
+            }
+            Self::Size(size) => {
+                write!(f, "size")?;
+                write_range(f, "x", &size.x)?;
+                write_range(f, "y", &size.y)
+            }
+            Self::PercentAllScreens(percent) => write!(f, "covers >={}% of all screens", percent),
+            Self::PercentMainScreen(percent) => write!(f, "covers >={}% of main screen", percent),
+            Self::Style(style) => write_style(f, "style", style),
+            Self::StyleEx(style_ex) => write_style(f, "style_ex", style_ex),
+            Self::Alpha(alpha) => {
+                write!(f, "layered")?;
+                write_range(f, "alpha", alpha)
+            }
+            Self::HasOwner(has_owner) => write!(f, "{}", if *has_owner { "has owner window" } else { "has no owner window" }),
+            Self::Cloaked(cloaked) => write!(f, "{}", if *cloaked { "cloaked" } else { "not cloaked" }),
+            Self::CaptureExcluded(excluded) => {
+                write!(f, "{}", if *excluded { "excluded from capture" } else { "visible to capture" })
+            }
+            Self::OwnerImage(image) => write_string(f, "owner image", image),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::{
+        window_info::Point,
+        WS_EX_LAYERED,
+        WS_EX_TOPMOST,
+        WS_EX_TRANSPARENT,
+    };
+
+    fn ctx() -> MatchContext {
+        MatchContext {
+            metrics: ScreenMetrics { screen: Point { x: 1920, y: 1080 }, desktop: Point { x: 1920, y: 1080 } },
+            target_pid: Some(4242),
+        }
+    }
+
+    // (layered AND transparent) AND (topmost OR covers >80% of main screen) AND NOT owned by the game
+    fn esp_expr() -> CriteriaExpr {
+        CriteriaExpr::all([
+            CriteriaExpr::StyleEx(StyleMatch::all(WS_EX_LAYERED | WS_EX_TRANSPARENT)),
+            CriteriaExpr::any([
+                CriteriaExpr::StyleEx(StyleMatch::all(WS_EX_TOPMOST)),
+                CriteriaExpr::PercentMainScreen(80.0),
+            ]),
+            CriteriaExpr::not(CriteriaExpr::OwnedByTarget),
+        ])
+    }
+
+    fn window(pid: u32, style_ex: u32, size: Point) -> WindowInfo {
+        WindowInfo { pid, style_ex: style_ex as isize, size, ..Default::default() }
+    }
+
+    #[test]
+    fn evaluates_nested_expression() {
+        let expr = esp_expr();
+        let click_through = WS_EX_LAYERED | WS_EX_TRANSPARENT;
+        let fullscreen = Point { x: 1920, y: 1080 };
+
+        assert!(expr.matches(&window(1001, click_through, fullscreen), &ctx()));
+        assert!(expr.matches(&window(1001, click_through | WS_EX_TOPMOST, Point { x: 200, y: 200 }), &ctx()));
+        assert!(!expr.matches(&window(1001, click_through, Point { x: 200, y: 200 }), &ctx()));
+        assert!(!expr.matches(&window(1001, WS_EX_LAYERED, fullscreen), &ctx()));
+        assert!(!expr.matches(&window(4242, click_through, fullscreen), &ctx()));
+    }
+
+    #[test]
+    fn trace_lists_matched_sub_expressions() {
+        let trace = esp_expr().explain(&window(1001, WS_EX_LAYERED | WS_EX_TRANSPARENT, Point { x: 1920, y: 1080 }), &ctx());
+
+        assert!(trace.matched);
+        assert_eq!(trace.matched_terms()[1..], [
+            "style_ex all=0x80020",
+            "(style_ex all=0x8 OR covers >=80% of main screen)",
+            "covers >=80% of main screen",
+            "NOT owned by target",
+        ]);
+    }
+
+    #[test]
+    fn trace_skips_terms_under_negation() {
+        let expr = CriteriaExpr::all([
+            CriteriaExpr::StyleEx(StyleMatch::all(WS_EX_LAYERED)),
+            CriteriaExpr::not(CriteriaExpr::any([
+                CriteriaExpr::OwnedByTarget,
+                CriteriaExpr::StyleEx(StyleMatch::all(WS_EX_TOPMOST)),
+            ])),
+        ]);
+
+        let trace = expr.explain(&window(4242, WS_EX_LAYERED, Point { x: 100, y: 100 }), &ctx());
+        assert!(!trace.matched);
+        assert_eq!(trace.matched_terms(), ["style_ex all=0x80000"]);
+    }
+
+    #[test]
+    fn parses_from_config() {
+        #[derive(Deserialize)]
+        struct Rule {
+            when: CriteriaExpr,
+        }
+
+        let rule: Rule = toml::from_str(r#"
+            when.all = [
+                { style_ex = { all = 0x80020 } },
+                { any = [{ style_ex = { all = 0x8 } }, { percent_main_screen = 80 }] },
+                { not = "owned_by_target" },
+            ]
+        "#).unwrap();
+
+        assert_eq!(rule.when.to_string(), esp_expr().to_string());
+    }
+
+    #[test]
+    fn matches_extended_window_attributes() {
+        let window = WindowInfo {
+            alpha: Some(40),
+            display_affinity: crate::core::overlay::WDA_EXCLUDEFROMCAPTURE,
+            owner_image_path: r"C:\Users\player\Downloads\loader.exe".into(),
+            ..Default::default()
+        };
+
+        let expr = CriteriaExpr::all([
+            CriteriaExpr::Alpha(ValueRange::at_most(128)),
+            CriteriaExpr::CaptureExcluded(true),
+            CriteriaExpr::Cloaked(false),
+            CriteriaExpr::HasOwner(false),
+            CriteriaExpr::OwnerImage(StringMatch::glob(r"*\downloads\*").unwrap()),
+        ]);
+        assert!(expr.matches(&window, &ctx()));
+        assert!(!CriteriaExpr::Alpha(ValueRange::at_most(128)).matches(&WindowInfo::default(), &ctx()));
+    }
+}
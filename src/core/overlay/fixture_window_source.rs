@@ -0,0 +1,59 @@
+// In-memory window list, used to run the overlay rules against known data instead of a live desktop
+
+use std::{
+    fs,
+    path::Path,
+};
+use anyhow::Context;
+use serde::Deserialize;
+use crate::core::overlay::{
+    WindowError,
+    window_info::{ScreenMetrics, WindowInfo},
+    window_source::WindowSource,
+};
+
+// Layout of a fixture file: a `metrics` table plus a list of [[windows]] tables (TOML) or a
+// "windows" array (JSON)
+#[derive(Deserialize)]
+struct FixtureFile {
+    #[serde(default)]
+    metrics: ScreenMetrics,
+    windows: Vec<WindowInfo>,
+}
+
+#[derive(Clone, Default)]
+pub struct FixtureWindowSource {
+    windows: Vec<WindowInfo>,
+    metrics: ScreenMetrics,
+}
+
+impl FixtureWindowSource {
+    pub fn new(windows: Vec<WindowInfo>, metrics: ScreenMetrics) -> Self {
+        Self { windows, metrics }
+    }
+
+    /// Loads fixtures from a .toml or .json file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read window fixtures from {}", path.display()))?;
+
+        let file: FixtureFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&contents)?,
+            Some("toml") => toml::from_str(&contents)?,
+            _ => anyhow::bail!("Unsupported window fixture format: {}", path.display()),
+        };
+
+        Ok(Self::new(file.windows, file.metrics))
+    }
+}
+
+impl WindowSource for FixtureWindowSource {
+    fn query_windows(&self) -> Result<Vec<WindowInfo>, WindowError> {
+        Ok(self.windows.clone())
+    }
+
+    fn screen_metrics(&self) -> Result<ScreenMetrics, WindowError> {
+        Ok(self.metrics)
+    }
+}
@@ -0,0 +1,376 @@
+// Building blocks for OverlayFinderParams criteria: how strings, style bits and geometry are compared.
+//
+// Every type deserializes from the short legacy form as well (a plain string is an exact match, a
+// plain number is an any-bits style or an exact value), so older rule files keep working.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use crate::core::overlay::window_info::{Point, Rect};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StringMatchMode {
+    #[default]
+    Exact,
+    IgnoreCase,
+    Regex,
+    Glob, // `*` and `?` wildcards, ignores case
+}
+
+/// How a window class or title is compared. An empty pattern is not a criterion.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(try_from = "StringMatchRepr", into = "StringMatchRepr")]
+pub struct StringMatch {
+    pub pattern: String,
+    pub mode: StringMatchMode,
+    compiled: Option<Regex>, // Regex and glob patterns, compiled once
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StringMatchRepr {
+    Exact(String),
+    Full {
+        pattern: String,
+        #[serde(default)]
+        mode: StringMatchMode,
+    },
+}
+
+impl StringMatch {
+    pub fn new(pattern: impl Into<String>, mode: StringMatchMode) -> Result<Self, regex::Error> {
+        let pattern = pattern.into();
+        let compiled = match mode {
+            StringMatchMode::Regex => Some(Regex::new(&pattern)?),
+            StringMatchMode::Glob => Some(RegexBuilder::new(&glob_to_regex(&pattern)).case_insensitive(true).build()?),
+            StringMatchMode::Exact | StringMatchMode::IgnoreCase => None,
+        };
+
+        Ok(Self { pattern, mode, compiled })
+    }
+
+    pub fn exact(pattern: impl Into<String>) -> Self {
+        Self { pattern: pattern.into(), mode: StringMatchMode::Exact, compiled: None }
+    }
+
+    pub fn ignore_case(pattern: impl Into<String>) -> Self {
+        Self { pattern: pattern.into(), mode: StringMatchMode::IgnoreCase, compiled: None }
+    }
+
+    pub fn regex(pattern: impl Into<String>) -> Result<Self, regex::Error> {
+        Self::new(pattern, StringMatchMode::Regex)
+    }
+
+    pub fn glob(pattern: impl Into<String>) -> Result<Self, regex::Error> {
+        Self::new(pattern, StringMatchMode::Glob)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match (&self.mode, &self.compiled) {
+            (StringMatchMode::Exact, _) => self.pattern == value,
+            (StringMatchMode::IgnoreCase, _) => self.pattern.to_lowercase() == value.to_lowercase(),
+            (_, Some(regex)) => regex.is_match(value),
+            (_, None) => false,
+        }
+    }
+}
+
+impl From<&str> for StringMatch {
+    fn from(pattern: &str) -> Self {
+        Self::exact(pattern)
+    }
+}
+
+impl From<String> for StringMatch {
+    fn from(pattern: String) -> Self {
+        Self::exact(pattern)
+    }
+}
+
+impl TryFrom<StringMatchRepr> for StringMatch {
+    type Error = regex::Error;
+
+    fn try_from(repr: StringMatchRepr) -> Result<Self, Self::Error> {
+        match repr {
+            StringMatchRepr::Exact(pattern) => Ok(Self::exact(pattern)),
+            StringMatchRepr::Full { pattern, mode } => Self::new(pattern, mode),
+        }
+    }
+}
+
+impl From<StringMatch> for StringMatchRepr {
+    fn from(value: StringMatch) -> Self {
+        StringMatchRepr::Full { pattern: value.pattern, mode: value.mode }
+    }
+}
+
+/// Anchored regex equivalent of a glob pattern
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Style bit requirements. Each set mask is a requirement, all of which have to hold:
+/// `any` needs at least one of its bits, `all` needs every bit, `none` forbids every bit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StyleMatchRepr")]
+pub struct StyleMatch {
+    pub any: u32,
+    pub all: u32,
+    pub none: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StyleMatchRepr {
+    Any(u32),
+    Masks {
+        #[serde(default)]
+        any: u32,
+        #[serde(default)]
+        all: u32,
+        #[serde(default)]
+        none: u32,
+    },
+}
+
+impl From<StyleMatchRepr> for StyleMatch {
+    fn from(repr: StyleMatchRepr) -> Self {
+        match repr {
+            StyleMatchRepr::Any(any) => Self::any(any),
+            StyleMatchRepr::Masks { any, all, none } => Self { any, all, none },
+        }
+    }
+}
+
+impl StyleMatch {
+    pub fn any(bits: u32) -> Self {
+        Self { any: bits, ..Default::default() }
+    }
+
+    pub fn all(bits: u32) -> Self {
+        Self { all: bits, ..Default::default() }
+    }
+
+    pub fn none(bits: u32) -> Self {
+        Self { none: bits, ..Default::default() }
+    }
+
+    /// Additionally forbids the given bits
+    pub fn without(mut self, bits: u32) -> Self {
+        self.none |= bits;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.any == 0 && self.all == 0 && self.none == 0
+    }
+
+    pub fn matches(&self, style: u32) -> bool {
+        (self.any == 0 || style & self.any != 0)
+            && style & self.all == self.all
+            && style & self.none == 0
+    }
+}
+
+impl From<u32> for StyleMatch {
+    fn from(bits: u32) -> Self {
+        Self::any(bits)
+    }
+}
+
+/// Inclusive bounds, either of which may be left open
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ValueRangeRepr")]
+pub struct ValueRange {
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ValueRangeRepr {
+    Exact(i32),
+    Bounds {
+        #[serde(default)]
+        min: Option<i32>,
+        #[serde(default)]
+        max: Option<i32>,
+    },
+}
+
+impl From<ValueRangeRepr> for ValueRange {
+    fn from(repr: ValueRangeRepr) -> Self {
+        match repr {
+            ValueRangeRepr::Exact(value) => Self::exact(value),
+            ValueRangeRepr::Bounds { min, max } => Self { min, max },
+        }
+    }
+}
+
+impl ValueRange {
+    pub fn exact(value: i32) -> Self {
+        Self { min: Some(value), max: Some(value) }
+    }
+
+    pub fn between(min: i32, max: i32) -> Self {
+        Self { min: Some(min), max: Some(max) }
+    }
+
+    pub fn at_least(min: i32) -> Self {
+        Self { min: Some(min), max: None }
+    }
+
+    pub fn at_most(max: i32) -> Self {
+        Self { min: None, max: Some(max) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+
+    pub fn contains(&self, value: i32) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+/// Window position, one range per edge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RectMatch {
+    pub left: ValueRange,
+    pub top: ValueRange,
+    pub right: ValueRange,
+    pub bottom: ValueRange,
+}
+
+impl RectMatch {
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty() && self.top.is_empty() && self.right.is_empty() && self.bottom.is_empty()
+    }
+
+    pub fn matches(&self, rect: &Rect) -> bool {
+        self.left.contains(rect.left)
+            && self.top.contains(rect.top)
+            && self.right.contains(rect.right)
+            && self.bottom.contains(rect.bottom)
+    }
+}
+
+impl From<Rect> for RectMatch {
+    fn from(rect: Rect) -> Self {
+        Self {
+            left: ValueRange::exact(rect.left),
+            top: ValueRange::exact(rect.top),
+            right: ValueRange::exact(rect.right),
+            bottom: ValueRange::exact(rect.bottom),
+        }
+    }
+}
+
+/// Window size, one range per dimension
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizeMatch {
+    pub x: ValueRange,
+    pub y: ValueRange,
+}
+
+impl SizeMatch {
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty() && self.y.is_empty()
+    }
+
+    pub fn matches(&self, size: &Point) -> bool {
+        self.x.contains(size.x) && self.y.contains(size.y)
+    }
+}
+
+impl From<Point> for SizeMatch {
+    fn from(size: Point) -> Self {
+        Self { x: ValueRange::exact(size.x), y: ValueRange::exact(size.y) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::{WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TRANSPARENT};
+
+    #[test]
+    fn string_modes() {
+        assert!(StringMatch::exact("Notepad").matches("Notepad"));
+        assert!(!StringMatch::exact("Notepad").matches("notepad"));
+        assert!(StringMatch::ignore_case("Notepad").matches("NOTEPAD"));
+
+        let regex = StringMatch::regex(r"^Overlay \d+$").unwrap();
+        assert!(regex.matches("Overlay 42"));
+        assert!(!regex.matches("Overlay x"));
+
+        let glob = StringMatch::glob("*ESP?").unwrap();
+        assert!(glob.matches("external esp1"));
+        assert!(!glob.matches("external esp"));
+        assert!(StringMatch::glob("a.b").unwrap().matches("A.B"));
+        assert!(!StringMatch::glob("a.b").unwrap().matches("axb"));
+
+        assert!(StringMatch::regex("(").is_err());
+    }
+
+    #[test]
+    fn style_modes() {
+        let click_through = WS_EX_LAYERED | WS_EX_TRANSPARENT;
+
+        assert!(StyleMatch::any(click_through).matches(WS_EX_LAYERED));
+        assert!(!StyleMatch::all(click_through).matches(WS_EX_LAYERED));
+        assert!(StyleMatch::all(click_through).matches(click_through | WS_EX_TOOLWINDOW));
+        assert!(!StyleMatch::all(click_through).without(WS_EX_TOOLWINDOW).matches(click_through | WS_EX_TOOLWINDOW));
+        assert!(StyleMatch::none(WS_EX_TOOLWINDOW).matches(click_through));
+    }
+
+    #[test]
+    fn legacy_and_extended_forms_deserialize() {
+        #[derive(Deserialize)]
+        struct Criteria {
+            class: StringMatch,
+            title: StringMatch,
+            style: StyleMatch,
+            style_ex: StyleMatch,
+            size: SizeMatch,
+        }
+
+        let criteria: Criteria = toml::from_str(r#"
+            class = "Chrome_WidgetWin_1"
+            title = { pattern = "*overlay*", mode = "glob" }
+            style = 0x10000000
+            style_ex = { all = 0x80020, none = 0x80 }
+            size = { x = 1920, y = { min = 1000 } }
+        "#).unwrap();
+
+        assert_eq!(criteria.class.mode, StringMatchMode::Exact);
+        assert!(criteria.title.matches("Some Overlay Window"));
+        assert_eq!(criteria.style, StyleMatch::any(0x1000_0000));
+        assert_eq!(criteria.style_ex, StyleMatch::all(0x80020).without(0x80));
+        assert_eq!(criteria.size.x, ValueRange::exact(1920));
+        assert_eq!(criteria.size.y, ValueRange::at_least(1000));
+
+        assert!(toml::from_str::<Criteria>(r#"
+            class = ""
+            title = { pattern = "(", mode = "regex" }
+            style = 0
+            style_ex = 0
+            size = {}
+        "#).is_err());
+    }
+}
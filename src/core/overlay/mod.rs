@@ -1,30 +1,69 @@
 // Adapted from https://www.unknowncheats.me/forum/anti-cheat-bypass/263403-window-hijacking-dont-overlay-betray.html
 
+#[cfg(windows)]
 use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::EnumWindows;
+#[cfg(windows)]
 use crate::core::overlay::overlay_finder_params::OverlayFinderParams;
-use crate::core::overlay::window_info::WindowInfo;
+#[cfg(windows)]
+use crate::core::overlay::{
+    criteria_expr::MatchContext,
+    window_info::{ScreenMetrics, WindowInfo},
+};
 
-pub(crate) mod window_info;
+pub mod window_info;
+pub mod match_criteria;
+pub mod criteria_expr;
+pub mod window_source;
+pub mod fixture_window_source;
+#[cfg(windows)]
+pub mod win32_window_source;
 pub mod overlay_finder;
 pub mod overlay_finder_params;
+pub mod overlay_rule;
 
-unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
-    // Convert the LPARAM back to our params structure
-    let params = &mut *(lparam.0 as *mut OverlayFinderParams);
+// Win32 window styles used by the overlay heuristics, defined here so they exist on every platform
+pub const WS_VISIBLE: u32 = 0x1000_0000;
+pub const WS_EX_TOPMOST: u32 = 0x0000_0008;
+pub const WS_EX_TRANSPARENT: u32 = 0x0000_0020;
+pub const WS_EX_TOOLWINDOW: u32 = 0x0000_0080;
+pub const WS_EX_LAYERED: u32 = 0x0008_0000;
+pub const WS_EX_NOACTIVATE: u32 = 0x0800_0000;
 
-    let window_info = WindowInfo::from_hwnd(hwnd);
+// SetWindowDisplayAffinity values that keep a window out of screen capture
+pub const WDA_MONITOR: u32 = 0x0000_0001;
+pub const WDA_EXCLUDEFROMCAPTURE: u32 = 0x0000_0011;
 
-    // Check if window matches our criteria
-    let (satisfied, unsatisfied) = params.matches_criteria(&window_info);
+#[derive(Debug)]
+pub enum WindowError {
+    FailedToEnumerateWindows,
+    FailedToQueryWindow,
+    WindowClosed,        // Closed between being enumerated and being queried
+    FailedToQueryScreen,
+    UnsupportedPlatform,
+}
 
-    // If no criteria are satisfied, skip this window
-    if satisfied == 0 {
-        return BOOL(1);
+// Minimal Display implementation - just show the variant name
+impl std::fmt::Display for WindowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
     }
+}
+
+impl std::error::Error for WindowError {}
 
-    // If we need all criteria to match and some don't, skip this window
-    if params.satisfy_all_criteria && unsatisfied > 0 {
+#[cfg(windows)]
+unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    // Convert the LPARAM back to our params structure
+    let params = &mut *(lparam.0 as *mut OverlayFinderParams);
+
+    // Skip windows that closed meanwhile, and windows that don't match our criteria
+    let (Ok(window_info), Ok(metrics)) = (WindowInfo::from_hwnd(hwnd), ScreenMetrics::current()) else {
+        return BOOL(1);
+    };
+    let ctx = MatchContext { metrics, target_pid: None };
+    if !params.is_match(&window_info, &ctx) {
         return BOOL(1);
     }
 
@@ -33,21 +72,26 @@ unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> B
     BOOL(1) // Continue enumeration
 }
 
-pub fn find_overlays(mut params: OverlayFinderParams) -> Vec<HWND> {
+#[cfg(windows)]
+pub fn find_overlays(mut params: OverlayFinderParams) -> Result<Vec<HWND>, WindowError> {
     unsafe {
         EnumWindows(Some(enum_windows_callback), LPARAM(&mut params as *mut _ as isize))
-            .expect("Failed to enumerate windows for window finder!");
+            .map_err(|_| WindowError::FailedToEnumerateWindows)?;
     }
-    params.hwnds
+    Ok(params.hwnds)
 }
 
+#[cfg(windows)]
 pub fn debug_print_overlays(handles: Vec<HWND>) {
     for (i, handle) in handles.iter().enumerate() {
         // Get window details
-        let window_info = unsafe { WindowInfo::from_hwnd(*handle) };
-
-        log::debug!("Window #{} found:", i + 1);
-        log::debug!("\n{}", window_info);
+        match unsafe { WindowInfo::from_hwnd(*handle) } {
+            Ok(window_info) => {
+                log::debug!("Window #{} found:", i + 1);
+                log::debug!("\n{}", window_info);
+            }
+            Err(err) => log::debug!("Window #{} could not be queried: {}", i + 1, err),
+        }
     }
 }
 
@@ -1,29 +1,26 @@
-use windows::Win32::{
-    Foundation::{
-        POINT,
-        RECT,
-    },
-};
+use std::{path::Path, sync::Once};
+use crate::core::allowlist::{Allowed, Allowlist};
 use crate::core::overlay::{
-    find_overlays,
+    WindowError,
+    criteria_expr::{CriteriaExpr, MatchContext},
+    fixture_window_source::FixtureWindowSource,
+    match_criteria::{RectMatch, SizeMatch, StringMatch, StyleMatch},
     overlay_finder_params::OverlayFinderParams,
-    window_info::WindowInfo,
+    overlay_rule::{OverlayRule, OverlayRuleSet},
+    window_info::{Point, Rect, WindowInfo},
+    window_source::{default_source, WindowSource},
 };
 
 // TODO! Make this into a builder style
 
+#[derive(Default)]
 pub struct OverlayFinder {
     params: OverlayFinderParams,
+    rules: Vec<OverlayRule>,
+    target_pid: Option<u32>,
+    source: Option<Box<dyn WindowSource>>, // Live windows of this platform when not set
     pub overlays: Vec<WindowInfo>,
-}
-
-impl Default for OverlayFinder {
-    fn default() -> Self {
-        Self {
-            params: OverlayFinderParams::default(),
-            overlays: Vec::new(),
-        }
-    }
+    pub allowed: Vec<Allowed<WindowInfo>>,
 }
 
 impl OverlayFinder {
@@ -31,38 +28,85 @@ impl OverlayFinder {
         Self::default()
     }
 
+    /// Enumerates windows from the given source instead of the live desktop
+    pub fn with_source(&mut self, source: impl WindowSource + 'static) -> &mut Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Loads windows from a .toml or .json fixture file, see FixtureWindowSource
+    pub fn with_fixture_file(&mut self, path: impl AsRef<Path>) -> anyhow::Result<&mut Self> {
+        Ok(self.with_source(FixtureWindowSource::from_file(path)?))
+    }
+
     // BUILDER METHODS -----------------------------------------------------------------------------
     pub fn with_pid_owner(&mut self, pid: u32) -> &mut Self {
         self.params.pid_owner = Some(pid);
         self
     }
 
+    /// Exact class name, see with_window_class_match for other modes
     pub fn with_window_class(&mut self, class_name: impl Into<String>) -> &mut Self {
-        self.params.wnd_class_name = class_name.into();
+        self.params.wnd_class_name = StringMatch::exact(class_name);
         self
     }
 
+    pub fn with_window_class_match(&mut self, class_name: StringMatch) -> &mut Self {
+        self.params.wnd_class_name = class_name;
+        self
+    }
+
+    /// Exact window title, see with_window_name_match for other modes
     pub fn with_window_name(&mut self, name: impl Into<String>) -> &mut Self {
-        self.params.wnd_name = name.into();
+        self.params.wnd_name = StringMatch::exact(name);
+        self
+    }
+
+    pub fn with_window_name_match(&mut self, name: StringMatch) -> &mut Self {
+        self.params.wnd_name = name;
+        self
+    }
+
+    /// Exact position, see with_position_range for bounds
+    pub fn with_position(&mut self, rect: Rect) -> &mut Self {
+        self.params.pos = rect.into();
         self
     }
 
-    pub fn with_position(&mut self, rect: RECT) -> &mut Self {
-        self.params.pos = rect;
+    pub fn with_position_range(&mut self, position: RectMatch) -> &mut Self {
+        self.params.pos = position;
         self
     }
 
-    pub fn with_size(&mut self, size: POINT) -> &mut Self {
+    /// Exact size, see with_size_range for bounds
+    pub fn with_size(&mut self, size: Point) -> &mut Self {
+        self.params.res = size.into();
+        self
+    }
+
+    pub fn with_size_range(&mut self, size: SizeMatch) -> &mut Self {
         self.params.res = size;
         self
     }
 
+    /// Window must have at least one of these style bits
     pub fn with_style(&mut self, style: u32) -> &mut Self {
+        self.params.style = StyleMatch::any(style);
+        self
+    }
+
+    pub fn with_style_match(&mut self, style: StyleMatch) -> &mut Self {
         self.params.style = style;
         self
     }
 
+    /// Window must have at least one of these extended style bits
     pub fn with_style_ex(&mut self, style_ex: u32) -> &mut Self {
+        self.params.style_ex = StyleMatch::any(style_ex);
+        self
+    }
+
+    pub fn with_style_ex_match(&mut self, style_ex: StyleMatch) -> &mut Self {
         self.params.style_ex = style_ex;
         self
     }
@@ -82,17 +126,166 @@ impl OverlayFinder {
         self
     }
 
+    /// Adds an expression that has to match on top of the other builder criteria
+    pub fn with_expr(&mut self, expr: CriteriaExpr) -> &mut Self {
+        self.params.expr = Some(expr);
+        self
+    }
+
+    /// PID of the game, used by CriteriaExpr::OwnedByTarget
+    pub fn with_target_pid(&mut self, pid: u32) -> &mut Self {
+        self.target_pid = Some(pid);
+        self
+    }
+
+    /// Adds a named rule, evaluated alongside the criteria set on the builder
+    pub fn with_rule(&mut self, rule: OverlayRule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_rules(&mut self, rules: OverlayRuleSet) -> &mut Self {
+        self.rules.extend(rules.rules);
+        self
+    }
+
+    /// Moves overlays matching an allowlist entry into `allowed`
+    pub fn filter_allowlisted(&mut self, allowlist: &Allowlist) -> &mut Self {
+        let mut kept = Vec::with_capacity(self.overlays.len());
+
+        for overlay in self.overlays.drain(..) {
+            match allowlist.match_window(&overlay) {
+                Some(entry) => {
+                    log::debug!("Allowing overlay \"{}\" [{}]: {}", overlay.title, overlay.class_name, entry.reason);
+                    self.allowed.push(Allowed {
+                        finding: overlay,
+                        entry: entry.name.clone(),
+                        reason: entry.reason.clone(),
+                    });
+                }
+                None => kept.push(overlay),
+            }
+        }
+
+        self.overlays = kept;
+        self
+    }
+
+    pub fn params(&self) -> &OverlayFinderParams {
+        &self.params
+    }
+
+    pub fn rules(&self) -> &[OverlayRule] {
+        &self.rules
+    }
+
+    /// Tags every window with the rules it matches and keeps the ones that matched any.
+    /// The criteria set on the builder act as a rule named "default" without severity.
+    pub fn evaluate(&self, windows: Vec<WindowInfo>, ctx: &MatchContext) -> Vec<WindowInfo> {
+        let default_rule = OverlayRule::new("default", 0, self.params.clone());
+
+        windows.into_iter()
+            .filter_map(|mut window| {
+                window.matched_rules = std::iter::once(&default_rule)
+                    .chain(&self.rules)
+                    .filter_map(|rule| rule.evaluate(&window, ctx))
+                    .collect();
+
+                (!window.matched_rules.is_empty()).then_some(window)
+            })
+            .collect()
+    }
+
+    /// Snapshots the windows and screen of the configured source
+    fn query_source(&self) -> Result<(Vec<WindowInfo>, MatchContext), WindowError> {
+        let default;
+        let source = match &self.source {
+            Some(source) => source.as_ref(),
+            None => {
+                default = default_source()?;
+                default.as_ref()
+            }
+        };
+
+        let ctx = MatchContext { metrics: source.screen_metrics()?, target_pid: self.target_pid };
+        Ok((source.query_windows()?, ctx))
+    }
+
     /// Updates the overlays field
     pub(crate) fn find(&mut self) -> Vec<WindowInfo> {
-        let hwnds = find_overlays(self.params.clone());
-        let overlays: Vec<WindowInfo> = hwnds.into_iter()
-            .filter_map(|handle| {
-                let window_info = unsafe { WindowInfo::from_hwnd(handle) };
-                Some(window_info)
-            })
-            .collect();
+        self.allowed.clear();
+
+        self.overlays = match self.query_source() {
+            Ok((windows, ctx)) => self.evaluate(windows, &ctx),
+            Err(WindowError::UnsupportedPlatform) => {
+                // Once is enough, watch mode would repeat it on every rescan
+                static WARNED: Once = Once::new();
+                WARNED.call_once(|| log::warn!("Overlay enumeration is not supported on this platform, skipping"));
+                Vec::new()
+            }
+            Err(e) => {
+                log::error!("Failed to enumerate windows: {}", e);
+                Vec::new()
+            }
+        };
 
-        self.overlays = overlays;
         self.overlays.clone()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::{WS_EX_LAYERED, WS_EX_TRANSPARENT};
+
+    #[test]
+    fn evaluate_tags_windows_with_builder_and_rule_matches() {
+        let ctx = MatchContext::default();
+        let window = |title: &str, style_ex: u32| WindowInfo {
+            title: title.into(),
+            style_ex: style_ex as isize,
+            ..Default::default()
+        };
+
+        let mut finder = OverlayFinder::new();
+        finder
+            .with_window_name_match(StringMatch::regex("^ESP").unwrap())
+            .with_rules(OverlayRuleSet::builtin())
+            .with_rule(OverlayRule::new("layered", 5, OverlayFinderParams {
+                style_ex: StyleMatch::any(WS_EX_LAYERED),
+                ..Default::default()
+            }));
+
+        let tagged = finder.evaluate(vec![
+            window("ESP box", WS_EX_LAYERED),
+            window("Notepad", 0),
+            window("", WS_EX_LAYERED | WS_EX_TRANSPARENT),
+        ], &ctx);
+
+        let names: Vec<Vec<&str>> = tagged.iter()
+            .map(|window| window.matched_rules.iter().map(|rule| rule.name.as_str()).collect())
+            .collect();
+        assert_eq!(names, [vec!["default", "layered"], vec!["layered"]]);
+    }
+
+    #[test]
+    fn find_uses_fixture_windows() {
+        let mut finder = OverlayFinder::new();
+        finder
+            .with_fixture_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/windows.toml"))
+            .unwrap()
+            .with_rules(OverlayRuleSet::builtin())
+            .with_target_pid(4242);
+
+        let overlays = finder.find();
+        let titles: Vec<&str> = overlays.iter().map(|overlay| overlay.title.as_str()).collect();
+        assert_eq!(titles, ["", "Discord Overlay"]);
+
+        let esp = &overlays[0];
+        assert_eq!(esp.owner_image_path, r"C:\Users\player\Downloads\loader.exe");
+        assert!(esp.is_capture_excluded());
+        assert_eq!(esp.alpha, Some(255));
+        let names: Vec<&str> = esp.matched_rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["click-through overlay", "fullscreen topmost window", "external ESP overlay"]);
+    }
+}
@@ -1,120 +1,194 @@
-use windows::Win32::Foundation::{HWND, POINT, RECT};
-use crate::core::overlay::window_info::WindowInfo;
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::Win32::Foundation::HWND;
+use crate::core::overlay::{
+    criteria_expr::{CriteriaExpr, ExprTrace, MatchContext},
+    match_criteria::{RectMatch, SizeMatch, StringMatch, StyleMatch},
+    window_info::WindowInfo,
+};
 
 // Structure to hold search criteria for finding overlay windows
-#[derive(Clone, Default)]
+// Field names in rule files are the short forms: class, title, position, size, satisfy_all, when
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct OverlayFinderParams {
     pub pid_owner: Option<u32>,     // Optional specific process ID to search for
-    pub wnd_class_name: String,     // Target window class name
-    pub wnd_name: String,           // Target window title
-    pub pos: RECT,                  // Target window position
-    pub res: POINT,                 // Target window size
+    #[serde(rename = "class")]
+    pub wnd_class_name: StringMatch, // Target window class name
+    #[serde(rename = "title")]
+    pub wnd_name: StringMatch,      // Target window title
+    #[serde(rename = "position")]
+    pub pos: RectMatch,             // Target window position
+    #[serde(rename = "size")]
+    pub res: SizeMatch,             // Target window size
     pub percent_all_screens: f32,   // Minimum percentage of total screen space
     pub percent_main_screen: f32,   // Minimum percentage of main screen
-    pub style: u32,                 // Required window styles
-    pub style_ex: u32,              // Required extended window styles
+    pub style: StyleMatch,          // Required window styles
+    pub style_ex: StyleMatch,       // Required extended window styles
+    #[serde(rename = "satisfy_all")]
     pub satisfy_all_criteria: bool, // Must match all criteria if true
+    #[serde(rename = "when", skip_serializing_if = "Option::is_none")]
+    pub expr: Option<CriteriaExpr>, // Must also match, if set
+    #[cfg(windows)]
+    #[serde(skip)]
     pub hwnds: Vec<HWND>,           // Collection of matching window handles
 }
 
 impl OverlayFinderParams {
-    /// Verifies if the current window matches our search criteria
-    pub(crate) fn matches_criteria(&self, info: &WindowInfo) -> (u8, u8) {
-        let mut satisfied = 0u8;     // Count of matched criteria
-        let mut unsatisfied = 0u8;   // Count of unmatched criteria
-
-        // If we're looking for a specific PID, check it
-        if let Some(target_pid) = self.pid_owner {
-            if target_pid == info.pid {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
-        }
+    /// Every criterion that is set, as a list of expressions
+    fn criteria(&self) -> Vec<CriteriaExpr> {
+        let mut criteria = Vec::new();
 
-        // Check if class name matches (if we're looking for one)
+        if let Some(pid) = self.pid_owner {
+            criteria.push(CriteriaExpr::PidOwner(pid));
+        }
         if !self.wnd_class_name.is_empty() {
-            if self.wnd_class_name == info.class_name {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+            criteria.push(CriteriaExpr::Class(self.wnd_class_name.clone()));
         }
-
-        // Check if window title matches (if we're looking for one)
         if !self.wnd_name.is_empty() {
-            if self.wnd_name == info.title {
-                satisfied += 1;
-            } else {
-                unsatisfied += 1;
-            }
+            criteria.push(CriteriaExpr::Title(self.wnd_name.clone()));
         }
-
-        // Check if position matches (if we specified one)
-        if self.pos.left != 0 || self.pos.top != 0 || self.pos.right != 0 || self.pos.bottom != 0 {
-            if self.pos == info.position {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+        if !self.pos.is_empty() {
+            criteria.push(CriteriaExpr::Position(self.pos));
         }
-
-        // Check if size matches (if we specified one)
-        if self.res.x != 0 || self.res.y != 0 {
-            if self.res == info.size {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+        if !self.res.is_empty() {
+            criteria.push(CriteriaExpr::Size(self.res));
         }
-
-        // Check screen space percentages
-        let (percent_all, percent_main) = info.get_screen_percentages();
-
-        // Check if window takes up enough of all screens
         if self.percent_all_screens != 0.0 {
-            if percent_all >= self.percent_all_screens {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+            criteria.push(CriteriaExpr::PercentAllScreens(self.percent_all_screens));
         }
-
-        // Check if window takes up enough of main screen
         if self.percent_main_screen != 0.0 {
-            if percent_main >= self.percent_main_screen {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+            criteria.push(CriteriaExpr::PercentMainScreen(self.percent_main_screen));
+        }
+        if !self.style.is_empty() {
+            criteria.push(CriteriaExpr::Style(self.style));
         }
+        if !self.style_ex.is_empty() {
+            criteria.push(CriteriaExpr::StyleEx(self.style_ex));
+        }
+
+        criteria
+    }
 
-        // Check if window has required style bits
-        if self.style != 0 {
-            if self.style & info.style as u32 != 0 {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+    /// The flat criteria (all or any of them, per satisfy_all_criteria) combined with `expr`
+    pub fn expression(&self) -> Option<CriteriaExpr> {
+        let mut criteria = self.criteria();
+        let flat = match criteria.len() {
+            0 => None,
+            1 => criteria.pop(),
+            _ if self.satisfy_all_criteria => Some(CriteriaExpr::All(criteria)),
+            _ => Some(CriteriaExpr::Any(criteria)),
+        };
+
+        match (flat, &self.expr) {
+            (Some(flat), Some(expr)) => Some(CriteriaExpr::all([flat, expr.clone()])),
+            (flat, expr) => flat.or_else(|| expr.clone()),
         }
+    }
+
+    /// Verifies if the current window matches our flat search criteria: (satisfied, unsatisfied)
+    pub fn matches_criteria(&self, info: &WindowInfo, ctx: &MatchContext) -> (u8, u8) {
+        let criteria = self.criteria();
+        let satisfied = criteria.iter().filter(|criterion| criterion.matches(info, ctx)).count() as u8;
+
+        (satisfied, criteria.len() as u8 - satisfied)
+    }
+
+    /// Evaluates the window, None if no criteria are set at all
+    pub fn explain(&self, info: &WindowInfo, ctx: &MatchContext) -> Option<ExprTrace> {
+        self.expression().map(|expr| expr.explain(info, ctx))
+    }
+
+    /// Whether a window counts as a match
+    pub(crate) fn is_match(&self, info: &WindowInfo, ctx: &MatchContext) -> bool {
+        self.expression().is_some_and(|expr| expr.matches(info, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::{
+        match_criteria::ValueRange,
+        window_info::{Point, Rect, ScreenMetrics},
+        WS_EX_LAYERED,
+        WS_EX_TOPMOST,
+        WS_EX_TRANSPARENT,
+        WS_VISIBLE,
+    };
 
-        // Check if window has required extended style bits
-        if self.style_ex != 0 {
-            if self.style_ex & info.style_ex as u32 != 0 {
-                satisfied += 1;
-            }
-            else {
-                unsatisfied += 1;
-            }
+    const CTX: MatchContext = MatchContext {
+        metrics: ScreenMetrics {
+            screen: Point { x: 1920, y: 1080 },
+            desktop: Point { x: 1920, y: 1080 },
+        },
+        target_pid: Some(4242),
+    };
+
+    fn window(title: &str, size: Point, style_ex: u32) -> WindowInfo {
+        WindowInfo {
+            pid: 1001,
+            class_name: "Chrome_WidgetWin_1".into(),
+            title: title.into(),
+            position: Rect { left: 0, top: 0, right: size.x, bottom: size.y },
+            size,
+            style: WS_VISIBLE as isize,
+            style_ex: style_ex as isize,
+            ..Default::default()
         }
+    }
+
+    #[test]
+    fn counts_satisfied_and_unsatisfied_criteria() {
+        let params = OverlayFinderParams {
+            pid_owner: Some(1001),
+            wnd_class_name: StringMatch::glob("chrome_*").unwrap(),
+            wnd_name: StringMatch::exact("Overlay"),
+            res: SizeMatch { x: ValueRange::at_least(1900), y: ValueRange::default() },
+            percent_main_screen: 90.0,
+            style_ex: StyleMatch::all(WS_EX_LAYERED | WS_EX_TRANSPARENT),
+            ..Default::default()
+        };
+
+        let fullscreen = window("ESP", Point { x: 1920, y: 1080 }, WS_EX_LAYERED);
+        assert_eq!(params.matches_criteria(&fullscreen, &CTX), (4, 2));
 
-        (satisfied, unsatisfied)
+        let small = window("Overlay", Point { x: 800, y: 600 }, WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST);
+        assert_eq!(params.matches_criteria(&small, &CTX), (4, 2));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn all_bits_rejects_partially_styled_windows() {
+        let params = OverlayFinderParams {
+            style: StyleMatch::any(WS_VISIBLE),
+            style_ex: StyleMatch::all(WS_EX_LAYERED | WS_EX_TRANSPARENT),
+            satisfy_all_criteria: true,
+            ..Default::default()
+        };
+
+        let layered = window("", Point { x: 100, y: 100 }, WS_EX_LAYERED);
+        let click_through = window("", Point { x: 100, y: 100 }, WS_EX_LAYERED | WS_EX_TRANSPARENT);
+
+        assert!(!params.is_match(&layered, &CTX));
+        assert!(params.is_match(&click_through, &CTX));
+        assert!(!OverlayFinderParams::default().is_match(&click_through, &CTX));
+    }
+
+    #[test]
+    fn expression_combines_with_flat_criteria() {
+        let params = OverlayFinderParams {
+            style_ex: StyleMatch::all(WS_EX_LAYERED),
+            expr: Some(CriteriaExpr::not(CriteriaExpr::OwnedByTarget)),
+            ..Default::default()
+        };
+
+        let cheat = window("", Point { x: 100, y: 100 }, WS_EX_LAYERED);
+        let game = WindowInfo { pid: 4242, ..cheat.clone() };
+        assert!(params.is_match(&cheat, &CTX));
+        assert!(!params.is_match(&game, &CTX));
+
+        let trace = params.explain(&game, &CTX).unwrap();
+        assert!(!trace.matched);
+        assert_eq!(trace.matched_terms(), ["style_ex all=0x80000"]);
+    }
+}
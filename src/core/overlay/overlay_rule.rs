@@ -0,0 +1,209 @@
+// Declarative overlay detection rules.
+//
+// Each rule is a named set of OverlayFinderParams with a severity. All rules are evaluated against
+// the same window enumeration, and every window is tagged with the rules it matched.
+
+use std::{
+    fs,
+    path::Path,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use crate::core::overlay::{
+    criteria_expr::MatchContext,
+    overlay_finder_params::OverlayFinderParams,
+    window_info::WindowInfo,
+};
+
+const BUILTIN_RULES: &str = include_str!("../../../config/overlay_rules.toml");
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OverlayRule {
+    pub name: String,
+    #[serde(default)]
+    pub severity: u32,              // Added to the overlay's score when the rule matches
+    #[serde(flatten)]
+    pub params: OverlayFinderParams,
+}
+
+/// A rule a window matched, stored on the WindowInfo
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleMatch {
+    pub name: String,
+    pub severity: u32,
+    #[serde(default)]
+    pub matched: Vec<String>, // Sub-expressions of the rule that matched, outermost first
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OverlayRuleSet {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<OverlayRule>,
+}
+
+impl OverlayRule {
+    pub fn new(name: impl Into<String>, severity: u32, params: OverlayFinderParams) -> Self {
+        Self { name: name.into(), severity, params }
+    }
+
+    pub fn matches(&self, window: &WindowInfo, ctx: &MatchContext) -> bool {
+        self.params.is_match(window, ctx)
+    }
+
+    /// The match record for a window, if the rule matches it
+    pub fn evaluate(&self, window: &WindowInfo, ctx: &MatchContext) -> Option<RuleMatch> {
+        let trace = self.params.explain(window, ctx).filter(|trace| trace.matched)?;
+
+        Some(RuleMatch {
+            name: self.name.clone(),
+            severity: self.severity,
+            matched: trace.matched_terms(),
+        })
+    }
+}
+
+impl OverlayRuleSet {
+    pub fn new(rules: Vec<OverlayRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Generic overlay heuristics shipped with the tool
+    pub fn builtin() -> Self {
+        Self::from_toml(BUILTIN_RULES).expect("Built-in overlay rules are invalid")
+    }
+
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Loads rules from a .toml or .json file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read overlay rules from {}", path.display()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(serde_json::from_str(&contents)?),
+            Some("toml") => Self::from_toml(&contents),
+            _ => anyhow::bail!("Unsupported overlay rules format: {}", path.display()),
+        }
+    }
+
+    pub fn extend(&mut self, other: OverlayRuleSet) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Every rule the window matches
+    pub fn evaluate(&self, window: &WindowInfo, ctx: &MatchContext) -> Vec<RuleMatch> {
+        self.rules.iter()
+            .filter_map(|rule| rule.evaluate(window, ctx))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::{
+        match_criteria::{StringMatchMode, StyleMatch, ValueRange},
+        window_info::{Point, ScreenMetrics},
+        WS_EX_LAYERED,
+        WS_EX_TOPMOST,
+        WS_EX_TRANSPARENT,
+        WS_VISIBLE,
+    };
+
+    #[test]
+    fn builtin_rules_parse() {
+        let rules = OverlayRuleSet::builtin();
+        assert!(!rules.rules.is_empty());
+        assert!(rules.rules.iter().all(|rule| !rule.name.is_empty()));
+        // Scoring adds transparent_overlay for click-through windows, the rule must not count it again
+        let click_through = rules.rules.iter().find(|rule| rule.name == "click-through overlay").unwrap();
+        assert_eq!(click_through.severity, 0);
+    }
+
+    #[test]
+    fn rule_fields_map_onto_params() {
+        let rules = OverlayRuleSet::from_toml(r#"
+            [[rule]]
+            name = "click-through"
+            severity = 25
+            class = "Chrome_WidgetWin_1"
+            title = "Overlay"
+            pid_owner = 1234
+            style = 0x10000000
+            style_ex = 0x80020
+            percent_main_screen = 80
+            satisfy_all = true
+            size = { x = 1920, y = 1080 }
+
+            [[rule]]
+            name = "unnamed window"
+            title = ""
+        "#).unwrap();
+
+        let rule = &rules.rules[0];
+        assert_eq!(rule.name, "click-through");
+        assert_eq!(rule.severity, 25);
+        assert!(rule.params.wnd_class_name.matches("Chrome_WidgetWin_1"));
+        assert_eq!(rule.params.wnd_name.mode, StringMatchMode::Exact);
+        assert_eq!(rule.params.pid_owner, Some(1234));
+        assert_eq!(rule.params.style, StyleMatch::any(WS_VISIBLE));
+        assert_eq!(rule.params.style_ex, StyleMatch::any(WS_EX_LAYERED | WS_EX_TRANSPARENT));
+        assert_eq!(rule.params.percent_main_screen, 80.0);
+        assert_eq!(rule.params.res.x, ValueRange::exact(1920));
+        assert!(rule.params.satisfy_all_criteria);
+
+        assert_eq!(rules.rules[1].severity, 0);
+        assert_eq!(rules.rules[1].params.pid_owner, None);
+    }
+
+    #[test]
+    fn builtin_rules_tag_windows() {
+        let ctx = MatchContext {
+            metrics: ScreenMetrics { screen: Point { x: 1920, y: 1080 }, desktop: Point { x: 1920, y: 1080 } },
+            target_pid: Some(4242),
+        };
+        let esp = WindowInfo {
+            title: "".into(),
+            size: Point { x: 1920, y: 1080 },
+            style: WS_VISIBLE as isize,
+            style_ex: (WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST) as isize,
+            ..Default::default()
+        };
+        let names: Vec<String> = OverlayRuleSet::builtin()
+            .evaluate(&esp, &ctx)
+            .into_iter()
+            .map(|rule| rule.name)
+            .collect();
+        assert_eq!(names, ["click-through overlay", "fullscreen topmost window", "external ESP overlay"]);
+
+        // Layered alone is not click-through
+        let layered = WindowInfo { style_ex: WS_EX_LAYERED as isize, ..esp };
+        assert!(OverlayRuleSet::builtin().evaluate(&layered, &ctx).is_empty());
+    }
+
+    #[test]
+    fn rules_with_expressions_record_matched_terms() {
+        let rules = OverlayRuleSet::from_toml(r#"
+            [[rule]]
+            name = "foreign click-through"
+            severity = 30
+            when.all = [
+                { style_ex = { all = 0x80020 } },
+                { not = "owned_by_target" },
+            ]
+        "#).unwrap();
+
+        let ctx = MatchContext { target_pid: Some(4242), ..Default::default() };
+        let overlay = WindowInfo { pid: 1001, style_ex: (WS_EX_LAYERED | WS_EX_TRANSPARENT) as isize, ..Default::default() };
+
+        let matches = rules.evaluate(&overlay, &ctx);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].matched[1..], ["style_ex all=0x80020", "NOT owned by target"]);
+
+        let game_overlay = WindowInfo { pid: 4242, ..overlay };
+        assert!(rules.evaluate(&game_overlay, &ctx).is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+// Live top-level windows through EnumWindows
+
+use windows::Win32::{
+    Foundation::{BOOL, HWND, LPARAM},
+    UI::WindowsAndMessaging::EnumWindows,
+};
+use crate::core::overlay::{
+    WindowError,
+    window_info::{ScreenMetrics, WindowInfo},
+    window_source::WindowSource,
+};
+
+pub struct Win32WindowSource;
+
+unsafe extern "system" fn collect_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let hwnds = &mut *(lparam.0 as *mut Vec<HWND>);
+    hwnds.push(hwnd);
+    BOOL(1) // Continue enumeration
+}
+
+impl WindowSource for Win32WindowSource {
+    /// Every top-level window, collected in a single EnumWindows pass. EnumWindows walks the
+    /// z-order from the top, so the enumeration index is the window's z-order.
+    fn query_windows(&self) -> Result<Vec<WindowInfo>, WindowError> {
+        let mut hwnds: Vec<HWND> = Vec::new();
+        unsafe {
+            EnumWindows(Some(collect_windows_callback), LPARAM(&mut hwnds as *mut _ as isize))
+                .map_err(|_| WindowError::FailedToEnumerateWindows)?;
+        }
+
+        let mut windows = Vec::with_capacity(hwnds.len());
+        for (z_order, hwnd) in hwnds.into_iter().enumerate() {
+            match unsafe { WindowInfo::from_hwnd(hwnd) } {
+                Ok(mut info) => {
+                    info.z_order = z_order as u32;
+                    windows.push(info);
+                }
+                // Closed since EnumWindows listed it
+                Err(WindowError::WindowClosed) => log::debug!("Skipping window {:?}, it was closed", hwnd),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(windows)
+    }
+
+    fn screen_metrics(&self) -> Result<ScreenMetrics, WindowError> {
+        ScreenMetrics::current()
+    }
+}
@@ -1,49 +1,146 @@
 use std::fmt;
-use windows::Win32::{
-    Foundation::{
-        HWND,
-        POINT,
-        RECT,
+use serde::{Deserialize, Serialize};
+use crate::core::overlay::{
+    overlay_rule::RuleMatch,
+    WDA_EXCLUDEFROMCAPTURE,
+    WDA_MONITOR,
+    WS_EX_TOPMOST,
+};
+#[cfg(windows)]
+use crate::core::overlay::{WindowError, WS_EX_LAYERED};
+#[cfg(windows)]
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{
+            CloseHandle,
+            COLORREF,
+            HWND,
+            POINT,
+            RECT,
+        },
+        Graphics::Dwm::{
+            DwmGetWindowAttribute,
+            DWMWA_CLOAKED,
+        },
+        System::Threading::{
+            OpenProcess,
+            QueryFullProcessImageNameW,
+            PROCESS_NAME_WIN32,
+            PROCESS_QUERY_LIMITED_INFORMATION,
+        },
+        UI::WindowsAndMessaging::{
+            GetAncestor,
+            GetClassNameW,
+            GetDesktopWindow,
+            GetLayeredWindowAttributes,
+            GetSystemMetrics,
+            GetWindow,
+            GetWindowDisplayAffinity,
+            GetWindowLongPtrW,
+            GetWindowRect,
+            GetWindowTextW,
+            GetWindowThreadProcessId,
+            IsWindow,
+            GA_PARENT,
+            GWL_EXSTYLE,
+            GWL_STYLE,
+            GW_OWNER,
+            LAYERED_WINDOW_ATTRIBUTES_FLAGS,
+            LWA_ALPHA,
+            LWA_COLORKEY,
+            SM_CXSCREEN,
+            SM_CYSCREEN,
+        },
     },
-    UI::WindowsAndMessaging::{
-        GetClassNameW,
-        GetDesktopWindow,
-        GetSystemMetrics,
-        GetWindowLongPtrW,
-        GetWindowRect,
-        GetWindowTextW,
-        GetWindowThreadProcessId,
-        GWL_EXSTYLE,
-        GWL_STYLE,
-        SM_CXSCREEN,
-        SM_CYSCREEN,
-    }
 };
 
+#[cfg(windows)]
 const MAX_CLASS_NAME: usize = 255;
+#[cfg(windows)]
 const MAX_WND_NAME: usize = MAX_CLASS_NAME;
 
+// Platform-neutral mirror of the Win32 RECT
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+// Platform-neutral mirror of the Win32 POINT
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[cfg(windows)]
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        Self { left: rect.left, top: rect.top, right: rect.right, bottom: rect.bottom }
+    }
+}
+
+#[cfg(windows)]
+impl From<POINT> for Point {
+    fn from(point: POINT) -> Self {
+        Self { x: point.x, y: point.y }
+    }
+}
+
+// Dimensions the screen percentage criteria are measured against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenMetrics {
+    pub screen: Point,  // SM_CXSCREEN x SM_CYSCREEN
+    pub desktop: Point, // Desktop window size
+}
+
 // Structure to hold all information about a window
-#[derive(Clone, Default)]
-pub(crate) struct WindowInfo {
-    pub hwnd: HWND,         // Window handle
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowInfo {
+    pub hwnd: usize,        // Window handle
     pub pid: u32,           // Process ID that owns the window
     pub tid: u32,           // Thread ID that created the window
     pub class_name: String, // Window class name
     pub title: String,      // Window title text
-    pub position: RECT,     // Window position (left, top, right, bottom)
-    pub size: POINT,        // Window size (width, height)
+    pub position: Rect,     // Window position (left, top, right, bottom)
+    pub size: Point,        // Window size (width, height)
     pub style: isize,       // Window style flags
     pub style_ex: isize,    // Extended window style flags
+    pub alpha: Option<u8>,      // Constant alpha of a layered window (LWA_ALPHA)
+    pub color_key: Option<u32>, // Transparent color of a layered window (LWA_COLORKEY), as COLORREF
+    pub z_order: u32,           // Position in the top-level z-order, 0 is the top
+    pub owner: usize,           // Owner window handle, 0 if none
+    pub parent: usize,          // Parent window handle, 0 for top-level windows
+    pub cloaked: bool,          // Hidden by DWM (other virtual desktop, suspended app, ...)
+    pub display_affinity: u32,  // SetWindowDisplayAffinity value, see is_capture_excluded
+    pub owner_image_path: String, // Executable of the owning process
+    pub matched_rules: Vec<RuleMatch>, // Overlay rules this window matched
+}
+
+impl WindowInfo {
+    pub fn is_topmost(&self) -> bool {
+        self.style_ex as u32 & WS_EX_TOPMOST != 0
+    }
+
+    /// Whether the window hides itself from screen capture and screen sharing
+    pub fn is_capture_excluded(&self) -> bool {
+        self.display_affinity == WDA_MONITOR || self.display_affinity == WDA_EXCLUDEFROMCAPTURE
+    }
 }
 
+#[cfg(windows)]
 impl WindowInfo {
-    /// Creates a WindowInfo struct from a window handle
-    pub unsafe fn from_hwnd(hwnd: HWND) -> Self {
+    /// Creates a WindowInfo struct from a window handle. Windows close at any time, one that is gone
+    /// by now gives WindowError::WindowClosed.
+    pub unsafe fn from_hwnd(hwnd: HWND) -> Result<Self, WindowError> {
         let mut info = WindowInfo::default();
 
         // Store handle
-        info.hwnd = hwnd;
+        info.hwnd = hwnd.0 as usize;
 
         // Get process ID and thread ID
         GetWindowThreadProcessId(hwnd, Some(&mut info.pid));
@@ -60,9 +157,15 @@ impl WindowInfo {
         info.title = String::from_utf16_lossy(&title[..len as usize]);
 
         // Get window position and calculate size
-        GetWindowRect(hwnd, &mut info.position)
-            .expect("Failed to get window rect!");
-        info.size = POINT {
+        let mut position = RECT::default();
+        if GetWindowRect(hwnd, &mut position).is_err() {
+            return Err(match IsWindow(hwnd).as_bool() {
+                true => WindowError::FailedToQueryWindow,
+                false => WindowError::WindowClosed,
+            });
+        }
+        info.position = position.into();
+        info.size = Point {
             x: info.position.right - info.position.left,  // Width
             y: info.position.bottom - info.position.top,  // Height
         };
@@ -71,43 +174,112 @@ impl WindowInfo {
         info.style = GetWindowLongPtrW(hwnd, GWL_STYLE);      // Basic Styles
         info.style_ex = GetWindowLongPtrW(hwnd, GWL_EXSTYLE); // Extended styles
 
-        info
+        // Layered window attributes, only set when the window uses SetLayeredWindowAttributes
+        if info.style_ex as u32 & WS_EX_LAYERED != 0 {
+            let mut color_key = COLORREF(0);
+            let mut alpha = 0u8;
+            let mut flags = LAYERED_WINDOW_ATTRIBUTES_FLAGS::default();
+            if GetLayeredWindowAttributes(hwnd, Some(&mut color_key), Some(&mut alpha), Some(&mut flags)).is_ok() {
+                if flags.contains(LWA_ALPHA) {
+                    info.alpha = Some(alpha);
+                }
+                if flags.contains(LWA_COLORKEY) {
+                    info.color_key = Some(color_key.0);
+                }
+            }
+        }
+
+        // Owner and parent (top-level windows report the desktop as their parent)
+        info.owner = GetWindow(hwnd, GW_OWNER).map_or(0, |owner| owner.0 as usize);
+        let parent = GetAncestor(hwnd, GA_PARENT);
+        if parent != GetDesktopWindow() {
+            info.parent = parent.0 as usize;
+        }
+
+        // Cloaked windows are not drawn even though they are visible
+        let mut cloaked = 0u32;
+        if DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut _ as *mut std::ffi::c_void,
+            size_of::<u32>() as u32
+        ).is_ok() {
+            info.cloaked = cloaked != 0;
+        }
+
+        // WDA_EXCLUDEFROMCAPTURE / WDA_MONITOR hide the window from screenshots and streams
+        let _ = GetWindowDisplayAffinity(hwnd, &mut info.display_affinity);
+
+        info.owner_image_path = get_process_image_path(info.pid).unwrap_or_default();
+
+        Ok(info)
     }
 
-    // Calculates what percentage of screen space the window occupies
-    pub fn get_screen_percentages(&self) -> (f32, f32) {
-        // Get total screen dimensions
-        let screen_width = unsafe { GetSystemMetrics(SM_CXSCREEN) };
-        let screen_height = unsafe { GetSystemMetrics(SM_CYSCREEN) };
+    // Calculates what percentage of screen space the window occupies, using the current screen
+    pub fn get_screen_percentages(&self) -> Result<(f32, f32), WindowError> {
+        Ok(self.screen_percentages(&ScreenMetrics::current()?))
+    }
+}
+
+/// Full path of a process' executable
+#[cfg(windows)]
+unsafe fn get_process_image_path(pid: u32) -> Option<String> {
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+
+    let mut buffer = [0u16; 1024];
+    let mut size = buffer.len() as u32;
+    let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size);
+    let _ = CloseHandle(process);
+
+    result.ok()?;
+    Some(String::from_utf16_lossy(&buffer[..size as usize]))
+}
 
-        // Calculate percentage of all screens
-        let ratio_all_screens_x = self.size.x as f32 / screen_width as f32;
-        let ratio_all_screens_y = self.size.y as f32 / screen_height as f32;
-        let percent_all_screens = ratio_all_screens_x * ratio_all_screens_y * 100.0;
+impl ScreenMetrics {
+    /// Queries the screen and desktop window dimensions of this machine
+    #[cfg(windows)]
+    pub fn current() -> Result<Self, WindowError> {
+        // Get total screen dimensions
+        let screen = Point {
+            x: unsafe { GetSystemMetrics(SM_CXSCREEN) },
+            y: unsafe { GetSystemMetrics(SM_CYSCREEN) },
+        };
 
         // Get main desktop window dimensions
         let desktop_hwnd = unsafe { GetDesktopWindow() };
         let mut desktop_rect = RECT::default();
         unsafe { GetWindowRect(desktop_hwnd, &mut desktop_rect) }
-            .expect("Failed to get window rect!");
+            .map_err(|_| WindowError::FailedToQueryScreen)?;
 
-        let desktop_width = desktop_rect.right - desktop_rect.left;
-        let desktop_height = desktop_rect.bottom - desktop_rect.top;
-
-        // Calculate percentage of main screen
-        let ratio_main_screen_x = self.size.x as f32 / desktop_width as f32;
-        let ratio_main_screen_y = self.size.y as f32 / desktop_height as f32;
-        let percent_main_screen = ratio_main_screen_x * ratio_main_screen_y * 100.0;
+        let desktop = Point {
+            x: desktop_rect.right - desktop_rect.left,
+            y: desktop_rect.bottom - desktop_rect.top,
+        };
 
-        (percent_all_screens, percent_main_screen)
+        Ok(Self { screen, desktop })
     }
+}
 
+impl WindowInfo {
+    // Calculates what percentage of the given screens the window occupies: (all screens, main screen)
+    pub fn screen_percentages(&self, metrics: &ScreenMetrics) -> (f32, f32) {
+        let percent_of = |area: &Point| {
+            if area.x <= 0 || area.y <= 0 {
+                return 0.0;
+            }
+            let ratio_x = self.size.x as f32 / area.x as f32;
+            let ratio_y = self.size.y as f32 / area.y as f32;
+            ratio_x * ratio_y * 100.0
+        };
+
+        (percent_of(&metrics.screen), percent_of(&metrics.desktop))
+    }
 }
 
 impl fmt::Display for WindowInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Window Details:")?;
-        writeln!(f, "Handle: {:?}", self.hwnd)?;
+        writeln!(f, "Handle: {:#x}", self.hwnd)?;
         writeln!(f, "  Title: {}", self.title)?;
         writeln!(f, "  Class Name: {}", self.class_name)?;
         writeln!(f, "  Process ID: {}", self.pid)?;
@@ -117,6 +289,35 @@ impl fmt::Display for WindowInfo {
                  self.position.right, self.position.bottom)?;
         writeln!(f, "  Size: {}x{}", self.size.x, self.size.y)?;
         writeln!(f, "  Style: {:#x}", self.style)?;
-        writeln!(f, "  Extended Style: {:#x}", self.style_ex)
+        writeln!(f, "  Extended Style: {:#x}", self.style_ex)?;
+        writeln!(f, "  Z-Order: {}{}", self.z_order, if self.is_topmost() { " (topmost)" } else { "" })?;
+        if let Some(alpha) = self.alpha {
+            writeln!(f, "  Alpha: {}", alpha)?;
+        }
+        if let Some(color_key) = self.color_key {
+            writeln!(f, "  Color Key: {:#08x}", color_key)?;
+        }
+        if self.owner != 0 || self.parent != 0 {
+            writeln!(f, "  Owner: {:#x} | Parent: {:#x}", self.owner, self.parent)?;
+        }
+        if self.cloaked {
+            writeln!(f, "  Cloaked: yes")?;
+        }
+        if self.is_capture_excluded() {
+            writeln!(f, "  Excluded From Capture: yes (affinity {:#x})", self.display_affinity)?;
+        }
+        if !self.owner_image_path.is_empty() {
+            writeln!(f, "  Owner Image: {}", self.owner_image_path)?;
+        }
+        if !self.matched_rules.is_empty() {
+            writeln!(f, "  Matched Rules:")?;
+            for rule in &self.matched_rules {
+                writeln!(f, "    {} (severity {})", rule.name, rule.severity)?;
+                for term in &rule.matched {
+                    writeln!(f, "      matched: {}", term)?;
+                }
+            }
+        }
+        Ok(())
     }
 }
\ No newline at end of file
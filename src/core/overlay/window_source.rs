@@ -0,0 +1,28 @@
+// Abstraction over where window records come from, so overlay rules can be evaluated without
+// depending on any one windowing system.
+
+use crate::core::overlay::{
+    WindowError,
+    window_info::{ScreenMetrics, WindowInfo},
+};
+
+pub trait WindowSource: Send + Sync {
+    /// Takes a snapshot of every top-level window, topmost first
+    fn query_windows(&self) -> Result<Vec<WindowInfo>, WindowError>;
+
+    /// Screen dimensions the screen percentage criteria are measured against
+    fn screen_metrics(&self) -> Result<ScreenMetrics, WindowError>;
+}
+
+/// Returns the window source for the platform we were compiled for
+pub fn default_source() -> Result<Box<dyn WindowSource>, WindowError> {
+    #[cfg(windows)]
+    {
+        Ok(Box::new(crate::core::overlay::win32_window_source::Win32WindowSource))
+    }
+
+    #[cfg(not(windows))]
+    {
+        Err(WindowError::UnsupportedPlatform)
+    }
+}
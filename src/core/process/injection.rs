@@ -0,0 +1,167 @@
+// Finds code in the target that didn't come from any loaded module.
+//
+// Manual mapping and shellcode injection both leave executable memory behind that isn't backed by a
+// file on disk, and quick and dirty injectors tend to allocate it readable, writable and executable
+// at once. JIT compilers (Java, .NET, LuaJIT, browsers) do the same legitimately, which is why game
+// profiles can turn this off.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::core::process::memory::MemoryRegion;
+
+// Code the kernel maps into every Linux process
+const KERNEL_CODE_MAPPINGS: &[&str] = &["[vdso]", "[vsyscall]", "[uprobes]"];
+
+/// An executable region that looks injected
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InjectedRegion {
+    pub start: u64,
+    pub end: u64,
+    pub permissions: String,   // rwxp style, like /proc/<pid>/maps
+    pub path: String,          // Empty for anonymous memory
+    pub unbacked: bool,        // Not backed by any file, so not part of a loaded module
+    pub writable: bool,        // Writable and executable at the same time
+}
+
+impl InjectedRegion {
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+impl fmt::Display for InjectedRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:X}-0x{:X} {} ({} bytes", self.start, self.end, self.permissions, self.size())?;
+        if self.unbacked {
+            write!(f, ", not backed by a module")?;
+        }
+        if self.writable {
+            write!(f, ", writable and executable")?;
+        }
+        write!(f, ")")?;
+
+        if !self.path.is_empty() {
+            write!(f, " - {}", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+fn permissions(region: &MemoryRegion) -> String {
+    [
+        if region.readable { 'r' } else { '-' },
+        if region.writable { 'w' } else { '-' },
+        if region.executable { 'x' } else { '-' },
+        if region.private { 'p' } else { 's' },
+    ].iter().collect()
+}
+
+/// Executable regions that are either not backed by a file or writable at the same time
+pub fn find_injected_code(regions: &[MemoryRegion]) -> Vec<InjectedRegion> {
+    regions.iter()
+        .filter(|region| region.executable && !KERNEL_CODE_MAPPINGS.contains(&region.path.as_str()))
+        .filter_map(|region| {
+            // Code mapped from a memfd or a deleted file is as good as anonymous
+            let unbacked = !region.is_file_backed() || region.is_deleted();
+            let writable = region.writable;
+            if !unbacked && !writable {
+                return None;
+            }
+
+            Some(InjectedRegion {
+                start: region.start,
+                end: region.end,
+                permissions: permissions(region),
+                path: region.path.clone(),
+                unbacked,
+                writable,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::process::memory::parse_maps;
+
+    #[test]
+    fn flags_anonymous_and_rwx_code() {
+        let regions = parse_maps(concat!(
+            "55d0c0a02000-55d0c0a06000 r-xp 00002000 08:01 1311 /usr/bin/game\n",
+            "7f1e2c000000-7f1e2c021000 rw-p 00000000 00:00 0 \n",
+            "7f1e2c100000-7f1e2c101000 rwxp 00000000 00:00 0 \n",
+            "7f1e2c200000-7f1e2c210000 r-xp 00000000 00:00 0 \n",
+            "7f1e2c300000-7f1e2c301000 rwxp 00000000 08:01 4096 /usr/lib/libpatched.so\n",
+            "7f1e2d000000-7f1e2d001000 r-xs 00000000 00:01 77 /memfd:payload (deleted)\n",
+            "7ffd5a3e5000-7ffd5a3e7000 r-xp 00000000 00:00 0 [vdso]\n",
+            "7ffd5a1f0000-7ffd5a211000 rw-p 00000000 00:00 0 [stack]\n",
+        ));
+
+        let injected = find_injected_code(&regions);
+        let found: Vec<(u64, bool, bool)> = injected.iter()
+            .map(|region| (region.start, region.unbacked, region.writable))
+            .collect();
+
+        assert_eq!(found, [
+            (0x7f1e2c100000, true, true),
+            (0x7f1e2c200000, true, false),
+            (0x7f1e2c300000, false, true),
+            (0x7f1e2d000000, true, false),
+        ]);
+        assert_eq!(injected[0].permissions, "rwxp");
+        assert_eq!(injected[0].to_string(), "0x7F1E2C100000-0x7F1E2C101000 rwxp (4096 bytes, not backed by a module, writable and executable)");
+    }
+
+    /// Forks a helper that maps an RWX page and reports its address through a pipe
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_rwx_page_mapped_by_helper_process() {
+        use crate::core::process::Process;
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        // SAFETY: the child only makes raw syscalls (mmap, write, pause) before it is killed
+        let child = unsafe { libc::fork() };
+        assert!(child >= 0);
+        if child == 0 {
+            unsafe {
+                let page = libc::mmap(
+                    std::ptr::null_mut(),
+                    4096,
+                    libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                );
+                // 0 tells the parent that RWX mappings are not allowed here
+                let address = if page == libc::MAP_FAILED { 0u64 } else { page as u64 };
+                libc::write(fds[1], &address as *const u64 as *const libc::c_void, 8);
+                loop {
+                    libc::pause();
+                }
+            }
+        }
+
+        let mut address = 0u64;
+        let read = unsafe { libc::read(fds[0], &mut address as *mut u64 as *mut libc::c_void, 8) };
+        let regions = Process::from_parts("helper", child as u32).memory_regions();
+
+        unsafe {
+            libc::kill(child, libc::SIGKILL);
+            libc::waitpid(child, std::ptr::null_mut(), 0);
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+
+        assert_eq!(read, 8);
+        assert_ne!(address, 0, "the helper could not map an RWX page");
+
+        let injected = find_injected_code(&regions.unwrap());
+        let page = injected.iter().find(|region| region.start <= address && address < region.end).unwrap();
+        assert!(page.unbacked && page.writable);
+        assert_eq!(page.permissions, "rwxp");
+    }
+}
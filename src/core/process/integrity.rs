@@ -0,0 +1,535 @@
+// Compares the code of loaded modules against their files on disk.
+//
+// Hooks and code cave patches overwrite bytes in a module's code, which then no longer match the
+// file it was loaded from. Code sections are the executable PT_LOAD segments of ELF files and the
+// executable sections of PE files. PE code is relocated to the module's actual base before comparing
+// and import address tables inside code sections are skipped, both legitimately differ from disk.
+
+use std::{
+    fmt,
+    fs,
+    ops::Range,
+};
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    crypto::hex,
+    process::{
+        memory::{MemoryError, MemoryReader},
+        module::ModuleInfo,
+    },
+};
+
+const PAGE_SIZE: u64 = 0x1000;
+
+// Runs of patched bytes closer together than this are reported as one range
+const MERGE_GAP: usize = 8;
+
+// Bytes of each side kept in the report, a hook rarely needs more to be recognised
+const MAX_REPORTED_BYTES: usize = 32;
+
+#[derive(Debug)]
+pub enum IntegrityError {
+    FailedToReadFile,
+    UnknownFormat,
+    Malformed,
+    Memory(MemoryError),
+}
+
+// Minimal Display implementation - just show the variant
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+/// Bytes a code section should contain once loaded
+#[derive(Clone, Debug, Default)]
+pub struct CodeSection {
+    pub name: String,              // PE section name, or "segment <index>" for ELF
+    pub address: u64,              // Where the section starts in the process
+    pub expected: Vec<u8>,
+    pub ignored: Vec<Range<usize>>, // Offsets into `expected` that may legitimately differ
+}
+
+/// Bytes of a module's code that differ from the file on disk
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatchedRange {
+    pub module: String,
+    pub section: String,
+    pub address: u64,
+    pub len: usize,
+    pub expected: Vec<u8>, // At most MAX_REPORTED_BYTES of the file's bytes
+    pub found: Vec<u8>,    // and of the bytes in memory
+}
+
+impl fmt::Display for PatchedRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} at 0x{:X}: {} bytes patched ({} -> {})",
+            self.module, self.section, self.address, self.len, hex(&self.expected), hex(&self.found))
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+/// Code sections of an ELF or PE file loaded at `base`
+pub fn code_sections(file: &[u8], base: u64) -> Result<Vec<CodeSection>, IntegrityError> {
+    let sections = match file.get(..4) {
+        Some(b"\x7fELF") => elf_code_sections(file, base),
+        Some([b'M', b'Z', ..]) => pe_code_sections(file, base),
+        _ => return Err(IntegrityError::UnknownFormat),
+    };
+    sections.ok_or(IntegrityError::Malformed)
+}
+
+// ELF -----------------------------------------------------------------------------------------------
+fn elf_code_sections(file: &[u8], base: u64) -> Option<Vec<CodeSection>> {
+    const PT_LOAD: u32 = 1;
+    const PF_X: u32 = 1;
+
+    let is_64 = match file.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    // Only little endian targets are supported
+    if *file.get(5)? != 1 {
+        return None;
+    }
+
+    let (ph_offset, ph_entry_size, ph_count) = if is_64 {
+        (u64_at(file, 0x20)? as usize, u16_at(file, 0x36)? as usize, u16_at(file, 0x38)? as usize)
+    }
+    else {
+        (u32_at(file, 0x1C)? as usize, u16_at(file, 0x2A)? as usize, u16_at(file, 0x2C)? as usize)
+    };
+
+    // (type, flags, offset, vaddr, filesz) of every program header
+    let mut segments = Vec::with_capacity(ph_count);
+    for index in 0..ph_count {
+        let header = ph_offset + index * ph_entry_size;
+        let segment = if is_64 {
+            (u32_at(file, header)?, u32_at(file, header + 4)?, u64_at(file, header + 8)?, u64_at(file, header + 16)?, u64_at(file, header + 32)?)
+        }
+        else {
+            (u32_at(file, header)?, u32_at(file, header + 24)?, u32_at(file, header + 4)? as u64, u32_at(file, header + 8)? as u64, u32_at(file, header + 16)? as u64)
+        };
+        segments.push(segment);
+    }
+
+    // The lowest loadable page is mapped at the module's base
+    let lowest = segments.iter()
+        .filter(|(kind, ..)| *kind == PT_LOAD)
+        .map(|(_, _, _, vaddr, _)| vaddr & !(PAGE_SIZE - 1))
+        .min()?;
+    let bias = base.wrapping_sub(lowest);
+
+    segments.iter()
+        .enumerate()
+        .filter(|(_, (kind, flags, ..))| *kind == PT_LOAD && flags & PF_X != 0)
+        .filter_map(|(index, (_, _, offset, vaddr, file_size))| {
+            let Some(end) = offset.checked_add(*file_size) else {
+                log::debug!("Skipping segment {}, its file range overflows", index);
+                return None;
+            };
+            Some(file.get(*offset as usize..end as usize).map(|expected| CodeSection {
+                name: format!("segment {}", index),
+                address: bias.wrapping_add(*vaddr),
+                expected: expected.to_vec(),
+                ignored: Vec::new(),
+            }))
+        })
+        .collect()
+}
+
+// PE ------------------------------------------------------------------------------------------------
+struct PeSection {
+    name: String,
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_size: u32,
+    raw_offset: u32,
+    characteristics: u32,
+}
+
+impl PeSection {
+    fn contains_rva(&self, rva: u32) -> bool {
+        rva >= self.virtual_address && self.end_rva().is_some_and(|end| rva < end)
+    }
+
+    /// First RVA past the section, None if the header's values overflow
+    fn end_rva(&self) -> Option<u32> {
+        self.virtual_address.checked_add(self.virtual_size.max(self.raw_size))
+    }
+
+    /// Bytes of the section that come from the file, the rest is zero filled
+    fn loaded_size(&self) -> u32 {
+        match self.virtual_size {
+            0 => self.raw_size,
+            size => size.min(self.raw_size),
+        }
+    }
+}
+
+fn pe_code_sections(file: &[u8], base: u64) -> Option<Vec<CodeSection>> {
+    const IMAGE_SCN_MEM_EXECUTE: u32 = 0x20000000;
+    const DIRECTORY_BASERELOC: usize = 5;
+    const DIRECTORY_IAT: usize = 12;
+
+    let pe = u32_at(file, 0x3C)? as usize;
+    if file.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    let section_count = u16_at(file, pe + 6)? as usize;
+    let optional_size = u16_at(file, pe + 20)? as usize;
+    let optional = pe + 24;
+
+    let (image_base, directory_count, directories) = match u16_at(file, optional)? {
+        0x10b => (u32_at(file, optional + 28)? as u64, u32_at(file, optional + 92)? as usize, optional + 96),
+        0x20b => (u64_at(file, optional + 24)?, u32_at(file, optional + 108)? as usize, optional + 112),
+        _ => return None,
+    };
+    let directory = |index: usize| -> Option<(u32, u32)> {
+        if index >= directory_count {
+            return None;
+        }
+        Some((u32_at(file, directories + index * 8)?, u32_at(file, directories + index * 8 + 4)?))
+    };
+
+    let mut sections = Vec::with_capacity(section_count);
+    for index in 0..section_count {
+        let header = optional + optional_size + index * 40;
+        let name = file.get(header..header + 8)?;
+        let section = PeSection {
+            name: String::from_utf8_lossy(name).trim_end_matches('\0').to_string(),
+            virtual_size: u32_at(file, header + 8)?,
+            virtual_address: u32_at(file, header + 12)?,
+            raw_size: u32_at(file, header + 16)?,
+            raw_offset: u32_at(file, header + 20)?,
+            characteristics: u32_at(file, header + 36)?,
+        };
+        if section.end_rva().is_none() || section.raw_offset.checked_add(section.loaded_size()).is_none() {
+            log::debug!("Skipping section {}, its bounds overflow", section.name);
+            continue;
+        }
+        sections.push(section);
+    }
+
+    let rva_to_offset = |rva: u32| -> Option<usize> {
+        let section = sections.iter().find(|section| section.contains_rva(rva))?;
+        Some(section.raw_offset.checked_add(rva - section.virtual_address)? as usize)
+    };
+
+    let mut code: Vec<(&PeSection, CodeSection)> = sections.iter()
+        .filter(|section| section.characteristics & IMAGE_SCN_MEM_EXECUTE != 0)
+        .map(|section| {
+            let start = section.raw_offset as usize;
+            Some((section, CodeSection {
+                name: section.name.clone(),
+                address: base.wrapping_add(section.virtual_address as u64),
+                expected: file.get(start..start + section.loaded_size() as usize)?.to_vec(),
+                ignored: Vec::new(),
+            }))
+        })
+        .collect::<Option<_>>()?;
+
+    // Apply base relocations for the address the module was actually loaded at
+    let delta = base.wrapping_sub(image_base);
+    if let Some((reloc_rva, reloc_size)) = directory(DIRECTORY_BASERELOC).filter(|(rva, size)| *rva != 0 && *size != 0) {
+        if delta != 0 {
+            let relocs = rva_to_offset(reloc_rva)?;
+            let mut block = relocs;
+
+            while block + 8 <= relocs + reloc_size as usize {
+                let page = u32_at(file, block)?;
+                let block_size = u32_at(file, block + 4)? as usize;
+                if block_size < 8 {
+                    break;
+                }
+
+                for entry in (block + 8..block + block_size).step_by(2) {
+                    let entry = u16_at(file, entry)?;
+                    let Some(rva) = page.checked_add((entry & 0xFFF) as u32) else {
+                        continue;
+                    };
+
+                    for (section, code_section) in &mut code {
+                        if !section.contains_rva(rva) {
+                            continue;
+                        }
+                        let offset = (rva - section.virtual_address) as usize;
+                        apply_relocation(&mut code_section.expected, offset, entry >> 12, delta);
+                    }
+                }
+
+                block += block_size;
+            }
+        }
+    }
+
+    // The loader fills in import address tables, which some linkers put inside .text
+    let iat = directory(DIRECTORY_IAT)
+        .filter(|(rva, size)| *rva != 0 && *size != 0)
+        .and_then(|(rva, size)| Some((rva, rva.checked_add(size)?)));
+    if let Some((iat_start, iat_end)) = iat {
+        for (section, code_section) in &mut code {
+            let start = iat_start.max(section.virtual_address);
+            let end = iat_end.min(section.virtual_address + section.loaded_size());
+            if start < end {
+                let offset = (start - section.virtual_address) as usize;
+                code_section.ignored.push(offset..offset + (end - start) as usize);
+            }
+        }
+    }
+
+    Some(code.into_iter().map(|(_, code_section)| code_section).collect())
+}
+
+fn apply_relocation(bytes: &mut [u8], offset: usize, kind: u16, delta: u64) {
+    const IMAGE_REL_BASED_HIGHLOW: u16 = 3;
+    const IMAGE_REL_BASED_DIR64: u16 = 10;
+
+    match kind {
+        IMAGE_REL_BASED_HIGHLOW => {
+            if let Some(value) = u32_at(bytes, offset) {
+                bytes[offset..offset + 4].copy_from_slice(&value.wrapping_add(delta as u32).to_le_bytes());
+            }
+        }
+        IMAGE_REL_BASED_DIR64 => {
+            if let Some(value) = u64_at(bytes, offset) {
+                bytes[offset..offset + 8].copy_from_slice(&value.wrapping_add(delta).to_le_bytes());
+            }
+        }
+        // IMAGE_REL_BASED_ABSOLUTE is padding, other types don't occur in x86/x64 images
+        _ => {}
+    }
+}
+
+// COMPARISON ----------------------------------------------------------------------------------------
+/// Ranges where `found` differs from the section's expected bytes. `found` may be shorter than the
+/// section if part of it couldn't be read.
+pub fn diff_section(module: &str, section: &CodeSection, found: &[u8]) -> Vec<PatchedRange> {
+    let is_ignored = |offset: usize| section.ignored.iter().any(|range| range.contains(&offset));
+
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for (offset, (expected, found)) in section.expected.iter().zip(found).enumerate() {
+        if expected == found || is_ignored(offset) {
+            continue;
+        }
+
+        match runs.last_mut() {
+            Some(run) if offset - run.end <= MERGE_GAP => run.end = offset + 1,
+            _ => runs.push(offset..offset + 1),
+        }
+    }
+
+    runs.into_iter()
+        .map(|run| {
+            let shown = run.start..run.end.min(run.start + MAX_REPORTED_BYTES);
+            PatchedRange {
+                module: module.to_string(),
+                section: section.name.clone(),
+                address: section.address + run.start as u64,
+                len: run.len(),
+                expected: section.expected[shown.clone()].to_vec(),
+                found: found[shown].to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Patched ranges in a module's code sections
+pub fn check_module(reader: &dyn MemoryReader, module: &ModuleInfo) -> Result<Vec<PatchedRange>, IntegrityError> {
+    let file = fs::read(&module.path).map_err(|_| IntegrityError::FailedToReadFile)?;
+
+    let mut patched = Vec::new();
+    for section in code_sections(&file, module.base)? {
+        let found = reader.read_bytes(section.address, section.expected.len())
+            .map_err(IntegrityError::Memory)?;
+        if found.len() < section.expected.len() {
+            log::debug!("Only read {} of {} bytes of {} {}", found.len(), section.expected.len(), module.name, section.name);
+        }
+
+        patched.extend(diff_section(&module.name, &section, &found));
+    }
+
+    Ok(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PE32+ image with a .text section holding one absolute pointer and an IAT slot
+    fn minimal_pe() -> Vec<u8> {
+        let mut file = vec![0u8; 0x400];
+        file[0..2].copy_from_slice(b"MZ");
+        file[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        file[0x80..0x84].copy_from_slice(b"PE\0\0");
+        file[0x86..0x88].copy_from_slice(&2u16.to_le_bytes());        // NumberOfSections
+        file[0x94..0x96].copy_from_slice(&0xF0u16.to_le_bytes());     // SizeOfOptionalHeader
+
+        let optional = 0x98;
+        file[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        file[optional + 24..optional + 32].copy_from_slice(&0x140000000u64.to_le_bytes());
+        file[optional + 108..optional + 112].copy_from_slice(&16u32.to_le_bytes());
+        let directories = optional + 112;
+        // Base relocations: 12 bytes at RVA 0x2000, IAT: 8 bytes at RVA 0x1020
+        file[directories + 40..directories + 48].copy_from_slice(&[0x00, 0x20, 0, 0, 12, 0, 0, 0]);
+        file[directories + 96..directories + 104].copy_from_slice(&[0x20, 0x10, 0, 0, 8, 0, 0, 0]);
+
+        let sections = optional + 0xF0;
+        let mut section = |index: usize, name: &[u8], rva: u32, raw: u32, characteristics: u32| {
+            let header = sections + index * 40;
+            file[header..header + name.len()].copy_from_slice(name);
+            file[header + 8..header + 12].copy_from_slice(&0x100u32.to_le_bytes());
+            file[header + 12..header + 16].copy_from_slice(&rva.to_le_bytes());
+            file[header + 16..header + 20].copy_from_slice(&0x100u32.to_le_bytes());
+            file[header + 20..header + 24].copy_from_slice(&raw.to_le_bytes());
+            file[header + 36..header + 40].copy_from_slice(&characteristics.to_le_bytes());
+        };
+        section(0, b".text", 0x1000, 0x200, 0x60000020);
+        section(1, b".reloc", 0x2000, 0x300, 0x42000040);
+
+        // mov rax, 0x140001010 at the start of .text
+        file[0x200..0x202].copy_from_slice(&[0x48, 0xB8]);
+        file[0x202..0x20A].copy_from_slice(&0x140001010u64.to_le_bytes());
+        file[0x20A] = 0xC3;
+        // One DIR64 relocation for the immediate at RVA 0x1002
+        file[0x300..0x304].copy_from_slice(&0x1000u32.to_le_bytes());
+        file[0x304..0x308].copy_from_slice(&12u32.to_le_bytes());
+        file[0x308..0x30A].copy_from_slice(&0xA002u16.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn relocates_pe_code_and_skips_the_iat() {
+        let sections = code_sections(&minimal_pe(), 0x7FF600000000).unwrap();
+        assert_eq!(sections.len(), 1);
+
+        let text = &sections[0];
+        assert_eq!(text.name, ".text");
+        assert_eq!(text.address, 0x7FF600001000);
+        assert_eq!(u64_at(&text.expected, 2), Some(0x7FF600001010));
+        assert_eq!(text.ignored.len(), 1);
+        assert_eq!(text.ignored[0], 0x20..0x28);
+
+        // A hook at the entry point and a resolved import
+        let mut found = text.expected.clone();
+        found[0x0A..0x0F].copy_from_slice(&[0xE9, 0x11, 0x22, 0x33, 0x44]);
+        found[0x20..0x28].copy_from_slice(&0x7FFA12345678u64.to_le_bytes());
+
+        let patched = diff_section("game.exe", text, &found);
+        assert_eq!(patched.len(), 1);
+        assert_eq!(patched[0].address, 0x7FF60000100A);
+        assert_eq!(patched[0].len, 5);
+        assert_eq!(patched[0].expected, [0xC3, 0, 0, 0, 0]);
+        assert_eq!(patched[0].to_string(), "game.exe .text at 0x7FF60000100A: 5 bytes patched (c300000000 -> e911223344)");
+    }
+
+    #[test]
+    fn skips_sections_and_directories_that_overflow() {
+        let mut file = minimal_pe();
+        let text = 0x98 + 0xF0;
+        file[text + 12..text + 16].copy_from_slice(&0xFFFFFF80u32.to_le_bytes());
+        let iat = 0x98 + 112 + 96;
+        file[iat..iat + 8].copy_from_slice(&[0xF0, 0xFF, 0xFF, 0xFF, 0x20, 0, 0, 0]);
+
+        assert!(code_sections(&file, 0x7FF600000000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merges_nearby_patches() {
+        let section = CodeSection {
+            name: ".text".into(),
+            address: 0x1000,
+            expected: vec![0x90; 64],
+            ignored: Vec::new(),
+        };
+        let mut found = section.expected.clone();
+        found[4] = 0xCC;
+        found[10] = 0xCC;
+        found[40] = 0xCC;
+
+        let patched = diff_section("game", &section, &found[..50]);
+        let ranges: Vec<(u64, usize)> = patched.iter().map(|range| (range.address, range.len)).collect();
+        assert_eq!(ranges, [(0x1004, 7), (0x1028, 1)]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[inline(never)]
+    extern "C" fn patch_target() -> u32 {
+        std::hint::black_box(0x1234_5678)
+    }
+
+    /// Forks a helper that overwrites the start of one of its own functions, like an inline hook
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_patched_text_in_helper_process() {
+        use crate::core::process::Process;
+
+        let target = patch_target as extern "C" fn() -> u32 as usize;
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+        // SAFETY: the child only makes raw syscalls and writes its own memory before it is killed
+        let child = unsafe { libc::fork() };
+        assert!(child >= 0);
+        if child == 0 {
+            unsafe {
+                let page = (target & !(PAGE_SIZE as usize - 1)) as *mut libc::c_void;
+                let writable = libc::mprotect(page, PAGE_SIZE as usize * 2, libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC) == 0;
+                if writable {
+                    std::ptr::write_volatile(target as *mut [u8; 4], [0xCC, 0xCC, 0xCC, 0xCC]);
+                    libc::mprotect(page, PAGE_SIZE as usize * 2, libc::PROT_READ | libc::PROT_EXEC);
+                }
+                // 0 tells the parent that the text could not be made writable
+                let status: u8 = writable as u8;
+                libc::write(fds[1], &status as *const u8 as *const libc::c_void, 1);
+                loop {
+                    libc::pause();
+                }
+            }
+        }
+
+        let mut status = 0u8;
+        let read = unsafe { libc::read(fds[0], &mut status as *mut u8 as *mut libc::c_void, 1) };
+
+        let process = Process::from_parts("helper", child as u32);
+        let patched = process.modules().map(|modules| {
+            let module = modules.into_iter()
+                .find(|module| module.base <= target as u64 && (target as u64) < module.base + module.size)
+                .unwrap();
+            check_module(process.memory(), &module)
+        });
+
+        unsafe {
+            libc::kill(child, libc::SIGKILL);
+            libc::waitpid(child, std::ptr::null_mut(), 0);
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+
+        assert_eq!(read, 1);
+        assert_eq!(status, 1, "the helper could not make its own text writable");
+
+        let patched = patched.unwrap().unwrap();
+        assert_eq!(patched.len(), 1, "{:?}", patched);
+        assert_eq!(patched[0].address, target as u64);
+        assert_eq!(patched[0].len, 4);
+        assert_eq!(patched[0].found, [0xCC; 4]);
+    }
+}
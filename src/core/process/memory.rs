@@ -0,0 +1,474 @@
+// Reads the memory of another process.
+//
+// Addresses are always 64-bit so 64-bit targets can be read on every platform. A read that runs
+// into unmapped memory part way through returns how many bytes it got instead of failing outright.
+
+use std::fmt;
+#[cfg(target_os = "linux")]
+use std::{
+    fs::{self, File},
+    io,
+    os::unix::fs::FileExt,
+    sync::OnceLock,
+};
+#[cfg(windows)]
+use windows::Win32::{
+    Foundation::{
+        HANDLE,
+        WIN32_ERROR,
+        ERROR_ACCESS_DENIED,
+        ERROR_INVALID_HANDLE,
+        ERROR_PARTIAL_COPY,
+    },
+    System::Diagnostics::Debug::ReadProcessMemory,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MemoryError {
+    InvalidAddress(u64),   // Nothing is mapped (or readable) at the address
+    PartialRead { address: u64, read: usize, requested: usize },
+    PermissionDenied,
+    ProcessExited,
+    Unsupported,
+    Os(i32),               // errno on Linux, Windows error code on Windows
+}
+
+// Minimal Display implementation - just show the variant
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for MemoryError {}
+
+/// A contiguous range of pages with the same protection and backing
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+    pub private: bool, // Copy-on-write (MAP_PRIVATE / MEM_PRIVATE)
+    pub offset: u64,   // Offset into the backing file
+    pub path: String,  // Backing file, or a pseudo name like [heap], empty for anonymous memory
+}
+
+impl MemoryRegion {
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Backed by a file rather than anonymous memory or a kernel pseudo mapping. Windows regions
+    /// carry NT device paths (\\Device\\HarddiskVolume3\\...).
+    pub fn is_file_backed(&self) -> bool {
+        self.path.starts_with(['/', '\\'])
+    }
+
+    /// Backed by a file that no longer exists on disk
+    pub fn is_deleted(&self) -> bool {
+        is_deleted_path(&self.path)
+    }
+}
+
+/// A memfd, or a file deleted after it was mapped
+pub(crate) fn is_deleted_path(path: &str) -> bool {
+    path.ends_with(" (deleted)") || path.starts_with("/memfd:")
+}
+
+/// Parses the contents of /proc/<pid>/maps, skipping lines it doesn't understand
+pub fn parse_maps(contents: &str) -> Vec<MemoryRegion> {
+    contents.lines()
+        .filter_map(|line| {
+            // address perms offset dev inode [path], the path may contain spaces
+            let mut fields = line.splitn(6, ' ');
+            let (start, end) = fields.next()?.split_once('-')?;
+            let perms = fields.next()?.as_bytes();
+            let offset = fields.next()?;
+            let path = fields.nth(2).unwrap_or("").trim_start();
+
+            if perms.len() < 4 {
+                return None;
+            }
+
+            Some(MemoryRegion {
+                start: u64::from_str_radix(start, 16).ok()?,
+                end: u64::from_str_radix(end, 16).ok()?,
+                readable: perms[0] == b'r',
+                writable: perms[1] == b'w',
+                executable: perms[2] == b'x',
+                private: perms[3] == b'p',
+                offset: u64::from_str_radix(offset, 16).ok()?,
+                path: path.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_maps(pid: u32) -> io::Result<Vec<MemoryRegion>> {
+    Ok(parse_maps(&fs::read_to_string(format!("/proc/{}/maps", pid))?))
+}
+
+pub trait MemoryReader: fmt::Debug + Send + Sync {
+    /// Reads as much of `buffer` as is mapped starting at `address` and returns the number of bytes
+    /// read. Only fails if not even the first byte could be read.
+    fn read_into(&self, address: u64, buffer: &mut [u8]) -> Result<usize, MemoryError>;
+
+    /// Reads several regions at once. Every region gets its own result, like read_into.
+    fn read_many(&self, regions: &mut [(u64, &mut [u8])]) -> Vec<Result<usize, MemoryError>> {
+        regions.iter_mut()
+            .map(|(address, buffer)| self.read_into(*address, buffer))
+            .collect()
+    }
+
+    /// Fills all of `buffer`, a short read is an error
+    fn read_exact(&self, address: u64, buffer: &mut [u8]) -> Result<(), MemoryError> {
+        let read = self.read_into(address, buffer)?;
+        if read < buffer.len() {
+            return Err(MemoryError::PartialRead { address, read, requested: buffer.len() });
+        }
+        Ok(())
+    }
+
+    /// Up to `len` bytes at `address`, shorter if the region ends early
+    fn read_bytes(&self, address: u64, len: usize) -> Result<Vec<u8>, MemoryError> {
+        let mut buffer = vec![0u8; len];
+        let read = self.read_into(address, &mut buffer)?;
+        buffer.truncate(read);
+        Ok(buffer)
+    }
+}
+
+/// Types for which any bit pattern is a valid value, so they can be filled from another process's memory.
+///
+/// # Safety
+/// Implementors must have no padding, no pointers or references, and no invalid bit patterns (bool,
+/// char and enums are out). #[repr(C)] structs made only of Pod fields qualify.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($type:ty),*) => {
+        $(unsafe impl Pod for $type {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+impl dyn MemoryReader + '_ {
+    /// Reads a plain value (integers, floats, arrays and #[repr(C)] structs of those)
+    pub fn read<T: Pod>(&self, address: u64) -> Result<T, MemoryError> {
+        let mut value = std::mem::MaybeUninit::<T>::zeroed();
+
+        // SAFETY: the buffer covers exactly the value, which is zeroed so every byte is initialized
+        let buffer = unsafe {
+            std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, std::mem::size_of::<T>())
+        };
+        self.read_exact(address, buffer)?;
+
+        // SAFETY: every byte was written and T is Pod, so any bytes make a valid value
+        Ok(unsafe { value.assume_init() })
+    }
+}
+
+// LINUX ---------------------------------------------------------------------------------------------
+/// Reads through process_vm_readv, falling back to /proc/<pid>/mem where the syscall is unavailable
+/// (kernels without CONFIG_CROSS_MEMORY_ATTACH, seccomp filters). Both need ptrace access to the
+/// target: being its parent, the same user with ptrace_scope 0, or CAP_SYS_PTRACE.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct LinuxMemoryReader {
+    pid: u32,
+    mem: OnceLock<Option<File>>, // Opened on the first fallback read
+}
+
+#[cfg(target_os = "linux")]
+impl LinuxMemoryReader {
+    pub fn new(pid: u32) -> Self {
+        Self { pid, mem: OnceLock::new() }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn read_vm(&self, address: u64, buffer: &mut [u8]) -> Result<usize, MemoryError> {
+        let local = libc::iovec { iov_base: buffer.as_mut_ptr() as *mut libc::c_void, iov_len: buffer.len() };
+        let remote = libc::iovec { iov_base: address as *mut libc::c_void, iov_len: buffer.len() };
+
+        // SAFETY: the local iovec covers exactly `buffer`, the remote one is only read by the kernel
+        let read = unsafe { libc::process_vm_readv(self.pid as libc::pid_t, &local, 1, &remote, 1, 0) };
+        if read < 0 {
+            return Err(errno_to_error(io::Error::last_os_error(), address));
+        }
+        Ok(read as usize)
+    }
+
+    fn read_procfs(&self, address: u64, buffer: &mut [u8]) -> Result<usize, MemoryError> {
+        let file = self.mem
+            .get_or_init(|| File::open(format!("/proc/{}/mem", self.pid)).ok())
+            .as_ref()
+            .ok_or(MemoryError::PermissionDenied)?;
+
+        // pread stops at the first page it can't read, like process_vm_readv
+        let mut read = 0;
+        while read < buffer.len() {
+            match file.read_at(&mut buffer[read..], address + read as u64) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(_) if read > 0 => break,
+                Err(error) => return Err(errno_to_error(error, address)),
+            }
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn errno_to_error(error: io::Error, address: u64) -> MemoryError {
+    match error.raw_os_error() {
+        Some(libc::EFAULT | libc::EIO) => MemoryError::InvalidAddress(address),
+        Some(libc::EPERM | libc::EACCES) => MemoryError::PermissionDenied,
+        Some(libc::ESRCH) => MemoryError::ProcessExited,
+        Some(libc::ENOSYS) => MemoryError::Unsupported,
+        Some(code) => MemoryError::Os(code),
+        None => MemoryError::Os(0),
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl MemoryReader for LinuxMemoryReader {
+    fn read_into(&self, address: u64, buffer: &mut [u8]) -> Result<usize, MemoryError> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        match self.read_vm(address, buffer) {
+            // EPERM can also come from a seccomp filter, /proc/<pid>/mem does its own access check
+            Err(MemoryError::Unsupported | MemoryError::PermissionDenied) => self.read_procfs(address, buffer),
+            result => result,
+        }
+    }
+
+    /// One process_vm_readv call for every region. Only if that comes up short are the regions
+    /// read one by one to find out which of them failed.
+    fn read_many(&self, regions: &mut [(u64, &mut [u8])]) -> Vec<Result<usize, MemoryError>> {
+        let requested: usize = regions.iter().map(|(_, buffer)| buffer.len()).sum();
+
+        // The kernel takes at most IOV_MAX (1024) regions per call
+        if regions.len() <= 1024 {
+            let local: Vec<libc::iovec> = regions.iter_mut()
+                .map(|(_, buffer)| libc::iovec { iov_base: buffer.as_mut_ptr() as *mut libc::c_void, iov_len: buffer.len() })
+                .collect();
+            let remote: Vec<libc::iovec> = regions.iter()
+                .map(|(address, buffer)| libc::iovec { iov_base: *address as *mut libc::c_void, iov_len: buffer.len() })
+                .collect();
+
+            // SAFETY: every local iovec covers exactly one of the borrowed buffers
+            let read = unsafe {
+                libc::process_vm_readv(
+                    self.pid as libc::pid_t,
+                    local.as_ptr(),
+                    local.len() as libc::c_ulong,
+                    remote.as_ptr(),
+                    remote.len() as libc::c_ulong,
+                    0,
+                )
+            };
+
+            if read >= 0 && read as usize == requested {
+                return regions.iter().map(|(_, buffer)| Ok(buffer.len())).collect();
+            }
+        }
+
+        regions.iter_mut()
+            .map(|(address, buffer)| self.read_into(*address, buffer))
+            .collect()
+    }
+}
+
+// WINDOWS -------------------------------------------------------------------------------------------
+/// Reads through ReadProcessMemory. The handle needs PROCESS_VM_READ and stays owned by the Process.
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct WindowsMemoryReader {
+    handle: HANDLE,
+}
+
+// SAFETY: HANDLE is just an identifier, ReadProcessMemory can be called from any thread
+#[cfg(windows)]
+unsafe impl Send for WindowsMemoryReader {}
+#[cfg(windows)]
+unsafe impl Sync for WindowsMemoryReader {}
+
+#[cfg(windows)]
+impl WindowsMemoryReader {
+    pub fn new(handle: HANDLE) -> Self {
+        Self { handle }
+    }
+}
+
+#[cfg(windows)]
+impl MemoryReader for WindowsMemoryReader {
+    fn read_into(&self, address: u64, buffer: &mut [u8]) -> Result<usize, MemoryError> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let mut read = 0usize;
+        let status = unsafe {
+            ReadProcessMemory(
+                self.handle,
+                address as usize as *const std::ffi::c_void,
+                buffer.as_mut_ptr() as *mut std::ffi::c_void,
+                buffer.len(),
+                Some(&mut read),
+            )
+        };
+
+        let Err(error) = status else {
+            return Ok(read);
+        };
+
+        match WIN32_ERROR::from_error(&error) {
+            // Windows reports how much it copied before running into an unreadable page
+            Some(ERROR_PARTIAL_COPY) if read > 0 => Ok(read),
+            Some(ERROR_PARTIAL_COPY) => Err(MemoryError::InvalidAddress(address)),
+            Some(ERROR_ACCESS_DENIED) => Err(MemoryError::PermissionDenied),
+            Some(ERROR_INVALID_HANDLE) => Err(MemoryError::ProcessExited),
+            Some(code) => Err(MemoryError::Os(code.0 as i32)),
+            None => Err(MemoryError::Os(error.code().0)),
+        }
+    }
+}
+
+// OTHER PLATFORMS -----------------------------------------------------------------------------------
+#[cfg(not(any(target_os = "linux", windows)))]
+#[derive(Debug)]
+pub struct UnsupportedMemoryReader;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+impl MemoryReader for UnsupportedMemoryReader {
+    fn read_into(&self, _address: u64, _buffer: &mut [u8]) -> Result<usize, MemoryError> {
+        Err(MemoryError::Unsupported)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+
+    fn spawn_child() -> Child {
+        Command::new("sleep").arg("30").stdout(Stdio::null()).spawn().unwrap()
+    }
+
+    fn kill(mut child: Child) {
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn parses_maps_lines() {
+        let regions = parse_maps(concat!(
+            "55d0c0a00000-55d0c0a02000 r--p 00000000 08:01 1311 /usr/bin/sleep\n",
+            "55d0c0a02000-55d0c0a06000 r-xp 00002000 08:01 1311 /usr/bin/sleep\n",
+            "7f1e2c000000-7f1e2c021000 rw-p 00000000 00:00 0 \n",
+            "7f1e2d000000-7f1e2d001000 rwxs 00000000 00:01 77 /memfd:payload (deleted)\n",
+            "7ffd5a1f0000-7ffd5a211000 rw-p 00000000 00:00 0                          [stack]\n",
+        ));
+
+        assert_eq!(regions.len(), 5);
+        assert_eq!(regions[1], MemoryRegion {
+            start: 0x55d0c0a02000,
+            end: 0x55d0c0a06000,
+            readable: true,
+            writable: false,
+            executable: true,
+            private: true,
+            offset: 0x2000,
+            path: "/usr/bin/sleep".into(),
+        });
+        assert!(regions[2].path.is_empty() && !regions[2].is_file_backed());
+        assert_eq!(regions[3].path, "/memfd:payload (deleted)");
+        assert!(regions[3].executable && regions[3].writable && !regions[3].private);
+        assert_eq!(regions[4].path, "[stack]");
+    }
+
+    #[test]
+    fn reads_mapped_executable_of_child() {
+        let child = spawn_child();
+        let pid = child.id();
+        // Give exec a moment so the maps show sleep instead of the test binary
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).unwrap();
+        let on_disk = std::fs::read(&exe).unwrap();
+        let header = read_maps(pid).unwrap().into_iter()
+            .find(|mapping| mapping.offset == 0 && mapping.path == exe.to_string_lossy())
+            .unwrap();
+
+        let reader = LinuxMemoryReader::new(pid);
+        let mut via_vm = [0u8; 64];
+        let mut via_procfs = [0u8; 64];
+        let vm = reader.read_vm(header.start, &mut via_vm);
+        let procfs = reader.read_procfs(header.start, &mut via_procfs);
+        let read = (&reader as &dyn MemoryReader).read_bytes(header.start, 64);
+        let magic = (&reader as &dyn MemoryReader).read::<u32>(header.start);
+        let invalid = (&reader as &dyn MemoryReader).read::<u64>(0);
+
+        kill(child);
+
+        let read = read.unwrap();
+        assert_eq!(read, &on_disk[..64]);
+        assert_eq!(magic, Ok(u32::from_le_bytes(*b"\x7fELF")));
+        assert_eq!(invalid, Err(MemoryError::InvalidAddress(0)));
+        // process_vm_readv itself may be filtered out, read_into then went through procfs
+        if vm != Err(MemoryError::PermissionDenied) {
+            assert_eq!(vm, Ok(64));
+            assert_eq!(via_vm[..], on_disk[..64]);
+        }
+        assert_eq!(procfs, Ok(64), "no ptrace access to our own child");
+        assert_eq!(via_procfs[..], on_disk[..64]);
+    }
+
+    #[test]
+    fn reports_partial_and_bulk_reads() {
+        let child = spawn_child();
+        let pid = child.id();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let mappings = read_maps(pid).unwrap();
+        // A readable mapping followed by a hole, so a read across its end comes up short
+        let edge = mappings.windows(2)
+            .find(|pair| pair[0].readable && pair[0].end < pair[1].start)
+            .map(|pair| pair[0].end)
+            .unwrap();
+        let start = mappings.iter().find(|mapping| mapping.readable).unwrap().start;
+
+        let reader = LinuxMemoryReader::new(pid);
+        let dyn_reader: &dyn MemoryReader = &reader;
+
+        let mut buffer = [0u8; 32];
+        let partial = dyn_reader.read_into(edge - 8, &mut buffer);
+        let exact = dyn_reader.read_exact(edge - 8, &mut buffer);
+        let procfs_partial = reader.read_procfs(edge - 8, &mut buffer);
+
+        let mut first = [0u8; 16];
+        let mut second = [0u8; 16];
+        let mut unmapped = [0u8; 16];
+        let bulk = dyn_reader.read_many(&mut [(start, &mut first[..]), (edge - 16, &mut second[..])]);
+        let mixed = dyn_reader.read_many(&mut [(start, &mut first[..]), (edge, &mut unmapped[..])]);
+
+        kill(child);
+
+        assert_eq!(partial, Ok(8));
+        assert_eq!(exact, Err(MemoryError::PartialRead { address: edge - 8, read: 8, requested: 32 }));
+        assert_eq!(procfs_partial, Ok(8));
+        assert_eq!(bulk, [Ok(16), Ok(16)]);
+        assert_eq!(mixed, [Ok(16), Err(MemoryError::InvalidAddress(edge))]);
+    }
+}
@@ -1,10 +1,22 @@
 // Adapted from https://github.com/WakelandBranz/wake_assault_cube
+#[allow(clippy::module_inception)]
 mod process;
+pub mod process_query;
+pub mod memory;
+pub mod module;
+pub mod injection;
+pub mod integrity;
 
+#[cfg(windows)]
 use process::*;
-use windows::core::PCSTR;
+use std::sync::Arc;
+use memory::{MemoryError, MemoryReader, MemoryRegion, Pod};
+use module::{FileHashCache, ModuleInfo};
+use process_query::{ProcessMatch, ProcessQuery};
+#[cfg(windows)]
 use windows::Win32::{
     Foundation::{
+        CloseHandle,
         HWND,
         HANDLE,
         RECT,
@@ -12,7 +24,6 @@ use windows::Win32::{
     System::{
         Diagnostics::{
             Debug::{
-                ReadProcessMemory,
                 WriteProcessMemory,
             }
         },
@@ -26,119 +37,313 @@ use windows::Win32::{
     UI::WindowsAndMessaging::{
         GetWindowRect,
         GetForegroundWindow,
-        FindWindowA,
         IsWindowVisible,
         IsIconic,
     },
 };
 
+#[derive(Debug)]
+pub enum ProcessError {
+    EmptyQuery,
+    NotFound,
+    AmbiguousMatch(Vec<u32>), // PIDs of every match
+    FailedToReadProcfs,
+    FailedToEnumerateWindows,
+    FailedToOpenProcess(u32), // Windows error code
+    FailedToListModules(u32), // Windows error code
+    ModuleNotFound,
+    FailedToQueryMemory,
+    UnsupportedPlatform,
+}
+
+// Minimal Display implementation - just show the variant
+impl std::fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
 // SAFETY: HANDLE is thread-safe as it's just an identifier
 // and base_address is only used for reading
 // Not sure why this is necessary though.
+#[cfg(windows)]
 unsafe impl Send for Process {}
+#[cfg(windows)]
 unsafe impl Sync for Process {}
 
 #[derive(Clone, Debug)]
 pub struct Process {
     pub(crate) name: String,
     pub pid: u32,
+    #[cfg(windows)]
     handle: HANDLE,
+    #[cfg(windows)]
     window_handle: HWND,
     //is_focused: Arc<AtomicBool>,
-    pub(crate) base_address: u32,
+    #[cfg(windows)]
+    pub(crate) base_address: u64,
+    memory: Arc<dyn MemoryReader>,
 }
 
 impl Process {
-    pub fn new(process_name: impl ToString + std::fmt::Display) -> Self {
-        let name = process_name.to_string();
+    /// Opens the only running process with this executable name (ignoring case).
+    /// Use ProcessQuery and Process::open to pick between several instances.
+    pub fn new(process_name: impl ToString + std::fmt::Display) -> Result<Self, ProcessError> {
+        Self::open(&ProcessQuery::new().with_name(process_name.to_string()).find_one()?)
+    }
 
-        let pid = get_pid_by_name(&name)
-            .unwrap_or_else(|| panic!("Could not get pid!"));
+    pub fn from_pid(pid: u32) -> Result<Self, ProcessError> {
+        Self::open(&ProcessQuery::new().with_pid(pid).find_one()?)
+    }
 
-        log::debug!("Got pid! - {}", &pid);
+    /// Opens a process found by a ProcessQuery
+    #[cfg(windows)]
+    pub fn open(process: &ProcessMatch) -> Result<Self, ProcessError> {
+        let name = process.name.clone();
+        let pid = process.pid;
+        log::debug!("Opening {} (PID: {})", name, pid);
 
-        let handle = unsafe {
-            match open_process_handle(pid) {
-                Ok(handle) => handle,
-                Err(error) => {
-                    panic!("Failed to open handle for {}. Error: {}", name, error)
-                }
-            }
-        };
-
-        let window_handle = unsafe {
-            // as_ptr() avoids allocating strings
-            FindWindowA(
-                PCSTR::from_raw("SDL_app\0".as_ptr()),
-                PCSTR::from_raw("AssaultCube\0".as_ptr()),
-            ).expect("Failed to find assault cube window")
-        };
+        let handle = unsafe { open_process_handle(pid) }
+            .map_err(ProcessError::FailedToOpenProcess)?;
 
         log::debug!("Got handle! - {:?}", &handle);
 
-        let base_address = unsafe {
-            match get_mod_base(pid, &name) {
-                Ok(mod_base) => {
-                    if mod_base.is_null() {
-                        panic!("Could not find module base for {}", name);
-                    }
-                    mod_base as u32  // Return the base address if not null
-                },
-                Err(error) => {
-                    panic!("Failed to get module base for {}. Error: {}", name, error)
-                },
+        let base_address = match unsafe { get_mod_base(pid, &name) } {
+            Ok(mod_base) if !mod_base.is_null() => mod_base as u64,
+            result => {
+                unsafe { let _ = CloseHandle(handle); }
+                return Err(result.map_or_else(ProcessError::FailedToListModules, |_| ProcessError::ModuleNotFound));
             }
         };
 
         log::debug!("Got base address! - {:?}", base_address);
 
-        Self {
+        Ok(Self {
             name,
             pid,
             handle,
-            window_handle,
-            base_address
-        }
+            window_handle: Self::find_main_window(process),
+            base_address,
+            memory: Arc::new(memory::WindowsMemoryReader::new(handle)),
+        })
+    }
+
+    /// Records a process found by a ProcessQuery
+    #[cfg(not(windows))]
+    pub fn open(process: &ProcessMatch) -> Result<Self, ProcessError> {
+        log::debug!("Opening {} (PID: {})", process.name, process.pid);
+
+        Ok(Self {
+            name: process.name.clone(),
+            pid: process.pid,
+            memory: platform_memory_reader(process.pid),
+        })
     }
 
-    /// Generic wrapper that uses try_read_bytes_into under the hood
-    pub fn read<T>(&self, address: u32) -> Option<T>
-    where T: Copy {
-        unsafe {
-            // Allocate zeroed memory
-            let mut buffer = std::mem::zeroed::<T>();
+    /// First visible top-level window of the process, if it has one
+    #[cfg(windows)]
+    fn find_main_window(process: &ProcessMatch) -> HWND {
+        use crate::core::overlay::{
+            win32_window_source::Win32WindowSource,
+            window_source::WindowSource,
+            WS_VISIBLE,
+        };
 
-            let buffer_slice = std::slice::from_raw_parts_mut(
-                &mut buffer as *mut T as *mut u8,
-                std::mem::size_of::<T>()
-            );
+        let windows = if process.windows.is_empty() {
+            Win32WindowSource.query_windows().unwrap_or_default()
+        }
+        else {
+            process.windows.clone()
+        };
 
-            self.try_read_bytes_into(address, buffer_slice)?;
-            Some(buffer)
+        windows.iter()
+            .find(|window| window.pid == process.pid && window.style as u32 & WS_VISIBLE != 0)
+            .map_or(HWND::default(), |window| HWND(window.hwnd as *mut std::ffi::c_void))
+    }
+
+    /// Builds a process record without touching the system, for tests
+    #[cfg(test)]
+    pub(crate) fn from_parts(name: impl ToString, pid: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            pid,
+            #[cfg(windows)]
+            handle: HANDLE::default(),
+            #[cfg(windows)]
+            window_handle: HWND::default(),
+            #[cfg(windows)]
+            base_address: 0,
+            #[cfg(windows)]
+            memory: Arc::new(memory::WindowsMemoryReader::new(HANDLE::default())),
+            #[cfg(not(windows))]
+            memory: platform_memory_reader(pid),
         }
     }
 
-    // Original function that does the actual reading
-    fn try_read_bytes_into(&self, address: u32, buffer: &mut [u8]) -> Option<()> {
-        if buffer.len() == 0 {
-            return Some(());
+    /// Every module loaded into the process, with file sizes and hashes
+    pub fn modules(&self) -> Result<Vec<ModuleInfo>, ProcessError> {
+        self.modules_cached(&mut FileHashCache::new())
+    }
+
+    /// Every executable and shared library loaded into the process, with file sizes and hashes.
+    /// Files that didn't change since they were put into the cache aren't hashed again.
+    #[cfg(target_os = "linux")]
+    pub fn modules_cached(&self, cache: &mut FileHashCache) -> Result<Vec<ModuleInfo>, ProcessError> {
+        let regions = memory::read_maps(self.pid).map_err(|_| ProcessError::FailedToReadProcfs)?;
+
+        let mut modules = module::modules_from_regions(&regions);
+        for module in &mut modules {
+            module.read_file_metadata_cached(cache);
         }
-        let status = unsafe {
-            ReadProcessMemory(
-                self.handle,
-                address as _,
-                buffer.as_mut_ptr() as _,
-                std::mem::size_of_val(buffer) as _,
-                None,
-            )
+        Ok(modules)
+    }
+
+    /// Every executable and DLL loaded into the process, with file sizes and hashes.
+    /// Files that didn't change since they were put into the cache aren't hashed again.
+    #[cfg(windows)]
+    pub fn modules_cached(&self, cache: &mut FileHashCache) -> Result<Vec<ModuleInfo>, ProcessError> {
+        let entries = unsafe { get_modules(self.pid) }.map_err(ProcessError::FailedToListModules)?;
+
+        Ok(entries.iter()
+            .map(|entry| {
+                let path = unsafe { std::ffi::CStr::from_ptr(entry.szExePath.as_ptr()) };
+                let mut module = ModuleInfo::new(path.to_string_lossy(), entry.modBaseAddr as u64, entry.modBaseSize as u64);
+                module.read_file_metadata_cached(cache);
+                module
+            })
+            .collect())
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub fn modules_cached(&self, _cache: &mut FileHashCache) -> Result<Vec<ModuleInfo>, ProcessError> {
+        Err(ProcessError::UnsupportedPlatform)
+    }
+
+    /// The executable the process was started from, out of its loaded modules
+    #[cfg(target_os = "linux")]
+    pub fn main_module<'a>(&self, modules: &'a [ModuleInfo]) -> Option<&'a ModuleInfo> {
+        let exe = std::fs::read_link(format!("/proc/{}/exe", self.pid)).ok()?;
+        modules.iter().find(|module| std::path::Path::new(&module.path) == exe)
+    }
+
+    /// The executable the process was started from, out of its loaded modules
+    #[cfg(windows)]
+    pub fn main_module<'a>(&self, modules: &'a [ModuleInfo]) -> Option<&'a ModuleInfo> {
+        modules.iter().find(|module| module.base == self.base_address)
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub fn main_module<'a>(&self, _modules: &'a [ModuleInfo]) -> Option<&'a ModuleInfo> {
+        None
+    }
+
+    /// Every mapped region of the process, lowest address first
+    #[cfg(target_os = "linux")]
+    pub fn memory_regions(&self) -> Result<Vec<MemoryRegion>, ProcessError> {
+        memory::read_maps(self.pid).map_err(|_| ProcessError::FailedToReadProcfs)
+    }
+
+    /// Every committed region of the process, lowest address first. Image and mapped regions
+    /// carry the NT device path of their file.
+    #[cfg(windows)]
+    pub fn memory_regions(&self) -> Result<Vec<MemoryRegion>, ProcessError> {
+        use windows::Win32::System::{
+            Memory::{VirtualQueryEx, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_PRIVATE},
+            ProcessStatus::GetMappedFileNameW,
         };
 
-        match status {
-            Ok(_) => Some(()),
-            Err(error) => {
-                log::error!("ReadProcessMemory failed: {}", error);
-                None
+        const READABLE: u32 = 0x02 | 0x04 | 0x08 | 0x20 | 0x40 | 0x80; // PAGE_READONLY ... PAGE_EXECUTE_WRITECOPY
+        const WRITABLE: u32 = 0x04 | 0x08 | 0x40 | 0x80;               // PAGE_READWRITE, PAGE_WRITECOPY and their EXECUTE_ variants
+        const EXECUTABLE: u32 = 0x10 | 0x20 | 0x40 | 0x80;             // PAGE_EXECUTE*
+
+        let mut regions = Vec::new();
+        let mut queried_any = false;
+        let mut address = 0usize;
+
+        loop {
+            let mut info = MEMORY_BASIC_INFORMATION::default();
+            let written = unsafe {
+                VirtualQueryEx(
+                    self.handle,
+                    Some(address as *const std::ffi::c_void),
+                    &mut info,
+                    std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                )
+            };
+            // Past the highest user mode address
+            if written == 0 {
+                break;
+            }
+            queried_any = true;
+
+            let start = info.BaseAddress as usize;
+            if info.State == MEM_COMMIT {
+                // The upper bits are modifiers like PAGE_GUARD
+                let protect = info.Protect.0 & 0xFF;
+
+                let path = if info.Type == MEM_PRIVATE {
+                    String::new()
+                }
+                else {
+                    let mut buffer = [0u16; 1024];
+                    let len = unsafe { GetMappedFileNameW(self.handle, start as *const std::ffi::c_void, &mut buffer) };
+                    String::from_utf16_lossy(&buffer[..len as usize])
+                };
+
+                regions.push(MemoryRegion {
+                    start: start as u64,
+                    end: (start + info.RegionSize) as u64,
+                    readable: protect & READABLE != 0,
+                    writable: protect & WRITABLE != 0,
+                    executable: protect & EXECUTABLE != 0,
+                    private: info.Type == MEM_PRIVATE,
+                    offset: 0,
+                    path,
+                });
             }
+
+            match start.checked_add(info.RegionSize) {
+                Some(next) if next > address => address = next,
+                _ => break,
+            }
+        }
+
+        if !queried_any {
+            return Err(ProcessError::FailedToQueryMemory);
         }
+        Ok(regions)
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    pub fn memory_regions(&self) -> Result<Vec<MemoryRegion>, ProcessError> {
+        Err(ProcessError::UnsupportedPlatform)
+    }
+
+    /// Reader for the process's memory
+    pub fn memory(&self) -> &dyn MemoryReader {
+        self.memory.as_ref()
+    }
+
+    /// Reads a plain value (integers, floats, arrays and #[repr(C)] structs of those)
+    pub fn read<T>(&self, address: u64) -> Result<T, MemoryError>
+    where T: Pod {
+        self.memory().read(address)
+    }
+
+    /// Reads as much of `buffer` as is mapped at `address`, returns the number of bytes read
+    pub fn read_bytes_into(&self, address: u64, buffer: &mut [u8]) -> Result<usize, MemoryError> {
+        self.memory.read_into(address, buffer)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(target_os = "linux")]
+fn platform_memory_reader(pid: u32) -> Arc<dyn MemoryReader> {
+    Arc::new(memory::LinuxMemoryReader::new(pid))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn platform_memory_reader(_pid: u32) -> Arc<dyn MemoryReader> {
+    Arc::new(memory::UnsupportedMemoryReader)
+}
@@ -0,0 +1,190 @@
+// Executables and shared libraries loaded into a process
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs,
+    path::Path,
+    time::SystemTime,
+};
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    allowlist::sha256_file,
+    process::memory::{is_deleted_path, MemoryRegion},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModuleInfo {
+    pub name: String,             // File name, e.g. "opengl32.dll"
+    pub path: String,             // Full path the module was loaded from
+    pub base: u64,                // Lowest mapped address
+    pub size: u64,                // Bytes mapped in the process
+    pub file_size: Option<u64>,   // Size on disk, None if the file is gone or can't be read
+    pub sha256: Option<String>,   // Hex encoded hash of the file on disk
+}
+
+impl ModuleInfo {
+    pub fn new(path: impl Into<String>, base: u64, size: u64) -> Self {
+        let path = path.into();
+        let name = path.rsplit(['\\', '/']).next().unwrap_or_default().to_string();
+        Self { name, path, base, size, ..Default::default() }
+    }
+
+    /// Fills in the size and hash of the file the module was loaded from
+    pub fn read_file_metadata(&mut self) {
+        self.read_file_metadata_cached(&mut FileHashCache::default());
+    }
+
+    /// Like read_file_metadata, taking the hash from the cache while the file's size and modification
+    /// time are unchanged
+    pub fn read_file_metadata_cached(&mut self, cache: &mut FileHashCache) {
+        let path = Path::new(&self.path);
+        let Some(metadata) = fs::metadata(path).ok().filter(|metadata| metadata.is_file()) else {
+            self.file_size = None;
+            self.sha256 = None;
+            return;
+        };
+
+        let file_size = metadata.len();
+        let modified = metadata.modified().ok();
+        self.file_size = Some(file_size);
+        self.sha256 = match cache.hashes.get(&self.path) {
+            Some((size, mtime, hash)) if *size == file_size && modified == Some(*mtime) => hash.clone(),
+            _ => {
+                let hash = sha256_file(path).ok();
+                // Without a modification time there is no telling whether the file changed
+                if let Some(modified) = modified {
+                    cache.hashes.insert(self.path.clone(), (file_size, modified, hash.clone()));
+                }
+                hash
+            }
+        };
+    }
+
+    /// Mapped size minus the size on disk. Images are mapped page aligned (and on Windows section
+    /// aligned), so a small positive difference is normal.
+    pub fn size_difference(&self) -> Option<i64> {
+        self.file_size.map(|file_size| self.size as i64 - file_size as i64)
+    }
+
+    /// The mapped file no longer exists, e.g. a library loaded from a memfd or deleted after loading
+    pub fn is_deleted(&self) -> bool {
+        is_deleted_path(&self.path)
+    }
+}
+
+/// Hashes of module files from earlier scans by path. Rescans of the same game would otherwise hash
+/// every loaded module again.
+#[derive(Clone, Debug, Default)]
+pub struct FileHashCache {
+    hashes: HashMap<String, (u64, SystemTime, Option<String>)>, // Size and modification time when hashed
+}
+
+impl FileHashCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Display for ModuleInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at 0x{:X} ({} bytes mapped", self.name, self.base, self.size)?;
+        match self.file_size {
+            Some(file_size) => write!(f, ", {} bytes on disk)", file_size)?,
+            None => write!(f, ", not on disk)")?,
+        }
+        write!(f, " - {}", self.path)
+    }
+}
+
+/// Groups the file-backed regions of a process into modules. Only files with at least one
+/// executable mapping count, which leaves out fonts, locale archives and other mapped data files.
+pub fn modules_from_regions(regions: &[MemoryRegion]) -> Vec<ModuleInfo> {
+    let mut modules: Vec<(ModuleInfo, bool)> = Vec::new();
+
+    for region in regions.iter().filter(|region| region.is_file_backed() && !region.path.starts_with("/dev/")) {
+        match modules.iter_mut().find(|(module, _)| module.path == region.path) {
+            Some((module, executable)) => {
+                let end = (module.base + module.size).max(region.end);
+                module.base = module.base.min(region.start);
+                module.size = end - module.base;
+                *executable |= region.executable;
+            }
+            None => modules.push((ModuleInfo::new(region.path.clone(), region.start, region.size()), region.executable)),
+        }
+    }
+
+    modules.into_iter()
+        .filter(|(_, executable)| *executable)
+        .map(|(module, _)| module)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{process::memory::parse_maps, temp_dir::TempDir};
+
+    #[test]
+    fn groups_executable_file_mappings() {
+        let regions = parse_maps(concat!(
+            "55d0c0a00000-55d0c0a02000 r--p 00000000 08:01 1311 /usr/bin/sleep\n",
+            "55d0c0a02000-55d0c0a06000 r-xp 00002000 08:01 1311 /usr/bin/sleep\n",
+            "55d0c0a06000-55d0c0a08000 r--p 00006000 08:01 1311 /usr/bin/sleep\n",
+            "7f1e2b000000-7f1e2b400000 r--p 00000000 08:01 2048 /usr/lib/locale/locale-archive\n",
+            "7f1e2c000000-7f1e2c028000 r--p 00000000 08:01 4096 /usr/lib/x86_64-linux-gnu/libc.so.6\n",
+            "7f1e2c028000-7f1e2c1bd000 r-xp 00028000 08:01 4096 /usr/lib/x86_64-linux-gnu/libc.so.6\n",
+            "7f1e2d000000-7f1e2d001000 r-xs 00000000 00:01 77 /memfd:payload (deleted)\n",
+            "7f1e2e000000-7f1e2e100000 rw-s 00000000 00:06 12 /dev/dri/card0\n",
+            "7ffd5a1f0000-7ffd5a211000 rw-p 00000000 00:00 0 [stack]\n",
+        ));
+
+        let modules = modules_from_regions(&regions);
+        let names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+        assert_eq!(names, ["sleep", "libc.so.6", "memfd:payload (deleted)"]);
+        assert_eq!((modules[0].base, modules[0].size), (0x55d0c0a00000, 0x8000));
+        assert!(modules[2].is_deleted());
+        assert!(!modules[1].is_deleted());
+    }
+
+    #[test]
+    fn hashes_files_again_only_when_they_change() {
+        let dir = TempDir::new("module_hash_cache");
+        let path = dir.join("module.so");
+        fs::write(&path, "first").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let mut cache = FileHashCache::new();
+        let mut module = ModuleInfo::new(path.to_string_lossy(), 0x10000, 0x1000);
+        module.read_file_metadata_cached(&mut cache);
+        let first = module.sha256.clone();
+
+        // Same size and modification time, the cached hash is kept
+        fs::write(&path, "other").unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        module.read_file_metadata_cached(&mut cache);
+        let unchanged = module.sha256.clone();
+
+        fs::File::options().write(true).open(&path).unwrap().set_modified(modified + std::time::Duration::from_secs(1)).unwrap();
+        module.read_file_metadata_cached(&mut cache);
+        let changed = module.sha256.clone();
+        let expected = sha256_file(&path).unwrap();
+
+        assert_eq!(unchanged, first);
+        assert_eq!(changed, Some(expected));
+        assert_ne!(changed, first);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn own_executable_is_listed_with_metadata() {
+        let exe = std::env::current_exe().unwrap();
+        let modules = crate::core::process::Process::from_parts("test", std::process::id()).modules().unwrap();
+
+        let own = modules.iter().find(|module| Path::new(&module.path) == exe).unwrap();
+        assert_eq!(own.file_size, Some(fs::metadata(&exe).unwrap().len()));
+        assert_eq!(own.sha256, Some(sha256_file(&exe).unwrap()));
+        assert!(own.size > 0);
+    }
+}
@@ -1,7 +1,8 @@
 // Thank you https://github.com/zofiaclient/memwar/tree/main/memwar/src (adapted for my use)
 
+#[cfg(windows)]
 use std::ffi::{c_void, CStr, CString};
-use sysinfo::System;
+#[cfg(windows)]
 use windows::Win32::{
     Foundation::{
         HANDLE,
@@ -24,24 +25,11 @@ use windows::Win32::{
     }
 };
 
+#[cfg(windows)]
 pub type DWORD = u32;
 
-/// Gets pid of process by name (case-insensitive!)
-pub(super) fn get_pid_by_name(process_name: &str) -> Option<u32> {
-    let s = System::new_all();
-    let lower_name = process_name.to_ascii_lowercase();
-
-    for (pid, process) in s.processes() {
-        let process_name = process.name().to_string_lossy().to_ascii_lowercase();
-        if process_name == lower_name {
-            return Some(pid.as_u32());
-        }
-    }
-
-    None
-}
-
 /// Opens a handle to the requested process
+#[cfg(windows)]
 pub(super) unsafe fn open_process_handle(pid: u32) -> Result<HANDLE, DWORD> {
     let handle = OpenProcess(
         PROCESS_ACCESS_RIGHTS(PROCESS_ALL_ACCESS.0),
@@ -60,6 +48,7 @@ pub(super) unsafe fn open_process_handle(pid: u32) -> Result<HANDLE, DWORD> {
 }
 
 /// Returns a list of modules in the given process
+#[cfg(windows)]
 pub(super) unsafe fn get_modules(pid: u32) -> Result<Vec<MODULEENTRY32>, DWORD> {
     let mut modules = Vec::new();
 
@@ -94,6 +83,7 @@ pub(super) unsafe fn get_modules(pid: u32) -> Result<Vec<MODULEENTRY32>, DWORD>
 /// # Returns
 /// * `Ok(*mut c_void)` - Pointer to module base address, or null if not found
 /// * `Err(DWORD)` - Windows error code if operation fails
+#[cfg(windows)]
 pub(super) unsafe fn get_mod_base(pid: u32, mod_name: &str) -> Result<*mut c_void, DWORD> {
     // Convert Rust string to null-terminated C string for Windows API compatibility
     // Will panic if string contains null bytes
@@ -0,0 +1,319 @@
+// Finds target processes by PID, name, executable path, command line or window title.
+//
+// Every process matching all of the set criteria is returned, so several running game instances
+// can be told apart (or all be scanned) instead of silently picking the first one.
+
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    overlay::{
+        match_criteria::StringMatch,
+        window_info::WindowInfo,
+        window_source::default_source,
+        WindowError,
+    },
+    process::ProcessError,
+};
+
+/// A running process that matched a query
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessMatch {
+    pub pid: u32,
+    pub name: String,         // Executable file name
+    pub exe_path: String,     // Empty when the path can't be read (e.g. another user's process)
+    pub cmdline: Vec<String>,
+    pub windows: Vec<WindowInfo>, // Top-level windows, only collected for window queries
+}
+
+impl ProcessMatch {
+    pub fn cmdline_string(&self) -> String {
+        self.cmdline.join(" ")
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProcessQuery {
+    pid: Option<u32>,
+    name: Option<String>,
+    exe_path: Option<String>,
+    cmdline: Option<String>,
+    window_class: Option<StringMatch>,
+    window_title: Option<StringMatch>,
+}
+
+impl ProcessQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // BUILDER METHODS -----------------------------------------------------------------------------
+    pub fn with_pid(&mut self, pid: u32) -> &mut Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Executable file name, ignoring case (e.g. "ac_client.exe")
+    pub fn with_name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Full executable path (ignoring case on Windows)
+    pub fn with_exe_path(&mut self, path: impl Into<String>) -> &mut Self {
+        self.exe_path = Some(path.into());
+        self
+    }
+
+    /// Substring of the space-joined command line
+    pub fn with_cmdline(&mut self, substring: impl Into<String>) -> &mut Self {
+        self.cmdline = Some(substring.into());
+        self
+    }
+
+    /// Class of a top-level window owned by the process
+    pub fn with_window_class(&mut self, class_name: impl Into<StringMatch>) -> &mut Self {
+        self.window_class = Some(class_name.into());
+        self
+    }
+
+    /// Title of a top-level window owned by the process (the same window as the class, if both are set)
+    pub fn with_window_title(&mut self, title: impl Into<StringMatch>) -> &mut Self {
+        self.window_title = Some(title.into());
+        self
+    }
+
+    fn has_window_criteria(&self) -> bool {
+        self.window_class.is_some() || self.window_title.is_some()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pid.is_none()
+            && self.name.is_none()
+            && self.exe_path.is_none()
+            && self.cmdline.is_none()
+            && !self.has_window_criteria()
+    }
+
+    /// Whether a process satisfies every set criterion
+    pub fn matches(&self, process: &ProcessMatch) -> bool {
+        self.pid.is_none_or(|pid| process.pid == pid)
+            && self.name.as_ref().is_none_or(|name| process.name.eq_ignore_ascii_case(name))
+            && self.exe_path.as_ref().is_none_or(|path| paths_equal(&process.exe_path, path))
+            && self.cmdline.as_ref().is_none_or(|substring| process.cmdline_string().contains(substring.as_str()))
+            && (!self.has_window_criteria() || process.windows.iter().any(|window| {
+                self.window_class.as_ref().is_none_or(|class_name| class_name.matches(&window.class_name))
+                    && self.window_title.as_ref().is_none_or(|title| title.matches(&window.title))
+            }))
+    }
+
+    /// Every running process matching the query, ordered by PID
+    pub fn find(&self) -> Result<Vec<ProcessMatch>, ProcessError> {
+        if self.is_empty() {
+            return Err(ProcessError::EmptyQuery);
+        }
+
+        let mut processes = snapshot(self.pid)?;
+
+        if self.has_window_criteria() {
+            let windows = match default_source().and_then(|source| source.query_windows()) {
+                Ok(windows) => windows,
+                Err(WindowError::UnsupportedPlatform) => {
+                    log::warn!("Window enumeration is not supported on this platform, no process will match window criteria");
+                    Vec::new()
+                }
+                Err(_) => return Err(ProcessError::FailedToEnumerateWindows),
+            };
+            attach_windows(&mut processes, windows);
+        }
+
+        let mut matches: Vec<ProcessMatch> = processes.into_iter()
+            .filter(|process| self.matches(process))
+            .collect();
+        matches.sort_by_key(|process| process.pid);
+
+        Ok(matches)
+    }
+
+    /// The single process matching the query
+    pub fn find_one(&self) -> Result<ProcessMatch, ProcessError> {
+        let mut matches = self.find()?;
+        match matches.len() {
+            0 => Err(ProcessError::NotFound),
+            1 => Ok(matches.remove(0)),
+            _ => Err(ProcessError::AmbiguousMatch(matches.iter().map(|process| process.pid).collect())),
+        }
+    }
+}
+
+/// Hands every window to the process that owns it
+fn attach_windows(processes: &mut [ProcessMatch], windows: Vec<WindowInfo>) {
+    for window in windows {
+        if let Some(process) = processes.iter_mut().find(|process| process.pid == window.pid) {
+            process.windows.push(window);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn paths_equal(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+#[cfg(not(windows))]
+fn paths_equal(a: &str, b: &str) -> bool {
+    a == b
+}
+
+fn file_name(path: &str) -> Option<String> {
+    Path::new(path).file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+/// Reads every process (or just `only_pid`) out of /proc
+#[cfg(target_os = "linux")]
+fn snapshot(only_pid: Option<u32>) -> Result<Vec<ProcessMatch>, ProcessError> {
+    if let Some(pid) = only_pid {
+        return Ok(read_proc_entry(pid).into_iter().collect());
+    }
+
+    let entries = fs::read_dir("/proc").map_err(|_| ProcessError::FailedToReadProcfs)?;
+
+    Ok(entries.flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(read_proc_entry)
+        .collect())
+}
+
+/// None if the process exited while we were looking at it
+#[cfg(target_os = "linux")]
+fn read_proc_entry(pid: u32) -> Option<ProcessMatch> {
+    let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+
+    let exe_path = fs::read_link(format!("/proc/{}/exe", pid))
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let cmdline: Vec<String> = fs::read(format!("/proc/{}/cmdline", pid))
+        .unwrap_or_default()
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect();
+
+    // comm is cut off at 15 characters, prefer the executable's real name
+    let name = file_name(&exe_path)
+        .or_else(|| cmdline.first().and_then(|arg0| file_name(arg0)))
+        .unwrap_or_else(|| comm.trim_end().to_string());
+
+    Some(ProcessMatch { pid, name, exe_path, cmdline, windows: Vec::new() })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn snapshot(only_pid: Option<u32>) -> Result<Vec<ProcessMatch>, ProcessError> {
+    use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+    let pids = only_pid.map(Pid::from_u32);
+    let to_update = match &pids {
+        Some(pid) => ProcessesToUpdate::Some(std::slice::from_ref(pid)),
+        None => ProcessesToUpdate::All,
+    };
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        to_update,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_exe(UpdateKind::Always)
+            .with_cmd(UpdateKind::Always),
+    );
+
+    Ok(system.processes()
+        .iter()
+        .map(|(pid, process)| {
+            let exe_path = process.exe()
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            ProcessMatch {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+                exe_path,
+                cmdline: process.cmd().iter().map(|arg| arg.to_string_lossy().to_string()).collect(),
+                windows: Vec::new(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, exe_path: &str, cmdline: &[&str]) -> ProcessMatch {
+        ProcessMatch {
+            pid,
+            name: file_name(exe_path).unwrap_or_default(),
+            exe_path: exe_path.to_string(),
+            cmdline: cmdline.iter().map(|arg| arg.to_string()).collect(),
+            windows: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn matches_every_set_criterion() {
+        let mut client = process(4242, "/opt/assaultcube/bin_unix/linux_64_client", &["linux_64_client", "--home=/home/player/.ac"]);
+        client.windows.push(WindowInfo { pid: 4242, title: "AssaultCube".into(), ..Default::default() });
+        let server = process(4243, "/opt/assaultcube/bin_unix/linux_64_server", &["linux_64_server", "-c12"]);
+
+        assert!(ProcessQuery::new().with_name("LINUX_64_CLIENT").matches(&client));
+        assert!(!ProcessQuery::new().with_name("linux_64_client").matches(&server));
+        assert!(ProcessQuery::new().with_exe_path("/opt/assaultcube/bin_unix/linux_64_server").matches(&server));
+        assert!(ProcessQuery::new().with_cmdline("--home=/home/player").matches(&client));
+        assert!(ProcessQuery::new().with_window_title("AssaultCube").matches(&client));
+        assert!(!ProcessQuery::new().with_window_title("AssaultCube").matches(&server));
+        assert!(!ProcessQuery::new().with_window_class("SDL_app").with_window_title("AssaultCube").matches(&client));
+        assert!(!ProcessQuery::new().with_pid(4242).with_cmdline("-c12").matches(&client));
+    }
+
+    #[test]
+    fn empty_query_is_an_error() {
+        assert!(matches!(ProcessQuery::new().find(), Err(ProcessError::EmptyQuery)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_spawned_children_through_procfs() {
+        use std::process::{Command, Stdio};
+
+        // An argument no other process will have
+        let marker = format!("{}.{}", 40 + std::process::id() % 10, std::process::id());
+        let mut children: Vec<_> = (0..2)
+            .map(|_| Command::new("sleep").arg(&marker).stdout(Stdio::null()).spawn().unwrap())
+            .collect();
+
+        let by_cmdline = ProcessQuery::new().with_cmdline(marker.as_str()).find().unwrap();
+        let by_pid = ProcessQuery::new().with_pid(children[0].id()).find_one().unwrap();
+        let ambiguous = ProcessQuery::new().with_cmdline(marker.as_str()).find_one();
+        let by_path = ProcessQuery::new()
+            .with_exe_path(by_pid.exe_path.as_str())
+            .with_cmdline(marker.as_str())
+            .find()
+            .unwrap();
+
+        for child in &mut children {
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+
+        let mut expected: Vec<u32> = children.iter().map(|child| child.id()).collect();
+        expected.sort();
+        assert_eq!(by_cmdline.iter().map(|process| process.pid).collect::<Vec<_>>(), expected);
+        assert_eq!(by_pid.cmdline, ["sleep", marker.as_str()]);
+        assert!(matches!(ambiguous, Err(ProcessError::AmbiguousMatch(pids)) if pids == expected));
+        assert_eq!(by_path.len(), 2);
+
+        assert!(matches!(ProcessQuery::new().with_cmdline(marker.as_str()).find_one(), Err(ProcessError::NotFound)));
+    }
+}
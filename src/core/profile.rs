@@ -0,0 +1,296 @@
+// Per-game target definitions.
+//
+// A profile names the executables of one game, how to recognise its main window, the modules it is
+// expected to load, and the overlay rules and allowlist entries that only make sense for that game.
+// Loaded modules other than the game's own executable that are neither expected nor in a trusted
+// directory are reported.
+
+use std::{
+    fs,
+    path::Path,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    allowlist::{Allowlist, AllowlistEntry},
+    overlay::{
+        match_criteria::StringMatch,
+        overlay_rule::{OverlayRule, OverlayRuleSet},
+    },
+    process::{
+        module::ModuleInfo,
+        process_query::{ProcessMatch, ProcessQuery},
+        Process,
+        ProcessError,
+    },
+};
+
+// (id, contents) of the profiles shipped with the tool
+const BUILTIN_PROFILES: &[(&str, &str)] = &[
+    ("assaultcube", include_str!("../../config/profiles/assaultcube.toml")),
+    ("cs2", include_str!("../../config/profiles/cs2.toml")),
+    ("minecraft", include_str!("../../config/profiles/minecraft.toml")),
+];
+
+/// Class and title the game's main window is expected to have
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MainWindow {
+    pub class: StringMatch,
+    pub title: StringMatch,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameProfile {
+    pub name: String,
+    pub executables: Vec<String>,       // File names, any of them identifies the game
+    pub main_window: MainWindow,
+    pub expected_modules: Vec<String>,  // Modules the game ships: file names next to its executable, or full paths
+    pub trusted_module_dirs: Vec<String>, // Modules loaded from below these directories are expected too
+    pub jit_code: bool,                 // The game generates code at runtime, skip the injected code scan
+    pub verify_modules: Vec<String>,    // Modules whose code is compared against disk, the main executable if empty
+    #[serde(rename = "rule")]
+    pub rules: Vec<OverlayRule>,
+    #[serde(rename = "allow")]
+    pub allowlist: Vec<AllowlistEntry>,
+}
+
+impl MainWindow {
+    pub fn is_empty(&self) -> bool {
+        self.class.is_empty() && self.title.is_empty()
+    }
+}
+
+impl GameProfile {
+    /// A shipped profile by its id (the file name in config/profiles without extension)
+    pub fn builtin(id: &str) -> Option<Self> {
+        BUILTIN_PROFILES.iter()
+            .find(|(builtin_id, _)| builtin_id.eq_ignore_ascii_case(id))
+            .map(|(id, contents)| Self::from_toml(contents)
+                .unwrap_or_else(|err| panic!("Built-in profile {} is invalid: {}", id, err)))
+    }
+
+    /// Ids of the shipped profiles
+    pub fn builtin_ids() -> impl Iterator<Item = &'static str> {
+        BUILTIN_PROFILES.iter().map(|(id, _)| *id)
+    }
+
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        let profile: Self = toml::from_str(contents)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Rejects allowlist entries that anyone could match. They end up in the global allowlist, so
+    /// they are held to the same rules as the allowlist files.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.allowlist().validate()
+            .with_context(|| format!("Invalid game profile {}", self.name))
+    }
+
+    /// Loads a profile from a .toml or .json file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read game profile from {}", path.display()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let profile: Self = serde_json::from_str(&contents)?;
+                profile.validate()?;
+                Ok(profile)
+            }
+            Some("toml") => Self::from_toml(&contents),
+            _ => anyhow::bail!("Unsupported game profile format: {}", path.display()),
+        }
+    }
+
+    pub fn rule_set(&self) -> OverlayRuleSet {
+        OverlayRuleSet::new(self.rules.clone())
+    }
+
+    pub fn allowlist(&self) -> Allowlist {
+        Allowlist::new(self.allowlist.clone())
+    }
+
+    /// Whether the profile says anything about loaded modules at all
+    pub fn checks_modules(&self) -> bool {
+        !self.expected_modules.is_empty() || !self.trusted_module_dirs.is_empty()
+    }
+
+    /// Whether the game loads this module by itself: it is the game's executable, comes from a
+    /// trusted directory, is expected by full path, or is expected by file name and lies below the
+    /// executable's directory (comparisons ignore case). A file name alone says nothing, anyone can
+    /// drop an opengl32.dll into Downloads.
+    pub fn is_expected_module(&self, module: &ModuleInfo, main_module: Option<&ModuleInfo>) -> bool {
+        let game_dir = main_module
+            .and_then(|main| main.path.rfind(['\\', '/']).map(|end| &main.path[..end]))
+            .filter(|dir| !dir.is_empty());
+
+        main_module.is_some_and(|main| main.path.eq_ignore_ascii_case(&module.path))
+            || self.trusted_module_dirs.iter().any(|dir| is_below_dir(&module.path, dir))
+            || self.expected_modules.iter().any(|expected| {
+                if expected.contains(['\\', '/']) {
+                    expected.eq_ignore_ascii_case(&module.path)
+                }
+                else {
+                    expected.eq_ignore_ascii_case(&module.name)
+                        && game_dir.is_some_and(|dir| is_below_dir(&module.path, dir))
+                }
+            })
+    }
+
+    /// Modules the profile doesn't expect, given the game's own executable
+    pub fn unexpected_modules(&self, modules: &[ModuleInfo], main_module: Option<&ModuleInfo>) -> Vec<ModuleInfo> {
+        modules.iter()
+            .filter(|module| !self.is_expected_module(module, main_module))
+            .cloned()
+            .collect()
+    }
+
+    fn query(&self, executable: &str, with_main_window: bool) -> ProcessQuery {
+        let mut query = ProcessQuery::new();
+        query.with_name(executable);
+
+        if with_main_window {
+            if !self.main_window.class.is_empty() {
+                query.with_window_class(self.main_window.class.clone());
+            }
+            if !self.main_window.title.is_empty() {
+                query.with_window_title(self.main_window.title.clone());
+            }
+        }
+
+        query
+    }
+
+    fn find(&self, with_main_window: bool) -> Result<Vec<ProcessMatch>, ProcessError> {
+        if self.executables.is_empty() {
+            return Err(ProcessError::EmptyQuery);
+        }
+
+        let mut matches = Vec::new();
+        for executable in &self.executables {
+            matches.extend(self.query(executable, with_main_window).find()?);
+        }
+        matches.sort_by_key(|process| process.pid);
+        matches.dedup_by_key(|process| process.pid);
+
+        Ok(matches)
+    }
+
+    /// Every running process with one of the profile's executable names, ordered by PID
+    pub fn find_processes(&self) -> Result<Vec<ProcessMatch>, ProcessError> {
+        self.find(false)
+    }
+
+    /// The running game. When several processes share an executable name (e.g. javaw.exe), only
+    /// the one owning the expected main window is picked.
+    pub fn find_process(&self) -> Result<ProcessMatch, ProcessError> {
+        let mut matches = self.find_processes()?;
+
+        if matches.len() > 1 && !self.main_window.is_empty() {
+            matches = self.find(true)?;
+        }
+
+        match matches.len() {
+            0 => Err(ProcessError::NotFound),
+            1 => Ok(matches.remove(0)),
+            _ => Err(ProcessError::AmbiguousMatch(matches.iter().map(|process| process.pid).collect())),
+        }
+    }
+
+    pub fn open_process(&self) -> Result<Process, ProcessError> {
+        Process::open(&self.find_process()?)
+    }
+}
+
+fn is_below_dir(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches(['\\', '/']);
+    path.get(..dir.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(dir))
+        && path[dir.len()..].starts_with(['\\', '/'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::overlay::{
+        criteria_expr::MatchContext,
+        window_info::{Point, Rect, ScreenMetrics, WindowInfo},
+    };
+
+    #[test]
+    fn builtin_profiles_parse() {
+        for id in GameProfile::builtin_ids() {
+            let profile = GameProfile::builtin(id).unwrap();
+            assert!(!profile.name.is_empty(), "{} has no name", id);
+            assert!(!profile.executables.is_empty(), "{} has no executables", id);
+        }
+
+        assert!(GameProfile::builtin("AssaultCube").is_some());
+        assert!(GameProfile::builtin("unknown game").is_none());
+    }
+
+    #[test]
+    fn rejects_allow_entries_anyone_can_match() {
+        let by_name = "name = \"Game\"\n[[allow]]\nname = \"Overlay\"\nreason = \"\"\nimage_path = \"overlay.exe\"\n";
+        let err = GameProfile::from_toml(by_name).unwrap_err();
+        assert!(format!("{:#}", err).contains("image_path overlay.exe"));
+
+        let by_title = "name = \"Game\"\n[[allow]]\nname = \"Launcher\"\nreason = \"\"\nwindow_title = \"Launcher\"\n";
+        let err = GameProfile::from_toml(by_title).unwrap_err();
+        assert!(format!("{:#}", err).contains("need an owner_image_path"));
+    }
+
+    #[test]
+    fn profile_rules_and_modules() {
+        let profile = GameProfile::builtin("assaultcube").unwrap();
+        let modules = [
+            ModuleInfo::new(r"C:\Games\AssaultCube\bin_win32\ac_client.exe", 0x400000, 0x1000),
+            ModuleInfo::new(r"C:\Games\AssaultCube\bin_win32\OPENGL32.DLL", 0x10000000, 0x1000),
+            ModuleInfo::new(r"C:\Windows\SysWOW64\winmm.dll", 0x20000000, 0x1000),
+            ModuleInfo::new(r"C:\Windows\SysWOW64Evil\winmm.dll", 0x30000000, 0x1000),
+            ModuleInfo::new(r"C:\Users\player\Downloads\cheat.dll", 0x40000000, 0x1000),
+            ModuleInfo::new(r"C:\Users\player\Downloads\opengl32.dll", 0x50000000, 0x1000),
+        ];
+        let unexpected = profile.unexpected_modules(&modules, Some(&modules[0]));
+        let paths: Vec<&str> = unexpected.iter().map(|module| module.path.as_str()).collect();
+        assert_eq!(paths, [
+            r"C:\Windows\SysWOW64Evil\winmm.dll",
+            r"C:\Users\player\Downloads\cheat.dll",
+            r"C:\Users\player\Downloads\opengl32.dll",
+        ]);
+
+        // Without knowing the game's executable, file names match nothing
+        assert_eq!(profile.unexpected_modules(&modules, None).len(), 5);
+
+        // The Linux client, whatever its name, is the game itself
+        let linux = [
+            ModuleInfo::new("/home/player/AssaultCube/bin_unix/linux_64_client", 0x55000000, 0x1000),
+            ModuleInfo::new("/usr/lib/x86_64-linux-gnu/libSDL2-2.0.so.0", 0x7f000000, 0x1000),
+        ];
+        assert!(profile.unexpected_modules(&linux, Some(&linux[0])).is_empty());
+        assert!(profile.main_window.title.matches("AssaultCube"));
+
+        let esp = WindowInfo {
+            pid: 1001,
+            style_ex: 0x00080028,
+            position: Rect { left: 0, top: 0, right: 1920, bottom: 1080 },
+            size: Point { x: 1920, y: 1080 },
+            ..Default::default()
+        };
+        let ctx = MatchContext {
+            metrics: ScreenMetrics {
+                screen: Point { x: 1920, y: 1080 },
+                desktop: Point { x: 1920, y: 1080 },
+            },
+            target_pid: Some(4242),
+        };
+
+        let matched = profile.rule_set().evaluate(&esp, &ctx);
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].severity, 35);
+        assert!(profile.rule_set().evaluate(&esp, &MatchContext { target_pid: Some(1001), ..ctx }).is_empty());
+    }
+}
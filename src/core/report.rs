@@ -0,0 +1,235 @@
+// Structured, serializable result of an anticheat scan
+
+use std::fmt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+use crate::core::{
+    allowlist::Allowed,
+    handle::handle_context::HandleContext,
+    overlay::window_info::WindowInfo,
+    forensics::execution_evidence::ExecutionEvidence,
+    history::report_diff::ReportDiff,
+    process::{
+        injection::InjectedRegion,
+        integrity::PatchedRange,
+        module::ModuleInfo,
+    },
+    scoring::Assessment,
+    watch::watch_event::WatchSummary,
+};
+
+/// Machine the scan ran on
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HostInfo {
+    pub hostname: String,
+    pub os_name: String,
+    pub os_version: String,
+    pub kernel_version: String,
+    pub arch: String,
+}
+
+impl HostInfo {
+    /// Collects host metadata of the current machine
+    pub fn collect() -> Self {
+        Self {
+            hostname: System::host_name().unwrap_or_default(),
+            os_name: System::name().unwrap_or_default(),
+            os_version: System::os_version().unwrap_or_default(),
+            kernel_version: System::kernel_version().unwrap_or_default(),
+            arch: System::cpu_arch(),
+        }
+    }
+}
+
+/// Process that was scanned
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TargetInfo {
+    pub name: String,
+    pub pid: u32,
+    #[serde(default)]
+    pub profile: Option<String>, // Name of the game profile the scan used
+}
+
+/// Findings that matched the allowlist, kept out of the verdict
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AllowedFindings {
+    pub handles: Vec<Allowed<HandleContext>>,
+    pub overlays: Vec<Allowed<WindowInfo>>,
+    #[serde(default)]
+    pub modules: Vec<Allowed<ModuleInfo>>,
+    #[serde(default)]
+    pub memory: Vec<Allowed<InjectedRegion>>,
+}
+
+impl AllowedFindings {
+    pub fn len(&self) -> usize {
+        self.handles.len() + self.overlays.len() + self.modules.len() + self.memory.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub tool_version: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub host: HostInfo,
+    pub target: TargetInfo,
+    pub handles: Vec<HandleContext>,
+    pub overlays: Vec<WindowInfo>,
+    #[serde(default)]
+    pub modules: Vec<ModuleInfo>, // Loaded modules the game profile doesn't expect
+    #[serde(default)]
+    pub injected: Vec<InjectedRegion>, // Executable memory outside of any module, or writable code
+    #[serde(default)]
+    pub patched: Vec<PatchedRange>,    // Code of the game's modules that differs from disk
+    #[serde(default)]
+    pub allowed: AllowedFindings,
+    pub assessment: Assessment,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<ReportDiff>, // Compared to the previous screenshare of this host and target
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub first_screenshare: bool,     // Recorded in the history, which had no earlier one of this host and target
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub executions: Vec<ExecutionEvidence>, // Programs that ran shortly before the scan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch: Option<WatchSummary>, // Set when the report ends a watch, it is then its worst scan
+}
+
+impl ScanReport {
+    pub fn has_detections(&self) -> bool {
+        !self.handles.is_empty()
+            || !self.overlays.is_empty()
+            || !self.modules.is_empty()
+            || !self.injected.is_empty()
+            || !self.patched.is_empty()
+    }
+
+    /// Pretty printed JSON, ready to be uploaded or written to disk
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// File name the report is stored under, unique per target and start time
+    pub fn file_name(&self) -> String {
+        let target: String = self.target.name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+
+        format!("scan_results_{}_{}.json", target, self.started_at.format("%Y%m%dT%H%M%SZ"))
+    }
+
+    /// Report without findings, for tests
+    #[cfg(test)]
+    pub(crate) fn sample(started_at: DateTime<Utc>) -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at,
+            finished_at: started_at,
+            host: HostInfo { hostname: "PLAYER-PC".into(), os_name: "Windows".into(), ..Default::default() },
+            target: TargetInfo { name: "ac_client.exe".into(), pid: 4242, profile: Some("AssaultCube".into()) },
+            handles: Vec::new(),
+            overlays: Vec::new(),
+            modules: Vec::new(),
+            injected: Vec::new(),
+            patched: Vec::new(),
+            allowed: AllowedFindings::default(),
+            assessment: Assessment { verdict: crate::core::scoring::Verdict::Clean, total_score: 0, findings: Vec::new() },
+            changes: None,
+            first_screenshare: false,
+            executions: Vec::new(),
+            watch: None,
+        }
+    }
+}
+
+impl fmt::Display for ScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Scan of {} (PID: {}) on {}", self.target.name, self.target.pid, self.host.hostname)?;
+        writeln!(f, "Started: {} | Finished: {}", self.started_at.to_rfc3339(), self.finished_at.to_rfc3339())?;
+        writeln!(f, "Verdict: {} (score {})", self.assessment.verdict, self.assessment.total_score)?;
+        if let Some(watch) = &self.watch {
+            writeln!(f, "Worst of {} scans, {} changes seen while watching:", watch.scans, watch.events.len())?;
+            for event in &watch.events {
+                writeln!(f, "  {}", event)?;
+            }
+        }
+
+        for finding in self.assessment.findings.iter().filter(|finding| finding.score > 0) {
+            writeln!(f, "  [{}] {}: {}", finding.score, finding.subject, finding.reasons.join(", "))?;
+        }
+
+        if !self.has_detections() {
+            writeln!(f, "No suspicious handles, overlays, modules, memory or patched code detected.")?;
+        }
+        else {
+            writeln!(f, "{} suspicious handles found.", self.handles.len())?;
+            for handle in &self.handles {
+                writeln!(f, "{}", handle)?;
+            }
+
+            writeln!(f, "{} suspicious overlays found.", self.overlays.len())?;
+            for overlay in &self.overlays {
+                writeln!(f, "{}", overlay)?;
+            }
+
+            writeln!(f, "{} unexpected modules found.", self.modules.len())?;
+            for module in &self.modules {
+                writeln!(f, "  {}", module)?;
+            }
+
+            writeln!(f, "{} injected code regions found.", self.injected.len())?;
+            for region in &self.injected {
+                writeln!(f, "  {}", region)?;
+            }
+
+            writeln!(f, "{} patched code ranges found.", self.patched.len())?;
+            for range in &self.patched {
+                writeln!(f, "  {}", range)?;
+            }
+        }
+
+        if !self.allowed.is_empty() {
+            writeln!(f, "{} allowed findings:", self.allowed.len())?;
+            for allowed in &self.allowed.handles {
+                writeln!(f, "  Handle from PID {} allowed as {} ({})", allowed.finding.process_id(), allowed.entry, allowed.reason)?;
+            }
+            for allowed in &self.allowed.overlays {
+                writeln!(f, "  Overlay \"{}\" allowed as {} ({})", allowed.finding.title, allowed.entry, allowed.reason)?;
+            }
+            for allowed in &self.allowed.modules {
+                writeln!(f, "  Module {} allowed as {} ({})", allowed.finding.name, allowed.entry, allowed.reason)?;
+            }
+            for allowed in &self.allowed.memory {
+                writeln!(f, "  Executable memory at 0x{:X} allowed as {} ({})", allowed.finding.start, allowed.entry, allowed.reason)?;
+            }
+        }
+
+        if !self.executions.is_empty() {
+            let closed = self.executions.iter().filter(|evidence| !evidence.running).count();
+            // Running programs are in the JSON report, the ones that were closed are the interesting part
+            writeln!(f, "{} recently executed programs, {} no longer running:", self.executions.len(), closed)?;
+            for evidence in self.executions.iter().filter(|evidence| !evidence.running) {
+                writeln!(f, "  {}", evidence)?;
+            }
+        }
+
+        if let Some(changes) = &self.changes {
+            write!(f, "{}", changes)?;
+        }
+        else if self.first_screenshare {
+            // The history lives on the player's machine, an emptied one looks exactly like this
+            writeln!(f, "No previous screenshare of {} on {} in the scan history.", self.target.name, self.host.hostname)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,427 @@
+// Turns raw findings into weighted scores and an overall verdict.
+//
+// Not every suspicious handle, overlay or module is malicious, so each finding adds up a score from the
+// traits that make it suspicious and the total is compared against configurable thresholds.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    handle::{
+        handle_context::HandleContext,
+        PROCESS_ALL_ACCESS,
+        PROCESS_CREATE_THREAD,
+        PROCESS_VM_READ,
+        PROCESS_VM_WRITE,
+    },
+    overlay::{
+        window_info::WindowInfo,
+        WS_EX_LAYERED,
+        WS_EX_TRANSPARENT,
+    },
+    process::{
+        injection::InjectedRegion,
+        integrity::PatchedRange,
+        module::ModuleInfo,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Clean,
+    Suspicious,
+    LikelyCheating,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Clean => write!(f, "clean"),
+            Verdict::Suspicious => write!(f, "suspicious"),
+            Verdict::LikelyCheating => write!(f, "likely cheating"),
+        }
+    }
+}
+
+/// Total scores at which a scan stops being clean
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub suspicious: u32,
+    pub likely_cheating: u32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            suspicious: 30,
+            likely_cheating: 80,
+        }
+    }
+}
+
+/// Weights added to a finding's score for each suspicious trait
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringConfig {
+    pub all_access: u32,          // PROCESS_ALL_ACCESS
+    pub vm_write: u32,            // PROCESS_VM_WRITE
+    pub vm_read: u32,             // PROCESS_VM_READ
+    pub create_thread: u32,       // PROCESS_CREATE_THREAD
+    pub unsigned_image: u32,      // Owning executable has no valid signature
+    pub unknown_image: u32,       // Owning executable could not be resolved
+    pub overlay: u32,             // Any window that matched the overlay criteria
+    pub transparent_overlay: u32, // WS_EX_LAYERED | WS_EX_TRANSPARENT (click-through)
+    pub topmost_overlay: u32,     // WS_EX_TOPMOST
+    pub capture_excluded_overlay: u32, // Hidden from screen capture through its display affinity
+    pub unexpected_module: u32,   // Loaded module the game profile doesn't expect
+    pub deleted_module: u32,      // Module whose file is gone (deleted after loading, memfd)
+    pub unbacked_code: u32,       // Executable memory that isn't part of any module
+    pub writable_code: u32,       // Memory that is writable and executable at once
+    pub max_memory_score: u32,    // Cap on the sum over all executable memory findings
+    pub patched_code: u32,        // Code of the game's modules that differs from the file on disk
+    pub thresholds: Thresholds,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            all_access: 60,
+            vm_write: 35,
+            vm_read: 15,
+            create_thread: 25,
+            unsigned_image: 25,
+            unknown_image: 15,
+            overlay: 10,
+            transparent_overlay: 25,
+            topmost_overlay: 15,
+            capture_excluded_overlay: 40,
+            unexpected_module: 30,
+            deleted_module: 30,
+            unbacked_code: 40,
+            writable_code: 30,
+            max_memory_score: 70,
+            patched_code: 35,
+            thresholds: Thresholds::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    Handle,
+    Overlay,
+    Module,
+    Memory,
+    PatchedCode,
+}
+
+/// A single finding with the reasons that contributed to its score
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoredFinding {
+    pub kind: FindingKind,
+    pub subject: String,
+    pub score: u32,
+    pub reasons: Vec<String>,
+}
+
+impl ScoredFinding {
+    fn new(kind: FindingKind, subject: String) -> Self {
+        Self { kind, subject, score: 0, reasons: Vec::new() }
+    }
+
+    fn add(&mut self, weight: u32, reason: &str) {
+        if weight == 0 {
+            return;
+        }
+        self.score += weight;
+        self.reasons.push(format!("+{} {}", weight, reason));
+    }
+}
+
+/// Verdict of a whole scan
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Assessment {
+    pub verdict: Verdict,
+    pub total_score: u32,
+    pub findings: Vec<ScoredFinding>,
+}
+
+impl ScoringConfig {
+    pub fn score_handle(&self, handle: &HandleContext) -> ScoredFinding {
+        let subject = match handle.paths() {
+            Some((_, win32_path)) if !win32_path.is_empty() => {
+                format!("Handle from PID {} ({})", handle.process_id(), win32_path)
+            }
+            _ => format!("Handle from PID {}", handle.process_id()),
+        };
+        let mut finding = ScoredFinding::new(FindingKind::Handle, subject);
+        let access = handle.granted_access();
+
+        // Full access already implies every specific right, so don't count them twice
+        if access == PROCESS_ALL_ACCESS {
+            finding.add(self.all_access, "PROCESS_ALL_ACCESS");
+        }
+        else {
+            if access & PROCESS_VM_WRITE != 0 {
+                finding.add(self.vm_write, "PROCESS_VM_WRITE");
+            }
+            if access & PROCESS_VM_READ != 0 {
+                finding.add(self.vm_read, "PROCESS_VM_READ");
+            }
+            if access & PROCESS_CREATE_THREAD != 0 {
+                finding.add(self.create_thread, "PROCESS_CREATE_THREAD");
+            }
+        }
+
+        match handle.paths() {
+            Some((_, win32_path)) if !win32_path.is_empty() => {
+                if handle.signed() == Some(false) {
+                    finding.add(self.unsigned_image, "owner is not signed");
+                }
+            }
+            _ => finding.add(self.unknown_image, "owner image could not be resolved"),
+        }
+
+        finding
+    }
+
+    pub fn score_overlay(&self, overlay: &WindowInfo) -> ScoredFinding {
+        let subject = format!("Overlay \"{}\" [{}] from PID {}", overlay.title, overlay.class_name, overlay.pid);
+        let mut finding = ScoredFinding::new(FindingKind::Overlay, subject);
+        let style_ex = overlay.style_ex as u32;
+
+        finding.add(self.overlay, "matched overlay criteria");
+        for rule in &overlay.matched_rules {
+            finding.add(rule.severity, &format!("rule \"{}\"", rule.name));
+        }
+
+        let click_through = WS_EX_LAYERED | WS_EX_TRANSPARENT;
+        if style_ex & click_through == click_through {
+            finding.add(self.transparent_overlay, "layered and click-through");
+        }
+        if overlay.is_topmost() {
+            finding.add(self.topmost_overlay, "always on top");
+        }
+        if overlay.is_capture_excluded() {
+            finding.add(self.capture_excluded_overlay, "excluded from screen capture");
+        }
+
+        finding
+    }
+
+    pub fn score_module(&self, module: &ModuleInfo) -> ScoredFinding {
+        let subject = format!("Module {} ({})", module.name, module.path);
+        let mut finding = ScoredFinding::new(FindingKind::Module, subject);
+
+        finding.add(self.unexpected_module, "not expected by the game profile");
+        if module.is_deleted() || module.file_size.is_none() {
+            finding.add(self.deleted_module, "file is not on disk");
+        }
+
+        finding
+    }
+
+    pub fn score_injected(&self, region: &InjectedRegion) -> ScoredFinding {
+        let subject = format!("Executable memory at 0x{:X} ({} bytes)", region.start, region.size());
+        let mut finding = ScoredFinding::new(FindingKind::Memory, subject);
+
+        if region.unbacked {
+            finding.add(self.unbacked_code, "not backed by a module");
+        }
+        if region.writable {
+            finding.add(self.writable_code, "writable and executable");
+        }
+
+        finding
+    }
+
+    pub fn score_patch(&self, patch: &PatchedRange) -> ScoredFinding {
+        let subject = format!("Patched code in {} at 0x{:X} ({} bytes)", patch.module, patch.address, patch.len);
+        let mut finding = ScoredFinding::new(FindingKind::PatchedCode, subject);
+        finding.add(self.patched_code, "differs from the file on disk");
+        finding
+    }
+
+    pub fn verdict(&self, total_score: u32) -> Verdict {
+        if total_score >= self.thresholds.likely_cheating {
+            Verdict::LikelyCheating
+        }
+        else if total_score >= self.thresholds.suspicious {
+            Verdict::Suspicious
+        }
+        else {
+            Verdict::Clean
+        }
+    }
+
+    /// Limits what executable memory adds up to. Regions come in dozens when something (a JIT, a
+    /// packer) allocates code, and on their own they shouldn't be enough for the top verdict.
+    fn cap_memory(&self, mut findings: Vec<ScoredFinding>) -> Vec<ScoredFinding> {
+        let mut remaining = self.max_memory_score;
+
+        for finding in findings.iter_mut().filter(|finding| finding.kind == FindingKind::Memory) {
+            if finding.score > remaining {
+                finding.reasons.push(format!("-{} executable memory capped at {}", finding.score - remaining, self.max_memory_score));
+                finding.score = remaining;
+            }
+            remaining -= finding.score;
+        }
+
+        findings
+    }
+
+    /// Scores every finding and produces the verdict for the scan
+    pub fn assess(
+        &self,
+        handles: &[HandleContext],
+        overlays: &[WindowInfo],
+        modules: &[ModuleInfo],
+        injected: &[InjectedRegion],
+        patched: &[PatchedRange],
+    ) -> Assessment {
+        let findings: Vec<ScoredFinding> = handles.iter()
+            .map(|handle| self.score_handle(handle))
+            .chain(overlays.iter().map(|overlay| self.score_overlay(overlay)))
+            .chain(modules.iter().map(|module| self.score_module(module)))
+            .chain(injected.iter().map(|region| self.score_injected(region)))
+            .chain(patched.iter().map(|patch| self.score_patch(patch)))
+            .collect();
+        let findings = self.cap_memory(findings);
+
+        let total_score = findings.iter().map(|finding| finding.score).sum();
+
+        Assessment {
+            verdict: self.verdict(total_score),
+            total_score,
+            findings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::handle::{
+        SystemHandleEntry,
+        handle_info::HandleInfo,
+        PROCESS_QUERY_INFORMATION,
+    };
+    use crate::core::overlay::{
+        criteria_expr::MatchContext,
+        overlay_rule::{OverlayRuleSet, RuleMatch},
+        WS_EX_TOPMOST,
+        WS_VISIBLE,
+    };
+
+    fn handle(access: u32, path: &str, signed: Option<bool>) -> HandleContext {
+        let mut info = HandleInfo::from_paths(path, path, access);
+        info.signed = signed;
+
+        HandleContext {
+            raw: SystemHandleEntry { process_id: 1001, granted_access: access, ..Default::default() },
+            info: Some(info),
+        }
+    }
+
+    fn overlay(title: &str, style_ex: u32) -> WindowInfo {
+        WindowInfo {
+            title: title.to_string(),
+            class_name: "Chrome_WidgetWin_1".to_string(),
+            style_ex: style_ex as isize,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unsigned_full_access_outweighs_overlay() {
+        let config = ScoringConfig::default();
+
+        let cheat = config.score_handle(&handle(PROCESS_ALL_ACCESS, r"C:\cheat.exe", Some(false)));
+        // Known overlays are kept out by the allowlist, by owner path. The title alone counts for nothing.
+        let discord = config.score_overlay(&overlay("Discord Overlay", WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST));
+
+        assert_eq!(cheat.score, 85);
+        assert_eq!(discord.score, 50);
+        assert!(cheat.score > discord.score);
+        assert_eq!(config.verdict(cheat.score), Verdict::LikelyCheating);
+    }
+
+    #[test]
+    fn assess_lists_reasons_and_verdict() {
+        let config = ScoringConfig::default();
+        let handles = [handle(PROCESS_QUERY_INFORMATION, r"C:\Windows\explorer.exe", Some(true))];
+        let overlays = [overlay("", WS_EX_LAYERED | WS_EX_TRANSPARENT)];
+
+        let mut injected = ModuleInfo::new("/memfd:payload (deleted)", 0x7f1e2d000000, 0x1000);
+        injected.read_file_metadata();
+
+        let assessment = config.assess(&handles, &overlays, &[injected], &[], &[]);
+        assert_eq!(assessment.findings.len(), 3);
+        assert_eq!(assessment.findings[0].score, 0);
+        assert_eq!(assessment.findings[1].reasons, ["+10 matched overlay criteria", "+25 layered and click-through"]);
+        assert_eq!(assessment.findings[2].reasons, ["+30 not expected by the game profile", "+30 file is not on disk"]);
+        assert_eq!(assessment.total_score, 95);
+        assert_eq!(assessment.verdict, Verdict::LikelyCheating);
+    }
+
+    #[test]
+    fn matched_rules_add_their_severity() {
+        let config = ScoringConfig::default();
+        let mut window = overlay("", 0);
+        window.matched_rules = vec![
+            RuleMatch { name: "fullscreen topmost window".into(), severity: 15, ..Default::default() },
+            RuleMatch { name: "default".into(), severity: 0, ..Default::default() },
+        ];
+
+        let finding = config.score_overlay(&window);
+        assert_eq!(finding.score, 25);
+        assert_eq!(finding.reasons, ["+10 matched overlay criteria", "+15 rule \"fullscreen topmost window\""]);
+    }
+
+    #[test]
+    fn click_through_windows_count_once() {
+        let config = ScoringConfig::default();
+        let mut window = overlay("", WS_EX_LAYERED | WS_EX_TRANSPARENT);
+        window.style = WS_VISIBLE as isize;
+        window.matched_rules = OverlayRuleSet::builtin().evaluate(&window, &MatchContext::default());
+        assert_eq!(window.matched_rules[0].name, "click-through overlay");
+
+        let finding = config.score_overlay(&window);
+        assert_eq!(finding.score, 35);
+        assert_eq!(finding.reasons, ["+10 matched overlay criteria", "+25 layered and click-through"]);
+    }
+
+    #[test]
+    fn executable_memory_alone_is_capped() {
+        let config = ScoringConfig::default();
+        let regions: Vec<InjectedRegion> = (0..10)
+            .map(|index| InjectedRegion {
+                start: 0x10000 * index,
+                end: 0x10000 * index + 0x1000,
+                unbacked: true,
+                ..Default::default()
+            })
+            .collect();
+
+        let assessment = config.assess(&[], &[], &[], &regions, &[]);
+        assert_eq!(assessment.total_score, 70);
+        assert_eq!(assessment.findings[1].score, 30);
+        assert_eq!(assessment.findings[1].reasons, ["+40 not backed by a module", "-10 executable memory capped at 70"]);
+        assert_eq!(assessment.findings[2].score, 0);
+        assert_eq!(assessment.verdict, Verdict::Suspicious);
+    }
+
+    #[test]
+    fn thresholds_are_configurable() {
+        let config = ScoringConfig {
+            thresholds: Thresholds { suspicious: 10, likely_cheating: 20 },
+            ..Default::default()
+        };
+
+        assert_eq!(config.verdict(9), Verdict::Clean);
+        assert_eq!(config.verdict(10), Verdict::Suspicious);
+        assert_eq!(config.verdict(20), Verdict::LikelyCheating);
+    }
+}
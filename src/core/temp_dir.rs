@@ -0,0 +1,38 @@
+// Scratch directory for tests, removed again when the test ends, also when an assertion failed
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Empty directory below the system temp dir. The process id in the name keeps concurrent test
+    /// runs apart, `name` the tests of one run.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("{}_{}", name, process::id()));
+        // Left over from a run that was killed
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("Failed to create temp dir");
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
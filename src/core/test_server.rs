@@ -0,0 +1,155 @@
+// Minimal HTTP/1.1 server for tests, standing in for upload services and webhooks.
+//
+// Every request is recorded, and answered with the next scripted response (the last one repeats).
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StubResponse {
+    pub status: u16,
+    pub body: String,
+    pub delay: Option<Duration>, // Wait this long before answering
+}
+
+pub struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+impl StubResponse {
+    pub fn new(status: u16, body: impl ToString) -> Self {
+        Self { status, body: body.to_string(), delay: None }
+    }
+
+    pub fn ok(body: impl ToString) -> Self {
+        Self::new(200, body)
+    }
+}
+
+impl TestServer {
+    /// Answers every request with 200 and the given body
+    pub fn ok(body: impl ToString) -> Self {
+        Self::start(vec![StubResponse::ok(body)])
+    }
+
+    /// Answers requests with the responses in order, repeating the last one
+    pub fn start(responses: Vec<StubResponse>) -> Self {
+        assert!(!responses.is_empty(), "Test server needs at least one response");
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind test server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let Ok(stream) = stream else { continue };
+                let response = responses[index.min(responses.len() - 1)].clone();
+                let recorded = Arc::clone(&recorded);
+                // Each connection gets its own thread so a delayed answer doesn't hold up retries
+                thread::spawn(move || serve(stream, &response, &recorded));
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn serve(stream: TcpStream, response: &StubResponse, recorded: &Mutex<Vec<RecordedRequest>>) -> Option<()> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    // Recorded before answering, so the client never sees a response to a request the test can't
+    recorded.lock().unwrap().push(read_request(&mut reader)?);
+
+    if let Some(delay) = response.delay {
+        thread::sleep(delay);
+    }
+
+    let mut stream = stream;
+    let reply = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body,
+    );
+    // The client may already have given up (timeouts), the request still counts
+    stream.write_all(reply.as_bytes()).ok()
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<RecordedRequest> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = header.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = RecordedRequest { method, path, headers, body: Vec::new() };
+    if let Some(length) = request.header("content-length").and_then(|length| length.parse::<usize>().ok()) {
+        request.body = vec![0; length];
+        reader.read_exact(&mut request.body).ok()?;
+    }
+    else if request.header("transfer-encoding").is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked")) {
+        request.body = read_chunked(reader)?;
+    }
+
+    Some(request)
+}
+
+fn read_chunked(reader: &mut impl BufRead) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let size = usize::from_str_radix(line.trim().split(';').next()?, 16).ok()?;
+
+        let mut chunk = vec![0; size + 2]; // Chunk data followed by CRLF
+        reader.read_exact(&mut chunk).ok()?;
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+}
@@ -0,0 +1,52 @@
+// Keeps reports in a local directory, e.g. a network share the staff can read
+
+use std::{fs, path::PathBuf};
+use anyhow::Context;
+use crate::core::uploading::report_sink::{ReportSink, SinkFuture};
+
+pub struct DirectorySink {
+    pub dir: PathBuf,
+}
+
+impl DirectorySink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn write(&self, file_name: &str, contents: &[u8]) -> anyhow::Result<String> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create report directory {}", self.dir.display()))?;
+
+        let path = self.dir.join(file_name);
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write report to {}", path.display()))?;
+
+        Ok(path.display().to_string())
+    }
+}
+
+impl ReportSink for DirectorySink {
+    fn describe(&self) -> String {
+        format!("directory {}", self.dir.display())
+    }
+
+    fn upload<'a>(&'a self, file_name: &'a str, contents: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(async move { self.write(file_name, contents) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::temp_dir::TempDir;
+
+    #[tokio::test]
+    async fn writes_report_below_directory() {
+        let dir = TempDir::new("directory_sink");
+        let sink = DirectorySink::new(dir.join("reports"));
+
+        let path = sink.upload("report.json", b"{}").await.unwrap();
+        assert_eq!(PathBuf::from(&path), dir.join("reports").join("report.json"));
+        assert_eq!(fs::read(&path).unwrap(), b"{}");
+    }
+}
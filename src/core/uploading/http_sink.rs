@@ -0,0 +1,129 @@
+// Multipart upload of the report to a file hosting service (tmpfiles.org by default)
+
+use anyhow::Context;
+use reqwest::multipart::{Form, Part};
+use crate::core::{
+    notify::notifier::redact_url,
+    uploading::report_sink::{ReportSink, SinkFuture},
+};
+
+const TMPFILES_URL: &str = "https://tmpfiles.org/api/v1/upload";
+
+pub struct HttpSink {
+    pub url: String,
+    pub field_name: String,          // Multipart field holding the file
+    pub url_pointer: Option<String>, // JSON pointer to the stored file's URL in the response, the raw body if None
+    client: reqwest::Client,
+}
+
+impl HttpSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            field_name: "file".to_string(),
+            url_pointer: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Anonymous upload to tmpfiles.org, files expire after an hour
+    pub fn tmpfiles() -> Self {
+        let mut sink = Self::new(TMPFILES_URL);
+        sink.url_pointer = Some("/data/url".to_string());
+        sink
+    }
+
+    async fn send(&self, file_name: &str, contents: &[u8]) -> anyhow::Result<String> {
+        let part = Part::bytes(contents.to_vec())
+            .file_name(file_name.to_string())
+            .mime_str("application/json")?;
+        let form = Form::new().part(self.field_name.clone(), part);
+
+        let response = self.client
+            .post(&self.url)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| format!("Failed to upload report to {}", redact_url(&self.url)))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Upload to {} failed with status: {}", redact_url(&self.url), response.status());
+        }
+
+        let body = response.text().await.map_err(reqwest::Error::without_url)?;
+        let Some(pointer) = &self.url_pointer else {
+            return Ok(body.trim().to_string());
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&body)
+            .with_context(|| format!("Upload response from {} is not JSON", redact_url(&self.url)))?;
+        json.pointer(pointer)
+            .and_then(|url| url.as_str())
+            .map(str::to_string)
+            .with_context(|| format!("Could not extract {} from upload response", pointer))
+    }
+}
+
+impl ReportSink for HttpSink {
+    fn describe(&self) -> String {
+        format!("HTTP upload to {}", redact_url(&self.url))
+    }
+
+    fn upload<'a>(&'a self, file_name: &'a str, contents: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(self.send(file_name, contents))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_server::{StubResponse, TestServer};
+
+    #[tokio::test]
+    async fn uploads_multipart_and_extracts_url() {
+        let server = TestServer::ok(r#"{"status":"success","data":{"url":"https://files.example/1/report.json"}}"#);
+        let mut sink = HttpSink::new(format!("{}/api/v1/upload", server.url));
+        sink.url_pointer = Some("/data/url".to_string());
+
+        let url = sink.upload("report.json", br#"{"verdict":"Clean"}"#).await.unwrap();
+        assert_eq!(url, "https://files.example/1/report.json");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/api/v1/upload");
+        assert!(requests[0].header("content-type").unwrap().starts_with("multipart/form-data"));
+
+        let body = requests[0].body_text();
+        assert!(body.contains(r#"name="file"; filename="report.json""#));
+        assert!(body.contains(r#"{"verdict":"Clean"}"#));
+    }
+
+    #[tokio::test]
+    async fn returns_raw_body_without_pointer() {
+        let server = TestServer::ok("https://paste.example/abc\n");
+        let url = HttpSink::new(server.url.clone()).upload("report.json", b"{}").await.unwrap();
+        assert_eq!(url, "https://paste.example/abc");
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_status_or_missing_url() {
+        let server = TestServer::start(vec![StubResponse::new(500, "oops")]);
+        assert!(HttpSink::new(server.url.clone()).upload("report.json", b"{}").await.is_err());
+
+        let server = TestServer::ok(r#"{"data":{}}"#);
+        let mut sink = HttpSink::new(server.url.clone());
+        sink.url_pointer = Some("/data/url".to_string());
+        assert!(sink.upload("report.json", b"{}").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn errors_leave_out_the_upload_path() {
+        // Nothing listens on port 1
+        let sink = HttpSink::new("http://127.0.0.1:1/upload/s3cr3t?key=token");
+        let err = format!("{:#}", sink.upload("report.json", b"{}").await.unwrap_err());
+        assert!(err.contains("127.0.0.1:1"), "{}", err);
+        assert!(!err.contains("s3cr3t") && !err.contains("token"), "{}", err);
+    }
+}
@@ -0,0 +1,12 @@
+// Delivery of scan reports to wherever they should be kept.
+//
+// Every destination implements ReportSink and receives the report straight from memory. Deliveries are
+// retried with backoff, and reports that still fail wait in the outbox for the next run.
+
+pub mod report_sink;
+pub mod http_sink;
+pub mod directory_sink;
+pub mod s3_sink;
+pub mod stdout_sink;
+pub mod retry;
+pub mod outbox;
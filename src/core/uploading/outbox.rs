@@ -0,0 +1,188 @@
+// Reports that could not be delivered, kept on disk until a later run manages to send them.
+//
+// The files sit on the player's machine, so each one carries an HMAC over its contents. The key comes
+// from whoever runs the screenshare (SCREENSHARE_OUTBOX_KEY) and is never written to disk, reports
+// that were edited or queued under another key are not replayed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::core::{
+    config::state_dir,
+    crypto::{hex, hmac_sha256},
+    report::ScanReport,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PendingReport {
+    pub file_name: String,
+    pub report: String,               // The report as JSON, exactly as it would have been uploaded
+    pub queued_at: DateTime<Utc>,
+    #[serde(default)]
+    pub uploaded_url: Option<String>, // Set once the upload went through but the webhook didn't
+    #[serde(default)]
+    pub notified: Vec<String>,        // Notifiers that already announced it
+    #[serde(default)]
+    pub attempts: u32,                // Deliveries tried so far, across runs
+    #[serde(default)]
+    pub last_error: String,
+    #[serde(default)]
+    pub mac: String,                  // Hex HMAC-SHA256 over everything else, set when queued
+}
+
+pub struct Outbox {
+    dir: PathBuf,
+    key: Vec<u8>,
+}
+
+impl PendingReport {
+    pub fn new(report: &ScanReport) -> anyhow::Result<Self> {
+        Ok(Self {
+            file_name: report.file_name(),
+            report: report.to_json()?,
+            queued_at: Utc::now(),
+            uploaded_url: None,
+            notified: Vec::new(),
+            attempts: 0,
+            last_error: String::new(),
+            mac: String::new(),
+        })
+    }
+
+    /// HMAC of the serialized report with the mac field left empty
+    fn compute_mac(&self, key: &[u8]) -> anyhow::Result<String> {
+        let unsigned = Self { mac: String::new(), ..self.clone() };
+        Ok(hex(&hmac_sha256(key, serde_json::to_string(&unsigned)?.as_bytes())))
+    }
+
+    pub fn scan_report(&self) -> anyhow::Result<ScanReport> {
+        Ok(ScanReport::from_json(&self.report)?)
+    }
+}
+
+impl Outbox {
+    /// Outbox in `dir` whose reports are signed with `key`
+    pub fn new(dir: impl Into<PathBuf>, key: impl Into<Vec<u8>>) -> Self {
+        Self { dir: dir.into(), key: key.into() }
+    }
+
+    /// The outbox below the per-user state directory, see config::state_dir
+    pub fn default_dir() -> PathBuf {
+        state_dir().join("outbox")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Signs and stores a report for later delivery, replacing an older copy of the same report
+    pub fn push(&self, pending: &mut PendingReport) -> anyhow::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create outbox {}", self.dir.display()))?;
+
+        pending.mac = pending.compute_mac(&self.key)?;
+        let path = self.dir.join(&pending.file_name);
+        fs::write(&path, serde_json::to_string_pretty(pending)?)
+            .with_context(|| format!("Failed to queue report in {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    /// Queued reports, oldest first. Files that can't be read or whose signature doesn't match are
+    /// skipped and left in place.
+    pub fn pending(&self) -> anyhow::Result<Vec<(PathBuf, PendingReport)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut pending = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let parsed = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str::<PendingReport>(&contents)?));
+            match parsed {
+                Ok(report) if self.verify(&report) => pending.push((path, report)),
+                Ok(_) => log::warn!("Skipping queued report {}, it was modified or queued with another key", path.display()),
+                Err(err) => log::warn!("Skipping unreadable queued report {}: {}", path.display(), err),
+            }
+        }
+
+        pending.sort_by_key(|(_, report)| report.queued_at);
+        Ok(pending)
+    }
+
+    fn verify(&self, pending: &PendingReport) -> bool {
+        pending.compute_mac(&self.key).is_ok_and(|mac| !pending.mac.is_empty() && mac == pending.mac)
+    }
+
+    pub fn remove(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::remove_file(path).with_context(|| format!("Failed to remove {} from the outbox", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::temp_dir::TempDir;
+
+    fn pending(file_name: &str, queued_at: DateTime<Utc>) -> PendingReport {
+        PendingReport {
+            file_name: file_name.to_string(),
+            report: "{}".to_string(),
+            queued_at,
+            uploaded_url: None,
+            notified: Vec::new(),
+            attempts: 1,
+            last_error: "offline".to_string(),
+            mac: String::new(),
+        }
+    }
+
+    #[test]
+    fn queues_and_removes_reports_oldest_first() {
+        let dir = TempDir::new("outbox");
+        let outbox = Outbox::new(dir.path(), "key");
+        assert!(outbox.pending().unwrap().is_empty());
+
+        let now = Utc::now();
+        outbox.push(&mut pending("newer.json", now)).unwrap();
+        let older = outbox.push(&mut pending("older.json", now - chrono::Duration::minutes(5))).unwrap();
+        fs::write(dir.join("garbage.json"), "not a report").unwrap();
+
+        let queued = outbox.pending().unwrap();
+        let names: Vec<&str> = queued.iter().map(|(_, report)| report.file_name.as_str()).collect();
+        assert_eq!(names, ["older.json", "newer.json"]);
+        assert_eq!(queued[0].1.last_error, "offline");
+
+        outbox.remove(older).unwrap();
+        assert_eq!(outbox.pending().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn skips_reports_that_were_edited_or_signed_with_another_key() {
+        let dir = TempDir::new("outbox_signed");
+        let outbox = Outbox::new(dir.path(), "key");
+
+        let path = outbox.push(&mut pending("edited.json", Utc::now())).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let edited = contents.replace(r#""report": "{}""#, r#""report": "{\"verdict\": \"clean\"}""#);
+        assert_ne!(edited, contents);
+        fs::write(&path, edited).unwrap();
+        Outbox::new(dir.path(), "other key").push(&mut pending("other_key.json", Utc::now())).unwrap();
+        outbox.push(&mut pending("kept.json", Utc::now())).unwrap();
+
+        let queued = outbox.pending().unwrap();
+        let names: Vec<&str> = queued.iter().map(|(_, report)| report.file_name.as_str()).collect();
+        assert_eq!(names, ["kept.json"]);
+    }
+}
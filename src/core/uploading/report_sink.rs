@@ -0,0 +1,16 @@
+// Abstraction over where a finished report is delivered, so the anticheat doesn't depend on any one
+// upload service.
+
+use std::{future::Future, pin::Pin};
+
+/// Future returned by a sink, resolving to where the report ended up
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = anyhow::Result<String>> + Send + 'a>>;
+
+pub trait ReportSink: Send + Sync {
+    /// Short description used in logs (e.g. "HTTP upload to https://...")
+    fn describe(&self) -> String;
+
+    /// Delivers the report contents under the given file name. Resolves to a URL or path that
+    /// points at the stored report.
+    fn upload<'a>(&'a self, file_name: &'a str, contents: &'a [u8]) -> SinkFuture<'a>;
+}
@@ -0,0 +1,131 @@
+// Timeouts and exponential backoff for report uploads and webhooks
+
+use std::{future::Future, time::Duration};
+use anyhow::Context;
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub timeout: Duration,         // Limit for a single attempt
+    pub max_attempts: u32,         // Including the first one
+    pub initial_backoff: Duration, // Wait after the first failure, doubled after every further one
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            max_attempts: 4,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt, for callers that handle failures themselves
+    pub fn once(timeout: Duration) -> Self {
+        Self { timeout, max_attempts: 1, ..Default::default() }
+    }
+
+    /// How long to wait after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Runs the operation until it succeeds, each attempt limited by the timeout. Returns the error
+    /// of the last attempt once all of them failed.
+    pub async fn run<T, F, Fut>(&self, what: &str, mut operation: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            let result = tokio::time::timeout(self.timeout, operation())
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Timed out after {:?}", self.timeout)));
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt >= max_attempts => {
+                    return Err(err).with_context(|| format!("{} failed after {} attempts", what, attempt));
+                }
+                Err(err) => {
+                    let backoff = self.backoff(attempt);
+                    log::warn!("{} failed (attempt {}/{}), retrying in {:?}: {:#}", what, attempt, max_attempts, backoff, err);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_millis(200),
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_limit() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(4), Duration::from_secs(8));
+        assert_eq!(policy.backoff(10), Duration::from_secs(30));
+        assert_eq!(policy.backoff(100), Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let calls = Cell::new(0);
+        let result = fast_policy(4).run("test", || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move {
+                if call < 3 { anyhow::bail!("failure {}", call) }
+                Ok(call)
+            }
+        }).await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let calls = Cell::new(0);
+        let result: anyhow::Result<()> = fast_policy(3).run("test", || {
+            calls.set(calls.get() + 1);
+            async { anyhow::bail!("always fails") }
+        }).await;
+
+        assert_eq!(calls.get(), 3);
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.contains("test failed after 3 attempts"));
+        assert!(message.contains("always fails"));
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_attempts() {
+        let result = fast_policy(1).run("test", || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }).await;
+
+        assert!(format!("{:#}", result.unwrap_err()).contains("Timed out"));
+    }
+}
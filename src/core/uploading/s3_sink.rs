@@ -0,0 +1,180 @@
+// PUT of the report into an S3-compatible bucket (AWS, MinIO, R2, ...), signed with AWS Signature
+// Version 4 when credentials are set. Buckets are addressed path-style, which every provider supports.
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use crate::core::{
+    crypto::{hex, hmac_sha256},
+    uploading::report_sink::{ReportSink, SinkFuture},
+};
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+#[derive(Clone)]
+pub struct S3Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+pub struct S3Sink {
+    pub endpoint: String,   // e.g. https://s3.eu-central-1.amazonaws.com
+    pub bucket: String,
+    pub region: String,
+    pub prefix: String,     // Prepended to the file name to form the object key
+    pub credentials: Option<S3Credentials>, // Unsigned requests if None (public or presigned buckets)
+    client: reqwest::Client,
+}
+
+impl S3Sink {
+    pub fn new(endpoint: impl Into<String>, bucket: impl Into<String>, region: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            bucket: bucket.into(),
+            region: region.into(),
+            prefix: String::new(),
+            credentials: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Path of the object below the endpoint, with every segment URI encoded
+    fn object_path(&self, file_name: &str) -> String {
+        let key = format!("{}{}", self.prefix, file_name);
+        let encoded: Vec<String> = std::iter::once(self.bucket.as_str())
+            .chain(key.split('/'))
+            .map(uri_encode)
+            .collect();
+
+        format!("/{}", encoded.join("/"))
+    }
+
+    async fn put(&self, file_name: &str, contents: &[u8]) -> anyhow::Result<String> {
+        let object_url = format!("{}{}", self.endpoint.trim_end_matches('/'), self.object_path(file_name));
+        let url = reqwest::Url::parse(&object_url)
+            .with_context(|| format!("Invalid S3 endpoint: {}", self.endpoint))?;
+
+        let payload_hash = hex(&Sha256::digest(contents));
+        let now = Utc::now();
+
+        let mut request = self.client
+            .put(url.clone())
+            .header("content-type", "application/json")
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", amz_date(&now));
+
+        if let Some(credentials) = &self.credentials {
+            request = request.header("authorization", self.authorization(credentials, &url, &payload_hash, &now));
+        }
+
+        let response = request
+            .body(contents.to_vec())
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload report to {}", object_url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("S3 upload to {} failed with status: {}", object_url, response.status());
+        }
+
+        Ok(object_url)
+    }
+
+    /// Authorization header value for a PUT to `url` at `now`
+    fn authorization(&self, credentials: &S3Credentials, url: &reqwest::Url, payload_hash: &str, now: &DateTime<Utc>) -> String {
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let date = now.format("%Y%m%d").to_string();
+        let amz_date = amz_date(now);
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            url.path(), host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash,
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, hex(&Sha256::digest(canonical_request.as_bytes())),
+        );
+
+        let key = [date.as_str(), self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", credentials.secret_key).into_bytes(), |key, part| {
+                hmac_sha256(&key, part.as_bytes()).to_vec()
+            });
+        let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key, scope, SIGNED_HEADERS, signature,
+        )
+    }
+}
+
+impl ReportSink for S3Sink {
+    fn describe(&self) -> String {
+        format!("S3 bucket {} at {}", self.bucket, self.endpoint)
+    }
+
+    fn upload<'a>(&'a self, file_name: &'a str, contents: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(self.put(file_name, contents))
+    }
+}
+
+fn amz_date(now: &DateTime<Utc>) -> String {
+    now.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Encodes everything but the unreserved characters, as SigV4 canonical URIs require
+fn uri_encode(segment: &str) -> String {
+    segment.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_server::{StubResponse, TestServer};
+
+    #[test]
+    fn encodes_object_key() {
+        let mut sink = S3Sink::new("http://localhost:9000", "reports", "us-east-1");
+        sink.prefix = "scans/host 1/".to_string();
+        assert_eq!(sink.object_path("report.json"), "/reports/scans/host%201/report.json");
+    }
+
+    #[tokio::test]
+    async fn puts_signed_object() {
+        let server = TestServer::ok("");
+        let mut sink = S3Sink::new(server.url.clone(), "reports", "eu-central-1");
+        sink.prefix = "scans/".to_string();
+        sink.credentials = Some(S3Credentials { access_key: "AKIDEXAMPLE".into(), secret_key: "secret".into() });
+
+        let url = sink.upload("report.json", b"{}").await.unwrap();
+        assert_eq!(url, format!("{}/reports/scans/report.json", server.url));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/reports/scans/report.json");
+        assert_eq!(requests[0].body, b"{}");
+        assert_eq!(requests[0].header("x-amz-content-sha256").unwrap(), hex(&Sha256::digest(b"{}")));
+
+        let authorization = requests[0].header("authorization").unwrap();
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+        assert!(authorization.contains("/eu-central-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="));
+    }
+
+    #[tokio::test]
+    async fn fails_when_bucket_rejects_upload() {
+        let server = TestServer::start(vec![StubResponse::new(403, "AccessDenied")]);
+        let sink = S3Sink::new(server.url.clone(), "reports", "us-east-1");
+        assert!(sink.upload("report.json", b"{}").await.is_err());
+        assert!(server.requests()[0].header("authorization").is_none());
+    }
+}
@@ -0,0 +1,21 @@
+// Prints the report, for running the tool by hand or piping it somewhere else
+
+use std::io::Write;
+use crate::core::uploading::report_sink::{ReportSink, SinkFuture};
+
+pub struct StdoutSink;
+
+impl ReportSink for StdoutSink {
+    fn describe(&self) -> String {
+        "stdout".to_string()
+    }
+
+    fn upload<'a>(&'a self, _file_name: &'a str, contents: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(contents)?;
+            writeln!(stdout)?;
+            Ok("stdout".to_string())
+        })
+    }
+}
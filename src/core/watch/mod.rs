@@ -0,0 +1,5 @@
+pub mod watch_event;
+pub mod scan_snapshot;
+pub mod watcher;
+#[cfg(windows)]
+pub mod win_event_trigger;
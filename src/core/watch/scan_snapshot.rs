@@ -0,0 +1,123 @@
+// What one scan of a watch found, reduced to what is needed to tell the next scan what is new.
+
+use chrono::{DateTime, Utc};
+use crate::core::{
+    anticheat::Anticheat,
+    handle::handle_context::HandleContext,
+    overlay::window_info::WindowInfo,
+    process::module::ModuleInfo,
+    watch::watch_event::{WatchEvent, WatchEventKind},
+};
+
+#[derive(Clone, Default)]
+pub struct ScanSnapshot {
+    pub handles: Vec<HandleContext>,  // Suspicious handles to the target that aren't allowlisted
+    pub overlays: Vec<WindowInfo>,    // Suspicious overlays that aren't allowlisted
+    pub modules: Vec<ModuleInfo>,     // Every loaded module
+    pub unexpected: Vec<ModuleInfo>,  // Loaded modules the game profile doesn't expect
+}
+
+impl ScanSnapshot {
+    /// Findings of the anticheat's last run
+    pub fn from_anticheat(anticheat: &Anticheat) -> Self {
+        Self {
+            handles: anticheat.handles().clone(),
+            overlays: anticheat.overlays().clone(),
+            modules: anticheat.loaded_modules().clone(),
+            unexpected: anticheat.modules().clone(),
+        }
+    }
+
+    /// Events for everything in this snapshot that the previous one didn't have
+    pub fn changes_since(&self, previous: &ScanSnapshot, timestamp: DateTime<Utc>) -> Vec<WatchEvent> {
+        let handles = self.handles.iter()
+            .filter(|handle| !previous.handles.iter().any(|known| same_handle(known, handle)))
+            .map(|handle| WatchEventKind::HandleOpened {
+                pid: handle.process_id(),
+                image_path: handle.paths()
+                    .map(|(_, win32_path)| win32_path.clone())
+                    .filter(|path| !path.is_empty()),
+                access_rights: handle.access_rights().to_vec(),
+            });
+
+        let overlays = self.overlays.iter()
+            .filter(|overlay| !previous.overlays.iter().any(|known| known.hwnd == overlay.hwnd && known.pid == overlay.pid))
+            .map(|overlay| WatchEventKind::OverlayAppeared {
+                hwnd: overlay.hwnd,
+                pid: overlay.pid,
+                title: overlay.title.clone(),
+                class_name: overlay.class_name.clone(),
+                image_path: overlay.owner_image_path.clone(),
+                rules: overlay.matched_rules.iter().map(|rule| rule.name.clone()).collect(),
+            });
+
+        let modules = self.modules.iter()
+            .filter(|module| !previous.modules.iter().any(|known| same_module(known, module)))
+            .map(|module| WatchEventKind::ModuleLoaded {
+                name: module.name.clone(),
+                path: module.path.clone(),
+                unexpected: self.unexpected.iter().any(|unexpected| same_module(unexpected, module)),
+            });
+
+        handles.chain(overlays)
+            .chain(modules)
+            .map(|kind| WatchEvent::new(timestamp, kind))
+            .collect()
+    }
+}
+
+/// The same handle value in the same process, opened with the same rights
+fn same_handle(a: &HandleContext, b: &HandleContext) -> bool {
+    a.process_id() == b.process_id()
+        && a.raw.handle_value == b.raw.handle_value
+        && a.granted_access() == b.granted_access()
+}
+
+/// Reloading a module at another address counts as a new load
+fn same_module(a: &ModuleInfo, b: &ModuleInfo) -> bool {
+    a.base == b.base && a.path.eq_ignore_ascii_case(&b.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::handle::{handle_info::HandleInfo, SystemHandleEntry, PROCESS_ALL_ACCESS};
+
+    fn handle(pid: u32, handle_value: u32) -> HandleContext {
+        HandleContext {
+            raw: SystemHandleEntry { process_id: pid, handle_value, granted_access: PROCESS_ALL_ACCESS, ..Default::default() },
+            info: Some(HandleInfo::from_paths(r"\Device\HarddiskVolume3\Tools\trainer.exe", r"C:\Tools\trainer.exe", PROCESS_ALL_ACCESS)),
+        }
+    }
+
+    #[test]
+    fn reports_only_new_findings() {
+        let now = Utc::now();
+        let previous = ScanSnapshot {
+            handles: vec![handle(1001, 0x40)],
+            modules: vec![ModuleInfo::new(r"C:\Games\game.exe", 0x400000, 0x1000)],
+            ..Default::default()
+        };
+        let current = ScanSnapshot {
+            handles: vec![handle(1001, 0x40), handle(1002, 0x44)],
+            overlays: vec![WindowInfo { hwnd: 0x1234, pid: 1002, title: "ESP".into(), ..Default::default() }],
+            modules: vec![
+                ModuleInfo::new(r"C:\GAMES\game.exe", 0x400000, 0x1000),
+                ModuleInfo::new(r"C:\Tools\hook.dll", 0x7ff000, 0x2000),
+            ],
+            unexpected: vec![ModuleInfo::new(r"C:\Tools\hook.dll", 0x7ff000, 0x2000)],
+        };
+
+        let events = current.changes_since(&previous, now);
+        let headlines: Vec<String> = events.iter().map(WatchEvent::headline).collect();
+        assert_eq!(headlines, [
+            "New handle opened by trainer.exe (PID 1002)",
+            "Overlay appeared: \"ESP\"",
+            "Unexpected module loaded: hook.dll",
+        ]);
+        assert!(events.iter().all(|event| event.timestamp == now));
+        assert!(events[0].details().contains("PROCESS_VM_WRITE"));
+
+        assert!(current.changes_since(&current, now).is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Something that appeared between two scans of a watch
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatchEventKind {
+    HandleOpened {
+        pid: u32,                   // Process that opened the handle
+        image_path: Option<String>, // Its executable, if it could be resolved
+        access_rights: Vec<String>,
+    },
+    OverlayAppeared {
+        hwnd: usize,
+        pid: u32,
+        title: String,
+        class_name: String,
+        image_path: String,
+        rules: Vec<String>, // Overlay rules the window matched
+    },
+    ModuleLoaded {
+        name: String,
+        path: String,
+        unexpected: bool, // Not one of the modules the game profile expects
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub timestamp: DateTime<Utc>, // When the scan that first saw it finished
+    #[serde(flatten)]
+    pub kind: WatchEventKind,
+}
+
+/// What a watch saw over all of its scans, attached to the report it ends with
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WatchSummary {
+    pub scans: usize,
+    pub events: Vec<WatchEvent>, // Everything that appeared after the first scan, in order
+}
+
+impl WatchEvent {
+    pub fn new(timestamp: DateTime<Utc>, kind: WatchEventKind) -> Self {
+        Self { timestamp, kind }
+    }
+
+    /// One line summary, e.g. "New handle opened by trainer.exe (PID 1002)"
+    pub fn headline(&self) -> String {
+        match &self.kind {
+            WatchEventKind::HandleOpened { pid, image_path, .. } => match image_path {
+                Some(path) => format!("New handle opened by {} (PID {})", file_name(path), pid),
+                None => format!("New handle opened by PID {}", pid),
+            },
+            WatchEventKind::OverlayAppeared { title, class_name, .. } if title.is_empty() => {
+                format!("Overlay appeared (class {})", class_name)
+            }
+            WatchEventKind::OverlayAppeared { title, .. } => format!("Overlay appeared: \"{}\"", title),
+            WatchEventKind::ModuleLoaded { name, unexpected: true, .. } => format!("Unexpected module loaded: {}", name),
+            WatchEventKind::ModuleLoaded { name, .. } => format!("Module loaded: {}", name),
+        }
+    }
+
+    /// What else is known about the change
+    pub fn details(&self) -> String {
+        match &self.kind {
+            WatchEventKind::HandleOpened { access_rights, .. } if access_rights.is_empty() => "Unknown access".to_string(),
+            WatchEventKind::HandleOpened { access_rights, .. } => access_rights.join(", "),
+            WatchEventKind::OverlayAppeared { pid, image_path, rules, .. } => {
+                let owner = match image_path.is_empty() {
+                    true => format!("PID {}", pid),
+                    false => format!("{} (PID {})", file_name(image_path), pid),
+                };
+                format!("Owned by {}, matched {}", owner, rules.join(", "))
+            }
+            WatchEventKind::ModuleLoaded { path, .. } => path.clone(),
+        }
+    }
+}
+
+impl fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.timestamp.format("%Y-%m-%d %H:%M:%S"), self.headline(), self.details())
+    }
+}
+
+/// Last component of a Windows or Unix path
+fn file_name(path: &str) -> &str {
+    path.rsplit(['\\', '/']).next().unwrap_or(path)
+}
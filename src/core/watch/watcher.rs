@@ -0,0 +1,351 @@
+// Watch mode: cheats often attach their handle or overlay only after the screenshare started, so the
+// target is rescanned on an interval (or sooner when an OS event hook asks for it) and whatever
+// appeared since the previous scan goes out to the notifiers. The watch ends with the report of its
+// worst scan, carrying every change seen along the way.
+
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::Notify, time::Instant};
+use chrono::Utc;
+use crate::core::{
+    anticheat::Anticheat,
+    config::WatchConfig,
+    notify::notification::Notification,
+    report::ScanReport,
+    scoring::Verdict,
+    watch::{
+        scan_snapshot::ScanSnapshot,
+        watch_event::{WatchEvent, WatchSummary},
+    },
+};
+
+/// Asks a running watcher to rescan now instead of at the end of the interval
+#[derive(Clone)]
+pub struct WatchTrigger {
+    notify: Arc<Notify>,
+}
+
+pub struct Watcher {
+    pub interval: Duration,
+    pub min_gap: Duration,           // Shortest time between two scans, collapses bursts of triggers
+    pub max_scans: Option<usize>,    // Stop after this many scans, run until stopped if None
+    pub duration: Option<Duration>,  // Stop after this long (with one last scan), run until stopped if None
+    anticheat: Anticheat,
+    previous: Option<ScanSnapshot>,
+    scans: usize,
+    worst: Option<ScanReport>,       // Highest scoring scan, a cheat may be gone again by the last one
+    events: Vec<WatchEvent>,         // Every change so far, notifiers may have missed some
+    trigger: WatchTrigger,
+}
+
+impl WatchTrigger {
+    pub fn rescan_now(&self) {
+        // Stores a permit if the watcher is busy scanning, so the request isn't lost
+        self.notify.notify_one();
+    }
+}
+
+impl Watcher {
+    pub fn new(anticheat: Anticheat) -> Self {
+        let config = WatchConfig::default();
+
+        Self {
+            interval: config.interval(),
+            min_gap: config.min_gap(),
+            max_scans: None,
+            duration: None,
+            anticheat,
+            previous: None,
+            scans: 0,
+            worst: None,
+            events: Vec::new(),
+            trigger: WatchTrigger { notify: Arc::new(Notify::new()) },
+        }
+    }
+
+    /// Takes the timing from the config and hooks OS events if it asks for them
+    pub fn apply_config(&mut self, config: &WatchConfig) -> &mut Self {
+        self.interval = config.interval();
+        self.min_gap = config.min_gap();
+        if config.os_events && !self.listen_for_os_events() {
+            log::debug!("No OS events to watch on this platform, rescanning every {:?}", self.interval);
+        }
+        self
+    }
+
+    pub fn trigger(&self) -> WatchTrigger {
+        self.trigger.clone()
+    }
+
+    /// Rescans whenever a window is shown, where the platform reports that. Returns whether it does.
+    pub fn listen_for_os_events(&self) -> bool {
+        #[cfg(windows)]
+        {
+            crate::core::watch::win_event_trigger::spawn(self.trigger())
+        }
+
+        #[cfg(not(windows))]
+        {
+            false
+        }
+    }
+
+    /// Runs the anticheat once and returns what appeared since the previous scan. The first scan is
+    /// the baseline and returns no events, its findings are in the anticheat's report.
+    pub fn scan(&mut self) -> anyhow::Result<Vec<WatchEvent>> {
+        self.anticheat.run()?;
+        self.scans += 1;
+
+        let worst_score = self.worst.as_ref().map(|worst| worst.assessment.total_score);
+        if worst_score.is_none_or(|worst_score| self.anticheat.assessment().total_score > worst_score) {
+            self.worst = Some(self.anticheat.report());
+        }
+
+        let snapshot = ScanSnapshot::from_anticheat(&self.anticheat);
+        let events = match &self.previous {
+            Some(previous) => snapshot.changes_since(previous, Utc::now()),
+            None => Vec::new(),
+        };
+        self.previous = Some(snapshot);
+        self.events.extend(events.iter().cloned());
+
+        Ok(events)
+    }
+
+    /// Sends the events to every notifier of the anticheat. Events aren't queued in the outbox, they
+    /// are listed in the final report (see final_report), which is.
+    pub async fn notify(&self, events: &[WatchEvent]) -> anyhow::Result<()> {
+        let notifiers = self.anticheat.notifiers();
+        if events.is_empty() || notifiers.is_empty() {
+            return Ok(());
+        }
+
+        let notification = Notification::from_events(&self.anticheat.report(), events);
+        let mut failed = Vec::new();
+        for notifier in notifiers {
+            log::debug!("Sending {} events to {}...", events.len(), notifier.describe());
+            if let Err(err) = self.anticheat.retry().run(&notifier.describe(), || notifier.notify(&notification)).await {
+                failed.push(format!("{:#}", err));
+            }
+        }
+
+        if !failed.is_empty() {
+            anyhow::bail!("{} of {} notifiers failed: {}", failed.len(), notifiers.len(), failed.join("; "));
+        }
+
+        Ok(())
+    }
+
+    /// Scans until `max_scans` or `duration` is reached (forever if neither is set), handing the events
+    /// of every scan to `on_events` and the notifiers. Stops at the first scan that fails, e.g. because
+    /// the game was closed.
+    pub async fn run(&mut self, mut on_events: impl FnMut(&[WatchEvent])) -> anyhow::Result<()> {
+        let deadline = self.duration.map(|duration| Instant::now() + duration);
+
+        loop {
+            let events = self.scan()?;
+            if !events.is_empty() {
+                on_events(&events);
+                if let Err(err) = self.notify(&events).await {
+                    log::error!("Failed to send watch events: {:#}", err);
+                }
+            }
+
+            let now = Instant::now();
+            if self.max_scans.is_some_and(|max_scans| self.scans >= max_scans)
+                || deadline.is_some_and(|deadline| now >= deadline)
+            {
+                return Ok(());
+            }
+
+            let mut next_scan = now + self.interval;
+            if let Some(deadline) = deadline {
+                next_scan = next_scan.min(deadline);
+            }
+
+            tokio::time::sleep_until((now + self.min_gap).min(next_scan)).await;
+            // Timing out is the regular interval, a trigger just comes first
+            let _ = tokio::time::timeout_at(next_scan, self.trigger.notify.notified()).await;
+        }
+    }
+
+    /// Report of the scan with the highest score (the latest one on a tie) with every change seen
+    /// during the watch
+    pub fn final_report(&self) -> ScanReport {
+        let last = self.anticheat.report();
+        let mut report = match &self.worst {
+            Some(worst) if worst.assessment.total_score > last.assessment.total_score => worst.clone(),
+            _ => last,
+        };
+
+        report.watch = Some(WatchSummary {
+            scans: self.scans,
+            events: self.events.clone(),
+        });
+        report
+    }
+
+    pub fn scans(&self) -> usize { self.scans }
+    /// Worst verdict of any scan so far
+    pub fn worst_verdict(&self) -> Verdict {
+        self.worst.as_ref().map_or(Verdict::Clean, |worst| worst.assessment.verdict)
+    }
+    /// Every change seen so far
+    pub fn events(&self) -> &[WatchEvent] { &self.events }
+    pub fn anticheat(&self) -> &Anticheat { &self.anticheat }
+    pub fn anticheat_mut(&mut self) -> &mut Anticheat { &mut self.anticheat }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use crate::core::{
+        handle::{
+            handle_info::HandleInfo,
+            handle_manager::HandleManager,
+            handle_source::HandleSource,
+            HandleError,
+            SystemHandleEntry,
+            SystemHandleType,
+            PROCESS_ALL_ACCESS,
+        },
+        notify::json_notifier::JsonNotifier,
+        overlay::{
+            window_info::{ScreenMetrics, WindowInfo},
+            window_source::WindowSource,
+            WindowError,
+        },
+        process::Process,
+        test_server::TestServer,
+        uploading::retry::RetryPolicy,
+    };
+
+    /// Handles and windows the test changes between scans
+    #[derive(Clone, Default)]
+    struct Desktop {
+        handles: Arc<Mutex<Vec<SystemHandleEntry>>>,
+        windows: Arc<Mutex<Vec<WindowInfo>>>,
+    }
+
+    impl HandleSource for Desktop {
+        fn query_handles(&self) -> Result<Vec<SystemHandleEntry>, HandleError> {
+            Ok(self.handles.lock().unwrap().clone())
+        }
+
+        fn handle_info(&self, entry: &SystemHandleEntry) -> Result<HandleInfo, HandleError> {
+            Ok(HandleInfo::from_paths(r"\Device\HarddiskVolume3\Tools\trainer.exe", r"C:\Tools\trainer.exe", entry.granted_access))
+        }
+    }
+
+    impl WindowSource for Desktop {
+        fn query_windows(&self) -> Result<Vec<WindowInfo>, WindowError> {
+            Ok(self.windows.lock().unwrap().clone())
+        }
+
+        fn screen_metrics(&self) -> Result<ScreenMetrics, WindowError> {
+            Ok(ScreenMetrics::default())
+        }
+    }
+
+    fn watcher(desktop: &Desktop) -> Watcher {
+        // Target ourselves so the module and Linux ptrace scans have a live process to look at
+        let game_pid = std::process::id();
+        let mut anticheat = Anticheat::with_handle_manager(
+            Process::from_parts("game", game_pid),
+            HandleManager::with_source(desktop.clone()).unwrap(),
+        );
+        anticheat.overlay_finder_mut()
+            .with_source(desktop.clone())
+            .with_window_name("ESP");
+        anticheat.with_outbox(None);
+        *anticheat.retry_mut() = RetryPolicy::once(Duration::from_secs(5));
+
+        Watcher::new(anticheat)
+    }
+
+    fn open_handle(desktop: &Desktop, owner: u32) {
+        desktop.handles.lock().unwrap().push(SystemHandleEntry {
+            process_id: owner,
+            handle_value: 0x40,
+            object_type_index: SystemHandleType::Process as u8,
+            granted_access: PROCESS_ALL_ACCESS,
+            target_pid: Some(std::process::id()),
+            ..Default::default()
+        });
+    }
+
+    #[tokio::test]
+    async fn reports_what_appeared_since_the_previous_scan() {
+        let server = TestServer::ok("{}");
+        let desktop = Desktop::default();
+        open_handle(&desktop, 1001);
+        let mut watcher = watcher(&desktop);
+        watcher.anticheat_mut().with_notifier(JsonNotifier::new(server.url.clone()));
+
+        assert!(watcher.scan().unwrap().is_empty(), "first scan is the baseline");
+        assert_eq!(watcher.anticheat().handle_detections(), 1);
+
+        open_handle(&desktop, 1002);
+        desktop.windows.lock().unwrap().push(WindowInfo { hwnd: 0x1234, pid: 1002, title: "ESP".into(), ..Default::default() });
+        let events = watcher.scan().unwrap();
+        let headlines: Vec<String> = events.iter().map(WatchEvent::headline).collect();
+        assert_eq!(headlines, ["New handle opened by trainer.exe (PID 1002)", "Overlay appeared: \"ESP\""]);
+
+        watcher.notify(&events).await.unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&server.requests()[0].body).unwrap();
+        assert_eq!(payload["kind"], "changes");
+        assert_eq!(payload["title"], "Changes during screenshare");
+        assert_eq!(payload["fields"][1]["name"], "Overlay appeared: \"ESP\"");
+        assert_eq!(payload["verdict"], "likely_cheating");
+
+        desktop.handles.lock().unwrap().clear();
+        desktop.windows.lock().unwrap().clear();
+        assert!(watcher.scan().unwrap().is_empty());
+        assert_eq!(watcher.anticheat().verdict(), Verdict::Clean);
+        assert_eq!(watcher.worst_verdict(), Verdict::LikelyCheating);
+        assert_eq!(watcher.scans(), 3);
+
+        // The trainer is gone by the last scan, the final report still shows it
+        let report = watcher.final_report();
+        assert_eq!(report.assessment.verdict, Verdict::LikelyCheating);
+        assert_eq!(report.handles.len(), 2);
+        let watch = report.watch.unwrap();
+        assert_eq!(watch.scans, 3);
+        assert_eq!(watch.events, events);
+    }
+
+    #[tokio::test]
+    async fn run_stops_after_max_scans() {
+        let desktop = Desktop::default();
+        let mut watcher = watcher(&desktop);
+        watcher.interval = Duration::from_millis(10);
+        watcher.min_gap = Duration::ZERO;
+        watcher.max_scans = Some(3);
+
+        let mut seen = Vec::new();
+        watcher.run(|events| seen.extend(events.iter().map(WatchEvent::headline))).await.unwrap();
+        assert_eq!(watcher.scans(), 3);
+        assert!(seen.is_empty());
+
+        open_handle(&desktop, 1001);
+        watcher.max_scans = Some(4);
+        watcher.run(|events| seen.extend(events.iter().map(WatchEvent::headline))).await.unwrap();
+        assert_eq!(seen, ["New handle opened by trainer.exe (PID 1001)"]);
+    }
+
+    #[tokio::test]
+    async fn trigger_cuts_the_interval_short() {
+        let mut watcher = watcher(&Desktop::default());
+        watcher.interval = Duration::from_secs(600);
+        watcher.min_gap = Duration::ZERO;
+        watcher.max_scans = Some(2);
+
+        // Sent before the watcher waits, the request is kept until it does
+        watcher.trigger().rescan_now();
+        tokio::time::timeout(Duration::from_secs(30), watcher.run(|_| {}))
+            .await
+            .expect("trigger didn't wake the watcher")
+            .unwrap();
+        assert_eq!(watcher.scans(), 2);
+    }
+}
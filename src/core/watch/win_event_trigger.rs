@@ -0,0 +1,77 @@
+// Rescans as soon as a top-level window is shown, so an overlay is caught when it appears rather than
+// at the end of the interval. Handles and modules have no comparable user-mode notification and are
+// only picked up by the regular rescans.
+
+use std::{sync::OnceLock, thread};
+use windows::Win32::{
+    Foundation::{HMODULE, HWND},
+    UI::{
+        Accessibility::{SetWinEventHook, HWINEVENTHOOK},
+        WindowsAndMessaging::{
+            DispatchMessageW,
+            GetMessageW,
+            TranslateMessage,
+            CHILDID_SELF,
+            EVENT_OBJECT_SHOW,
+            MSG,
+            OBJID_WINDOW,
+            WINEVENT_OUTOFCONTEXT,
+            WINEVENT_SKIPOWNPROCESS,
+        },
+    },
+};
+use crate::core::watch::watcher::WatchTrigger;
+
+// The hook callback has no user data, it finds the watcher through here
+static TRIGGER: OnceLock<WatchTrigger> = OnceLock::new();
+
+unsafe extern "system" fn on_window_shown(
+    _hook: HWINEVENTHOOK,
+    _event: u32,
+    _hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    // Carets, cursors and menu items send the same event for their objects
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+
+    if let Some(trigger) = TRIGGER.get() {
+        trigger.rescan_now();
+    }
+}
+
+/// Hooks EVENT_OBJECT_SHOW on a thread of its own, which pumps the messages the hook is delivered
+/// through. Only one watcher per process can be woken this way, returns false for any other.
+pub fn spawn(trigger: WatchTrigger) -> bool {
+    if TRIGGER.set(trigger).is_err() {
+        return false;
+    }
+
+    thread::spawn(|| unsafe {
+        let hook = SetWinEventHook(
+            EVENT_OBJECT_SHOW,
+            EVENT_OBJECT_SHOW,
+            HMODULE::default(),
+            Some(on_window_shown),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        if hook.is_invalid() {
+            log::warn!("Failed to hook window events, overlays are only found by the regular rescans");
+            return;
+        }
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, HWND::default(), 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    });
+
+    true
+}
@@ -1,6 +1,6 @@
 pub mod core;
 
-#[cfg(test)]
+#[cfg(all(test, windows))]
 mod tests {
     use std::future::poll_fn;
     use sysinfo::get_current_pid;
@@ -24,12 +24,9 @@ mod tests {
             handle_info::HandleInfo,
             SystemHandleType
         },
-        process::{
-            Process,
-        }
+        profile::GameProfile,
     };
     use std::env;
-    use dotenvy_macro::dotenv;
 
     // This is a completely impractical test
     // Scans for possibly suspicious activity for assault cube.
@@ -41,11 +38,9 @@ mod tests {
             .format_timestamp_secs()
             .init();
 
-        let process = Process::new("ac_client.exe");
-
         log::info!("Starting checks...");
 
-        let mut anticheat = Anticheat::new(process.clone());
+        let mut anticheat = Anticheat::new(GameProfile::builtin("assaultcube").unwrap())?;
         // Set overlay finder params
         anticheat.overlay_finder_mut()
             .with_style(WS_VISIBLE.0)
@@ -54,8 +49,6 @@ mod tests {
             .satisfy_all_criteria(true);
 
         anticheat.run()?;
-        //anticheat.parse_webhook_url();
-        //anticheat.send_webhook(WEBHOOK_URL)?;
         log::info!("--- Anticheat scan results ---\n{}", anticheat);
         log::info!("Completed all checks!");
 
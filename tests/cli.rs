@@ -0,0 +1,248 @@
+// Runs the screenshare binary and checks its output and exit codes
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+};
+
+/// Scratch directory, removed again when the test ends, also when an assertion failed
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("{}_{}", name, process::id()));
+        // Left over from a run that was killed
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("Failed to create temp dir");
+        Self { path }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn screenshare(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_screenshare"))
+        .args(args)
+        .env_remove("SCREENSHARE_CONFIG")
+        .output()
+        .expect("Failed to run screenshare")
+}
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name).display().to_string()
+}
+
+fn saved_report(dir: &TempDir, verdict: &str, total_score: u32) -> PathBuf {
+    let path = dir.join(format!("{}.json", verdict));
+    fs::write(&path, format!(r#"{{
+        "tool_version": "0.1.0",
+        "started_at": "2026-01-01T12:00:00Z",
+        "finished_at": "2026-01-01T12:00:05Z",
+        "host": {{ "hostname": "PLAYER-PC", "os_name": "Windows", "os_version": "11", "kernel_version": "26100", "arch": "x86_64" }},
+        "target": {{ "name": "ac_client.exe", "pid": 4242 }},
+        "handles": [],
+        "overlays": [],
+        "assessment": {{ "verdict": "{}", "total_score": {}, "findings": [] }}
+    }}"#, verdict, total_score)).unwrap();
+    path
+}
+
+#[test]
+fn report_exit_code_follows_verdict() {
+    let dir = TempDir::new("cli_report");
+    let clean = saved_report(&dir, "clean", 0);
+    let output = screenshare(&["report", clean.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("ac_client.exe"));
+
+    let suspicious = saved_report(&dir, "likely_cheating", 120);
+    let output = screenshare(&["report", "--format", "json", suspicious.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"total_score\": 120"));
+}
+
+#[test]
+fn lists_fixture_handles_and_windows() {
+    let output = screenshare(&["list-handles", "--fixture", &fixture("handles.toml"), "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    let handles: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(!handles.as_array().unwrap().is_empty());
+
+    let output = screenshare(&["list-windows", "--fixture", &fixture("windows.toml")]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("ESPWindow"));
+}
+
+#[test]
+fn watch_rescans_and_prints_final_report() {
+    let pid = process::id().to_string();
+    let output = screenshare(&["watch", "--pid", &pid, "--offline", "--no-history", "--no-forensics", "--interval", "1", "--scans", "2"]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Verdict: clean"));
+}
+
+#[test]
+fn reports_are_uploaded_without_notifiers() {
+    let pid = process::id().to_string();
+    let dir = TempDir::new("cli_upload");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_screenshare"))
+        .args(["scan", "--pid", &pid, "--no-history", "--no-forensics", "--upload-dir", dir.join("reports").to_str().unwrap()])
+        .env_remove("SCREENSHARE_CONFIG")
+        .output()
+        .expect("Failed to run screenshare");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_dir(dir.join("reports")).unwrap().count(), 1);
+}
+
+#[test]
+fn undeliverable_reports_without_an_outbox_fail_the_scan() {
+    let pid = process::id().to_string();
+    // Nothing listens on port 1
+    let output = Command::new(env!("CARGO_BIN_EXE_screenshare"))
+        .args(["scan", "--pid", &pid, "--no-history", "--no-forensics"])
+        .env_remove("SCREENSHARE_CONFIG")
+        .env_remove("SCREENSHARE_OUTBOX_KEY")
+        .env("SCREENSHARE_UPLOAD_URL", "http://127.0.0.1:1/upload")
+        .env("SCREENSHARE_MAX_ATTEMPTS", "1")
+        .output()
+        .expect("Failed to run screenshare");
+
+    assert_ne!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not queued"));
+}
+
+#[test]
+fn json_reports_are_printed_once_with_the_stdout_sink() {
+    let pid = process::id().to_string();
+    let dir = TempDir::new("cli_stdout_sink");
+    let config = dir.join("screenshare.toml");
+    fs::write(&config, "[upload]\nkind = \"stdout\"\n").unwrap();
+
+    let output = screenshare(&["scan", "--pid", &pid, "--no-history", "--no-forensics", "--format", "json", "--config", config.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["target"]["pid"].as_u64(), Some(process::id() as u64));
+}
+
+#[test]
+fn history_ignores_the_delivery_config() {
+    let dir = TempDir::new("cli_history_only");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_screenshare"))
+        .args(["history", "--history-dir", dir.path().to_str().unwrap()])
+        .env_remove("SCREENSHARE_CONFIG")
+        .env("SCREENSHARE_UPLOAD_URL", "not a url")
+        .output()
+        .expect("Failed to run screenshare");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 reports"));
+}
+
+#[test]
+fn scans_are_recorded_and_diffed() {
+    let pid = process::id().to_string();
+    let dir = TempDir::new("cli_history");
+    let history_dir = dir.path().to_str().unwrap();
+
+    for _ in 0..2 {
+        let output = screenshare(&["scan", "--pid", &pid, "--offline", "--no-forensics", "--history-dir", history_dir]);
+        assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let output = screenshare(&["history", "--history-dir", history_dir, "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+
+    let target = entries[0]["target"].as_str().unwrap();
+    let output = screenshare(&["diff", "--target", target, "--history-dir", history_dir]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No new or gone findings"));
+
+    // Nothing before a saved report from another machine
+    let reports = TempDir::new("cli_diff_report");
+    let report = saved_report(&reports, "clean", 0);
+    let output = screenshare(&["diff", report.to_str().unwrap(), "--history-dir", history_dir]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No earlier screenshare of ac_client.exe on PLAYER-PC"));
+}
+
+#[test]
+fn forensics_reads_copied_artifacts() {
+    let dir = TempDir::new("cli_forensics");
+    fs::create_dir_all(dir.join("Downloads")).unwrap();
+    fs::write(dir.join("ConsoleHost_history.txt"), "Get-ChildItem\n& \"C:\\Users\\me\\Downloads\\inject.exe\" -p 4242\n").unwrap();
+    fs::write(dir.join("Downloads").join("inject.exe"), b"MZ").unwrap();
+
+    let output = screenshare(&[
+        "forensics", "--artifacts-only", "--format", "json",
+        "--shell-history", dir.join("ConsoleHost_history.txt").to_str().unwrap(),
+        "--dir", dir.join("Downloads").to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let evidence: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let sources: Vec<&str> = evidence.as_array().unwrap().iter().map(|found| found["source"].as_str().unwrap()).collect();
+    assert_eq!(sources, ["recent_file", "shell_history"]);
+    assert_eq!(evidence[1]["path"], r"C:\Users\me\Downloads\inject.exe");
+
+    let output = screenshare(&["forensics", "--artifacts-only", "--amcache", dir.join("missing.hve").to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn errors_exit_with_2() {
+    let output = screenshare(&["scan", "--offline"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Nothing to scan"));
+
+    let output = screenshare(&["scan", "--profile", "not a game", "--offline"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("neither a built-in profile"));
+
+    let output = screenshare(&["report", "/does/not/exist.json"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = screenshare(&["no-such-command"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn prefetch_parses_copied_files() {
+    let output = screenshare(&["prefetch", &fixture("prefetch/INJECTOR.EXE-0C1D2E3F.pf"), "--files"]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("(version 30, compressed)"));
+    assert!(stdout.contains("Run count: 9"));
+    assert!(stdout.contains(r"    \VOLUME{02db3c4d5e6f7081-77e4a9b0}\TOOLS\AIMBOT.DLL"));
+
+    // Run times of the files themselves, not when they were copied
+    let output = screenshare(&[
+        "forensics", "--artifacts-only", "--format", "json", "--lookback-hours", "1000000",
+        "--prefetch-dir", &fixture("prefetch"),
+    ]);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let evidence: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(evidence.as_array().unwrap().len(), 5);
+    assert_eq!(evidence[0]["name"], "INJECTOR.EXE");
+    assert_eq!(evidence[0]["timestamp"], "2026-10-17T09:12:40Z");
+
+    let output = screenshare(&["prefetch", &fixture("prefetch/missing.pf")]);
+    assert_eq!(output.status.code(), Some(2));
+}